@@ -0,0 +1,118 @@
+//! Request authentication (`auth=` in a provider section)
+//!
+//! * `bearer` (default): `Authorization: Bearer KEY`
+//! * `header:NAME`: the key as the value of header NAME, for gateways that
+//!   want e.g. `X-Api-Key`
+//! * `hmac-sha256`: `api_key` is a shared secret. Each request carries
+//!   `X-Timestamp` (Unix seconds) and `X-Signature: sha256=HEX`, where HEX
+//!   is the HMAC-SHA256 of `TIMESTAMP.BODY` under the secret; no key is sent
+//!
+//! Akuma has no wall clock, so the time for signing is learned from the
+//! `Date` header of the plain-HTTP responses meow reads (chat replies,
+//! model lists, the version probe). Before the first signed request with
+//! no time known yet, `sync_clock` asks the provider itself with a `HEAD`.
+//! The TLS stream doesn't show response headers, so an https gateway can
+//! only be signed for once some plain-HTTP server has told us the time.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::{AuthMode, Provider};
+use crate::crypto::{self, HmacSha256};
+use super::transport::{self, Endpoint, ReadError};
+
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Unix seconds at uptime zero; 0 until a Date header has been seen
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+/// How long `sync_clock` waits for the provider to answer
+const SYNC_TIMEOUT_US: u64 = 5_000_000;
+
+const NO_CLOCK: &str = "hmac-sha256 auth needs the time, and no server has sent a Date header yet";
+
+/// Current Unix time, if a server has told us
+pub fn now() -> Option<u64> {
+    match CLOCK.load(Ordering::Relaxed) {
+        0 => None,
+        base => Some(base + libakuma::uptime() / 1_000_000),
+    }
+}
+
+/// Set the clock from the `Date` header in a response head, if there is one
+pub fn note_date(headers: &str) {
+    let Some(unix) = super::client::header_value(headers, "date").and_then(parse_http_date) else { return };
+    let base = unix.saturating_sub(libakuma::uptime() / 1_000_000);
+    CLOCK.store(base.max(1), Ordering::Relaxed);
+}
+
+/// Make sure the time is known before signing for `provider`
+pub fn sync_clock(provider: &Provider) -> Result<(), &'static str> {
+    if provider.auth != AuthMode::HmacSha256 || now().is_some() {
+        return Ok(());
+    }
+    let endpoint = Endpoint::for_provider(provider).ok_or("Invalid provider URL")?;
+    if matches!(endpoint, Endpoint::Tcp { tls: true, .. }) {
+        return Err(NO_CLOCK);
+    }
+    let stream = transport::connect(&endpoint).map_err(|_| "Connection failed")?;
+    let request = format!("HEAD / HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", endpoint.host_header());
+    stream.write_all(request.as_bytes())?;
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 512];
+    let start = libakuma::uptime();
+    while libakuma::uptime() - start < SYNC_TIMEOUT_US && !reply.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(ReadError::Failed) => break,
+            Ok(n) => reply.extend_from_slice(&buf[..n]),
+            Err(ReadError::WouldBlock) => libakuma::sleep_ms(10),
+        }
+    }
+    note_date(&String::from_utf8_lossy(&reply));
+    now().map(|_| ()).ok_or(NO_CLOCK)
+}
+
+/// Headers proving the request comes from us. `body` hands the request
+/// body over in pieces (empty for a GET). Fails for `hmac-sha256` while the
+/// time is unknown (see `sync_clock`).
+pub fn headers(provider: &Provider, body: impl FnOnce(&mut dyn FnMut(&str))) -> Result<Vec<(String, String)>, &'static str> {
+    let Some(key) = provider.api_key.as_deref() else { return Ok(Vec::new()) };
+    match &provider.auth {
+        AuthMode::Bearer => Ok(alloc::vec![(String::from("Authorization"), format!("Bearer {}", key))]),
+        AuthMode::Header(name) => Ok(alloc::vec![(name.clone(), String::from(key))]),
+        AuthMode::HmacSha256 => {
+            let timestamp = format!("{}", now().ok_or(NO_CLOCK)?);
+            let mut mac = HmacSha256::new(key.as_bytes());
+            mac.update(timestamp.as_bytes());
+            mac.update(b".");
+            body(&mut |piece: &str| mac.update(piece.as_bytes()));
+            Ok(alloc::vec![
+                (String::from(TIMESTAMP_HEADER), timestamp),
+                (String::from(SIGNATURE_HEADER), format!("sha256={}", crypto::hex(&mac.finish()))),
+            ])
+        }
+    }
+}
+
+/// Unix seconds of an IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`)
+fn parse_http_date(s: &str) -> Option<u64> {
+    let mut parts = s.split_whitespace().skip(1);
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]
+        .iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut hms = parts.next()?.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, m, sec) = (hms.next()??, hms.next()??, hms.next()??);
+    if year < 1970 || day == 0 || day > 31 {
+        return None;
+    }
+    // Days since 1970-01-01, counting years from March so leap days come last
+    let (y, mo) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era_days = 365 * y + y / 4 - y / 100 + y / 400 + (153 * mo + 2) / 5 + day - 1;
+    let days = era_days - 719_468;
+    Some(days * 86_400 + h * 3_600 + m * 60 + sec)
+}
@@ -1,33 +1,195 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
-use libakuma::net::{resolve, TcpStream};
 use libakuma_tls::{HttpHeaders, HttpStreamTls, StreamResult, TLS_RECORD_SIZE};
 use crate::util::StackBuffer;
 use core::fmt::Write;
-use crate::ui::tui::layout::Stdout;
 
-use crate::config::{Provider, ApiType};
+use crate::app::{attachments, Message};
+use crate::config::{Provider, ApiType, DEFAULT_MAX_RETRIES, DEFAULT_INITIAL_BACKOFF_MS, DEFAULT_REQUEST_TIMEOUT_S};
 use crate::tui_app;
-use super::types::{StreamResponse, StreamStats};
+use crate::ui::output;
+use super::auth;
+use super::middleware::{self, Request};
+use super::transport::{self, Endpoint, ReadError, Transport};
+use super::think::ThinkFilter;
+use super::tool_calls::ToolCalls;
+use super::types::{Sampling, StreamResponse, StreamStats};
 
-const MAX_RETRIES: u32 = 10;
-const DEFAULT_MAX_TOKENS: usize = 16384;
+/// Retry policy: the config's `max_retries`, `initial_backoff_ms` and
+/// `request_timeout_s`, changed at runtime with `/timeout`
+static MAX_RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_MAX_RETRIES);
+static INITIAL_BACKOFF_MS: AtomicU64 = AtomicU64::new(DEFAULT_INITIAL_BACKOFF_MS);
+static REQUEST_TIMEOUT_S: AtomicU64 = AtomicU64::new(DEFAULT_REQUEST_TIMEOUT_S);
+/// Request bytes gathered before each socket write
+const SEND_CHUNK_SIZE: usize = 2048;
 
-/// Attempt to send request with retries and exponential backoff
+pub fn set_retry_policy(max_retries: u32, initial_backoff_ms: u64, request_timeout_s: u64) {
+    MAX_RETRIES.store(max_retries, Ordering::Relaxed);
+    INITIAL_BACKOFF_MS.store(initial_backoff_ms, Ordering::Relaxed);
+    REQUEST_TIMEOUT_S.store(request_timeout_s.max(1), Ordering::Relaxed);
+}
+
+/// (max_retries, initial_backoff_ms, request_timeout_s)
+pub fn retry_policy() -> (u32, u64, u64) {
+    (MAX_RETRIES.load(Ordering::Relaxed), INITIAL_BACKOFF_MS.load(Ordering::Relaxed), REQUEST_TIMEOUT_S.load(Ordering::Relaxed))
+}
+
+/// Time without data after which a response is given up on
+fn request_timeout_us() -> u64 {
+    REQUEST_TIMEOUT_S.load(Ordering::Relaxed) * 1_000_000
+}
+
+/// How often the streaming readers redraw the footer and print waiting
+/// dots in low-bandwidth mode
+const LOW_BW_BATCH_US: u64 = 1_000_000;
+
+/// Lets footer redraws and progress through at most every
+/// `LOW_BW_BATCH_US` in low-bandwidth mode, and every time otherwise
+struct Pacer {
+    every_us: u64,
+    last_us: u64,
+}
+
+impl Pacer {
+    fn new() -> Self {
+        let low_bw = crate::app::state::LOW_BANDWIDTH.load(Ordering::SeqCst);
+        Pacer { every_us: if low_bw { LOW_BW_BATCH_US } else { 0 }, last_us: 0 }
+    }
+
+    fn due(&mut self) -> bool {
+        let now = libakuma::uptime();
+        if self.last_us != 0 && now - self.last_us < self.every_us {
+            return false;
+        }
+        self.last_us = now;
+        true
+    }
+}
+
+/// First and latest SSE event ids of the stream being read
+static mut EVENT_IDS: (Option<String>, Option<String>) = (None, None);
+
+fn event_ids() -> &'static mut (Option<String>, Option<String>) {
+    unsafe { &mut *core::ptr::addr_of_mut!(EVENT_IDS) }
+}
+
+/// A reply cut off mid-stream after SSE event `event_id`. Servers that
+/// number their events pick the stream up after it when the request is
+/// sent again with `Last-Event-ID`, so nothing is repeated and the model
+/// isn't asked to "continue".
+struct Resume {
+    text: String,
+    stats: StreamStats,
+    event_id: String,
+    /// First event id of the cut stream; a server that starts over with it
+    /// ignored `Last-Event-ID`
+    first_id: Option<String>,
+}
+
+impl Resume {
+    /// Resumable if it is a cut-off SSE stream that carried event ids
+    fn from(response: StreamResponse, provider: &Provider) -> Result<Self, StreamResponse> {
+        match response {
+            StreamResponse::Partial(text, stats) if provider.api_type == ApiType::OpenAI => {
+                let (first, last) = event_ids();
+                match last.take() {
+                    Some(event_id) => Ok(Resume { text, stats, event_id, first_id: first.take() }),
+                    None => Err(StreamResponse::Partial(text, stats)),
+                }
+            }
+            other => Err(other),
+        }
+    }
+
+    /// The cut-off text followed by what the resumed stream delivered
+    fn join(self, rest: StreamResponse) -> StreamResponse {
+        let (variant, more, stats): (fn(String, StreamStats) -> StreamResponse, _, _) = match rest {
+            StreamResponse::Complete(t, s) => (StreamResponse::Complete, t, s),
+            StreamResponse::Partial(t, s) => (StreamResponse::Partial, t, s),
+            StreamResponse::Stopped(t, s) => (StreamResponse::Stopped, t, s),
+        };
+        let mut text = self.text;
+        text.push_str(&more);
+        let stats = StreamStats {
+            ttft_us: self.stats.ttft_us,
+            stream_us: self.stats.stream_us + stats.stream_us,
+            total_bytes: text.len(),
+            fakes: 0,
+        };
+        variant(text, stats)
+    }
+
+    fn give_up(self) -> StreamResponse {
+        StreamResponse::Partial(self.text, self.stats)
+    }
+}
+
+/// Note an SSE `id:` line. Fails if a resumed stream starts over.
+fn note_event_id(line: &str, resume: Option<&Resume>) -> Result<(), &'static str> {
+    let Some(id) = line.trim_end().strip_prefix("id:") else { return Ok(()) };
+    let id = id.trim();
+    let (first, last) = event_ids();
+    if first.is_none() {
+        if resume.is_some_and(|r| r.first_id.as_deref() == Some(id)) {
+            return Err("Server restarted the stream");
+        }
+        *first = Some(String::from(id));
+    }
+    *last = Some(String::from(id));
+    Ok(())
+}
+
+/// Attempt to send request with retries and exponential backoff. Under
+/// `--replay` the recorded response comes back instead, and under
+/// `--record` the outcome is written down (see `replay`).
 pub fn send_with_retry(
     model: &str,
     provider: &Provider,
-    history_json: &str,
+    messages: &[&Message],
+    sampling: &Sampling,
     is_continuation: bool,
     current_tokens: usize,
     token_limit: usize,
     mem_kb: usize,
 ) -> Result<StreamResponse, &'static str> {
-    let mut backoff_ms: u64 = 500;
-    let is_tui = tui_app::TUI_ACTIVE.load(Ordering::SeqCst);
+    let result = match crate::replay::next_response() {
+        Some(recorded) => recorded,
+        None => {
+            let result = send_live(model, provider, messages, sampling, is_continuation, current_tokens, token_limit, mem_kb);
+            crate::replay::note_response(&result);
+            result
+        }
+    };
+    crate::app::metrics::record_request(&result);
+    crate::session_log::response(&result);
+    result
+}
+
+fn send_live(
+    model: &str,
+    provider: &Provider,
+    messages: &[&Message],
+    sampling: &Sampling,
+    is_continuation: bool,
+    current_tokens: usize,
+    token_limit: usize,
+    mem_kb: usize,
+) -> Result<StreamResponse, &'static str> {
+    let _t = crate::heap::tag(crate::heap::Tag::Client);
+    // A real request supersedes the warmup; the provider works on one at a time
+    super::warmup::cancel();
+    let endpoint = Endpoint::for_provider(provider).ok_or("Invalid URL")?;
+    if let Some(e) = endpoint.unsupported() {
+        return Err(e);
+    }
+    auth::sync_clock(provider)?;
+    let (max_retries, mut backoff_ms, _) = retry_policy();
+    // The first try plus the retries
+    let attempts = max_retries + 1;
+    let mut resume: Option<Resume> = None;
 
     let status_prefix = if is_continuation {
         "[MEOW] continuing"
@@ -36,23 +198,28 @@ pub fn send_with_retry(
     };
     
     tui_app::update_streaming_status(status_prefix, 0, None);
+    crate::app::state::SOFT_STOP.store(false, Ordering::SeqCst);
     
-    if !is_tui {
-        if is_continuation {
-            libakuma::print("[continuing");
-        } else {
-            libakuma::print("[jacking in");
-        }
+    if is_continuation {
+        output::progress("[continuing");
+    } else if crate::app::state::PLAIN.load(Ordering::SeqCst) {
+        output::progress("[connecting");
+    } else {
+        output::progress("[jacking in");
     }
 
     let start_time = libakuma::uptime();
 
-    for attempt in 0..MAX_RETRIES {
+    for attempt in 0..attempts {
         if attempt > 0 {
-            if !is_tui {
-                let mut stdout = Stdout;
-                let _ = write!(stdout, " retry {}", attempt);
-            }
+            crate::app::metrics::record_retry();
+        }
+        if resume.is_some() {
+            // Picking a cut stream up again; no backoff, the server was fine
+            output::progress("\n[resuming");
+            tui_app::update_streaming_status("[MEOW] resuming", 0, None);
+        } else if attempt > 0 {
+            output::progress(&format!(" retry {}", attempt));
             let mut status_buf_data = [0u8; 64];
             let mut status_buf = StackBuffer::new(&mut status_buf_data);
             let _ = write!(status_buf, "{} retry {}", status_prefix, attempt);
@@ -62,53 +229,42 @@ pub fn send_with_retry(
         }
 
         if tui_app::tui_is_cancelled() {
-            if !is_tui {
-                libakuma::print("\n[cancelled]");
-            }
+            output::progress("\n[cancelled]");
             tui_app::clear_streaming_status();
             return Err("Request cancelled");
         }
 
-        if !is_tui {
-            libakuma::print(".");
+        output::progress(".");
+
+        let (path, mut request_body) = build_chat_request(model, provider, messages, sampling)?;
+        if let Some(r) = &resume {
+            request_body.headers.push((String::from("Last-Event-ID"), r.event_id.clone()));
         }
+        crate::session_log::request(&provider.name, model, &path, attempt, |f| request_body.pieces(f));
+        *event_ids() = (None, None);
 
-        let stream = match connect_to_provider(provider) {
-            Ok(s) => s,
-            Err(e) => {
-                if attempt == MAX_RETRIES - 1 {
-                    if !is_tui { 
-                        let mut stdout = Stdout;
-                        let _ = write!(stdout, "] {}", e); 
+        if let Endpoint::Tcp { host, tls: true, .. } = &endpoint {
+            let stream = match transport::connect_tcp(&endpoint) {
+                Ok(s) => s,
+                Err(e) => {
+                    if attempt == attempts - 1 {
+                        output::progress(&format!("] {}", e));
+                        return Err("Connection failed");
                     }
-                    return Err("Connection failed");
+                    continue;
                 }
-                continue;
-            }
-        };
-
-        tui_app::update_streaming_status("[MEOW] waiting", 0, None);
-        if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-            crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
-        }
-        if !is_tui { libakuma::print("."); }
-
-        let (path, request_body) = build_chat_request(model, provider, history_json);
+            };
+            show_waiting(current_tokens, token_limit, mem_kb);
 
-        if provider.is_https() {
-            let (host, _) = provider.host_port().ok_or("Invalid URL")?;
-            
             let mut read_buf = alloc::vec![0u8; TLS_RECORD_SIZE];
             let mut write_buf = alloc::vec![0u8; TLS_RECORD_SIZE];
             
-            let mut http_stream = match HttpStreamTls::connect(stream, &host, &mut read_buf, &mut write_buf) {
+            let mut http_stream = match HttpStreamTls::connect(stream, host, &mut read_buf, &mut write_buf) {
                 Ok(s) => s,
                 Err(e) => {
-                                    if attempt == MAX_RETRIES - 1 {
-                                        if !is_tui { 
-                                            let mut stdout = Stdout;
-                                            let _ = write!(stdout, "] TLS error: {:?}", e); 
-                                        }                        return Err("TLS handshake failed");
+                    if attempt == attempts - 1 {
+                        output::progress(&format!("] TLS error: {:?}", e));
+                        return Err("TLS handshake failed");
                     }
                     continue;
                 }
@@ -116,145 +272,290 @@ pub fn send_with_retry(
             
             let mut headers = HttpHeaders::new();
             headers.content_type("application/json");
-            if let Some(key) = &provider.api_key {
-                headers.bearer_auth(key);
+            for (name, value) in &request_body.headers {
+                headers.add(name, value);
             }
             
-            if let Err(_) = http_stream.post(&host, &path, &request_body, &headers) {
-                if attempt == MAX_RETRIES - 1 {
-                    if !is_tui { libakuma::print("] "); }
+            // HttpStreamTls::post takes the body as one string, so TLS still
+            // builds it, but only once and at its exact size
+            if let Err(_) = http_stream.post(host, &path, &request_body.to_json(), &headers) {
+                if attempt == attempts - 1 {
+                    output::progress("] ");
                     return Err("Failed to send request");
                 }
                 continue;
             }
             
-            if !is_tui { 
-                libakuma::print("] waiting");
-            }
+            output::progress("] waiting");
             
-            match read_streaming_with_http_stream_tls(&mut http_stream, start_time, provider, current_tokens, token_limit, mem_kb, is_tui) {
-                Ok(response) => return Ok(response),
+            match read_streaming_with_http_stream_tls(&mut http_stream, start_time, provider, resume.as_ref(), current_tokens, token_limit, mem_kb) {
+                Ok(response) => match resume_or_finish(response, &mut resume, provider, attempt == attempts - 1) {
+                    Some(done) => return Ok(done),
+                    None => continue,
+                },
                 Err(e) => {
-                    if e == "Request cancelled" { return Err(e); }
-                    if attempt == MAX_RETRIES - 1 { return Err(e); }
-                    if !is_tui { 
-                        let mut stdout = Stdout;
-                        let _ = write!(stdout, " ({})", e); 
-                    }
+                    if e == "Request cancelled" { return cancelled(); }
+                    if let Some(r) = resume.take() { return Ok(finish_response(r.give_up())); }
+                    if attempt == attempts - 1 { return Err(e); }
+                    output::progress(&format!(" ({})", e));
                     continue;
                 }
             }
         } else {
-            if let Err(e) = send_post_request(&stream, &path, &request_body, provider) {
-                if attempt == MAX_RETRIES - 1 {
-                    if !is_tui { libakuma::print("] "); }
+            let stream = match transport::connect(&endpoint) {
+                Ok(s) => s,
+                Err(e) => {
+                    if attempt == attempts - 1 {
+                        output::progress(&format!("] {}", e));
+                        return Err("Connection failed");
+                    }
+                    continue;
+                }
+            };
+            show_waiting(current_tokens, token_limit, mem_kb);
+
+            if let Err(e) = send_post_request(stream.as_ref(), &path, &request_body, &endpoint, provider) {
+                if attempt == attempts - 1 {
+                    output::progress("] ");
                     return Err(e);
                 }
                 continue;
             }
 
-            if !is_tui {
-                libakuma::print("] waiting");
-            }
+            output::progress("] waiting");
 
-            match read_streaming_response_with_progress(&stream, start_time, provider, current_tokens, token_limit, mem_kb, is_tui) {
-                Ok(response) => return Ok(response),
+            match read_streaming_response_with_progress(stream.as_ref(), start_time, provider, resume.as_ref(), current_tokens, token_limit, mem_kb) {
+                Ok(response) => match resume_or_finish(response, &mut resume, provider, attempt == attempts - 1) {
+                    Some(done) => return Ok(done),
+                    None => continue,
+                },
                 Err(e) => {
-                    if e == "Request cancelled" { return Err(e); }
-                    if attempt == MAX_RETRIES - 1 { return Err(e); }
-                    if !is_tui { 
-                        let mut stdout = Stdout;
-                        let _ = write!(stdout, " ({})", e); 
-                    }
+                    if e == "Request cancelled" { return cancelled(); }
+                    if let Some(r) = resume.take() { return Ok(finish_response(r.give_up())); }
+                    if attempt == attempts - 1 { return Err(e); }
+                    output::progress(&format!(" ({})", e));
                     continue;
                 }
             }
         }
     }
 
-    Err("Max retries exceeded")
+    match resume {
+        Some(r) => Ok(finish_response(r.give_up())),
+        None => Err("Max retries exceeded"),
+    }
 }
 
-fn connect_to_provider(provider: &Provider) -> Result<TcpStream, String> {
-    let (host, port) = provider.host_port().ok_or_else(|| String::from("Invalid provider URL"))?;
-    let ip = resolve(&host).map_err(|_| format!("DNS resolution failed for: {}", host))?;
-    let addr_str = format!("{}.{}.{}.{}:{}", ip[0], ip[1], ip[2], ip[3], port);
-    TcpStream::connect(&addr_str).map_err(|_| format!("Connection failed to: {}", addr_str))
+/// A request cancelled mid-stream. Returning drops the connection, which is
+/// what stops the server (Ollama has no cancel endpoint); this only tells
+/// the user.
+fn cancelled() -> Result<StreamResponse, &'static str> {
+    output::progress("\n[cancelled]");
+    tui_app::clear_streaming_status();
+    Err("Request cancelled")
 }
 
-fn send_post_request(stream: &TcpStream, path: &str, body: &str, provider: &Provider) -> Result<(), &'static str> {
-    let (host, port) = provider.host_port().ok_or("Invalid URL")?;
-    let auth_header = match &provider.api_key {
-        Some(key) => format!("Authorization: Bearer {}\r\n", key),
-        None => String::new(),
+/// A finished read: the response to return, or None with `resume` set when
+/// the stream was cut and can be picked up with another request
+fn resume_or_finish(response: StreamResponse, resume: &mut Option<Resume>, provider: &Provider, last_attempt: bool) -> Option<StreamResponse> {
+    let response = match resume.take() {
+        Some(r) => r.join(response),
+        None => response,
     };
-    let request = format!(
+    if last_attempt {
+        return Some(finish_response(response));
+    }
+    match Resume::from(response, provider) {
+        Ok(r) => {
+            *resume = Some(r);
+            None
+        }
+        Err(response) => Some(finish_response(response)),
+    }
+}
+
+/// Connected; waiting for the server to answer
+fn show_waiting(current_tokens: usize, token_limit: usize, mem_kb: usize) {
+    tui_app::update_streaming_status("[MEOW] waiting", 0, None);
+    output::tick(current_tokens, token_limit, mem_kb);
+    output::progress(".");
+}
+
+pub(super) fn send_post_request(stream: &dyn Transport, path: &str, body: &ChatBody, endpoint: &Endpoint, provider: &Provider) -> Result<(), &'static str> {
+    let mut extra = String::new();
+    for (name, value) in &body.headers {
+        extra.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    let head = format!(
         "POST {} HTTP/1.0\r\n\
-         Host: {}:{}\r\n\
+         Host: {}\r\n\
          Content-Type: application/json\r\n\
          {}Content-Length: {}\r\n\
          Connection: close\r\n\
-         \r\n\
-         {}",
-        path, host, port, auth_header, body.len(), body
+         \r\n",
+        path, endpoint.host_header(), extra, body.len()
     );
-    stream.write_all(request.as_bytes()).map_err(|_| "Failed to send request")
+    let mut writer = ChunkedWriter { stream, buf: Vec::with_capacity(SEND_CHUNK_SIZE), failed: false };
+    writer.push(&head);
+    body.pieces(|p| writer.push(p));
+    writer.flush();
+    if writer.failed { Err("Failed to send request") } else { Ok(()) }
 }
 
-fn build_chat_request(model: &str, provider: &Provider, history_json: &str) -> (String, String) {
-    match provider.api_type {
-        ApiType::Ollama => {
-            let body = format!(
-                "{{\"model\":\"{}\",\"messages\":{},\"stream\":true,\"options\":{{\"num_predict\":{}}}}}",
-                model, history_json, DEFAULT_MAX_TOKENS
-            );
-            (String::from("/api/chat"), body)
+/// Collects small pieces into `SEND_CHUNK_SIZE` writes; the first failed
+/// write makes the rest no-ops
+struct ChunkedWriter<'a> {
+    stream: &'a dyn Transport,
+    buf: Vec<u8>,
+    failed: bool,
+}
+
+impl ChunkedWriter<'_> {
+    fn push(&mut self, piece: &str) {
+        if self.buf.len() + piece.len() > SEND_CHUNK_SIZE {
+            self.flush();
+        }
+        if piece.len() >= SEND_CHUNK_SIZE {
+            if !self.failed && self.stream.write_all(piece.as_bytes()).is_err() {
+                self.failed = true;
+            }
+        } else {
+            self.buf.extend_from_slice(piece.as_bytes());
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.failed && !self.buf.is_empty() && self.stream.write_all(&self.buf).is_err() {
+            self.failed = true;
         }
+        self.buf.clear();
+    }
+}
+
+/// Chat request JSON, produced in pieces straight from the history so the
+/// messages are never copied into one big string
+pub(super) struct ChatBody<'a> {
+    head: String,
+    messages: &'a [&'a Message],
+    /// Which provider's message format to write
+    api_type: ApiType,
+    tail: String,
+    /// HTTP headers to send along: the provider's `extra_headers`, the
+    /// middleware's, then authentication
+    headers: Vec<(String, String)>,
+}
+
+impl ChatBody<'_> {
+    fn pieces(&self, mut f: impl FnMut(&str)) {
+        f(&self.head);
+        for (i, msg) in self.messages.iter().enumerate() {
+            if i > 0 { f(","); }
+            msg.request_pieces(&self.api_type, attachments::is_inlined(i, self.messages.len()), &mut f);
+        }
+        f(&self.tail);
+    }
+
+    /// Content-Length, summed from the message sizes
+    fn len(&self) -> usize {
+        let mut len = 0;
+        self.pieces(|p| len += p.len());
+        len
+    }
+
+    fn to_json(&self) -> String {
+        let mut out = String::with_capacity(self.len());
+        self.pieces(|p| out.push_str(p));
+        out
+    }
+}
+
+pub(super) fn build_chat_request<'a>(model: &'a str, provider: &'a Provider, messages: &'a [&'a Message], sampling: &Sampling) -> Result<(String, ChatBody<'a>), &'static str> {
+    let path = match provider.api_type {
+        ApiType::Ollama => String::from("/api/chat"),
         ApiType::OpenAI => {
-            let body = format!(
-                "{{\"model\":\"{}\",\"messages\":{},\"stream\":true,\"max_tokens\":{}}}",
-                model, history_json, DEFAULT_MAX_TOKENS
-            );
             let base = provider.base_path();
-            let path = if base.is_empty() || base == "/" {
+            if base.is_empty() || base == "/" {
                 String::from("/v1/chat/completions")
             } else if base.ends_with("/v1") {
                 format!("{}/chat/completions", base)
             } else {
                 format!("{}/chat/completions", base.trim_end_matches('/'))
-            };
-            (path, body)
+            }
         }
+    };
+    let mut req = Request { provider, model, path, options: Vec::new(), max_tokens: sampling.max_tokens, fields: Vec::new(), headers: Vec::new() };
+    if let Some(t) = sampling.temperature {
+        req.options.push(("temperature", format!("{:.2}", t)));
+    }
+    if let Some(seed) = sampling.seed {
+        req.options.push(("seed", format!("{}", seed)));
     }
+    middleware::on_request(&mut req);
+
+    let head = format!("{{\"model\":\"{}\",\"messages\":[", model);
+    let mut tail = String::from("],\"stream\":true");
+    // Ollama nests sampling options under "options"; OpenAI takes them top-level
+    let nested = provider.api_type == ApiType::Ollama;
+    if nested && !req.options.is_empty() {
+        tail.push_str(",\"options\":{");
+    }
+    for (i, (key, value)) in req.options.iter().enumerate() {
+        if i > 0 || !nested { tail.push(','); }
+        tail.push_str(&format!("\"{}\":{}", key, value));
+    }
+    if nested && !req.options.is_empty() {
+        tail.push('}');
+    }
+    for field in &req.fields {
+        tail.push(',');
+        tail.push_str(field);
+    }
+    tail.push('}');
+    let mut headers = provider.extra_headers.clone();
+    headers.extend(req.headers.into_iter().map(|(name, value)| (String::from(name), value)));
+    let mut body = ChatBody { head, messages, api_type: provider.api_type.clone(), tail, headers };
+    let auth = auth::headers(provider, |f| body.pieces(f))?;
+    body.headers.extend(auth);
+    Ok((req.path, body))
 }
 
 fn read_streaming_with_http_stream_tls(
     stream: &mut HttpStreamTls<'_>,
     start_time: u64,
     provider: &Provider,
+    resume: Option<&Resume>,
     current_tokens: usize,
     token_limit: usize,
     mem_kb: usize,
-    is_tui: bool,
 ) -> Result<StreamResponse, &'static str> {
     let mut full_response = String::new();
     let mut pending_lines = String::new();
-    let mut first_token_received = false;
+    // A resumed reply is already on screen
+    let mut first_token_received = resume.is_some();
     let mut stream_completed = false;
+    // HttpStreamTls reports Done only once the body framing is satisfied
+    let mut transport_clean = false;
     let mut ttft_us = 0;
-    let mut stream_start_us = 0;
+    let mut last_data_us = libakuma::uptime();
+    let mut stream_start_us = if resume.is_some() { libakuma::uptime() } else { 0 };
+    let mut calls = ToolCalls::new();
+    let mut think = ThinkFilter::new(resume.is_some());
+    let mut ticks = Pacer::new();
 
     loop {
         tui_app::tui_handle_input(current_tokens, token_limit, mem_kb);
-        if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-            crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
-        }
+        if ticks.due() { output::tick(current_tokens, token_limit, mem_kb); }
         if tui_app::tui_is_cancelled() { return Err("Request cancelled"); }
+        if tui_app::tui_is_soft_stopped() { return Ok(stop_stream(full_response, ttft_us, stream_start_us)); }
         match stream.read_chunk() {
             StreamResult::Data(data) => {
+                last_data_us = libakuma::uptime();
+                crate::crash::note_stream_chunk(&data);
                 if let Ok(s) = core::str::from_utf8(&data) { pending_lines.push_str(s); }
                 while let Some(newline_pos) = pending_lines.find('\n') {
                     let line = &pending_lines[..newline_pos];
+                    note_event_id(line, resume)?;
+                    calls.feed(line, provider);
                     if !line.is_empty() {
                         if let Some((content, done)) = parse_streaming_line(line, provider) {
                             if !content.is_empty() {
@@ -264,25 +565,15 @@ fn read_streaming_with_http_stream_tls(
                                     ttft_us = now - start_time;
                                     stream_start_us = now;
                                     tui_app::update_streaming_status("[MEOW] streaming", 0, None);
-                                    if !is_tui {
-                                        libakuma::print(" ");
-                                        print_elapsed(ttft_us / 1000);
-                                        libakuma::print("\n");
-                                    } else {
-                                        tui_app::start_streaming(9);
-                                    }
-                                }
-                                if is_tui {
-                                    tui_app::process_streaming_chunk(&content);
-                                } else {
-                                    tui_app::tui_print_assistant(&content);
                                 }
+                                think.feed(&content, ttft_us / 1000, 0);
                                 full_response.push_str(&content);
                             }
                             if done {
-                                if is_tui { tui_app::finish_streaming(); }
+                                output::reply_end();
                                 tui_app::clear_streaming_status();
-                                return Ok(StreamResponse::Complete(full_response.clone(), StreamStats { ttft_us, stream_us: libakuma::uptime() - stream_start_us, total_bytes: 0, fakes: 0 }));
+                                calls.append_to(&mut full_response);
+                                return Ok(StreamResponse::Complete(full_response, StreamStats { ttft_us, stream_us: libakuma::uptime() - stream_start_us, total_bytes: 0, fakes: 0 }));
                             }
                         }
                     }
@@ -290,13 +581,20 @@ fn read_streaming_with_http_stream_tls(
                 }
             }
                                     StreamResult::WouldBlock => { 
-                                        if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-                                            crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
+                                        if ticks.due() { output::tick(current_tokens, token_limit, mem_kb); }
+                                        if libakuma::uptime() - last_data_us > request_timeout_us() {
+                                            output::reply_end();
+                                            return Err("Timeout waiting for response");
                                         }
                                         libakuma::sleep_ms(1); 
                                     }
                                     StreamResult::Done => {
+                                        transport_clean = true;
                                         let remaining = pending_lines.trim();
+                                        for line in remaining.lines() {
+                                            note_event_id(line, resume)?;
+                                            calls.feed(line, provider);
+                                        }
                                         if !remaining.is_empty() {
                                             if let Some((content, done)) = parse_streaming_line(remaining, provider) {
                                                 if !content.is_empty() {
@@ -306,22 +604,12 @@ fn read_streaming_with_http_stream_tls(
                                                         ttft_us = now - start_time;
                                                                                             stream_start_us = now;
                                                                                             tui_app::update_streaming_status("[MEOW] streaming", 0, None);
-                                                                                            if !is_tui {                                                            libakuma::print(" ");
-                                                            print_elapsed(ttft_us / 1000);
-                                                            libakuma::print("\n");
-                                                        } else {
-                                                            tui_app::start_streaming(9);
-                                                        }
-                                                    }
-                                                    if is_tui {
-                                                        tui_app::process_streaming_chunk(&content);
-                                                    } else {
-                                                        tui_app::tui_print(&content);
                                                     }
+                                                    think.feed(&content, ttft_us / 1000, 0);
                                                     full_response.push_str(&content);
                                                 }
                                                 if done {
-                                                    if is_tui { tui_app::finish_streaming(); }
+                                                    output::reply_end();
                                                     stream_completed = true;
                                                     tui_app::clear_streaming_status();
                                                 }
@@ -330,28 +618,96 @@ fn read_streaming_with_http_stream_tls(
                                         break;
                                     }
                                     StreamResult::Error(_) => { 
-                                        if is_tui { tui_app::finish_streaming(); }
+                                        output::reply_end();
                                         return Err("Server returned error"); 
                                     }
                                 }
                             }
                             let stats = StreamStats { ttft_us, stream_us: if first_token_received { libakuma::uptime() - stream_start_us } else { 0 }, total_bytes: full_response.len(), fakes: 0 };
-    if !stream_completed && !full_response.is_empty() {
-        full_response.shrink_to_fit();
-        return Ok(StreamResponse::Partial(full_response, stats));
+    calls.append_to(&mut full_response);
+    Ok(classify_response(full_response, stream_completed, transport_clean, stats))
+}
+
+/// Where a `Transfer-Encoding: chunked` body is in its framing
+#[derive(Clone, Copy)]
+enum Framing {
+    Size,
+    /// Data bytes left in the current chunk
+    Data(usize),
+    /// The CRLF after a chunk's data
+    DataEnd,
+    /// After the zero-size chunk, up to the empty line
+    Trailers,
+    Done,
+    Broken,
+}
+
+/// Follows the chunk sizes of a chunked body to tell whether it ended with
+/// the zero-size chunk and its (possibly empty) trailers. The bytes
+/// themselves still go to the line parser, which skips the size lines.
+struct ChunkFraming {
+    state: Framing,
+    /// The size or trailer line read so far
+    line: Vec<u8>,
+}
+
+impl ChunkFraming {
+    fn new() -> Self {
+        ChunkFraming { state: Framing::Size, line: Vec::new() }
+    }
+
+    fn feed(&mut self, mut bytes: &[u8]) {
+        while let Some((&b, rest)) = bytes.split_first() {
+            match self.state {
+                Framing::Data(left) => {
+                    let take = left.min(bytes.len());
+                    self.state = if take == left { Framing::DataEnd } else { Framing::Data(left - take) };
+                    bytes = &bytes[take..];
+                    continue;
+                }
+                Framing::Done | Framing::Broken => return,
+                _ => {}
+            }
+            bytes = rest;
+            if b != b'\n' {
+                // Only short lines matter; a long trailer is kept to its start
+                if self.line.len() < 256 { self.line.push(b); }
+                continue;
+            }
+            let line = core::mem::take(&mut self.line);
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+            self.state = match self.state {
+                Framing::Size => {
+                    // The size may carry `;name=value` extensions
+                    let size = core::str::from_utf8(line).ok()
+                        .and_then(|s| usize::from_str_radix(s.split(';').next()?.trim(), 16).ok());
+                    match size {
+                        Some(0) => Framing::Trailers,
+                        Some(n) => Framing::Data(n),
+                        None => Framing::Broken,
+                    }
+                }
+                Framing::DataEnd if line.is_empty() => Framing::Size,
+                Framing::DataEnd => Framing::Broken,
+                Framing::Trailers if line.is_empty() => Framing::Done,
+                state => state,
+            };
+        }
+    }
+
+    fn complete(&self) -> bool {
+        matches!(self.state, Framing::Done)
     }
-    full_response.shrink_to_fit();
-    Ok(StreamResponse::Complete(full_response, stats))
 }
 
 fn read_streaming_response_with_progress(
-    stream: &TcpStream,
+    stream: &dyn Transport,
     start_time: u64,
     provider: &Provider,
+    resume: Option<&Resume>,
     current_tokens: usize,
     token_limit: usize,
     mem_kb: usize,
-    is_tui: bool,
 ) -> Result<StreamResponse, &'static str> {
     let mut buf = [0u8; 1024];
     let mut pending_data = Vec::new();
@@ -359,23 +715,34 @@ fn read_streaming_response_with_progress(
     let mut full_response = String::new();
     let mut read_attempts = 0u32;
     let mut dots_printed = 0u32;
-    let mut first_token_received = false;
+    // A resumed reply is already on screen
+    let mut first_token_received = resume.is_some();
     let mut any_data_received = false;
     let mut stream_completed = false;
+    let mut content_length: Option<usize> = None;
+    let mut chunked = false;
+    let mut body_bytes = 0usize;
+    let mut framing = ChunkFraming::new();
     let mut ttft_us = 0;
-    let mut stream_start_us = 0;
+    let mut last_data_us = libakuma::uptime();
+    let mut stream_start_us = if resume.is_some() { libakuma::uptime() } else { 0 };
+    let mut calls = ToolCalls::new();
+    let mut think = ThinkFilter::new(resume.is_some());
+    let mut ticks = Pacer::new();
+    let mut dots = Pacer::new();
 
     loop {
         tui_app::tui_handle_input(current_tokens, token_limit, mem_kb);
-        if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-            crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
-        }
+        if ticks.due() { output::tick(current_tokens, token_limit, mem_kb); }
         if tui_app::tui_is_cancelled() { return Err("Request cancelled"); }
+        if tui_app::tui_is_soft_stopped() { return Ok(stop_stream(full_response, ttft_us, stream_start_us)); }
         match stream.read(&mut buf) {
             Ok(0) => {
                 if !any_data_received { return Err("Connection closed by server"); }
                 if let Ok(remaining_str) = core::str::from_utf8(&pending_data) {
                     for line in remaining_str.trim().lines() {
+                        note_event_id(line, resume)?;
+                        calls.feed(line, provider);
                         if let Some((content, done)) = parse_streaming_line(line, provider) {
                             if !content.is_empty() {
                                 if !first_token_received {
@@ -384,23 +751,12 @@ fn read_streaming_response_with_progress(
                                     ttft_us = now - start_time;
                                     stream_start_us = now;
                                     tui_app::update_streaming_status("[MEOW] streaming", 0, None);
-                                    if !is_tui {
-                                        for _ in 0..(7 + dots_printed) { libakuma::print("\x08 \x08"); }
-                                        print_elapsed(ttft_us / 1000);
-                                        libakuma::print("\n");
-                                    } else {
-                                        tui_app::start_streaming(9);
-                                    }
-                                }
-                                if is_tui {
-                                    tui_app::process_streaming_chunk(&content);
-                                } else {
-                                    tui_app::tui_print_assistant(&content);
                                 }
+                                think.feed(&content, ttft_us / 1000, 7 + dots_printed);
                                 full_response.push_str(&content);
                             }
                             if done { 
-                                if is_tui { tui_app::finish_streaming(); }
+                                output::reply_end();
                                 stream_completed = true; 
                                 tui_app::clear_streaming_status(); 
                             }
@@ -412,22 +768,33 @@ fn read_streaming_response_with_progress(
             Ok(n) => {
                 any_data_received = true;
                 read_attempts = 0;
+                last_data_us = libakuma::uptime();
+                crate::crash::note_stream_chunk(&buf[..n]);
                 pending_data.extend_from_slice(&buf[..n]);
                 if !headers_parsed {
                     if let Some(pos) = find_header_end(&pending_data) {
                         let header_str = core::str::from_utf8(&pending_data[..pos]).unwrap_or("");
+                        auth::note_date(header_str);
                         if !header_str.contains(" 200 ") { return Err("Server returned error"); }
+                        content_length = header_value(header_str, "content-length").and_then(|v| v.parse().ok());
+                        chunked = header_value(header_str, "transfer-encoding").map(|v| v.eq_ignore_ascii_case("chunked")).unwrap_or(false);
                         headers_parsed = true;
                         pending_data.drain(..pos + 4);
+                        body_bytes = pending_data.len();
+                        if chunked { framing.feed(&pending_data); }
                     }
                     continue;
                 }
+                body_bytes += n;
+                if chunked { framing.feed(&buf[..n]); }
                 if let Ok(body_str) = core::str::from_utf8(&pending_data) {
                     let last_newline = body_str.rfind('\n');
                     let complete_part = match last_newline { Some(pos) => &body_str[..pos + 1], None => continue };
                     let mut is_done = false;
                     for line in complete_part.lines() {
                         if line.is_empty() { continue; }
+                        note_event_id(line, resume)?;
+                        calls.feed(line, provider);
                         if let Some((content, done)) = parse_streaming_line(line, provider) {
                             if !content.is_empty() {
                                 if !first_token_received {
@@ -436,23 +803,12 @@ fn read_streaming_response_with_progress(
                                     ttft_us = now - start_time;
                                     stream_start_us = now;
                                     tui_app::update_streaming_status("[MEOW] streaming", 0, None);
-                                    if !is_tui {
-                                        for _ in 0..(7 + dots_printed) { libakuma::print("\x08 \x08"); }
-                                        print_elapsed(ttft_us / 1000);
-                                        libakuma::print("\n");
-                                    } else {
-                                        tui_app::start_streaming(9);
-                                    }
-                                }
-                                if is_tui {
-                                    tui_app::process_streaming_chunk(&content);
-                                } else {
-                                    tui_app::tui_print_assistant(&content);
                                 }
+                                think.feed(&content, ttft_us / 1000, 7 + dots_printed);
                                 full_response.push_str(&content);
                             }
                             if done { 
-                                if is_tui { tui_app::finish_streaming(); }
+                                output::reply_end();
                                 is_done = true; 
                                 tui_app::clear_streaming_status(); 
                                 break; 
@@ -461,35 +817,133 @@ fn read_streaming_response_with_progress(
                     }
                     if let Some(pos) = last_newline { pending_data.drain(..pos + 1); }
                     if is_done {
+                        calls.append_to(&mut full_response);
                         return Ok(StreamResponse::Complete(full_response, StreamStats { ttft_us, stream_us: libakuma::uptime() - stream_start_us, total_bytes: 0, fakes: 0 }));
                     }
                 }
             }
-            Err(e) => {
-                if e.kind == libakuma::net::ErrorKind::WouldBlock || e.kind == libakuma::net::ErrorKind::TimedOut {
-                    if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-                        crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
-                    }
-                    read_attempts += 1;
-                    if read_attempts % 50 == 0 && !first_token_received && !is_tui { libakuma::print("."); dots_printed += 1; }
-                    if read_attempts > 6000 { return Err("Timeout waiting for response"); }
-                    libakuma::sleep_ms(1);
-                    continue;
-                }
-                return Err("Network error");
+            Err(ReadError::WouldBlock) => {
+                if ticks.due() { output::tick(current_tokens, token_limit, mem_kb); }
+                read_attempts += 1;
+                if read_attempts % 50 == 0 && !first_token_received && dots.due() { output::progress("."); dots_printed += 1; }
+                if libakuma::uptime() - last_data_us > request_timeout_us() { return Err("Timeout waiting for response"); }
+                libakuma::sleep_ms(1);
+                continue;
             }
+            Err(ReadError::Failed) => return Err("Network error"),
         }
     }
+    let transport_clean = if chunked {
+        framing.complete()
+    } else {
+        content_length.map(|len| body_bytes >= len).unwrap_or(false)
+    };
     let stats = StreamStats { ttft_us, stream_us: if first_token_received { libakuma::uptime() - stream_start_us } else { 0 }, total_bytes: full_response.len(), fakes: 0 };
-    if !stream_completed && !full_response.is_empty() {
-        full_response.shrink_to_fit();
-        return Ok(StreamResponse::Partial(full_response, stats));
+    calls.append_to(&mut full_response);
+    Ok(classify_response(full_response, stream_completed, transport_clean, stats))
+}
+
+/// Cut think blocks and fabricated tool results out of a finished reply,
+/// count the fakes in the stats, and run the response hooks on what is left
+fn finish_response(response: StreamResponse) -> StreamResponse {
+    // Same variant back, around the cleaned-up text
+    let (variant, text, mut stats): (fn(String, StreamStats) -> StreamResponse, _, _) = match response {
+        StreamResponse::Complete(text, stats) => (StreamResponse::Complete, text, stats),
+        StreamResponse::Partial(text, stats) => (StreamResponse::Partial, text, stats),
+        StreamResponse::Stopped(text, stats) => (StreamResponse::Stopped, text, stats),
+    };
+    let (text, fakes) = strip_fake_tool_results(super::think::strip(text));
+    stats.fakes = fakes;
+    middleware::on_response(&text);
+    variant(text, stats)
+}
+
+/// Soft stop: end the reply where it is and keep what arrived
+fn stop_stream(full_response: String, ttft_us: u64, stream_start_us: u64) -> StreamResponse {
+    output::reply_end();
+    tui_app::clear_streaming_status();
+    let stream_us = if stream_start_us > 0 { libakuma::uptime() - stream_start_us } else { 0 };
+    let total_bytes = full_response.len();
+    StreamResponse::Stopped(full_response, StreamStats { ttft_us, stream_us, total_bytes, fakes: 0 })
+}
+
+const TOOL_RESULT_START: &str = "[Tool Result]";
+const TOOL_RESULT_END: &str = "[End Tool Result]";
+
+/// Remove `[Tool Result] … [End Tool Result]` blocks the model wrote itself,
+/// along with the `[Current Directory: …]` line real results carry. Only
+/// meow writes tool results, so any in a reply are made up. A block with no
+/// end marker runs to the end of the reply.
+fn strip_fake_tool_results(text: String) -> (String, usize) {
+    if !text.contains(TOOL_RESULT_START) {
+        return (text, 0);
     }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    let mut count = 0;
+    while let Some(start) = rest.find(TOOL_RESULT_START) {
+        out.push_str(&rest[..start]);
+        count += 1;
+        rest = match rest[start..].find(TOOL_RESULT_END) {
+            Some(end) => {
+                let after = &rest[start + end + TOOL_RESULT_END.len()..];
+                let trimmed = after.trim_start();
+                if trimmed.starts_with("[Current Directory:") {
+                    trimmed.find('\n').map(|i| &trimmed[i + 1..]).unwrap_or("")
+                } else {
+                    after
+                }
+            }
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    (out, count)
+}
+
+/// Decide Complete vs Partial for a stream that ended. Some keep-alive
+/// proxies close cleanly without forwarding the provider's done marker, so
+/// a missing marker alone isn't enough to call the response cut off: if the
+/// transport framing was satisfied and the text reads as finished, keep it.
+fn classify_response(mut full_response: String, done_seen: bool, transport_clean: bool, stats: StreamStats) -> StreamResponse {
     full_response.shrink_to_fit();
-    Ok(StreamResponse::Complete(full_response, stats))
+    if done_seen || full_response.is_empty() || (transport_clean && looks_finished(&full_response)) {
+        StreamResponse::Complete(full_response, stats)
+    } else {
+        StreamResponse::Partial(full_response, stats)
+    }
+}
+
+/// Ends like a sentence (or a kaomoji/tool block) with every code fence closed
+fn looks_finished(text: &str) -> bool {
+    let fences = text.lines().filter(|l| l.trim_start().starts_with("```")).count();
+    if fences % 2 != 0 {
+        return false;
+    }
+    match text.trim_end().chars().last() {
+        Some(c) => matches!(c, '.' | '!' | '?' | ')' | '~' | '`' | '}' | '"' | '*' | '♪' | '。' | '！' | '？'),
+        None => false,
+    }
+}
+
+/// Value of an HTTP header, matched case-insensitively
+pub(super) fn header_value<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) { Some(value.trim()) } else { None }
+    })
 }
 
+/// Parse one stream line and run the chunk hooks over its content
 fn parse_streaming_line(line: &str, provider: &Provider) -> Option<(String, bool)> {
+    let (mut content, done) = parse_provider_line(line, provider)?;
+    if !content.is_empty() {
+        middleware::on_chunk(&mut content);
+    }
+    Some((content, done))
+}
+
+fn parse_provider_line(line: &str, provider: &Provider) -> Option<(String, bool)> {
     match provider.api_type {
         ApiType::Ollama => {
             let done = line.contains("\"done\":true") || line.contains("\"done\": true");
@@ -580,26 +1034,66 @@ fn find_header_end(data: &[u8]) -> Option<usize> {
     None
 }
 
-fn print_elapsed(ms: u64) {
-    let mut buf_data = [0u8; 32];
-    let mut buf = StackBuffer::new(&mut buf_data);
-    let mut stdout = Stdout;
-    if ms < 1000 { 
-        let _ = write!(buf, "~(=^‥^)ノ [{}ms]", ms); 
-        let _ = write!(stdout, "{}", buf.as_str());
-    } else { 
-        let _ = write!(buf, "~(=^‥^)ノ [{}.{}s]", ms / 1000, (ms % 1000) / 100); 
-        let _ = write!(stdout, "{}", buf.as_str());
-    }
-}
-
 fn poll_sleep(ms: u64, current_tokens: usize, token_limit: usize, mem_kb: usize) {
     let end = libakuma::uptime() + ms * 1000;
     while libakuma::uptime() < end { 
         tui_app::tui_handle_input(current_tokens, token_limit, mem_kb); 
-        if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-            crate::ui::tui::render::render_footer(current_tokens, token_limit, mem_kb);
-        }
+        output::tick(current_tokens, token_limit, mem_kb);
         libakuma::sleep_ms(10); 
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framed(reads: &[&[u8]]) -> ChunkFraming {
+        let mut framing = ChunkFraming::new();
+        for read in reads {
+            framing.feed(read);
+        }
+        framing
+    }
+
+    #[test]
+    fn complete_body() {
+        assert!(framed(&[b"5\r\nhello\r\n0\r\n\r\n"]).complete());
+    }
+
+    #[test]
+    fn split_across_reads() {
+        // Every boundary, including inside the size line and the final CRLF
+        let body = b"b\r\ndata: {}\n\n\r\n0\r\n\r\n";
+        for cut in 1..body.len() {
+            assert!(framed(&[&body[..cut], &body[cut..]]).complete(), "cut at {}", cut);
+        }
+        // A terminator-looking run inside chunk data doesn't end the body
+        assert!(!framed(&[b"9\r\n0\r\n\r\nab\r\n"]).complete());
+    }
+
+    #[test]
+    fn size_extensions() {
+        assert!(framed(&[b"a;name=value\r\n0123456789\r\n0;last\r\n\r\n"]).complete());
+    }
+
+    #[test]
+    fn trailers() {
+        assert!(framed(&[b"3\r\nabc\r\n0\r\nX-Checksum: 1\r\nX-Other: 2\r\n\r\n"]).complete());
+        assert!(!framed(&[b"3\r\nabc\r\n0\r\nX-Checksum: 1\r\n"]).complete());
+    }
+
+    #[test]
+    fn missing_final_crlf() {
+        assert!(!framed(&[b"5\r\nhello\r\n0\r\n"]).complete());
+        assert!(!framed(&[b"5\r\nhello\r\n"]).complete());
+    }
+
+    #[test]
+    fn malformed_size() {
+        let framing = framed(&[b"zz\r\nhello\r\n0\r\n\r\n"]);
+        assert!(matches!(framing.state, Framing::Broken));
+        assert!(!framing.complete());
+        // Data longer than its size leaves no CRLF where one belongs
+        assert!(matches!(framed(&[b"2\r\nhello\r\n0\r\n\r\n"]).state, Framing::Broken));
+    }
+}
@@ -0,0 +1,107 @@
+//! Request/response middleware
+//!
+//! Hooks registered here see every chat request before it is sent and every
+//! parsed chunk as it streams in, so logging, redaction, token counting or
+//! provider-specific request fields live in one place instead of as
+//! conditionals in `send_with_retry`. Hooks run in registration order.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::config::{ApiType, Provider};
+
+/// An outgoing chat request, before its body is written
+pub struct Request<'a> {
+    pub provider: &'a Provider,
+    pub model: &'a str,
+    /// Endpoint path, e.g. `/api/chat`
+    pub path: String,
+    /// Sampling options as (name, JSON value), e.g. ("temperature", "0.85")
+    pub options: Vec<(&'static str, String)>,
+    /// Per-request reply length cap, if the caller set one
+    pub max_tokens: Option<usize>,
+    /// Extra top-level JSON members written verbatim, e.g. `"keep_alive":"5m"`
+    pub fields: Vec<String>,
+    /// Extra HTTP headers as (name, value)
+    pub headers: Vec<(&'static str, String)>,
+}
+
+pub trait Middleware {
+    fn name(&self) -> &'static str;
+    fn on_request(&self, _req: &mut Request) {}
+    /// One parsed content chunk; may be rewritten or emptied
+    fn on_chunk(&self, _chunk: &mut String) {}
+    /// The whole reply, once the stream has ended
+    fn on_response(&self, _text: &str) {}
+}
+
+static mut HOOKS: Vec<&'static dyn Middleware> = Vec::new();
+
+fn hooks() -> &'static mut Vec<&'static dyn Middleware> {
+    unsafe { &mut *core::ptr::addr_of_mut!(HOOKS) }
+}
+
+/// Add a hook; registering the same name twice is a no-op
+pub fn register(hook: &'static dyn Middleware) {
+    if !hooks().iter().any(|h| h.name() == hook.name()) {
+        hooks().push(hook);
+    }
+}
+
+/// The hooks every build runs with
+pub fn install_defaults() {
+    // Before MaxTokens, which writes the cap `/params` may set
+    register(&crate::app::params::Params);
+    register(&MaxTokens);
+    register(&NativeTools);
+}
+
+pub fn on_request(req: &mut Request) {
+    for hook in hooks().iter() {
+        hook.on_request(req);
+    }
+}
+
+pub fn on_chunk(chunk: &mut String) {
+    for hook in hooks().iter() {
+        hook.on_chunk(chunk);
+    }
+}
+
+pub fn on_response(text: &str) {
+    for hook in hooks().iter() {
+        hook.on_response(text);
+    }
+}
+
+/// Caps the reply length from the current /verbosity, under whichever
+/// name the provider's API expects
+pub struct MaxTokens;
+
+impl Middleware for MaxTokens {
+    fn name(&self) -> &'static str { "max-tokens" }
+
+    fn on_request(&self, req: &mut Request) {
+        let max_tokens = req.max_tokens.unwrap_or_else(|| crate::app::state::get_verbosity().max_tokens());
+        let key = match req.provider.api_type {
+            ApiType::Ollama => "num_predict",
+            ApiType::OpenAI => "max_tokens",
+        };
+        req.options.push((key, format!("{}", max_tokens)));
+    }
+}
+
+/// Offers the tool list as a `tools` array to providers with `native_tools`
+/// on; their structured calls are read back by `api::tool_calls`
+pub struct NativeTools;
+
+impl Middleware for NativeTools {
+    fn name(&self) -> &'static str { "native-tools" }
+
+    fn on_request(&self, req: &mut Request) {
+        if req.provider.native_tools {
+            req.fields.push(format!("\"tools\":{}", crate::tools::registry::schema()));
+        }
+    }
+}
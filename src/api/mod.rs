@@ -1,40 +1,43 @@
 pub mod types;
+pub mod auth;
 pub mod client;
+pub mod transport;
+pub mod middleware;
+pub mod warmup;
+pub mod think;
+pub mod tool_calls;
+pub mod version;
+pub mod probe;
 
 pub use types::*;
 pub use client::send_with_retry;
 
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
-use libakuma::net::{TcpStream, resolve};
 use libakuma_tls::{https_get, HttpHeaders};
 use crate::config::{ApiType, Provider};
+use transport::{Endpoint, ReadError, Transport};
+
+/// Timeout for metadata queries (model lists, model info)
+const METADATA_TIMEOUT_US: u64 = 5_000_000;
 
 /// Connect to a provider (HTTP only)
-fn connect(provider: &Provider) -> Result<TcpStream, ProviderError> {
-    let (host, port) = provider.host_port()
+fn connect(provider: &Provider) -> Result<(Box<dyn Transport>, Endpoint), ProviderError> {
+    let endpoint = Endpoint::for_provider(provider)
         .ok_or_else(|| ProviderError::ConnectionFailed(String::from("Invalid URL")))?;
-
-    let ip = resolve(&host).map_err(|_| {
-        ProviderError::ConnectionFailed(format!("DNS resolution failed for: {}", host))
-    })?;
-
-    let addr_str = format!("{}.{}.{}.{}:{}", ip[0], ip[1], ip[2], ip[3], port);
-
-    TcpStream::connect(&addr_str).map_err(|_| {
-        ProviderError::ConnectionFailed(format!("Connection failed to: {}", addr_str))
-    })
+    let stream = transport::connect(&endpoint).map_err(ProviderError::ConnectionFailed)?;
+    Ok((stream, endpoint))
 }
 
-fn read_response(stream: &TcpStream) -> Result<String, ProviderError> {
+fn read_response(stream: &dyn Transport) -> Result<String, ProviderError> {
     let mut response = Vec::new();
     let mut buf = [0u8; 4096];
     let start_time = libakuma::uptime();
-    let timeout_us = 5_000_000; // 5 seconds timeout for metadata queries
 
     loop {
-        if libakuma::uptime() - start_time > timeout_us {
+        if libakuma::uptime() - start_time > METADATA_TIMEOUT_US {
             return Err(ProviderError::RequestFailed(String::from("Read timeout")));
         }
 
@@ -44,19 +47,19 @@ fn read_response(stream: &TcpStream) -> Result<String, ProviderError> {
                 response.extend_from_slice(&buf[..n]);
                 if response.len() > 256 * 1024 { break; }
             }
-            Err(e) => {
-                if e.kind == libakuma::net::ErrorKind::WouldBlock
-                    || e.kind == libakuma::net::ErrorKind::TimedOut
-                {
-                    libakuma::sleep_ms(10);
-                    continue;
-                }
-                break;
+            Err(ReadError::WouldBlock) => {
+                libakuma::sleep_ms(10);
+                continue;
             }
+            Err(ReadError::Failed) => break,
         }
     }
 
-    Ok(String::from_utf8_lossy(&response).into_owned())
+    let text = String::from_utf8_lossy(&response).into_owned();
+    if let Some(end) = text.find("\r\n\r\n") {
+        auth::note_date(&text[..end]);
+    }
+    Ok(text)
 }
 
 pub fn list_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderError> {
@@ -66,25 +69,24 @@ pub fn list_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderError>
     }
 }
 
-fn list_ollama_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderError> {
-    let (host, port) = provider.host_port()
-        .ok_or_else(|| ProviderError::ConnectionFailed(String::from("Invalid URL")))?;
-
-    let stream = connect(provider)?;
+/// Connect and send `GET /api/tags`; the reply is read by the caller
+fn request_ollama_tags(provider: &Provider) -> Result<Box<dyn Transport>, ProviderError> {
+    let (stream, endpoint) = connect(provider)?;
 
     let request = format!(
         "GET /api/tags HTTP/1.0\r\n\
-         Host: {}:{}\r\n\
+         Host: {}\r\n\
          Connection: close\r\n\
          \r\n",
-        host, port
+        endpoint.host_header()
     );
 
     stream.write_all(request.as_bytes())
         .map_err(|_| ProviderError::RequestFailed(String::from("Write failed")))?;
+    Ok(stream)
+}
 
-    let response_str = read_response(&stream)?;
-
+fn parse_ollama_tags_response(response_str: &str) -> Result<Vec<ModelInfo>, ProviderError> {
     let body = response_str
         .find("\r\n\r\n")
         .map(|pos| &response_str[pos + 4..])
@@ -93,6 +95,76 @@ fn list_ollama_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderErr
     parse_ollama_models(body)
 }
 
+fn list_ollama_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderError> {
+    let stream = request_ollama_tags(provider)?;
+    let response_str = read_response(stream.as_ref())?;
+    parse_ollama_tags_response(&response_str)
+}
+
+/// Every provider's model list, in config order. Ollama requests all go out
+/// first and their replies are read round-robin as they arrive, so those
+/// providers are queried at the same time; OpenAI-style ones go through
+/// the blocking `https_get` one after another.
+pub fn list_models_all(providers: &[Provider]) -> Vec<(String, Result<Vec<ModelInfo>, ProviderError>)> {
+    struct Pending {
+        idx: usize,
+        stream: Box<dyn Transport>,
+        response: Vec<u8>,
+        done: bool,
+    }
+
+    let mut results: Vec<Option<Result<Vec<ModelInfo>, ProviderError>>> = providers.iter().map(|_| None).collect();
+    let mut pending: Vec<Pending> = Vec::new();
+    for (idx, p) in providers.iter().enumerate() {
+        if p.api_type != ApiType::Ollama { continue; }
+        match request_ollama_tags(p) {
+            Ok(stream) => pending.push(Pending { idx, stream, response: Vec::new(), done: false }),
+            Err(e) => results[idx] = Some(Err(e)),
+        }
+    }
+
+    let mut buf = [0u8; 4096];
+    let start_time = libakuma::uptime();
+    while pending.iter().any(|p| !p.done) && libakuma::uptime() - start_time < METADATA_TIMEOUT_US {
+        let mut idle = true;
+        for p in pending.iter_mut().filter(|p| !p.done) {
+            match p.stream.read(&mut buf) {
+                Ok(0) | Err(ReadError::Failed) => p.done = true,
+                Ok(n) => {
+                    idle = false;
+                    p.response.extend_from_slice(&buf[..n]);
+                    if p.response.len() > 256 * 1024 { p.done = true; }
+                }
+                Err(ReadError::WouldBlock) => {}
+            }
+        }
+        if idle { libakuma::sleep_ms(10); }
+    }
+    for p in pending {
+        results[p.idx] = Some(if p.done {
+            parse_ollama_tags_response(&String::from_utf8_lossy(&p.response))
+        } else {
+            Err(ProviderError::RequestFailed(String::from("Read timeout")))
+        });
+    }
+
+    providers.iter().zip(results).map(|(p, r)| {
+        let r = r.unwrap_or_else(|| list_models(p));
+        (p.name.clone(), r)
+    }).collect()
+}
+
+/// Prefixed names (`provider/model`) from the last `/models all`
+static mut MODEL_CACHE: Option<Vec<String>> = None;
+
+pub fn cache_models(names: Vec<String>) {
+    unsafe { *core::ptr::addr_of_mut!(MODEL_CACHE) = Some(names); }
+}
+
+pub fn cached_models() -> Option<&'static [String]> {
+    unsafe { (*core::ptr::addr_of!(MODEL_CACHE)).as_deref() }
+}
+
 fn parse_ollama_models(json: &str) -> Result<Vec<ModelInfo>, ProviderError> {
     let mut models = Vec::new();
 
@@ -151,7 +223,10 @@ fn list_openai_models(provider: &Provider) -> Result<Vec<ModelInfo>, ProviderErr
     };
 
     let mut headers = HttpHeaders::new();
-    if let Some(key) = &provider.api_key { headers.bearer_auth(key); }
+    let auth = auth::headers(provider, |_| {}).map_err(|e| ProviderError::RequestFailed(String::from(e)))?;
+    for (name, value) in provider.extra_headers.iter().cloned().chain(auth) {
+        headers.add(&name, &value);
+    }
 
     let response = https_get(&url, &headers)
         .map_err(|_| ProviderError::RequestFailed(String::from("TLS/HTTP request failed")))?;
@@ -247,30 +322,60 @@ fn extract_json_number(json: &str, key: &str) -> Option<u64> {
     trimmed[..end].parse().ok()
 }
 
-pub fn query_model_info(model: &str, provider: &Provider) -> Option<usize> {
-    if provider.api_type != ApiType::Ollama { return None; }
-    let (host, port) = provider.host_port()?;
-    let stream = connect(provider).ok()?;
+/// Context-window lookup in flight, see `start_model_info`
+static mut MODEL_INFO: Option<probe::Probe> = None;
+
+fn model_info() -> &'static mut Option<probe::Probe> {
+    unsafe { &mut *core::ptr::addr_of_mut!(MODEL_INFO) }
+}
+
+/// Ask Ollama for `model`'s context window in the background, replacing any
+/// lookup in flight. False when there is nothing to ask: other providers
+/// don't report one, and TLS streams can't be polled.
+pub fn start_model_info(model: &str, provider: &Provider) -> bool {
+    *model_info() = None;
+    if provider.api_type != ApiType::Ollama { return false; }
+    let Some(endpoint) = Endpoint::for_provider(provider) else { return false; };
+    if matches!(endpoint, Endpoint::Tcp { tls: true, .. }) || endpoint.unsupported().is_some() {
+        return false;
+    }
     let body = format!("{{\"model\":\"{}\"}}", model);
     let request = format!(
         "POST /api/show HTTP/1.0\r\n\
-         Host: {}:{}\r\n\
+         Host: {}\r\n\
          Content-Type: application/json\r\n\
          Content-Length: {}\r\n\
          Connection: close\r\n\
          \r\n\
          {}",
-        host, port, body.len(), body
+        endpoint.host_header(), body.len(), body
     );
-    stream.write_all(request.as_bytes()).ok()?;
-    let response_str = read_response(&stream).ok()?;
-    if let Some(pos) = response_str.find("\"num_ctx\"") {
-        let after = &response_str[pos + 9..];
-        let num_start = after.find(|c: char| c.is_ascii_digit())?;
-        let rest = &after[num_start..];
-        let num_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
-        let num_str = &rest[..num_end];
-        return num_str.parse().ok();
-    }
-    None
-}
\ No newline at end of file
+    *model_info() = Some(probe::Probe::new(endpoint, request, METADATA_TIMEOUT_US));
+    true
+}
+
+/// Check on the context-window lookup without blocking. Once it is done:
+/// the model's `num_ctx`, or `DEFAULT_CONTEXT_WINDOW` when there was no
+/// answer.
+pub fn poll_model_info() -> Option<usize> {
+    let reply = match model_info().as_mut()?.poll() {
+        probe::Poll::Pending => return None,
+        probe::Poll::Done(reply) => Some(reply),
+        probe::Poll::Unreachable | probe::Poll::TimedOut => None,
+    };
+    *model_info() = None;
+    Some(reply.as_deref().and_then(parse_num_ctx).unwrap_or(crate::config::DEFAULT_CONTEXT_WINDOW))
+}
+
+pub fn cancel_model_info() {
+    *model_info() = None;
+}
+
+fn parse_num_ctx(response_str: &str) -> Option<usize> {
+    let pos = response_str.find("\"num_ctx\"")?;
+    let after = &response_str[pos + 9..];
+    let num_start = after.find(|c: char| c.is_ascii_digit())?;
+    let rest = &after[num_start..];
+    let num_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..num_end].parse().ok()
+}
@@ -0,0 +1,83 @@
+//! Background requests for the idle loop
+//!
+//! The version probe and the context-window lookup ask the provider small
+//! questions while the user is typing. A `Probe` holds on to its request
+//! until the first `poll`, so nothing touches the network (not even DNS)
+//! before the TUI has drawn; later polls read whatever has arrived without
+//! blocking, until the reply is complete or the timeout passes.
+//!
+//! Plain sockets only: TLS streams can't be parked between polls.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::transport::{self, Endpoint, ReadError, Transport};
+
+/// `/api/show` replies carry the whole modelfile; stop reading well past that
+const MAX_REPLY: usize = 256 * 1024;
+
+pub enum Poll {
+    /// Still waiting; poll again
+    Pending,
+    /// The raw HTTP reply, headers included
+    Done(String),
+    /// Couldn't connect or send, or the reply was cut short
+    Unreachable,
+    TimedOut,
+}
+
+pub struct Probe {
+    endpoint: Endpoint,
+    /// Taken by the first poll, which connects and sends it
+    request: Option<String>,
+    stream: Option<Box<dyn Transport>>,
+    started: u64,
+    timeout_us: u64,
+    reply: Vec<u8>,
+}
+
+impl Probe {
+    /// A request to `endpoint`, sent on the first `poll`
+    pub fn new(endpoint: Endpoint, request: String, timeout_us: u64) -> Self {
+        Probe { endpoint, request: Some(request), stream: None, started: 0, timeout_us, reply: Vec::new() }
+    }
+
+    /// Connect and send on the first call, then read without blocking.
+    /// Anything but `Pending` is final.
+    pub fn poll(&mut self) -> Poll {
+        if let Some(request) = self.request.take() {
+            let Ok(stream) = transport::connect(&self.endpoint) else { return Poll::Unreachable; };
+            if stream.write_all(request.as_bytes()).is_err() {
+                return Poll::Unreachable;
+            }
+            self.stream = Some(stream);
+            self.started = libakuma::uptime();
+            return Poll::Pending;
+        }
+        let Some(stream) = self.stream.as_ref() else { return Poll::Unreachable; };
+        let mut buf = [0u8; 512];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(n) if n > 0 && self.reply.len() < MAX_REPLY => self.reply.extend_from_slice(&buf[..n]),
+                Err(ReadError::WouldBlock) => {
+                    if libakuma::uptime() - self.started > self.timeout_us {
+                        return Poll::TimedOut;
+                    }
+                    return Poll::Pending;
+                }
+                // EOF, an error or enough bytes: read what came
+                _ => break,
+            }
+        }
+        self.stream = None;
+        let reply = String::from_utf8_lossy(&self.reply).into_owned();
+        match reply.find("\r\n\r\n") {
+            Some(end) => {
+                super::auth::note_date(&reply[..end]);
+                Poll::Done(reply)
+            }
+            None => Poll::Unreachable,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! `<think>` blocks from reasoning models (deepseek-r1, qwq, ...)
+//!
+//! These models open their reply with a `<think>...</think>` section that
+//! can run to thousands of tokens. `ThinkFilter` sits between the stream
+//! and the output: the thinking is shown dimmed as it arrives with
+//! `/think show`, or by default only counted in the status line and folded
+//! into one line once it ends. The reply proper starts on screen with the
+//! first text after it. `strip` takes the blocks out of the finished reply,
+//! so they never reach the history sent back to the provider.
+//!
+//! Only a `<think>` before any other text counts, so a reply that merely
+//! mentions the tag is shown as it is.
+
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::config::COLOR_GRAY_DIM;
+use crate::tui_app;
+use crate::ui::output;
+
+const OPEN: &str = "<think>";
+const CLOSE: &str = "</think>";
+
+/// `/think show`: print the thinking (dimmed) instead of folding it
+pub static SHOW: AtomicBool = AtomicBool::new(false);
+
+#[derive(PartialEq)]
+enum Phase {
+    /// No reply text yet; a `<think>` here opens a block
+    Before,
+    Thinking,
+    Reply,
+}
+
+pub struct ThinkFilter {
+    phase: Phase,
+    /// Text that may be the start of a tag, kept until the next chunk says
+    held: String,
+    /// Estimated tokens of the current block
+    tokens: usize,
+}
+
+impl ThinkFilter {
+    /// `resumed`: the reply is already on screen, so text goes straight to it
+    pub fn new(resumed: bool) -> Self {
+        Self { phase: if resumed { Phase::Reply } else { Phase::Before }, held: String::new(), tokens: 0 }
+    }
+
+    /// Route a chunk of the stream. The reply is started on screen (after
+    /// `ttft_ms`, over `erase` columns of progress) with its first text.
+    pub fn feed(&mut self, text: &str, ttft_ms: u64, erase: u32) {
+        if self.phase == Phase::Reply {
+            output::reply_chunk(text);
+            return;
+        }
+        self.held.push_str(text);
+        loop {
+            match self.phase {
+                Phase::Before => {
+                    let trimmed = self.held.trim_start();
+                    if let Some(rest) = trimmed.strip_prefix(OPEN) {
+                        self.held = String::from(rest);
+                        self.phase = Phase::Thinking;
+                        self.tokens = 0;
+                        if SHOW.load(Ordering::Relaxed) {
+                            output::message(COLOR_GRAY_DIM, "\n");
+                        }
+                        continue;
+                    }
+                    if OPEN.starts_with(trimmed) {
+                        // Whitespace or part of the tag so far
+                        return;
+                    }
+                    self.phase = Phase::Reply;
+                    output::reply_start(ttft_ms, erase);
+                    output::reply_chunk(self.held.trim_start());
+                    self.held.clear();
+                    return;
+                }
+                Phase::Thinking => {
+                    if let Some(end) = self.held.find(CLOSE) {
+                        let rest = String::from(&self.held[end + CLOSE.len()..]);
+                        self.held.truncate(end);
+                        self.think(true);
+                        self.held = rest;
+                        self.phase = Phase::Before;
+                        continue;
+                    }
+                    self.think(false);
+                    return;
+                }
+                Phase::Reply => return,
+            }
+        }
+    }
+
+    /// Show or count the held thinking, keeping back what may be the start
+    /// of `</think>`; with `closed`, the block ended after it
+    fn think(&mut self, closed: bool) {
+        let keep = if closed { 0 } else { partial_tag_len(&self.held, CLOSE) };
+        let cut = self.held.len() - keep;
+        let text = String::from(&self.held[..cut]);
+        self.held.drain(..cut);
+        self.tokens += crate::app::tokens::count(&text);
+        if SHOW.load(Ordering::Relaxed) {
+            if !text.is_empty() {
+                output::message(COLOR_GRAY_DIM, &text);
+            }
+            if closed {
+                output::message(COLOR_GRAY_DIM, "\n\n");
+            }
+        } else if closed {
+            output::notification(COLOR_GRAY_DIM, &format!("Thought for ~{} tokens (/think show to see it)\n", self.tokens));
+            tui_app::update_streaming_status("[MEOW] streaming", 0, None);
+        } else {
+            tui_app::update_streaming_status(&format!("[MEOW] thinking ~{} tok", self.tokens), 0, None);
+        }
+    }
+}
+
+/// Length of the longest end of `text` that `tag` starts with
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len().min(text.len() + 1))
+        .rev()
+        .find(|&n| text.is_char_boundary(text.len() - n) && tag.starts_with(&text[text.len() - n..]))
+        .unwrap_or(0)
+}
+
+/// The reply without its leading think blocks. A block that never closed
+/// (the stream was cut off while thinking) takes the rest of the reply.
+pub fn strip(text: String) -> String {
+    if !text.trim_start().starts_with(OPEN) {
+        return text;
+    }
+    let mut rest = text.trim_start();
+    while let Some(inside) = rest.strip_prefix(OPEN) {
+        rest = match inside.find(CLOSE) {
+            Some(end) => inside[end + CLOSE.len()..].trim_start(),
+            None => "",
+        };
+    }
+    String::from(rest)
+}
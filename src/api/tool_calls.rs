@@ -0,0 +1,163 @@
+//! Structured tool calls from providers with `native_tools` on
+//!
+//! OpenAI streams a call as `tool_calls` deltas: the first carries the
+//! function name, later ones append pieces of the arguments string, all
+//! keyed by `index`. Ollama sends each call whole, with the arguments as an
+//! object. Either way the calls are collected while the reply streams and
+//! turned into the same ```json command blocks the text protocol uses, so
+//! the chat loop finds and runs them through `tools::execute_tool_command`
+//! like any other. An OpenAI call id rides along as `"call_id"`, so the
+//! result can go back as a "tool" message answering it.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::config::{ApiType, Provider};
+use crate::app::json_escape_to;
+use crate::tools::helpers::extract_string_field;
+
+struct Call {
+    index: usize,
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+#[derive(Default)]
+pub struct ToolCalls {
+    calls: Vec<Call>,
+}
+
+impl ToolCalls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pick up any tool calls in one stream line
+    pub fn feed(&mut self, line: &str, provider: &Provider) {
+        if !provider.native_tools {
+            return;
+        }
+        let Some(pos) = line.find("\"tool_calls\"") else { return };
+        let list = &line[pos..];
+        match provider.api_type {
+            ApiType::OpenAI => self.feed_openai(list),
+            ApiType::Ollama => self.feed_ollama(list),
+        }
+    }
+
+    fn feed_openai(&mut self, list: &str) {
+        // One entry per `"index"`; a delta usually carries a single call
+        let mut rest = list;
+        while let Some(pos) = rest.find("\"index\"") {
+            let entry = &rest[pos + "\"index\"".len()..];
+            let end = entry.find("\"index\"").unwrap_or(entry.len());
+            let entry_text = &entry[..end];
+            let index = number_after_colon(entry_text).unwrap_or(0);
+            let call = match self.calls.iter().position(|c| c.index == index) {
+                Some(i) => &mut self.calls[i],
+                None => {
+                    self.calls.push(Call { index, id: String::new(), name: String::new(), arguments: String::new() });
+                    self.calls.last_mut().unwrap()
+                }
+            };
+            // Only the first delta of a call carries its id
+            if call.id.is_empty() {
+                if let Some(id) = extract_string_field(entry_text, "id") {
+                    call.id = id;
+                }
+            }
+            if let Some(name) = extract_string_field(entry_text, "name") {
+                call.name.push_str(&name);
+            }
+            if let Some(args) = extract_string_field(entry_text, "arguments") {
+                call.arguments.push_str(&args);
+            }
+            rest = &entry[end..];
+        }
+    }
+
+    fn feed_ollama(&mut self, list: &str) {
+        let mut rest = list;
+        while let Some(pos) = rest.find("\"function\"") {
+            let entry = &rest[pos + "\"function\"".len()..];
+            let Some(name) = extract_string_field(entry, "name") else { break };
+            let arguments = entry.find("\"arguments\"")
+                .and_then(|p| object_at(&entry[p + "\"arguments\"".len()..]))
+                .unwrap_or("{}");
+            let index = self.calls.len();
+            self.calls.push(Call { index, id: String::new(), name, arguments: String::from(arguments) });
+            rest = entry;
+        }
+    }
+
+    /// Append the collected calls to the reply as command blocks
+    pub fn append_to(&self, text: &mut String) {
+        for call in self.calls.iter().filter(|c| !c.name.is_empty()) {
+            let args = call.arguments.trim();
+            let args = if args.is_empty() { "{}" } else { args };
+            let call_id = if call.id.is_empty() { String::new() } else { format!(",\"call_id\":\"{}\"", call.id) };
+            text.push_str(&format!(
+                "\n```json\n{{\"command\":{{\"tool\":\"{}\",\"args\":{}}}{}}}\n```\n",
+                call.name, args, call_id
+            ));
+        }
+    }
+}
+
+/// The `tool_calls` array for the assistant message that made `commands`
+/// (command blocks from `append_to`), or None if none of them has a call id
+pub fn assistant_calls(commands: &[&str]) -> Option<String> {
+    let mut out = String::from("[");
+    for json in commands {
+        let Some(id) = extract_string_field(json, "call_id") else { continue };
+        let name = extract_string_field(json, "tool").unwrap_or_default();
+        let args = json.find("\"args\"").and_then(|p| object_at(&json[p + "\"args\"".len()..])).unwrap_or("{}");
+        if out.len() > 1 { out.push(','); }
+        out.push_str(&format!("{{\"id\":\"{}\",\"type\":\"function\",\"function\":{{\"name\":\"{}\",\"arguments\":\"", id, name));
+        json_escape_to(args, &mut out);
+        out.push_str("\"}}");
+    }
+    out.push(']');
+    if out.len() > 2 { Some(out) } else { None }
+}
+
+fn number_after_colon(s: &str) -> Option<usize> {
+    let s = s.trim_start().strip_prefix(':')?.trim_start();
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+/// The JSON object (or array) right after a `:`, brackets inside strings
+/// ignored
+pub fn object_at(s: &str) -> Option<&str> {
+    let s = s.trim_start().strip_prefix(':')?.trim_start();
+    if !s.starts_with(['{', '[']) {
+        return None;
+    }
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&s[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,122 @@
+//! Provider connections
+//!
+//! The provider's `base_url` scheme picks the transport:
+//!
+//! - `http://host:port/...`  plain TCP
+//! - `https://host:port/...` TCP wrapped in TLS by `HttpStreamTls`
+//! - `unix:///path/to.sock`  unix-domain socket
+//! - `vsock://cid:port/...`  virtio vsock (the host is usually cid 2)
+//!
+//! Plain byte streams go through the `Transport` trait so the HTTP code
+//! doesn't care what is underneath. TLS stays separate because
+//! `HttpStreamTls` owns its `TcpStream` and speaks HTTP itself.
+//!
+//! libakuma currently exposes TCP only, so unix and vsock endpoints are
+//! parsed and rejected up front with a clear error; supporting one is an
+//! `impl Transport` plus an arm in `connect`.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::format;
+
+use libakuma::net::{resolve, ErrorKind, TcpStream};
+
+use crate::config::Provider;
+
+pub enum ReadError {
+    /// Nothing to read yet (or the read timed out); try again
+    WouldBlock,
+    Failed,
+}
+
+/// A connected byte stream to a provider
+pub trait Transport {
+    fn write_all(&self, data: &[u8]) -> Result<(), &'static str>;
+    /// Ok(0) means the peer closed the connection
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ReadError>;
+}
+
+impl Transport for TcpStream {
+    fn write_all(&self, data: &[u8]) -> Result<(), &'static str> {
+        TcpStream::write_all(self, data).map_err(|_| "Write failed")
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        TcpStream::read(self, buf).map_err(|e| {
+            if e.kind == ErrorKind::WouldBlock || e.kind == ErrorKind::TimedOut {
+                ReadError::WouldBlock
+            } else {
+                ReadError::Failed
+            }
+        })
+    }
+}
+
+pub enum Endpoint {
+    Tcp { host: String, port: u16, tls: bool },
+    Unix { path: String },
+    Vsock { cid: u32, port: u32 },
+}
+
+impl Endpoint {
+    pub fn for_provider(provider: &Provider) -> Option<Self> {
+        let url = provider.base_url.as_str();
+        if let Some(path) = url.strip_prefix("unix://") {
+            if path.is_empty() { return None; }
+            return Some(Endpoint::Unix { path: String::from(path) });
+        }
+        if let Some(rest) = url.strip_prefix("vsock://") {
+            let authority = rest.split('/').next().unwrap_or("");
+            let (cid, port) = authority.split_once(':')?;
+            return Some(Endpoint::Vsock { cid: cid.parse().ok()?, port: port.parse().ok()? });
+        }
+        let (host, port) = provider.host_port()?;
+        Some(Endpoint::Tcp { host, port, tls: provider.is_https() })
+    }
+
+    /// Value for the HTTP Host header
+    pub fn host_header(&self) -> String {
+        match self {
+            Endpoint::Tcp { host, port, .. } => format!("{}:{}", host, port),
+            Endpoint::Unix { .. } | Endpoint::Vsock { .. } => String::from("localhost"),
+        }
+    }
+
+    /// Error for transports this libakuma build can't open
+    pub fn unsupported(&self) -> Option<&'static str> {
+        match self {
+            Endpoint::Tcp { .. } => None,
+            Endpoint::Unix { .. } => Some("unix:// providers need unix socket support in libakuma"),
+            Endpoint::Vsock { .. } => Some("vsock:// providers need vsock support in libakuma"),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Endpoint::Tcp { host, port, tls } => format!("{}://{}:{}", if *tls { "https" } else { "http" }, host, port),
+            Endpoint::Unix { path } => format!("unix://{}", path),
+            Endpoint::Vsock { cid, port } => format!("vsock://{}:{}", cid, port),
+        }
+    }
+}
+
+/// Open a TCP connection to a `Tcp` endpoint (for TLS callers)
+pub fn connect_tcp(endpoint: &Endpoint) -> Result<TcpStream, String> {
+    match endpoint {
+        Endpoint::Tcp { host, port, .. } => {
+            let ip = resolve(host).map_err(|_| format!("DNS resolution failed for: {}", host))?;
+            let addr_str = format!("{}.{}.{}.{}:{}", ip[0], ip[1], ip[2], ip[3], port);
+            TcpStream::connect(&addr_str).map_err(|_| format!("Connection failed to: {}", addr_str))
+        }
+        other => Err(format!("Not a TCP endpoint: {}", other.describe())),
+    }
+}
+
+/// Open a plain byte stream to the endpoint. TLS endpoints get the bare TCP
+/// socket; wrapping it is up to the caller.
+pub fn connect(endpoint: &Endpoint) -> Result<Box<dyn Transport>, String> {
+    match endpoint {
+        Endpoint::Tcp { .. } => Ok(Box::new(connect_tcp(endpoint)?)),
+        other => Err(format!("{}: {}", other.describe(), other.unsupported().unwrap_or("unsupported transport"))),
+    }
+}
@@ -7,11 +7,22 @@ pub struct StreamStats {
     pub fakes: usize,
 }
 
+/// Per-request sampling overrides; None leaves the provider's default
+#[derive(Clone, Copy, Default)]
+pub struct Sampling {
+    pub temperature: Option<f32>,
+    pub seed: Option<u64>,
+    /// Reply length cap instead of the /verbosity one
+    pub max_tokens: Option<usize>,
+}
+
 pub enum StreamResponse {
     /// Response completed normally (server sent done signal)
     Complete(String, StreamStats),
     /// Response was interrupted mid-stream (connection closed before done signal)
     Partial(String, StreamStats),
+    /// The user stopped the stream (soft stop); what arrived is the answer
+    Stopped(String, StreamStats),
 }
 
 #[derive(Debug)]
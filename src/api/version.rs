@@ -0,0 +1,115 @@
+//! Provider version probe
+//!
+//! When the TUI starts or the provider changes, the provider is asked what
+//! it is running: Ollama answers `GET /api/version`, OpenAI-compatible
+//! servers name themselves in the `openai-version` or `server` header of
+//! `GET /models`. Like the warmup, the probe runs in the background and the
+//! idle loop calls `poll`, so a slow or dead provider never holds up
+//! startup; it is reported as unreachable instead. The answer is cached for
+//! `/provider`.
+//!
+//! TLS streams can't be parked between polls, so HTTPS providers aren't
+//! probed; they show as "version unknown".
+
+use alloc::string::String;
+use alloc::format;
+
+use crate::config::{ApiType, Provider};
+use crate::tools::helpers::extract_string_field;
+use super::client::header_value;
+use super::probe::{self, Probe};
+use super::transport::Endpoint;
+
+/// Long enough for a busy local server, short enough to matter at startup
+const PROBE_TIMEOUT_US: u64 = 3_000_000;
+
+/// (provider name, "linked to …" line) from the last finished probe
+static mut LAST: Option<(String, String)> = None;
+static mut CURRENT: Option<(Probe, ApiType)> = None;
+/// What the in-flight probe reports as, "Ollama @ 10.0.2.2" style
+static mut TARGET: (String, String) = (String::new(), String::new());
+
+fn current() -> &'static mut Option<(Probe, ApiType)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(CURRENT) }
+}
+
+fn target() -> &'static mut (String, String) {
+    unsafe { &mut *core::ptr::addr_of_mut!(TARGET) }
+}
+
+fn finish(text: String) -> String {
+    *current() = None;
+    let (name, _) = target();
+    unsafe { *core::ptr::addr_of_mut!(LAST) = Some((name.clone(), text.clone())); }
+    text
+}
+
+fn kind(api_type: &ApiType) -> &'static str {
+    match api_type {
+        ApiType::Ollama => "Ollama",
+        ApiType::OpenAI => "OpenAI-compatible",
+    }
+}
+
+/// Start probing `provider`, replacing any probe in flight. Nothing is sent
+/// until the first `poll`. Returns the line to show right away when there is
+/// nothing to wait for (HTTPS, a bad base_url).
+pub fn start(provider: &Provider) -> Option<String> {
+    *current() = None;
+    let host = provider.host_port().map(|(h, _)| h).unwrap_or_else(|| provider.base_url.clone());
+    *target() = (provider.name.clone(), format!("{} @ {}", kind(&provider.api_type), host));
+    let Some(endpoint) = Endpoint::for_provider(provider) else {
+        return Some(finish(format!("{} unreachable (bad base_url)", target().1)));
+    };
+    if matches!(endpoint, Endpoint::Tcp { tls: true, .. }) {
+        return Some(finish(format!("{}, version unknown", target().1)));
+    }
+    let path = match provider.api_type {
+        ApiType::Ollama => String::from("/api/version"),
+        ApiType::OpenAI => match provider.base_path().trim_end_matches('/') {
+            "" => String::from("/v1/models"),
+            base => format!("{}/models", base),
+        },
+    };
+    let mut request = format!("GET {} HTTP/1.0\r\nHost: {}\r\n", path, endpoint.host_header());
+    // Unsigned while the time is unknown; the reply's Date header still counts
+    for (name, value) in provider.extra_headers.iter().cloned().chain(super::auth::headers(provider, |_| {}).unwrap_or_default()) {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("Connection: close\r\n\r\n");
+    *current() = Some((Probe::new(endpoint, request, PROBE_TIMEOUT_US), provider.api_type.clone()));
+    None
+}
+
+/// Check on the probe without blocking (the first call connects); the line
+/// to show once it is done
+pub fn poll() -> Option<String> {
+    let (probe, api_type) = current().as_mut()?;
+    let reply = match probe.poll() {
+        probe::Poll::Pending => return None,
+        probe::Poll::Done(reply) => reply,
+        probe::Poll::Unreachable => return Some(finish(format!("{} unreachable", target().1))),
+        probe::Poll::TimedOut => {
+            return Some(finish(format!("{} unreachable (no answer in {}s)", target().1, PROBE_TIMEOUT_US / 1_000_000)));
+        }
+    };
+    let (head, body) = reply.split_once("\r\n\r\n").unwrap_or((reply.as_str(), ""));
+    let version = match api_type {
+        ApiType::Ollama => extract_string_field(body, "version"),
+        ApiType::OpenAI => header_value(head, "openai-version").or_else(|| header_value(head, "server")).map(String::from),
+    };
+    let text = match version {
+        Some(v) => {
+            let (kind, host) = target().1.split_once(" @ ").unwrap_or((target().1.as_str(), ""));
+            format!("linked to {} {} @ {}", kind, v, host)
+        }
+        None => format!("linked to {}", target().1),
+    };
+    Some(finish(text))
+}
+
+/// The last probe result for `provider`, if it has been probed
+pub fn cached(provider: &Provider) -> Option<String> {
+    let last = unsafe { (*core::ptr::addr_of!(LAST)).as_ref() }?;
+    if last.0 == provider.name { Some(last.1.clone()) } else { None }
+}
@@ -0,0 +1,74 @@
+//! Cold-start warmup
+//!
+//! With `warmup` on, a tiny request (the system prompt plus "ready?", one
+//! token of output) goes out when the TUI starts or the model changes, so
+//! the provider loads the model and caches the prompt while the user is
+//! still typing. It runs in the background: the TUI's idle loop calls `poll`,
+//! and the reply is dropped as soon as its first bytes arrive, since by then
+//! the prompt has been evaluated. Any real request cancels it.
+//!
+//! Only plain-socket providers are warmed. TLS endpoints are remote APIs
+//! with nothing to load, and the TLS stream can't be parked between polls.
+
+use alloc::boxed::Box;
+
+use crate::app::Message;
+use crate::config::Provider;
+use super::client;
+use super::transport::{self, Endpoint, ReadError, Transport};
+use super::types::Sampling;
+
+/// Give up on a warmup that hasn't answered by then (large model, slow disk)
+const WARMUP_TIMEOUT_US: u64 = 120_000_000;
+
+struct Warmup {
+    stream: Box<dyn Transport>,
+    started: u64,
+}
+
+static mut CURRENT: Option<Warmup> = None;
+
+fn current() -> &'static mut Option<Warmup> {
+    unsafe { &mut *core::ptr::addr_of_mut!(CURRENT) }
+}
+
+/// Send the priming request, replacing any warmup in flight. False if the
+/// provider isn't warmed or the request couldn't be sent.
+pub fn start(model: &str, provider: &Provider, system_prompt: &str) -> bool {
+    cancel();
+    let Some(endpoint) = Endpoint::for_provider(provider) else { return false; };
+    if matches!(endpoint, Endpoint::Tcp { tls: true, .. }) || endpoint.unsupported().is_some() {
+        return false;
+    }
+    let Ok(stream) = transport::connect(&endpoint) else { return false; };
+
+    let system = Message::new("system", system_prompt);
+    let ready = Message::new("user", "ready?");
+    let messages = [&system, &ready];
+    let sampling = Sampling { max_tokens: Some(1), ..Sampling::default() };
+    let Ok((path, body)) = client::build_chat_request(model, provider, &messages, &sampling) else { return false; };
+    if client::send_post_request(stream.as_ref(), &path, &body, &endpoint, provider).is_err() {
+        return false;
+    }
+    *current() = Some(Warmup { stream, started: libakuma::uptime() });
+    true
+}
+
+/// Check on the warmup without blocking; true while it is still in flight
+pub fn poll() -> bool {
+    let Some(w) = current().as_ref() else { return false; };
+    let mut buf = [0u8; 256];
+    let finished = match w.stream.read(&mut buf) {
+        Err(ReadError::WouldBlock) => libakuma::uptime() - w.started > WARMUP_TIMEOUT_US,
+        // First bytes, EOF or an error: either way there is nothing left to wait for
+        _ => true,
+    };
+    if finished {
+        *current() = None;
+    }
+    !finished
+}
+
+pub fn cancel() {
+    *current() = None;
+}
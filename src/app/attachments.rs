@@ -0,0 +1,266 @@
+//! Attachments: large tool outputs and `@file` expansions
+//!
+//! Instead of pasting a big tool result into the history, the message holds
+//! it as an attachment and its text only names it ("attachment #3"). A
+//! request inlines the attachments of the last few messages and sends the
+//! older ones as a one-line stub, so a file read twenty steps ago stops
+//! costing context without the message around it going away. When the
+//! context fills up, attachment bodies are dropped (oldest first) before
+//! the conversation itself is compacted.
+//!
+//! Sessions and crash dumps store messages with their attachments inlined,
+//! so a loaded session has the text but no attachments.
+//!
+//! Images (`--image`, `/image`, `@shot.png`) are kept base64-encoded on the
+//! user message and go out the way the provider takes them: an Ollama
+//! `images` array or OpenAI `image_url` content parts. Like attachments they
+//! are only sent while the message is recent, and are never stored.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::history::{calculate_history_tokens, Content, Message};
+
+/// Tool output at least this long becomes an attachment
+pub const MIN_ATTACH_BYTES: usize = 4 * 1024;
+/// Attachments of this many of the newest messages go out in full
+pub const INLINE_RECENT: usize = 6;
+
+/// Largest image file that will be attached
+pub const MAX_IMAGE_BYTES: usize = 4 * 1024 * 1024;
+/// Rough tokens a provider charges for one image
+const IMAGE_TOKENS: usize = 768;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+/// Images attached with `--image` or `/image`, for the next message
+static mut PENDING_IMAGES: Vec<Image> = Vec::new();
+
+fn pending_images() -> &'static mut Vec<Image> {
+    unsafe { &mut *core::ptr::addr_of_mut!(PENDING_IMAGES) }
+}
+
+#[derive(Clone)]
+pub struct Attachment {
+    pub id: u32,
+    /// What it holds: "FileRead src/main.rs", "@notes.md"
+    pub label: String,
+    /// None once compaction has dropped it
+    pub body: Option<Content>,
+    /// Size of the body, kept after it is dropped
+    pub bytes: usize,
+}
+
+impl Attachment {
+    pub fn new(label: &str, text: &str) -> Self {
+        let _t = crate::heap::tag(crate::heap::Tag::History);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            label: String::from(label),
+            body: Some(Content::from(text)),
+            bytes: text.len(),
+        }
+    }
+
+    /// How the message text refers to it
+    pub fn reference(&self) -> String {
+        format!("attachment #{} ({}, {})", self.id, self.label, size(self.bytes))
+    }
+
+    /// Feed the attachment as it goes out to `f`: the body when `inline`
+    /// and still held, otherwise a stub saying it was left out
+    pub fn pieces(&self, inline: bool, mut f: impl FnMut(&str)) {
+        match (&self.body, inline) {
+            (Some(body), true) => {
+                f(&format!("\n\n[Attachment #{}: {}]\n", self.id, self.label));
+                for chunk in body.chunks() {
+                    f(chunk);
+                }
+                f(&format!("\n[End Attachment #{}]", self.id));
+            }
+            _ => f(&format!(
+                "\n\n[Attachment #{}: {}, {} - no longer included; run the tool again if you need it]",
+                self.id, self.label, size(self.bytes)
+            )),
+        }
+    }
+
+    /// Estimated tokens for `pieces`
+    pub fn token_count(&self, inline: bool) -> usize {
+        match (&self.body, inline) {
+            (Some(body), true) => body.token_count() + 16,
+            _ => 32,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Image {
+    pub id: u32,
+    /// The path it was read from
+    pub label: String,
+    pub mime: &'static str,
+    /// Base64 of the file; None once compaction has dropped it
+    pub data: Option<Content>,
+    pub bytes: usize,
+}
+
+impl Image {
+    /// Read and encode the image at `path`, which must be in the sandbox
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mime = image_mime(path).ok_or_else(|| format!("{} is not a png, jpeg, gif or webp image", path))?;
+        let resolved = crate::tools::context::resolve_path(path)
+            .ok_or_else(|| format!("{} is outside the sandbox", path))?;
+        let bytes = read_image(&resolved, path)?;
+        let _t = crate::heap::tag(crate::heap::Tag::History);
+        let mut data = Content::default();
+        base64_encode_to(&bytes, &mut data);
+        Ok(Self { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), label: String::from(path), mime, data: Some(data), bytes: bytes.len() })
+    }
+
+    /// How the message text refers to it
+    pub fn reference(&self) -> String {
+        format!("image #{} ({}, {})", self.id, self.label, size(self.bytes))
+    }
+
+    /// Estimated tokens when sent; nothing once left out
+    pub fn token_count(&self, inline: bool) -> usize {
+        if inline && self.data.is_some() { IMAGE_TOKENS } else { 0 }
+    }
+}
+
+/// The MIME type for an image path, by its extension
+pub fn image_mime(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn read_image(resolved: &str, path: &str) -> Result<Vec<u8>, String> {
+    use crate::tools::fs::{read_file, ReadFileError};
+    read_file(resolved, MAX_IMAGE_BYTES).map_err(|e| match e {
+        ReadFileError::NotFound => format!("cannot open {}", path),
+        ReadFileError::TooLarge => format!("{} is over the {} limit for images", path, self::size(MAX_IMAGE_BYTES)),
+        ReadFileError::Unreadable => format!("cannot read {}", path),
+    })
+}
+
+fn base64_encode_to(bytes: &[u8], out: &mut Content) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    // Encoded a block at a time so the Content chunks fill without a big copy
+    let mut block = String::with_capacity(4096);
+    for group in bytes.chunks(3) {
+        let n = (group[0] as u32) << 16
+            | (*group.get(1).unwrap_or(&0) as u32) << 8
+            | *group.get(2).unwrap_or(&0) as u32;
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            block.push(if i <= group.len() { ALPHABET[(n >> shift) as usize & 63] as char } else { '=' });
+        }
+        if block.len() >= 4092 {
+            out.push_str(&block);
+            block.clear();
+        }
+    }
+    out.push_str(&block);
+}
+
+/// Attach the image at `path` to the next message. Returns how it is
+/// referred to.
+pub fn queue_image(path: &str) -> Result<String, String> {
+    let image = Image::load(path)?;
+    let reference = image.reference();
+    pending_images().push(image);
+    Ok(reference)
+}
+
+/// Queue images again, ahead of any queued since; those whose data was
+/// dropped stay dropped
+pub fn requeue_images(images: Vec<Image>) {
+    let pending = pending_images();
+    let mut kept: Vec<Image> = images.into_iter().filter(|i| i.data.is_some()).collect();
+    kept.append(pending);
+    *pending = kept;
+}
+
+/// The queued images, for the message being sent
+pub fn take_images() -> Vec<Image> {
+    core::mem::take(pending_images())
+}
+
+/// Whether message `pos` of `len` has its attachments inlined
+pub fn is_inlined(pos: usize, len: usize) -> bool {
+    pos + INLINE_RECENT >= len
+}
+
+fn size(bytes: usize) -> String {
+    if bytes >= 1024 { format!("{}KB", bytes / 1024) } else { format!("{} bytes", bytes) }
+}
+
+/// Attach every `@path` in `text` that names a readable file, read the way
+/// FileRead would. Anything else starting with `@` is left alone.
+pub fn expand_mentions(text: &str) -> Vec<Attachment> {
+    let mut found: Vec<Attachment> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(path) = mention(word) else { continue };
+        if image_mime(path).is_some() || found.iter().any(|a| a.label[1..] == *path) {
+            continue;
+        }
+        let result = crate::tools::fs::tool_file_read(path);
+        if result.success {
+            found.push(Attachment::new(&format!("@{}", path), &result.output));
+        }
+    }
+    found
+}
+
+/// Load every `@path` in `text` that names an image not already in `have`.
+/// Images that can't be read are left as text, with a warning.
+pub fn image_mentions(text: &str, have: &[Image]) -> Vec<Image> {
+    let mut found: Vec<Image> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(path) = mention(word) else { continue };
+        if image_mime(path).is_none() || found.iter().chain(have).any(|i| i.label == path) {
+            continue;
+        }
+        match Image::load(path) {
+            Ok(image) => found.push(image),
+            Err(e) => crate::ui::output::notification(crate::config::COLOR_YELLOW, &format!("Not attached: {}\n", e)),
+        }
+    }
+    found
+}
+
+/// The path in an `@path` word, without trailing punctuation
+fn mention(word: &str) -> Option<&str> {
+    let path = word.strip_prefix('@')?;
+    let path = path.trim_end_matches(|c: char| matches!(c, ',' | '.' | ';' | ':' | ')' | '?' | '!'));
+    (!path.is_empty()).then_some(path)
+}
+
+/// Drop attachment bodies, oldest first, until the history is estimated at
+/// no more than `target` tokens. Returns how many were dropped.
+pub fn shed(history: &mut [Message], target: usize) -> usize {
+    let mut dropped = 0;
+    for i in 0..history.len() {
+        if calculate_history_tokens(history) <= target {
+            break;
+        }
+        for att in history[i].attachments.iter_mut() {
+            if att.body.take().is_some() {
+                dropped += 1;
+            }
+        }
+        for image in history[i].images.iter_mut() {
+            if image.data.take().is_some() {
+                dropped += 1;
+            }
+        }
+    }
+    dropped
+}
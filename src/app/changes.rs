@@ -0,0 +1,245 @@
+//! Per-turn workspace change summary
+//!
+//! Before a file tool runs, the files it can touch are snapshotted (once per
+//! turn, so the first snapshot is the state the turn started from). When the
+//! turn ends the snapshots are compared against disk and folded into one
+//! line like `modified: src/config.rs (+12/-3), created: tests/parser.rs (+40)`.
+//! The line is printed, and handed to the model with the next user message
+//! so it knows what the previous turn left behind.
+//!
+//! Only the file tools are journaled; whatever a Shell command writes is not
+//! seen here. Every file a turn changed is also remembered for the rest of
+//! the session, for `/checkpoint`, and open checkpoints copy a file before
+//! its first change.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::{open, close, read_fd, fstat, open_flags};
+
+use crate::tools::context::{get_working_dir, resolve_path};
+use crate::tools::helpers::extract_string_field;
+
+/// Files larger than this are tracked as changed/unchanged without line counts
+const MAX_SNAPSHOT_SIZE: usize = 256 * 1024;
+
+#[derive(PartialEq)]
+enum Snapshot {
+    Missing,
+    Text(String),
+    /// Exists, but too large or not UTF-8; compared by size only
+    Opaque(usize),
+}
+
+/// Snapshots taken this turn, in the order the files were first touched
+static mut JOURNAL: Vec<(String, Snapshot)> = Vec::new();
+/// Summary of the last turn, waiting to be sent with the next user message
+static mut PENDING: Option<String> = None;
+/// Every file changed by a turn since meow started, first change first
+static mut SESSION_FILES: Vec<String> = Vec::new();
+
+fn journal() -> &'static mut Vec<(String, Snapshot)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(JOURNAL) }
+}
+
+fn pending() -> &'static mut Option<String> {
+    unsafe { &mut *core::ptr::addr_of_mut!(PENDING) }
+}
+
+/// A tab's snapshots and pending summary while another tab is active. The
+/// session's file list is shared: all tabs work on the same files.
+#[derive(Default)]
+pub struct Parked {
+    journal: Vec<(String, Snapshot)>,
+    pending: Option<String>,
+}
+
+/// Take the journal out for a tab that is being put aside
+pub fn park() -> Parked {
+    Parked { journal: core::mem::take(journal()), pending: pending().take() }
+}
+
+pub fn unpark(parked: Parked) {
+    *journal() = parked.journal;
+    *pending() = parked.pending;
+}
+
+fn session_files_mut() -> &'static mut Vec<String> {
+    unsafe { &mut *core::ptr::addr_of_mut!(SESSION_FILES) }
+}
+
+/// Absolute paths of the files changed this session, created and deleted
+/// ones included
+pub fn session_files() -> Vec<String> {
+    session_files_mut().clone()
+}
+
+/// Paths a tool call may create, modify or remove
+fn touched_paths(tool_json: &str) -> Vec<String> {
+    let Some(tool) = extract_string_field(tool_json, "tool") else { return Vec::new(); };
+    let fields: &[&str] = match tool.as_str() {
+        "FileWrite" | "FileAppend" | "FileEdit" | "FileDelete" => &["filename"],
+        "FileRename" => &["source_filename", "destination_filename"],
+        "FileCopy" => &["destination"],
+        "FileMove" => &["source", "destination"],
+        _ => &[],
+    };
+    fields.iter()
+        .filter_map(|f| extract_string_field(tool_json, f))
+        .filter_map(|p| resolve_path(&p))
+        .collect()
+}
+
+/// Called before a tool runs; remembers the pre-turn state of its files
+pub fn before_tool(tool_json: &str) {
+    for path in touched_paths(tool_json) {
+        super::checkpoint::before_change(&path);
+        if journal().iter().any(|(p, _)| *p == path) { continue; }
+        let snap = snapshot(&path);
+        journal().push((path, snap));
+    }
+}
+
+/// Line counts added/removed, ignoring order (a cheap stand-in for a diff)
+fn line_delta(old: &str, new: &str) -> (usize, usize) {
+    let mut counts: BTreeMap<&str, isize> = BTreeMap::new();
+    for l in old.lines() { *counts.entry(l).or_insert(0) -= 1; }
+    for l in new.lines() { *counts.entry(l).or_insert(0) += 1; }
+    let added = counts.values().filter(|n| **n > 0).map(|n| *n as usize).sum();
+    let removed = counts.values().filter(|n| **n < 0).map(|n| n.unsigned_abs()).sum();
+    (added, removed)
+}
+
+/// Path as shown to the user: relative to the working directory when inside it
+fn display_path(path: &str) -> &str {
+    let cwd = get_working_dir();
+    if cwd != "/" {
+        if let Some(rest) = path.strip_prefix(cwd.as_str()).and_then(|r| r.strip_prefix('/')) {
+            return rest;
+        }
+    }
+    path
+}
+
+/// Compare this turn's snapshots with disk. Returns the summary line (also
+/// kept for the next turn), or None when nothing changed.
+pub fn finish_turn() -> Option<String> {
+    let entries = core::mem::take(journal());
+    let mut created: Vec<(&str, &Snapshot)> = Vec::new();
+    let mut deleted: Vec<(&str, &Snapshot)> = Vec::new();
+    let mut parts: Vec<String> = Vec::new();
+    let now: Vec<Snapshot> = entries.iter().map(|(p, _)| snapshot(p)).collect();
+    for ((path, before), after) in entries.iter().zip(now.iter()) {
+        if before != after {
+            super::summary::record_file(display_path(path));
+            if !session_files_mut().contains(path) {
+                session_files_mut().push(path.clone());
+            }
+        }
+    }
+
+    for ((path, before), after) in entries.iter().zip(now.iter()) {
+        match (before, after) {
+            (Snapshot::Missing, Snapshot::Missing) => {}
+            (Snapshot::Missing, _) => created.push((path.as_str(), after)),
+            (_, Snapshot::Missing) => deleted.push((path.as_str(), before)),
+            (Snapshot::Text(old), Snapshot::Text(new)) if old != new => {
+                let (a, r) = line_delta(old, new);
+                parts.push(format!("modified: {} (+{}/-{})", display_path(path), a, r));
+            }
+            (b, a) if b != a => parts.push(format!("modified: {}", display_path(path))),
+            _ => {}
+        }
+    }
+
+    // A file that vanished while an identical one appeared was moved
+    deleted.retain(|(from, old)| {
+        match created.iter().position(|(_, new)| new == old) {
+            Some(i) => {
+                let (to, _) = created.remove(i);
+                parts.push(format!("renamed: {} -> {}", display_path(from), display_path(to)));
+                false
+            }
+            None => true,
+        }
+    });
+    for (path, snap) in created {
+        match snap {
+            Snapshot::Text(t) => parts.push(format!("created: {} (+{})", display_path(path), t.lines().count())),
+            _ => parts.push(format!("created: {}", display_path(path))),
+        }
+    }
+    for (path, snap) in deleted {
+        match snap {
+            Snapshot::Text(t) => parts.push(format!("deleted: {} (-{})", display_path(path), t.lines().count())),
+            _ => parts.push(format!("deleted: {}", display_path(path))),
+        }
+    }
+
+    if parts.is_empty() {
+        *pending() = None;
+        return None;
+    }
+    let summary = parts.join(", ");
+    *pending() = Some(summary.clone());
+    Some(summary)
+}
+
+/// Last turn's summary, once; None if it changed nothing
+pub fn take_pending() -> Option<String> {
+    pending().take()
+}
+
+/// Put a summary that was sent with a message taken back (`/retry`) in
+/// front of whatever is pending, so the message sent again still has it
+pub fn carry_over(summary: &str) {
+    *pending() = Some(match pending().take() {
+        Some(newer) => format!("{}, {}", summary, newer),
+        None => String::from(summary),
+    });
+}
+
+/// Forget everything, e.g. when the conversation is cleared
+pub fn reset() {
+    journal().clear();
+    *pending() = None;
+}
+
+fn snapshot(path: &str) -> Snapshot {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return Snapshot::Missing;
+    }
+    let size = match fstat(fd) {
+        Ok(s) => s.st_size as usize,
+        Err(_) => {
+            close(fd);
+            return Snapshot::Opaque(0);
+        }
+    };
+    if size > MAX_SNAPSHOT_SIZE {
+        close(fd);
+        return Snapshot::Opaque(size);
+    }
+    let mut buf = alloc::vec![0u8; size];
+    let mut filled = 0;
+    while filled < size {
+        let n = read_fd(fd, &mut buf[filled..]);
+        if n < 0 {
+            close(fd);
+            return Snapshot::Opaque(size);
+        }
+        if n == 0 {
+            break;
+        }
+        filled += n as usize;
+    }
+    close(fd);
+    buf.truncate(filled);
+    match String::from_utf8(buf) {
+        Ok(text) => Snapshot::Text(text),
+        Err(_) => Snapshot::Opaque(size),
+    }
+}
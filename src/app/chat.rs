@@ -1,15 +1,135 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
-use core::sync::atomic::Ordering;
 
-use crate::config::{Provider, DEFAULT_CONTEXT_WINDOW, COLOR_PEARL, COLOR_GREEN_LIGHT, COLOR_GRAY_BRIGHT, COLOR_RESET, COLOR_YELLOW, TOKEN_LIMIT_FOR_COMPACTION};
+use crate::config::{Provider, DEFAULT_CONTEXT_WINDOW, COLOR_PEARL, COLOR_GREEN_LIGHT, COLOR_GRAY_BRIGHT, COLOR_RESET, COLOR_YELLOW, compaction_threshold};
 use crate::api::{self, StreamResponse};
 use crate::tools;
-use crate::tui_app;
-use super::history::{Message, trim_history, compact_history, calculate_history_tokens};
+use crate::ui::output;
+use super::history::{Message, trim_history, compact_history, calculate_history_tokens, drop_pending_calls};
+use super::{attachments, changes, latency, metrics, narration, postprocess, state, summary};
 
 const MAX_TOOL_ITERATIONS: usize = 20;
+/// Cut-off responses continued in a row before the partial text is accepted
+const MAX_CONTINUATIONS: usize = 3;
+/// Unusable replies in a row retried with adjusted sampling before giving up
+const MAX_SAMPLING_RETRIES: usize = 2;
+/// Temperature for the first sampling retry, raised by `TEMPERATURE_STEP` per retry
+const RETRY_TEMPERATURE: f32 = 0.85;
+const TEMPERATURE_STEP: f32 = 0.15;
+/// Temperature for `/retry hot`
+pub const HOT_TEMPERATURE: f32 = 1.2;
+
+/// Sent with the user's message after a turn that changed files
+const CHANGES_NOTE: &str = "\n\n[System: Workspace changes from your last turn: ";
+
+/// The last message the user sent, for `/retry`
+static mut LAST_PROMPT: String = String::new();
+/// The prompt `/retry` last sent again and the final answer it replaced,
+/// for `/diff-answers`
+static mut REPLACED: Option<(String, String)> = None;
+/// Sampling for the whole of the next turn, set by `/retry`
+static mut NEXT_SAMPLING: Option<api::Sampling> = None;
+
+fn last_prompt() -> &'static mut String {
+    unsafe { &mut *core::ptr::addr_of_mut!(LAST_PROMPT) }
+}
+
+fn next_sampling() -> &'static mut Option<api::Sampling> {
+    unsafe { &mut *core::ptr::addr_of_mut!(NEXT_SAMPLING) }
+}
+
+fn replaced() -> &'static mut Option<(String, String)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(REPLACED) }
+}
+
+/// What `/retry` and `/diff-answers` know about a tab's conversation while
+/// another tab is active
+#[derive(Default)]
+pub struct Parked {
+    last_prompt: String,
+    replaced: Option<(String, String)>,
+    next_sampling: Option<api::Sampling>,
+}
+
+/// Take the conversation state out for a tab that is being put aside
+pub fn park() -> Parked {
+    Parked {
+        last_prompt: core::mem::take(last_prompt()),
+        replaced: replaced().take(),
+        next_sampling: next_sampling().take(),
+    }
+}
+
+pub fn unpark(parked: Parked) {
+    *last_prompt() = parked.last_prompt;
+    *replaced() = parked.replaced;
+    *next_sampling() = parked.next_sampling;
+}
+
+/// Take the last turn back out of the history for `/retry`: the user's
+/// message and everything that came after it. Returns the message to send
+/// again, which goes out with `sampling`; its `--image`/`/image` images and
+/// workspace-change note go with it again, and its final answer is kept
+/// for `/diff-answers`. None if there is no turn to take back.
+pub fn rewind_last_turn(history: &mut Vec<Message>, sampling: api::Sampling) -> Option<String> {
+    let prompt = last_prompt();
+    if prompt.is_empty() {
+        return None;
+    }
+    let pos = history.iter().rposition(|m| m.role == "user" && m.content.chunks().collect::<String>().starts_with(prompt.as_str()))?;
+    let mut turn = history.drain(pos..);
+    let message = turn.next()?;
+    let answer = turn.filter(|m| m.role == "assistant").last();
+    *replaced() = answer.map(|m| (prompt.clone(), m.content.chunks().collect()));
+    let content: String = message.content.chunks().collect();
+    if let Some(note) = content.split_once(CHANGES_NOTE).and_then(|(_, rest)| rest.split_once(']')) {
+        changes::carry_over(note.0);
+    }
+    attachments::requeue_images(message.images);
+    *next_sampling() = Some(sampling);
+    Some(prompt.clone())
+}
+
+/// The answer `/retry` replaced and the one that came instead, oldest
+/// first. None unless the last turn is a retry that has been answered.
+pub fn retried_answers(history: &[Message]) -> Option<(String, String)> {
+    let (prompt, old) = replaced().as_ref()?;
+    // Something else asked since
+    if prompt != last_prompt() {
+        return None;
+    }
+    let pos = history.iter().rposition(|m| m.role == "user" && m.content.chunks().collect::<String>().starts_with(prompt.as_str()))?;
+    let new = history[pos..].iter().rfind(|m| m.role == "assistant")?;
+    Some((old.clone(), new.content.chunks().collect()))
+}
+
+/// Reply cap for quick answers, a few seconds of generation on most setups
+pub const QUICK_MAX_TOKENS: usize = 256;
+const QUICK_INSTRUCTION: &str = "Answer in a few sentences at most, directly and without preamble. No tools are available for this answer.";
+
+/// Asks for the summary in the `auto_compact` round-trip
+const AUTO_COMPACT_REQUEST: &str = "[System: The conversation is close to the context limit and is about to be compacted. Write a summary of it for your own future reference: the user's goals, what has been done, files touched, decisions made and what is still open. Keep names, paths and numbers exact. Reply with the summary only, no tool calls.]";
+
+/// Replies that are retried with different sampling rather than accepted
+#[derive(Clone, Copy, PartialEq)]
+enum Unusable {
+    Empty,
+    Refusal,
+    FakeToolResult,
+    MalformedToolCall,
+}
+
+impl Unusable {
+    fn describe(self) -> &'static str {
+        match self {
+            Unusable::Empty => "Empty response",
+            Unusable::Refusal => "Refusal",
+            Unusable::FakeToolResult => "Fake tool result",
+            Unusable::MalformedToolCall => "Malformed tool call",
+        }
+    }
+}
 
 pub fn chat_once(
     model: &str,
@@ -18,12 +138,79 @@ pub fn chat_once(
     history: &mut Vec<Message>,
     context_window: Option<usize>,
     system_prompt: &str,
+) -> Result<(), &'static str> {
+    let start = libakuma::uptime();
+    summary::record_turn();
+    if state::AUTO_COMPACT.load(core::sync::atomic::Ordering::SeqCst) {
+        let window = context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW);
+        let threshold = compaction_threshold(window);
+        let user_tokens = super::tokens::count(user_message);
+        // Old attachments go first; the conversation is summarized only if
+        // that isn't enough
+        if calculate_history_tokens(history) + user_tokens > threshold {
+            let dropped = attachments::shed(history, threshold.saturating_sub(user_tokens));
+            if dropped > 0 {
+                print_notification(COLOR_YELLOW, &format!("Context nearly full: dropped {} old attachment(s)", dropped), 0);
+                print_msg(COLOR_RESET, "\n");
+            }
+        }
+        let tokens = calculate_history_tokens(history) + user_tokens;
+        if tokens > threshold && history.len() > 3 {
+            auto_compact(model, provider, history, system_prompt)?;
+        }
+    }
+    let result = run_turn(model, provider, user_message, history, context_window, system_prompt);
+    match result {
+        Ok(()) => latency::record_turn(&provider.name, model, libakuma::uptime() - start),
+        Err(e) => {
+            if e == "Request cancelled" { metrics::record_cancel(); }
+            // Close the journal, or the next turn would start from this
+            // turn's snapshots
+            report_changes();
+        }
+    }
+    result
+}
+
+fn run_turn(
+    model: &str,
+    provider: &Provider,
+    user_message: &str,
+    history: &mut Vec<Message>,
+    context_window: Option<usize>,
+    system_prompt: &str,
 ) -> Result<(), &'static str> {
     trim_history(history);
-    history.push(Message::new("user", user_message));
+    *last_prompt() = String::from(user_message);
+    // Ask for a reply in the user's language; the intent checker below only
+    // understands English, so it sits this turn out
+    let reply_language = super::lang::detect(user_message);
+    let language = reply_language.map(|lang| Message::new("system", &format!("The user is writing in {}. Reply in {}.", lang, lang)));
+    let mut content = String::from(user_message);
+    if let Some(summary) = changes::take_pending() {
+        content.push_str(&format!("{}{}]", CHANGES_NOTE, summary));
+    }
+    let mut msg = Message::new("user", &content);
+    msg.attachments = attachments::expand_mentions(user_message);
+    msg.images = attachments::take_images();
+    let mentioned = attachments::image_mentions(user_message, &msg.images);
+    msg.images.extend(mentioned);
+    if !msg.attachments.is_empty() || !msg.images.is_empty() {
+        let names: Vec<String> = msg.attachments.iter().map(|a| a.reference())
+            .chain(msg.images.iter().map(|i| i.reference()))
+            .collect();
+        msg.content.push_str(&format!("\n\n[System: Attached: {}]", names.join(", ")));
+    }
+    history.push(msg);
+    narration::start_turn();
 
     let mut total_tools_called: usize = 0;
     let mut total_fakes_detected: usize = 0;
+    let mut continuations: usize = 0;
+    let mut bad_streak: usize = 0;
+    // `/retry hot` asks for its sampling for the whole turn
+    let turn_sampling = next_sampling().take().unwrap_or_default();
+    let mut sampling = turn_sampling;
     let mut all_responses = String::new();
 
     for iteration in 0..MAX_TOOL_ITERATIONS {
@@ -31,15 +218,14 @@ pub fn chat_once(
         let mem_kb = libakuma::memory_usage() / 1024;
         let token_limit = context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW);
 
-        let mut messages_json = String::with_capacity(current_tokens * 4);
-        messages_json.push('[');
-        for (i, msg) in history.iter().enumerate() {
-            if i > 0 { messages_json.push(','); }
-            msg.write_json(&mut messages_json);
-        }
-        messages_json.push(']');
+        // Sent with every request but never stored, so /verbosity and /goal
+        // apply at once and compaction can't lose them; the language note
+        // only lasts this turn
+        let hint = state::get_verbosity().instruction().map(|i| Message::new("system", i));
+        let goals = super::goals::context_note().map(|g| Message::new("system", &g));
+        let outgoing: Vec<&Message> = history.iter().chain(goals.as_ref()).chain(language.as_ref()).chain(hint.as_ref()).collect();
 
-        let stream_result = api::send_with_retry(model, provider, &messages_json, iteration > 0, current_tokens, token_limit, mem_kb);
+        let stream_result = api::send_with_retry(model, provider, &outgoing, &sampling, iteration > 0, current_tokens, token_limit, mem_kb);
         
         let stream_result = match stream_result {
             Ok(res) => res,
@@ -49,30 +235,73 @@ pub fn chat_once(
                 return Err(e);
             }
         };
+        let (StreamResponse::Complete(_, s) | StreamResponse::Partial(_, s) | StreamResponse::Stopped(_, s)) = &stream_result;
+        latency::record_ttft(&provider.name, model, s.ttft_us);
+        summary::record_request(current_tokens, s.total_bytes);
         
-        let (assistant_response, mut stats) = match stream_result {
-            StreamResponse::Complete(response, stats) => (response, stats),
+        let (assistant_response, stats) = match stream_result {
+            StreamResponse::Complete(response, stats) => {
+                continuations = 0;
+                (response, stats)
+            }
+            StreamResponse::Partial(partial, stats) if continuations >= MAX_CONTINUATIONS => {
+                print_notification(COLOR_PEARL, "Response still cut off after several retries; keeping what arrived", 0);
+                continuations = 0;
+                (partial, stats)
+            }
             StreamResponse::Partial(partial, stats) => {
+                continuations += 1;
                 print_stats(&stats, &partial);
                 if !partial.is_empty() {
-                    history.push(Message::new("assistant", &partial));
+                    history.push(Message::new("assistant", &postprocess::strip_ansi(&partial)));
                     history.push(Message::new("user", "[System: Your response was cut off mid-stream. Please continue exactly where you left off.]"));
                 }
                 continue;
             }
+            StreamResponse::Stopped(partial, stats) => {
+                // Soft stop: what arrived is the answer; no tools, no continuation
+                print_stats(&stats, &partial);
+                print_notification(COLOR_YELLOW, "Stopped; keeping the reply so far", 0);
+                let text = postprocess::process(&partial, &state::get_postprocess());
+                if !text.trim().is_empty() {
+                    history.push(Message::new("assistant", &text));
+                }
+                report_changes();
+                return Ok(());
+            }
         };
 
-        let is_fake = assistant_response.contains("[Tool Result]");
-        if is_fake {
-            stats.fakes = 1;
-            total_fakes_detected += 1;
-        }
+        // The client has already cut fabricated [Tool Result] blocks out and
+        // counted them
+        let is_fake = stats.fakes > 0;
+        total_fakes_detected += stats.fakes;
 
         print_stats(&stats, &assistant_response);
 
+        let unusable = if is_fake {
+            Some(Unusable::FakeToolResult)
+        } else if assistant_response.trim().is_empty() {
+            Some(Unusable::Empty)
+        } else if looks_like_refusal(&assistant_response) {
+            Some(Unusable::Refusal)
+        } else {
+            None
+        };
+        if let Some(kind) = unusable {
+            let retrying = escalate_sampling(kind, &mut bad_streak, &mut sampling);
+            match kind {
+                // A refusal that survives the retries is the answer
+                Unusable::Refusal if !retrying => {}
+                _ if !retrying => return Err("Model kept returning unusable responses"),
+                Unusable::Empty | Unusable::Refusal => continue,
+                _ => {}
+            }
+        }
+
+        let mut correction = None;
         if is_fake {
-            let intent_phrases = extract_intent_phrases(&assistant_response);
-            let mut self_check_msg = String::from("[System Notice] You outputted a fake '[Tool Result]'. You must NOT hallucinate tool results. \nIf you want to perform an action, you MUST use the precise tool for it.\n");
+            let intent_phrases = if reply_language.is_some() { Vec::new() } else { extract_intent_phrases(&assistant_response) };
+            let mut self_check_msg = String::from("[System Notice] You outputted a fake '[Tool Result]'. It has been removed from your reply. You must NOT hallucinate tool results. \nIf you want to perform an action, you MUST use the precise tool for it.\n");
             if !intent_phrases.is_empty() {
                 self_check_msg.push_str("\nBased on your stated intent: ");
                 for (i, intent) in intent_phrases.iter().enumerate() {
@@ -82,10 +311,20 @@ pub fn chat_once(
                 self_check_msg.push_str("\nPlease call the appropriate tool.\n");
             }
             self_check_msg.push_str("\nAvailable tools:\n(Refer to the tool list provided in your system prompt)");
-            history.push(Message::new("user", &self_check_msg));
-            print_notification(COLOR_PEARL, "Fake Tool Result detected", 0);
+            print_notification(COLOR_PEARL, &format!("Fake Tool Result detected and removed ({})", stats.fakes), 0);
             print_msg(COLOR_RESET, "\n");
-            continue;
+
+            // Without a real tool call left there is nothing to run; keep
+            // the rest of the reply and ask again
+            if tools::find_tool_calls(&assistant_response).1.is_empty() {
+                let text = postprocess::process(&assistant_response, &state::get_postprocess());
+                if !text.trim().is_empty() {
+                    history.push(Message::new("assistant", &text));
+                }
+                history.push(Message::new("system", &self_check_msg));
+                continue;
+            }
+            correction = Some(self_check_msg);
         }
 
         all_responses.push_str(&assistant_response);
@@ -105,25 +344,71 @@ pub fn chat_once(
         let (mut current_llm_response_text, tool_calls) = tools::find_tool_calls(&assistant_response);
 
         if !tool_calls.is_empty() {
-            for tool_call in tool_calls {
+            let mut malformed = false;
+            // Native calls are kept on the assistant message, each answered
+            // by a "tool" message below
+            let commands: Vec<&str> = tool_calls.iter().map(|c| c.json.as_str()).collect();
+            if let Some(calls) = api::tool_calls::assistant_calls(&commands) {
+                let text = postprocess::process(&current_llm_response_text, &state::get_postprocess());
+                let mut msg = Message::new("assistant", &text);
+                msg.tool_calls = Some(calls);
+                history.push(msg);
+                current_llm_response_text.clear();
+            }
+            let call_count = tool_calls.len();
+            for (i, tool_call) in tool_calls.into_iter().enumerate() {
                 total_tools_called += 1;
+                if let Some(note) = narration::before_tool(&tool_call.json, call_count - i - 1) {
+                    print_notification(COLOR_GRAY_BRIGHT, &note, 0);
+                }
                 if !current_llm_response_text.is_empty() {
-                    history.push(Message::new("assistant", &current_llm_response_text));
+                    let text = postprocess::process(&current_llm_response_text, &state::get_postprocess());
+                    history.push(Message::new("assistant", &text));
                     current_llm_response_text.clear();
                 }
 
+                // Keeps our sandbox lock fresh through long tool runs
+                crate::instance::heartbeat();
+                changes::before_tool(&tool_call.json);
                 let tool_start = libakuma::uptime();
-                let tool_result = if let Some(result) = tools::execute_tool_command(&tool_call.json) {
+                let is_ask_user = tools::helpers::extract_string_field(&tool_call.json, "tool").as_deref() == Some("AskUser");
+                let tool_result = if is_ask_user {
+                    // Pauses the loop without using up an iteration of its own
+                    match ask_user(&tool_call.json, current_tokens, token_limit, mem_kb) {
+                        Some(r) => r,
+                        None => {
+                            drop_pending_calls(history);
+                            return Err("Request cancelled");
+                        }
+                    }
+                } else if let Some(decision) = approve_tool(&tool_call.json, current_tokens, token_limit, mem_kb) {
+                    match decision {
+                        Decision::Declined(r) => r,
+                        Decision::Cancelled => {
+                            drop_pending_calls(history);
+                            return Err("Request cancelled");
+                        }
+                    }
+                } else if let Some(result) = tools::execute_tool_command(&tool_call.json) {
                     result
                 } else {
+                    malformed = true;
                     tools::ToolResult::err("Failed to parse or execute tool command")
                 };
                 let tool_duration_us = libakuma::uptime() - tool_start;
+                crate::session_log::tool(&tool_call.json, &tool_result, tool_duration_us);
+                if let Some(name) = tools::helpers::extract_string_field(&tool_call.json, "tool") {
+                    summary::record_tool(&name, tool_result.success);
+                }
                 
                 let (color, status) = if tool_result.success { (COLOR_GREEN_LIGHT, "Success") } else { (COLOR_PEARL, "Failed") };
                 let status_content = format!("Tool Status: {}", status);
 
+                let name = tools::helpers::extract_string_field(&tool_call.json, "tool").unwrap_or_default();
+                output::long_text(&format!("{} output", name), &tool_result.output);
                 if tool_result.success {
+                    crate::ui::tui::preview::on_tool_success(&tool_call.json);
+                    crate::ui::tui::diff_view::record_tool_change(&tool_call.json, tools::fs::take_last_edit_offset());
                     print_msg(COLOR_RESET, "\n");
                     print_msg(COLOR_GRAY_BRIGHT, &tool_result.output);
                     print_msg(COLOR_RESET, "\n\n");
@@ -136,32 +421,50 @@ pub fn chat_once(
                     print_msg(COLOR_RESET, "\n\n");
                 }
 
-                let current_cwd = tools::get_working_dir();
+                let current_cwd = tools::context::virtualize(tools::get_working_dir());
+                // Large output rides along as an attachment, so it can leave
+                // the context on its own once the conversation moves on
+                let attachment = (tool_result.output.len() >= attachments::MIN_ATTACH_BYTES)
+                    .then(|| attachments::Attachment::new(&tool_label(&tool_call.json), &tool_result.output));
+                let output = match &attachment {
+                    Some(a) => format!("(output in {})", a.reference()),
+                    None => tool_result.output.clone(),
+                };
                 let tool_result_msg = if tool_result.success {
-                    format!("[Tool Result]\n{}\n[End Tool Result]\n[Current Directory: {}]\n\nPlease continue your response based on this result.", tool_result.output, current_cwd)
+                    format!("[Tool Result]\n{}\n[End Tool Result]\n[Current Directory: {}]\n\nPlease continue your response based on this result.", output, current_cwd)
                 } else {
-                    format!("[Tool Result]\nTool failed: {}\n[End Tool Result]\n[Current Directory: {}]\n\nPlease analyze the failure and try again with a corrected command or different approach.", tool_result.output, current_cwd)
+                    format!("[Tool Result]\nTool failed: {}\n[End Tool Result]\n[Current Directory: {}]\n\nPlease analyze the failure and try again with a corrected command or different approach.", output, current_cwd)
                 };
-                history.push(Message::new("user", &tool_result_msg));
+                let call_id = tools::helpers::extract_string_field(&tool_call.json, "call_id");
+                let mut msg = Message::tool(&tool_result_msg, call_id.as_deref());
+                msg.attachments.extend(attachment);
+                history.push(msg);
                 trim_history(history);
                 compact_history(history);
             }
+            if let Some(msg) = correction {
+                history.push(Message::new("system", &msg));
+            }
+            if malformed {
+                if !escalate_sampling(Unusable::MalformedToolCall, &mut bad_streak, &mut sampling) {
+                    return Err("Model kept returning malformed tool calls");
+                }
+            } else {
+                bad_streak = 0;
+                sampling = turn_sampling;
+            }
             continue;
         }
 
         if !current_llm_response_text.is_empty() {
-            history.push(Message::new("assistant", &current_llm_response_text));
-            if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) && tool_calls.is_empty() {
-                // We've already printed it raw during streaming. 
-                // For now, let's just leave it. If the user wants a full re-render, 
-                // we'd need a way to clear the previous output.
-            }
+            let text = postprocess::process(&current_llm_response_text, &state::get_postprocess());
+            history.push(Message::new("assistant", &text));
         }
         
         trim_history(history);
         compact_history(history);
 
-        let intent_phrases = extract_intent_phrases(&all_responses);
+        let intent_phrases = if reply_language.is_some() { Vec::new() } else { extract_intent_phrases(&all_responses) };
         let mismatch = !intent_phrases.is_empty() && total_tools_called == 0;
         let has_fakes = total_fakes_detected > 0;
         let intent_content = format!("Intent phrases: {} | Tools called: {} | Fakes: {}", intent_phrases.len(), total_tools_called, total_fakes_detected);
@@ -170,6 +473,8 @@ pub fn chat_once(
         else { print_notification(COLOR_GREEN_LIGHT, &intent_content, 0); }
 
         if mismatch {
+            bad_streak = 0;
+            sampling = turn_sampling;
             print_notification(COLOR_PEARL, "Self check", 0);
             print_msg(COLOR_RESET, "\n\n");
             let mut intents_list = String::new();
@@ -179,26 +484,258 @@ pub fn chat_once(
             continue;
         }
 
-        if let Some(ctx_window) = context_window {
+        let auto = state::AUTO_COMPACT.load(core::sync::atomic::Ordering::SeqCst);
+        if let (Some(ctx_window), false) = (context_window, auto) {
             let current_tokens = calculate_history_tokens(history);
-            if current_tokens > TOKEN_LIMIT_FOR_COMPACTION && current_tokens < ctx_window {
+            if current_tokens > compaction_threshold(ctx_window) && current_tokens < ctx_window {
                 print_msg(COLOR_RESET, "\n[!] Token count is high - consider asking Meow-chan to compact context\n");
             }
         }
+        report_changes();
         return Ok(());
     }
     print_msg(COLOR_RESET, "\n[!] Max tool iterations reached\n");
+    report_changes();
     Ok(())
 }
 
-fn print_msg(color: &str, s: &str) {
-    if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-        crate::tui_app::tui_print_with_indent(s, "", 9, Some(color));
+/// Compaction round-trip for `auto_compact`: ask the model to summarize the
+/// conversation, then hand the summary to the CompactContext path. The
+/// user's message is sent afterwards, against the compacted history.
+fn auto_compact(model: &str, provider: &Provider, history: &mut Vec<Message>, system_prompt: &str) -> Result<(), &'static str> {
+    print_notification(COLOR_YELLOW, &format!("Context at {} tokens; compacting before sending", calculate_history_tokens(history)), 0);
+    print_msg(COLOR_RESET, "\n");
+    let request = Message::new("user", AUTO_COMPACT_REQUEST);
+    let outgoing: Vec<&Message> = history.iter().chain(core::iter::once(&request)).collect();
+    let current_tokens = calculate_history_tokens(history);
+    let mem_kb = libakuma::memory_usage() / 1024;
+    let response = match api::send_with_retry(model, provider, &outgoing, &api::Sampling::default(), false, current_tokens, DEFAULT_CONTEXT_WINDOW, mem_kb) {
+        Ok(r) => r,
+        Err(e) => {
+            print_msg(COLOR_RESET, "\n");
+            print_notification(COLOR_PEARL, &format!("Compaction request error: {}", e), 0);
+            return Err(e);
+        }
+    };
+    let (StreamResponse::Complete(text, stats) | StreamResponse::Partial(text, stats) | StreamResponse::Stopped(text, stats)) = response;
+    summary::record_request(current_tokens, stats.total_bytes);
+    print_stats(&stats, &text);
+
+    // Models that answer with the tool call anyway go through it as is
+    let result = try_execute_compact_context(&text, history, system_prompt)
+        .unwrap_or_else(|| compact_to_summary(&postprocess::strip_ansi(text.trim()), history, system_prompt));
+    if result.success {
+        print_notification(COLOR_GREEN_LIGHT, &result.output, 0);
     } else {
-        if color != COLOR_RESET { libakuma::print(color); }
-        libakuma::print(s);
-        if color != COLOR_RESET { libakuma::print(COLOR_RESET); }
+        print_notification(COLOR_PEARL, &format!("Auto-compaction failed: {}", result.output), 0);
+    }
+    print_msg(COLOR_RESET, "\n");
+    Ok(())
+}
+
+/// Quick-answer mode: one request with the persona alone (no tool docs), a
+/// small reply cap and no tool loop, for questions that just need a line
+/// or two. The exchange is still added to history.
+pub fn quick_once(
+    model: &str,
+    provider: &Provider,
+    user_message: &str,
+    history: &mut Vec<Message>,
+    persona: &str,
+) -> Result<(), &'static str> {
+    let start = libakuma::uptime();
+    trim_history(history);
+    *last_prompt() = String::from(user_message);
+    let system = Message::new("system", &format!("{}\n\n{}", persona, QUICK_INSTRUCTION));
+    let question = Message::new("user", user_message);
+    let outgoing = [&system, &question];
+    let current_tokens = calculate_history_tokens(history);
+    let mem_kb = libakuma::memory_usage() / 1024;
+    let turn_sampling = next_sampling().take().unwrap_or_default();
+    let sampling = api::Sampling { max_tokens: Some(QUICK_MAX_TOKENS), ..turn_sampling };
+
+    let response = match api::send_with_retry(model, provider, &outgoing, &sampling, false, current_tokens, DEFAULT_CONTEXT_WINDOW, mem_kb) {
+        Ok(r) => r,
+        Err(e) => {
+            print_msg(COLOR_RESET, "\n");
+            print_notification(COLOR_PEARL, &format!("Request error: {}", e), 0);
+            if e == "Request cancelled" { metrics::record_cancel(); }
+            return Err(e);
+        }
+    };
+    let (StreamResponse::Complete(text, stats) | StreamResponse::Partial(text, stats) | StreamResponse::Stopped(text, stats)) = response;
+    latency::record_ttft(&provider.name, model, stats.ttft_us);
+    latency::record_turn(&provider.name, model, libakuma::uptime() - start);
+    summary::record_turn();
+    summary::record_request(outgoing.iter().map(|m| m.content.token_count()).sum(), stats.total_bytes);
+    print_stats(&stats, &text);
+
+    history.push(question);
+    history.push(Message::new("assistant", &postprocess::process(&text, &state::get_postprocess())));
+    trim_history(history);
+    compact_history(history);
+    Ok(())
+}
+
+/// Count an unusable reply and pick warmer sampling with a fresh seed for
+/// the retry, noting the change. Returns false once the retries are spent.
+fn escalate_sampling(kind: Unusable, streak: &mut usize, sampling: &mut api::Sampling) -> bool {
+    *streak += 1;
+    if *streak > MAX_SAMPLING_RETRIES {
+        print_notification(COLOR_PEARL, &format!("{} after {} sampling retries; giving up", kind.describe(), MAX_SAMPLING_RETRIES), 0);
+        return false;
+    }
+    let temperature = RETRY_TEMPERATURE + TEMPERATURE_STEP * (*streak - 1) as f32;
+    let seed = libakuma::uptime();
+    *sampling = api::Sampling { temperature: Some(temperature), seed: Some(seed), max_tokens: None };
+    print_notification(COLOR_YELLOW, &format!("{}; retrying with temperature {:.2}, seed {}", kind.describe(), temperature, seed), 0);
+    true
+}
+
+/// Openers of a short reply that declines the task. Long replies that
+/// start this way usually go on to explain or offer something, so they stay.
+const REFUSAL_PREFIXES: &[&str] = &[
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+    "i can't help with",
+    "i cannot help with",
+    "i can't assist with",
+    "i cannot assist with",
+    "i'm unable to help",
+    "i am unable to help",
+];
+
+fn looks_like_refusal(text: &str) -> bool {
+    let t = text.trim_start().to_lowercase().replace('’', "'");
+    t.len() < 300 && REFUSAL_PREFIXES.iter().any(|p| t.starts_with(p))
+}
+
+/// Print what this turn did to the workspace; the same line goes to the
+/// model with the next message
+fn report_changes() {
+    if let Some(summary) = changes::finish_turn() {
+        print_notification(COLOR_GRAY_BRIGHT, &format!("Changes: {}", summary), 0);
+    }
+}
+
+/// Show the model's question and wait for the user's answer
+fn ask_user(tool_json: &str, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<tools::ToolResult> {
+    let question = match tools::helpers::extract_string_field(tool_json, "question") {
+        Some(q) if !q.trim().is_empty() => q,
+        _ => return Some(tools::ToolResult::err("AskUser requires a non-empty 'question'")),
+    };
+
+    print_msg(COLOR_RESET, "\n");
+    print_notification(COLOR_YELLOW, "Question for you", 0);
+    print_msg(COLOR_YELLOW, &question);
+    print_msg(COLOR_RESET, "\n\n");
+
+    let answer = output::read_line(true, current_tokens, token_limit, mem_kb)?;
+
+    Some(tools::ToolResult::ok(format!("User answered: {}", answer)))
+}
+
+enum Decision {
+    Declined(tools::ToolResult),
+    Cancelled,
+}
+
+/// Ask before running a tool `auto_approve` doesn't cover, showing what
+/// the call will do. None means the call may run.
+fn approve_tool(tool_json: &str, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<Decision> {
+    let tool = tools::helpers::extract_string_field(tool_json, "tool")?;
+    if !tools::permission::needs_approval(&tool) {
+        return confirm_risky_shell(tool_json, current_tokens, token_limit, mem_kb);
+    }
+
+    print_msg(COLOR_RESET, "\n");
+    print_notification(COLOR_PEARL, &format!("Run {}?", tool), 0);
+    print_msg(COLOR_YELLOW, &format!("{}\n[a]pprove / [d]eny / [A]lways", describe_call(&tool, tool_json)));
+    print_msg(COLOR_RESET, "\n\n");
+
+    let answer = match output::read_line(false, current_tokens, token_limit, mem_kb) {
+        Some(a) => a,
+        None => return Some(Decision::Cancelled),
+    };
+    match answer.trim() {
+        "a" | "approve" | "y" | "yes" => None,
+        "A" | "always" => {
+            tools::permission::approve_always(&tool);
+            None
+        }
+        _ => Some(Decision::Declined(tools::ToolResult::err(&format!(
+            "The user denied this {} call. Don't retry it as is; ask what they want instead, or take another approach.", tool
+        )))),
+    }
+}
+
+/// The arguments of a tool call worth seeing before approving it
+fn describe_call(tool: &str, tool_json: &str) -> String {
+    let field = |name: &str| tools::helpers::extract_string_field(tool_json, name);
+    if let Some(custom) = tools::custom::find(tool) {
+        return match tools::custom::command(custom, tool_json) {
+            Ok(cmd) => format!("$ {}", cmd),
+            Err(e) => e,
+        };
+    }
+    if tool == "Shell" {
+        let cmd = field("cmd").unwrap_or_default();
+        let assessment = tools::permission::assess(&cmd);
+        if assessment.risk == tools::permission::Risk::Low {
+            return format!("$ {}", cmd);
+        }
+        return format!("$ {}\n{} risk: this will {}.", cmd, assessment.risk.as_str(), assessment.summary);
+    }
+    let mut lines = String::new();
+    for key in ["filename", "path", "source", "destination", "source_filename", "destination_filename", "url", "branch", "name", "message", "key", "value", "id", "title"] {
+        if let Some(value) = field(key) {
+            let value = match value.char_indices().nth(200) {
+                Some((cut, _)) => format!("{}...", &value[..cut]),
+                None => value,
+            };
+            lines.push_str(&format!("{}: {}\n", key, value));
+        }
+    }
+    if let Some(content) = field("content").or_else(|| field("new_text")) {
+        lines.push_str(&format!("{} lines, {} bytes\n", content.lines().count(), content.len()));
+    }
+    if lines.is_empty() {
+        lines.push_str(tool_json);
     }
+    String::from(lines.trim_end())
+}
+
+/// Show risky Shell commands with their risk level and what they will do,
+/// and ask before running them. None means the command may run.
+fn confirm_risky_shell(tool_json: &str, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<Decision> {
+    let tool = tools::helpers::extract_string_field(tool_json, "tool")?;
+    let cmd = match tools::custom::find(&tool) {
+        // A custom tool is checked as the command it expands to
+        Some(custom) => tools::custom::command(custom, tool_json).ok()?,
+        None if tool == "Shell" => tools::helpers::extract_string_field(tool_json, "cmd")?,
+        None => return None,
+    };
+    let assessment = tools::permission::assess(&cmd);
+    if assessment.risk == tools::permission::Risk::Low { return None; }
+
+    print_msg(COLOR_RESET, "\n");
+    print_notification(COLOR_PEARL, &format!("Shell command, {} risk", assessment.risk.as_str()), 0);
+    print_msg(COLOR_YELLOW, &format!("$ {}\nThis will {}.\nRun it? [y/N]", cmd, assessment.summary));
+    print_msg(COLOR_RESET, "\n\n");
+
+    let answer = match output::read_line(false, current_tokens, token_limit, mem_kb) {
+        Some(a) => a,
+        None => return Some(Decision::Cancelled),
+    };
+
+    if answer.trim().eq_ignore_ascii_case("y") || answer.trim().eq_ignore_ascii_case("yes") {
+        return None;
+    }
+    Some(Decision::Declined(tools::ToolResult::err(&format!("The user declined to run this command ({}). Ask before retrying, or take a safer approach.", assessment.summary))))
+}
+
+fn print_msg(color: &str, s: &str) {
+    output::message(color, s);
 }
 
 fn print_notification(color: &str, message: &str, duration_us: u64) {
@@ -208,17 +745,7 @@ fn print_notification(color: &str, message: &str, duration_us: u64) {
         content.push_str(&format_duration(duration_us));
     }
     content.push('\n');
-    
-    if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-        let col = tui_app::CUR_COL.load(Ordering::SeqCst);
-        if col != 0 { tui_app::tui_print_with_indent("\n", "", 0, None); }
-        tui_app::tui_print_with_indent(&content, "     --- ", 9, Some(color));
-    } else {
-        libakuma::print(color);
-        libakuma::print("     --- ");
-        libakuma::print(&content);
-        libakuma::print(COLOR_RESET);
-    }
+    output::notification(color, &content);
 }
 
 fn format_duration(us: u64) -> String {
@@ -228,16 +755,20 @@ fn format_duration(us: u64) -> String {
     else { format!("{}ms", ms) }
 }
 
+/// Attachment label for a tool's output: the tool and what it worked on
+fn tool_label(tool_json: &str) -> String {
+    let field = |name: &str| tools::helpers::extract_string_field(tool_json, name);
+    let tool = field("tool").unwrap_or_else(|| String::from("tool"));
+    match field("filename").or_else(|| field("path")).or_else(|| field("url")).or_else(|| field("cmd")) {
+        Some(arg) if arg.len() <= 60 => format!("{} {}", tool, arg),
+        _ => format!("{} output", tool),
+    }
+}
+
 fn print_stats(stats: &api::StreamStats, full_response: &str) {
-    let tokens = (stats.total_bytes + 3) / 4;
+    let tokens = super::tokens::count(full_response);
     let tps = if stats.stream_us > 0 { (tokens as f64) / (stats.stream_us as f64 / 1_000_000.0) } else { 0.0 };
-    if tui_app::TUI_ACTIVE.load(Ordering::SeqCst) {
-        if full_response.ends_with('\n') { tui_app::tui_print_with_indent("\n", "", 0, None); }
-        else { tui_app::tui_print_with_indent("\n\n", "", 0, None); }
-    } else {
-        if full_response.ends_with('\n') { libakuma::print("\n"); }
-        else { libakuma::print("\n\n"); }
-    }
+    output::text(if full_response.ends_with('\n') { "\n" } else { "\n\n" });
     let stats_content = format!("First: {}ms | Stream: {}ms | Size: {:.2}KB | TPS: {:.1}", stats.ttft_us / 1000, stats.stream_us / 1000, stats.total_bytes as f64 / 1024.0, tps);
     print_notification(COLOR_YELLOW, &stats_content, stats.ttft_us + stats.stream_us);
 }
@@ -281,14 +812,19 @@ fn try_execute_compact_context(response: &str, history: &mut Vec<Message>, syste
 
     if !json_block.contains("\"CompactContext\"") { return None; }
     let summary = extract_json_string(json_block, "summary")?;
-    if summary.is_empty() { return Some(tools::ToolResult::err("CompactContext requires a non-empty summary")); }
+    Some(compact_to_summary(&summary, history, system_prompt))
+}
+
+/// Replace the history with the system prompt and `summary`
+fn compact_to_summary(summary: &str, history: &mut Vec<Message>, system_prompt: &str) -> tools::ToolResult {
+    if summary.is_empty() { return tools::ToolResult::err("CompactContext requires a non-empty summary"); }
     let tokens_before = calculate_history_tokens(history);
     history.clear();
     history.push(Message::new("system", system_prompt));
     history.push(Message::new("user", &format!("[Previous Conversation Summary]\n{}\n[End Summary]\n\nThe conversation above has been compacted. Continue from here.", summary)));
     history.push(Message::new("assistant", "Understood nya~! I've loaded the conversation summary into my memory banks. Ready to continue where we left off! (=^・ω・^=)"));
     let tokens_after = calculate_history_tokens(history);
-    Some(tools::ToolResult::ok(format!("Context compacted: {} tokens -> {} tokens (saved {} tokens)", tokens_before, tokens_after, tokens_before - tokens_after)))
+    tools::ToolResult::ok(format!("Context compacted: {} tokens -> {} tokens (saved {} tokens)", tokens_before, tokens_after, tokens_before - tokens_after))
 }
 
 fn extract_json_string(json: &str, key: &str) -> Option<String> {
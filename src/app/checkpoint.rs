@@ -0,0 +1,179 @@
+//! Workspace checkpoints
+//!
+//! `/checkpoint NAME` copies every file changed this session (as recorded
+//! by the change journal) to /var/meow/checkpoints/NAME, and `/restore NAME`
+//! writes the copies back. It is coarser than undoing single changes, meant
+//! for trying a risky refactor and returning to a known state.
+//!
+//! A file tool that touches a file the checkpoint doesn't hold yet first
+//! adds it (copy on write), so the refactor's new edits and new files are
+//! covered too: restoring writes the former back and removes the latter.
+//! That only works for checkpoints saved by this meow; restoring an older
+//! one lists the files changed since that it doesn't hold.
+//!
+//! A checkpoint is a directory holding the copies as `0`, `1`, ... and a
+//! `manifest` with one line per file: `N PATH` for a copy, `- PATH` for a
+//! file that did not exist at checkpoint time.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::{open, close, write_fd, read_dir, open_flags};
+
+use crate::tools::fs;
+
+pub const CHECKPOINTS_DIR: &str = "/var/meow/checkpoints";
+/// Larger files are skipped rather than copied
+const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+/// A checkpoint saved this session, kept up to date by `before_change`
+struct Active {
+    name: String,
+    /// Files it holds, copied or noted as absent
+    files: Vec<String>,
+    /// Number of the next copy
+    next_id: usize,
+}
+
+static mut ACTIVE: Vec<Active> = Vec::new();
+
+fn active() -> &'static mut Vec<Active> {
+    unsafe { &mut *core::ptr::addr_of_mut!(ACTIVE) }
+}
+
+fn dir(name: &str) -> String {
+    format!("{}/{}", CHECKPOINTS_DIR, name)
+}
+
+/// Names of the saved checkpoints, sorted
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = match read_dir(CHECKPOINTS_DIR) {
+        Some(entries) => entries.into_iter().map(|e| e.name).filter(|n| !n.starts_with('.')).collect(),
+        None => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+fn read_file(path: &str) -> Option<Vec<u8>> {
+    fs::read_file(path, MAX_FILE_SIZE).ok()
+}
+
+fn write_file(path: &str, data: &[u8]) -> bool {
+    let fd = open(path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return false;
+    }
+    let written = write_fd(fd, data);
+    close(fd);
+    written >= 0 && written as usize == data.len()
+}
+
+/// Copy `path` into checkpoint directory `dir` as copy `id`. Returns its
+/// manifest line (`- PATH` when the file doesn't exist), None when it
+/// couldn't be copied.
+fn capture(dir: &str, path: &str, id: usize) -> Option<String> {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return Some(format!("- {}\n", path));
+    }
+    close(fd);
+    let data = read_file(path)?;
+    write_file(&format!("{}/{}", dir, id), &data).then(|| format!("{} {}\n", id, path))
+}
+
+/// Snapshot `files` under `name`; returns (files copied, files noted as
+/// absent, files skipped). Files first changed later are added as they
+/// change.
+pub fn save(name: &str, files: &[String]) -> Result<(usize, usize, Vec<String>), &'static str> {
+    if !super::session::is_valid_session_name(name) {
+        return Err("Invalid checkpoint name (use letters, digits, '-', '_' or '.')");
+    }
+    let dir = dir(name);
+    libakuma::mkdir_p(&dir);
+    let mut manifest = String::new();
+    let (mut copied, mut absent, mut skipped) = (0, 0, Vec::new());
+    for path in files {
+        let Some(line) = capture(&dir, path, copied) else {
+            skipped.push(path.clone());
+            continue;
+        };
+        if line.starts_with('-') { absent += 1; } else { copied += 1; }
+        manifest.push_str(&line);
+    }
+    if !write_file(&format!("{}/manifest", dir), manifest.as_bytes()) {
+        return Err("Failed to write the checkpoint manifest");
+    }
+    active().retain(|a| a.name != name);
+    active().push(Active { name: String::from(name), files: files.to_vec(), next_id: copied });
+    Ok((copied, absent, skipped))
+}
+
+/// Called before a file tool touches `path`: checkpoints saved this session
+/// that don't hold it yet take a copy of it first
+pub fn before_change(path: &str) {
+    for checkpoint in active().iter_mut() {
+        if checkpoint.files.iter().any(|f| f == path) {
+            continue;
+        }
+        // Held from now on even when the copy fails, so it isn't retried
+        checkpoint.files.push(String::from(path));
+        let dir = dir(&checkpoint.name);
+        let Some(line) = capture(&dir, path, checkpoint.next_id) else { continue };
+        if !line.starts_with('-') {
+            checkpoint.next_id += 1;
+        }
+        let fd = open(&format!("{}/manifest", dir), open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_APPEND);
+        if fd >= 0 {
+            write_fd(fd, line.as_bytes());
+            close(fd);
+        }
+    }
+}
+
+/// Write the files of checkpoint `name` back; returns (files restored,
+/// files that couldn't be written or removed, or that it doesn't hold)
+pub fn restore(name: &str) -> Result<(usize, Vec<String>), &'static str> {
+    if !super::session::is_valid_session_name(name) {
+        return Err("Invalid checkpoint name");
+    }
+    let dir = dir(name);
+    let manifest = read_file(&format!("{}/manifest", dir)).ok_or("Checkpoint not found")?;
+    let manifest = core::str::from_utf8(&manifest).map_err(|_| "Checkpoint manifest is not valid UTF-8")?;
+    let (mut restored, mut failed) = (0, Vec::new());
+    let mut held: Vec<&str> = Vec::new();
+    for line in manifest.lines() {
+        let Some((id, path)) = line.split_once(' ') else { continue };
+        held.push(path);
+        if id == "-" {
+            // Created since the checkpoint: remove it if it is still there
+            let fd = open(path, open_flags::O_RDONLY);
+            if fd >= 0 {
+                close(fd);
+                match fs::remove_file(path) {
+                    Ok(()) => restored += 1,
+                    // The error already names the file
+                    Err(e) => failed.push(e),
+                }
+            }
+            continue;
+        }
+        let ok = read_file(&format!("{}/{}", dir, id)).is_some_and(|data| {
+            if let Some((parent, _)) = path.rsplit_once('/') {
+                if !parent.is_empty() {
+                    libakuma::mkdir_p(parent);
+                }
+            }
+            write_file(path, &data)
+        });
+        if ok { restored += 1; } else { failed.push(String::from(path)); }
+    }
+    // Only an older checkpoint misses files: ours copy them on first change
+    for path in super::changes::session_files() {
+        if !held.contains(&path.as_str()) {
+            failed.push(format!("{} (changed since; not in the checkpoint)", path));
+        }
+    }
+    Ok((restored, failed))
+}
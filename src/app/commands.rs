@@ -2,14 +2,19 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
 
-use crate::config::{Config, Provider, ApiType, TOKEN_LIMIT_FOR_COMPACTION};
+use crate::config::{Config, Provider, ApiType, compaction_threshold};
 use crate::api;
 use crate::tui_app;
-use super::history::{Message, calculate_history_tokens};
+use super::history::{Message, calculate_history_tokens, estimate_tokens};
 
 pub enum CommandResult {
     Continue,
+    /// Like `Continue`, but the output is only shown, not kept in the
+    /// history (views too big to resend with every request)
+    Display,
     Quit,
+    /// Send this message as a new turn (`/retry`)
+    Send(String),
 }
 
 pub fn handle_command(
@@ -18,7 +23,7 @@ pub fn handle_command(
     provider: &mut Provider,
     config: &mut Config,
     history: &mut Vec<Message>,
-    system_prompt: &str,
+    system_prompt: &mut String,
 ) -> (CommandResult, Option<String>) {
     let parts: Vec<&str> = cmd.splitn(2, ' ').collect();
     let command = parts[0];
@@ -30,7 +35,8 @@ pub fn handle_command(
         }
         "/clear" | "/reset" => {
             history.clear();
-            history.push(Message::new("system", system_prompt));
+            history.push(Message::new("system", system_prompt.as_str()));
+            super::changes::reset();
             (CommandResult::Continue, Some(String::from("～ *swishes tail* Memory wiped nya~! Fresh start! (=^・ω・^=)")))
         }
         "/model" => {
@@ -58,6 +64,19 @@ pub fn handle_command(
                     }
                 }
                 Some(new_model) => {
+                    // `provider/model` (as listed by /models all) switches both at once
+                    if let Some((prov_name, name)) = new_model.split_once('/') {
+                        if let Some(p) = config.get_provider(prov_name) {
+                            *provider = p.clone();
+                            config.current_provider = String::from(prov_name);
+                            crate::app::state::set_verbosity(provider.verbosity);
+                            *model = String::from(name);
+                            config.current_model = String::from(name);
+                            let _ = config.save();
+                            tui_app::set_model_and_provider(model, &provider.name);
+                            return (CommandResult::Continue, Some(format!("～ *ears twitch* Switched to {} on {} nya~!", name, prov_name)));
+                        }
+                    }
                     *model = String::from(new_model);
                     config.current_model = String::from(new_model);
                     let _ = config.save();
@@ -70,6 +89,44 @@ pub fn handle_command(
                 }
             }
         }
+        "/models" => {
+            let refresh = match arg {
+                Some("all") => false,
+                Some("all refresh") => true,
+                _ => return (CommandResult::Continue, Some(String::from("～ Usage: /models all [refresh] nya~"))),
+            };
+            let current = format!("{}/{}", provider.name, model);
+            let mut output = String::from("～ Models across all providers: ～\n");
+            match api::cached_models() {
+                Some(names) if !refresh => {
+                    for name in names {
+                        let marker = if *name == current { " (current)" } else { "" };
+                        output.push_str(&format!("  {}{}\n", name, marker));
+                    }
+                    output.push_str("  (cached; '/models all refresh' to query again)\n");
+                }
+                _ => {
+                    let mut names = Vec::new();
+                    for (prov_name, result) in api::list_models_all(&config.providers) {
+                        match result {
+                            Ok(models) => {
+                                for m in models {
+                                    let name = format!("{}/{}", prov_name, m.name);
+                                    let marker = if name == current { " (current)" } else { "" };
+                                    let size_info = m._parameter_size.as_ref().map(|s| format!(" [{}]", s)).unwrap_or_default();
+                                    output.push_str(&format!("  {}{}{}\n", name, size_info, marker));
+                                    names.push(name);
+                                }
+                            }
+                            Err(e) => output.push_str(&format!("  {}: unavailable ({:?})\n", prov_name, e)),
+                        }
+                    }
+                    api::cache_models(names);
+                }
+            }
+            output.push_str("  Tip: '/model provider/model' switches both at once nya~!");
+            (CommandResult::Continue, Some(output))
+        }
         "/provider" => {
             match arg {
                 Some("?") | Some("list") => {
@@ -90,23 +147,92 @@ pub fn handle_command(
                     if let Some(p) = config.get_provider(prov_name) {
                         *provider = p.clone();
                         config.current_provider = String::from(prov_name);
+                        if let Some(m) = provider.default_model.clone() {
+                            config.current_model = m.clone();
+                            *model = m;
+                        }
                         let _ = config.save();
+                        crate::app::state::set_verbosity(provider.verbosity);
                         tui_app::set_model_and_provider(model, &provider.name);
-                        (CommandResult::Continue, Some(format!("～ *ears twitch* Switched to provider: {} nya~!", prov_name)))
+                        let mut msg = format!("～ *ears twitch* Switched to provider: {} nya~!", prov_name);
+                        if provider.default_model.is_some() {
+                            msg.push_str(&format!("\n  Neural link: {}", model));
+                        }
+                        (CommandResult::Continue, Some(msg))
                     } else {
                         (CommandResult::Continue, Some(format!("～ Unknown provider: {} ...Run 'meow init' to add it nya~", prov_name)))
                     }
                 }
                 None => {
-                    (CommandResult::Continue, Some(format!("～ Current provider: {} ({})
-  Tip: Use '/provider list' to see configured providers nya~!", provider.name, provider.base_url)))
+                    let link = api::version::cached(provider).map(|l| format!("\n  {}", l)).unwrap_or_default();
+                    (CommandResult::Continue, Some(format!("～ Current provider: {} ({}){}
+  Tip: Use '/provider list' to see configured providers nya~!", provider.name, provider.base_url, link)))
                 }
             }
         }
         "/tokens" => {
             let current = calculate_history_tokens(history);
             (CommandResult::Continue, Some(format!("～ Current token usage: {} / {} 
-  Tip: Ask Meow to 'compact the context' when tokens are high nya~!", current, TOKEN_LIMIT_FOR_COMPACTION)))
+  Tip: Ask Meow to 'compact the context' when tokens are high nya~!", current, compaction_threshold(super::prompt::context_window()))))
+        }
+        "/prompt" => {
+            match arg {
+                Some("show") => {
+                    (CommandResult::Display, Some(format!("～ Effective system prompt ({} chars): ～\n\n```text\n{}\n```", system_prompt.len(), system_prompt)))
+                }
+                Some("tokens") => {
+                    let parts = super::prompt::build_parts(config);
+                    let mut output = format!("～ System prompt: ~{} tokens ～\n", estimate_tokens(system_prompt));
+                    output.push_str(&format!("  - persona ({}): ~{}\n", parts.persona_source, estimate_tokens(&parts.persona)));
+                    output.push_str(&format!("  - tools: ~{}\n", estimate_tokens(&parts.tools)));
+                    if let Some(c) = parts.chainlink {
+                        output.push_str(&format!("  - chainlink: ~{}\n", estimate_tokens(c)));
+                    }
+                    if let Some(ref m) = parts.project_map {
+                        output.push_str(&format!("  - project map: ~{}\n", estimate_tokens(m)));
+                    }
+                    if super::prompt::is_compact() {
+                        output.push_str(&format!("  (compact prompt: the model's context window is under {} tokens)\n", super::prompt::SMALL_CONTEXT));
+                    }
+                    if parts.assemble() != *system_prompt {
+                        output.push_str("  (breakdown reflects current settings; use '/prompt reload' to apply them)\n");
+                    }
+                    (CommandResult::Continue, Some(output))
+                }
+                Some("reload") => {
+                    let before = estimate_tokens(system_prompt);
+                    rebuild_system_prompt(config, history, system_prompt);
+                    (CommandResult::Continue, Some(format!("～ *ears perk up* System prompt regenerated nya~! (~{} -> ~{} tokens)", before, estimate_tokens(system_prompt))))
+                }
+                _ => {
+                    (CommandResult::Continue, Some(String::from("～ Usage: /prompt show | tokens | reload nya~")))
+                }
+            }
+        }
+        "/system" => {
+            let (sub, rest) = match arg {
+                Some(a) => a.split_once(' ').map(|(s, r)| (s, r.trim())).unwrap_or((a, "")),
+                None => ("", ""),
+            };
+            match sub {
+                "" => {
+                    let source = if config.system_prompt.is_some() { "set with /system set" } else { "built from the personality" };
+                    (CommandResult::Display, Some(format!("～ System prompt ({}, {} chars): ～\n\n```text\n{}\n```", source, system_prompt.len(), system_prompt)))
+                }
+                "set" if !rest.is_empty() => {
+                    config.system_prompt = Some(String::from(rest));
+                    let _ = config.save();
+                    rebuild_system_prompt(config, history, system_prompt);
+                    (CommandResult::Continue, Some(format!("～ Persona replaced and saved nya~ (~{} tokens with the tool docs). /system reset goes back ～", estimate_tokens(system_prompt))))
+                }
+                "reset" => {
+                    config.system_prompt = None;
+                    let _ = config.save();
+                    rebuild_system_prompt(config, history, system_prompt);
+                    (CommandResult::Continue, Some(format!("～ System prompt back to the {} persona nya~ ～", config.personality().name)))
+                }
+                _ => (CommandResult::Continue, Some(String::from("～ Usage: /system | /system set TEXT | /system reset ～"))),
+            }
         }
         "/personality" => {
             match arg {
@@ -122,7 +248,7 @@ pub fn handle_command(
                     if crate::config::PERSONALITIES.iter().any(|p| p.name == new_p) {
                         config.current_personality = String::from(new_p);
                         let _ = config.save();
-                        (CommandResult::Continue, Some(format!("～ Personality set to {}. (Restart or /clear to apply fully) ～", new_p)))
+                        (CommandResult::Continue, Some(format!("～ Personality set to {}. (Use /prompt reload to apply it now) ～", new_p)))
                     } else {
                         (CommandResult::Continue, Some(format!("～ Unknown personality: {}. Use '/personality list' to see available ones. ～", new_p)))
                     }
@@ -132,6 +258,141 @@ pub fn handle_command(
                 }
             }
         }
+        "/persona" => {
+            let professional = match arg {
+                Some("professional") | Some("off") => true,
+                Some("default") | Some("on") => false,
+                None => !config.professional,
+                Some(other) => return (CommandResult::Continue, Some(format!("～ Unknown mode '{}'. Use /persona off|on nya~", other))),
+            };
+            config.professional = professional;
+            crate::app::state::PLAIN.store(professional, core::sync::atomic::Ordering::SeqCst);
+            crate::app::state::set_postprocess(super::postprocess::Passes::from_config(config));
+            let _ = config.save();
+            rebuild_system_prompt(config, history, system_prompt);
+            let msg = if professional {
+                "Professional mode on: plain persona, no kaomoji."
+            } else {
+                "～ Persona back on, Meow-chan can be herself again nya~!"
+            };
+            (CommandResult::Continue, Some(String::from(msg)))
+        }
+        "/verbosity" => {
+            use crate::config::Verbosity;
+            match arg {
+                None => (CommandResult::Continue, Some(format!("～ Verbosity: {} (default for {}: {}) ～",
+                    crate::app::state::get_verbosity().as_str(), provider.name, provider.verbosity.as_str()))),
+                Some(a) => {
+                    let mut words = a.split_whitespace();
+                    let Some(v) = words.next().and_then(Verbosity::from_str) else {
+                        return (CommandResult::Continue, Some(String::from("～ Usage: /verbosity brief|normal|detailed [default] nya~")));
+                    };
+                    crate::app::state::set_verbosity(v);
+                    // `default` also stores it as the provider's default
+                    if words.next() == Some("default") {
+                        provider.verbosity = v;
+                        if let Some(p) = config.providers.iter_mut().find(|p| p.name == provider.name) {
+                            p.verbosity = v;
+                        }
+                        let _ = config.save();
+                        return (CommandResult::Continue, Some(format!("～ Verbosity set to {} and saved as the default for {} nya~", v.as_str(), provider.name)));
+                    }
+                    (CommandResult::Continue, Some(format!("～ Verbosity set to {} nya~ (max {} tokens per reply)", v.as_str(), v.max_tokens())))
+                }
+            }
+        }
+        "/timeout" => {
+            use crate::api::client::{retry_policy, set_retry_policy};
+            let usage = "～ Usage: /timeout [SECONDS] [retries N] [backoff MS] [save] nya~";
+            let (mut retries, mut backoff, mut timeout) = retry_policy();
+            let mut save = false;
+            let mut words = arg.unwrap_or("").split_whitespace();
+            while let Some(word) = words.next() {
+                let number = |w: Option<&str>| w.and_then(|n| n.parse::<u64>().ok());
+                match word {
+                    "retries" => match number(words.next()) {
+                        Some(n) => retries = n.min(u32::MAX as u64) as u32,
+                        None => return (CommandResult::Continue, Some(String::from(usage))),
+                    },
+                    "backoff" => match number(words.next()) {
+                        Some(n) => backoff = n,
+                        None => return (CommandResult::Continue, Some(String::from(usage))),
+                    },
+                    "save" => save = true,
+                    w => match number(Some(w)) {
+                        Some(n) if n > 0 => timeout = n,
+                        _ => return (CommandResult::Continue, Some(String::from(usage))),
+                    },
+                }
+            }
+            set_retry_policy(retries, backoff, timeout);
+            let summary = format!("timeout {}s without data, {} retries, first backoff {}ms (doubling)", timeout, retries, backoff);
+            if save {
+                config.max_retries = retries;
+                config.initial_backoff_ms = backoff;
+                config.request_timeout_s = timeout;
+                let _ = config.save();
+                return (CommandResult::Continue, Some(format!("～ Requests: {}; saved nya~", summary)));
+            }
+            (CommandResult::Continue, Some(format!("～ Requests: {} ～", summary)))
+        }
+        "/quick" => {
+            use core::sync::atomic::Ordering;
+            let on = match arg {
+                Some("on") => true,
+                Some("off") => false,
+                None => !crate::app::state::QUICK.load(Ordering::SeqCst),
+                Some(_) => return (CommandResult::Continue, Some(String::from("～ Usage: /quick [on|off] nya~"))),
+            };
+            crate::app::state::QUICK.store(on, Ordering::SeqCst);
+            if on {
+                (CommandResult::Continue, Some(format!("～ Quick mode on nya~! Short answers (max {} tokens), no tools. /quick again to switch back.", super::chat::QUICK_MAX_TOKENS)))
+            } else {
+                (CommandResult::Continue, Some(String::from("～ Quick mode off, full agent mode again nya~")))
+            }
+        }
+        "/retry" => {
+            let temperature = match arg {
+                None => None,
+                Some("hot") => Some(super::chat::HOT_TEMPERATURE),
+                Some(t) => match t.parse::<f32>() {
+                    Ok(t) if (0.0..=2.0).contains(&t) => Some(t),
+                    _ => return (CommandResult::Continue, Some(String::from("～ Usage: /retry [hot|TEMPERATURE] nya~"))),
+                },
+            };
+            // A fresh seed with a new temperature, or the reroll could come out the same
+            let sampling = api::Sampling { temperature, seed: temperature.map(|_| libakuma::uptime()), max_tokens: None };
+            match super::chat::rewind_last_turn(history, sampling) {
+                Some(prompt) => {
+                    let how = temperature.map(|t| format!(" at temperature {:.2}", t)).unwrap_or_default();
+                    crate::ui::output::notification(crate::config::COLOR_GRAY_BRIGHT, &format!("Retrying the last message{}\n", how));
+                    (CommandResult::Send(prompt), None)
+                }
+                None => (CommandResult::Continue, Some(String::from("～ Nothing to retry yet nya~"))),
+            }
+        }
+        "/image" => match arg {
+            Some(path) => match super::attachments::queue_image(path) {
+                Ok(reference) => (CommandResult::Continue, Some(format!("～ Attached {} to your next message nya~", reference))),
+                Err(e) => (CommandResult::Continue, Some(format!("～ *ears droop* {} nya~", e))),
+            },
+            None => (CommandResult::Continue, Some(String::from("～ Usage: /image <PATH> (png, jpeg, gif or webp; or write @PATH in your message) nya~"))),
+        },
+        "/think" => {
+            use core::sync::atomic::Ordering;
+            let show = match arg {
+                Some("show") => true,
+                Some("hide") => false,
+                None => !crate::api::think::SHOW.load(Ordering::Relaxed),
+                Some(_) => return (CommandResult::Continue, Some(String::from("～ Usage: /think [show|hide] nya~"))),
+            };
+            crate::api::think::SHOW.store(show, Ordering::Relaxed);
+            if show {
+                (CommandResult::Continue, Some(String::from("～ Showing <think> blocks (dimmed) as they stream nya~")))
+            } else {
+                (CommandResult::Continue, Some(String::from("～ Folding <think> blocks into one line nya~")))
+            }
+        }
         "/markdown" => {
             config.render_markdown = !config.render_markdown;
             crate::app::state::set_render_markdown(config.render_markdown);
@@ -139,6 +400,346 @@ pub fn handle_command(
             let status = if config.render_markdown { "enabled" } else { "disabled" };
             (CommandResult::Continue, Some(format!("～ *tail swishes* Markdown rendering {} nya~!", status)))
         }
+        "/view" => {
+            use crate::ui::tui::preview;
+            match arg {
+                Some("off") | Some("close") => {
+                    preview::close_split();
+                    (CommandResult::Continue, Some(String::from("～ Split view closed nya~!")))
+                }
+                Some(path) => match preview::open_split(Some(path)) {
+                    Ok(msg) => (CommandResult::Continue, Some(msg)),
+                    Err(e) => (CommandResult::Continue, Some(format!("～ {} nya...", e))),
+                },
+                None if preview::is_open() => {
+                    preview::close_split();
+                    (CommandResult::Continue, Some(String::from("～ Split view closed nya~!")))
+                }
+                None => match preview::open_split(None) {
+                    Ok(msg) => (CommandResult::Continue, Some(msg)),
+                    Err(e) => (CommandResult::Continue, Some(format!("～ {} nya...", e))),
+                },
+            }
+        }
+        "/diff" => {
+            if !crate::ui::tui::diff_view::has_change() {
+                return (CommandResult::Continue, Some(String::from("～ No file edits to show yet nya~")));
+            }
+            crate::ui::tui::diff_view::show();
+            tui_app::restore_screen();
+            (CommandResult::Continue, None)
+        }
+        "/params" => {
+            crate::ui::tui::params_view::show(model, config);
+            tui_app::restore_screen();
+            let opts = crate::app::params::current(model);
+            let set: Vec<String> = crate::config::GenOptions::KEYS.iter()
+                .filter_map(|k| opts.get(k).map(|v| format!("{}={}", k, v)))
+                .collect();
+            if set.is_empty() {
+                return (CommandResult::Continue, Some(format!("～ {} uses the provider's defaults nya~", model)));
+            }
+            (CommandResult::Continue, Some(format!("～ Parameters for {}: {} ～", model, set.join(", "))))
+        }
+        "/set" => {
+            use crate::config::GenOptions;
+            let Some(arg) = arg.filter(|a| !a.is_empty()) else {
+                let opts = crate::app::params::global();
+                let set: Vec<String> = GenOptions::KEYS.iter()
+                    .filter_map(|k| opts.get(k).map(|v| format!("{}={}", k, v)))
+                    .collect();
+                if set.is_empty() {
+                    return (CommandResult::Continue, Some(format!("～ No parameters set for every model; the provider's defaults apply. Usage: /set <{}> [VALUE] nya~", GenOptions::KEYS.join("|"))));
+                }
+                return (CommandResult::Continue, Some(format!("～ Parameters for every model: {} ～", set.join(", "))));
+            };
+            let (key, value) = arg.split_once(' ').unwrap_or((arg, ""));
+            match crate::app::params::set_global(key, value, config) {
+                Ok(()) if value.trim().is_empty() => (CommandResult::Continue, Some(format!("～ {} unset; the provider decides nya~", key))),
+                Ok(()) => (CommandResult::Continue, Some(format!("～ {} set to {} for every model nya~", key, value.trim()))),
+                Err(e) => (CommandResult::Continue, Some(format!("～ *ears droop* {} nya...", e))),
+            }
+        }
+        "/page" => {
+            if !crate::ui::tui::pager::has_text() {
+                return (CommandResult::Continue, Some(String::from("～ Nothing long enough to page yet nya~")));
+            }
+            crate::ui::tui::pager::show_last();
+            tui_app::restore_screen();
+            (CommandResult::Continue, None)
+        }
+        "/wrap" => {
+            use crate::ui::tui::wrap;
+            let mut words = arg.unwrap_or("").split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) => return (CommandResult::Continue, Some(wrap::describe())),
+                (Some("on"), None) => wrap::set_word_wrap(true),
+                (Some("off"), None) => wrap::set_word_wrap(false),
+                (Some("full"), None) => wrap::set_max_width(0),
+                (Some("left"), None) => wrap::set_center(false),
+                (Some("center"), None) => wrap::set_center(true),
+                (Some("scroll"), Some(n)) if !wrap::word_wrap() => match n.parse() {
+                    Ok(cols) => wrap::set_h_scroll(cols),
+                    Err(_) => return (CommandResult::Continue, Some(String::from("～ Usage: /wrap scroll COLUMNS ～"))),
+                },
+                (Some("scroll"), _) => return (CommandResult::Continue, Some(String::from("～ Scrolling sideways needs /wrap off first nya ～"))),
+                (Some(n), None) if n.bytes().all(|b| b.is_ascii_digit()) => wrap::set_max_width(n.parse().unwrap_or(0)),
+                _ => return (CommandResult::Continue, Some(String::from("～ Usage: /wrap [on|off|COLUMNS|full|left|center|scroll N] ～"))),
+            }
+            // Lay the conversation out again with the new settings
+            tui_app::restore_screen();
+            (CommandResult::Continue, Some(wrap::describe()))
+        }
+        "/animation" => {
+            let enable = match arg {
+                Some("on") => true,
+                Some("off") => false,
+                None => !config.animation,
+                Some(other) => return (CommandResult::Continue, Some(format!("～ Unknown option '{}'. Use /animation on|off nya~", other))),
+            };
+            config.animation = enable;
+            crate::app::state::set_animation(enable);
+            let _ = config.save();
+            let msg = if enable {
+                "～ *tail wiggles* Status animation on nya~!"
+            } else {
+                "～ Status animation frozen; the footer now redraws only when it changes nya~"
+            };
+            (CommandResult::Continue, Some(String::from(msg)))
+        }
+        "/motd" => {
+            match arg {
+                Some("off") => {
+                    config.motd = None;
+                    let _ = config.save();
+                    crate::ui::tui::banner::refresh_motd(config);
+                    (CommandResult::Continue, Some(String::from("～ Message of the day cleared nya~")))
+                }
+                Some(text) => {
+                    config.motd = Some(String::from(text));
+                    let _ = config.save();
+                    match crate::ui::tui::banner::refresh_motd(config) {
+                        Some(m) => (CommandResult::Continue, Some(format!("～ Message of the day set: {} ～", m))),
+                        None => (CommandResult::Continue, Some(String::from("～ Message of the day set, but it resolved to nothing (is chainlink installed?) ～"))),
+                    }
+                }
+                None => match crate::ui::tui::banner::refresh_motd(config) {
+                    Some(m) => (CommandResult::Continue, Some(format!("～ {} ～", m))),
+                    None => (CommandResult::Continue, Some(String::from("～ No message of the day. Set one with /motd TEXT or /motd @issues nya~"))),
+                },
+            }
+        }
+        "/issues" => {
+            let Some(section) = crate::tools::chainlink::open_issues_context() else {
+                return (CommandResult::Continue, Some(String::from("～ chainlink isn't installed (or failed), no issues to show nya ～")));
+            };
+            // Refresh the list in the context message while it's still in
+            // the history; the output below is added to it either way
+            if let Some(ctx) = history.iter_mut().find(|m| m.role == "user" && m.content.chunks().next().is_some_and(|c| c.starts_with("[System Context]"))) {
+                let text: String = ctx.content.chunks().collect();
+                *ctx = Message::new("user", &crate::tools::chainlink::with_issues_context(&text, &section));
+            }
+            (CommandResult::Continue, Some(section))
+        }
+        "/issue" => {
+            use crate::tools::chainlink;
+            let mut words = arg.unwrap_or("").split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("link"), Some(n)) => {
+                    let Ok(id) = n.trim_start_matches('#').parse::<usize>() else {
+                        return (CommandResult::Continue, Some(String::from("～ Usage: /issue link N ～")));
+                    };
+                    if !chainlink::chainlink_available() {
+                        return (CommandResult::Continue, Some(String::from("～ chainlink isn't installed, nothing to link to nya ～")));
+                    }
+                    if id == 0 || !chainlink::tool_chainlink_show(id).success {
+                        return (CommandResult::Continue, Some(format!("～ No chainlink issue #{} nya... ～", id)));
+                    }
+                    chainlink::link_issue(Some(id));
+                    (CommandResult::Continue, Some(format!("～ Session linked to issue #{}: tool commits will reference it, and the session summary is commented on it when the session is saved at exit ～", id)))
+                }
+                (Some("unlink"), None) => {
+                    chainlink::link_issue(None);
+                    (CommandResult::Continue, Some(String::from("～ Session unlinked from its issue ～")))
+                }
+                (None, _) => match chainlink::linked_issue() {
+                    Some(id) => (CommandResult::Continue, Some(format!("～ Session linked to issue #{} (/issue unlink to stop) ～", id))),
+                    None => (CommandResult::Continue, Some(String::from("～ Session not linked to an issue. Use /issue link N ～"))),
+                },
+                _ => (CommandResult::Continue, Some(String::from("～ Usage: /issue [link N | unlink] ～"))),
+            }
+        }
+        "/keytest" => {
+            use crate::ui::tui::{input, keys};
+            let action = arg.filter(|a| !a.is_empty());
+            if let Some(a) = action {
+                if keys::action(a).is_none() {
+                    let names: Vec<&str> = keys::ACTIONS.iter().map(|(n, _)| *n).collect();
+                    return (CommandResult::Continue, Some(format!("～ Unknown action '{}'. Actions: {} ～", a, names.join(", "))));
+                }
+            }
+            crate::ui::output::notification(crate::config::COLOR_GRAY_BRIGHT, "Press the key to test (5s)...\n");
+            let Some(bytes) = keys::capture(5000) else {
+                return (CommandResult::Continue, Some(String::from("～ No key pressed ～")));
+            };
+            let seq = keys::format_sequence(&bytes);
+            let (event, n) = input::parse_input(&bytes);
+            let reads_as = if n == bytes.len() && event != input::InputEvent::Unknown {
+                keys::action_name(event).map(String::from).unwrap_or_else(|| format!("{:?}", event))
+            } else {
+                String::from("nothing meow knows")
+            };
+            match action {
+                Some(a) => {
+                    if let Err(e) = keys::bind(a, &seq) {
+                        return (CommandResult::Continue, Some(format!("～ {} ～", e)));
+                    }
+                    config.keys.retain(|(_, s)| *s != seq);
+                    config.keys.push((String::from(a), seq.clone()));
+                    let _ = config.save();
+                    (CommandResult::Continue, Some(format!("～ Bound `{}` to {} (was: {}) and saved it under [keys] ～", seq, a, reads_as)))
+                }
+                None => (CommandResult::Continue, Some(format!("～ That key sends `{}`, which reads as {}. `/keytest ACTION` binds it ～", seq, reads_as))),
+            }
+        }
+        "/save" => {
+            let name = arg.filter(|a| !a.is_empty()).unwrap_or(super::session::DEFAULT_NAME);
+            match super::session::save_session(name, history) {
+                Ok(n) => {
+                    let mut msg = format!("～ Saved {} messages (~{} tokens) as session '{}'. /load {} brings them back nya~", n, calculate_history_tokens(history), name, name);
+                    // A linked issue hears about every save, not just the one on exit
+                    if let Some(id) = super::summary::post_to_linked_issue() {
+                        msg.push_str(&format!("\n  Summary commented on chainlink issue #{}", id));
+                    }
+                    msg.push_str(" ～");
+                    (CommandResult::Continue, Some(msg))
+                }
+                Err(e) => (CommandResult::Continue, Some(format!("～ Couldn't save session '{}': {} ～", name, e))),
+            }
+        }
+        "/checkpoint" => {
+            match arg.filter(|a| !a.is_empty()) {
+                None => {
+                    let saved = super::checkpoint::list();
+                    let files = super::changes::session_files();
+                    let saved = if saved.is_empty() { String::from("none yet") } else { saved.join(", ") };
+                    (CommandResult::Continue, Some(format!("～ Checkpoints: {}
+  {} file(s) changed this session. /checkpoint NAME snapshots them ～", saved, files.len())))
+                }
+                Some(name) => match super::checkpoint::save(name, &super::changes::session_files()) {
+                    Ok((copied, absent, skipped)) => {
+                        let mut msg = format!("～ Checkpoint '{}': {} file(s) saved", name, copied);
+                        if absent > 0 {
+                            msg.push_str(&format!(", {} noted as not present", absent));
+                        }
+                        if !skipped.is_empty() {
+                            msg.push_str(&format!("\n  Skipped (unreadable or over 1MB): {}", skipped.join(", ")));
+                        }
+                        msg.push_str(&format!("\n  Files changed from now on are added before their first change; /restore {} brings it all back nya~ ～", name));
+                        (CommandResult::Continue, Some(msg))
+                    }
+                    Err(e) => (CommandResult::Continue, Some(format!("～ Couldn't save checkpoint '{}': {} ～", name, e))),
+                },
+            }
+        }
+        "/goal" => {
+            let usage = "～ Usage: /goal set TEXT | done [N] | list | clear ～";
+            let (sub, rest) = match arg {
+                Some(a) => a.split_once(' ').map(|(s, r)| (s, r.trim())).unwrap_or((a, "")),
+                None => ("list", ""),
+            };
+            match sub {
+                "list" => (CommandResult::Continue, Some(super::goals::list())),
+                "set" if !rest.trim_matches('"').trim().is_empty() => {
+                    let n = super::goals::set(rest);
+                    (CommandResult::Continue, Some(format!("～ Goal {} set nya~ It goes with every request until /goal done {} ～", n, n)))
+                }
+                "done" => {
+                    let n = if rest.is_empty() { None } else {
+                        match rest.parse::<usize>() {
+                            Ok(n) => Some(n),
+                            Err(_) => return (CommandResult::Continue, Some(String::from(usage))),
+                        }
+                    };
+                    match super::goals::done(n) {
+                        Ok(text) => {
+                            crate::app::state::push_message(format!(
+                                "The goal \"{}\" is marked done. Make a final check that it really is complete (build, run the tests, or re-read what changed) and report anything still missing.",
+                                text
+                            ));
+                            (CommandResult::Continue, Some(format!("～ Goal done: {} — asking for a final check nya~ ～", text)))
+                        }
+                        Err(e) => (CommandResult::Continue, Some(format!("～ {} ～", e))),
+                    }
+                }
+                "clear" => {
+                    super::goals::clear();
+                    (CommandResult::Continue, Some(String::from("～ Goals cleared ～")))
+                }
+                _ => (CommandResult::Continue, Some(String::from(usage))),
+            }
+        }
+        "/restore" => {
+            let Some(name) = arg.filter(|a| !a.is_empty()) else {
+                return (CommandResult::Continue, Some(String::from("～ Usage: /restore NAME (see /checkpoint for the saved ones) ～")));
+            };
+            match super::checkpoint::restore(name) {
+                Ok((restored, failed)) => {
+                    let mut msg = format!("～ Restored {} file(s) from checkpoint '{}'", restored, name);
+                    if !failed.is_empty() {
+                        msg.push_str(&format!("\n  Not restored: {}", failed.join(", ")));
+                    }
+                    msg.push_str(" ～");
+                    (CommandResult::Continue, Some(msg))
+                }
+                Err(e) => (CommandResult::Continue, Some(format!("～ Couldn't restore checkpoint '{}': {} ～", name, e))),
+            }
+        }
+        "/load" => {
+            let name = arg.filter(|a| !a.is_empty()).unwrap_or(super::session::DEFAULT_NAME);
+            match super::session::load_session(name) {
+                Ok(loaded) => {
+                    *history = loaded;
+                    // The session keeps the system prompt it was saved with
+                    match history.first() {
+                        Some(first) if first.role == "system" => *system_prompt = first.content.chunks().collect(),
+                        _ => history.insert(0, Message::new("system", system_prompt.as_str())),
+                    }
+                    super::changes::reset();
+                    (CommandResult::Continue, Some(format!(
+                        "～ Loaded session '{}': {} messages, ~{} / {} tokens ～",
+                        name, history.len(), calculate_history_tokens(history), compaction_threshold(super::prompt::context_window())
+                    )))
+                }
+                Err(e) => {
+                    let saved = super::session::list_sessions();
+                    let hint = if saved.is_empty() { String::from("no saved sessions yet") } else { format!("saved: {}", saved.join(", ")) };
+                    (CommandResult::Continue, Some(format!("～ Couldn't load session '{}': {} ({}) ～", name, e, hint)))
+                }
+            }
+        }
+        "/mem" => (CommandResult::Continue, Some(mem_report(history))),
+        "/diff-answers" => {
+            // The last answer against the one it replaced when /retry asked
+            // the same prompt again
+            match super::chat::retried_answers(history) {
+                Some((old, new)) => (CommandResult::Continue, Some(super::word_diff::compare(&old, &new))),
+                None => (CommandResult::Continue, Some(String::from("～ Nothing to compare nya~ /retry the last message first, then /diff-answers shows what changed ～"))),
+            }
+        }
+        "/1" | "/2" | "/3" => {
+            let n = command.as_bytes()[1] - b'0';
+            match super::suggest::get(n as usize) {
+                Some(s) => {
+                    crate::app::state::push_message(s);
+                    (CommandResult::Continue, None)
+                }
+                None => (CommandResult::Continue, Some(format!("～ No suggestion {} right now nya~", n))),
+            }
+        }
+        "/latency" => (CommandResult::Continue, Some(super::latency::report())),
+        "/stats" => (CommandResult::Continue, Some(super::metrics::report())),
+        "/tools" => (CommandResult::Continue, Some(crate::tools::registry::describe())),
         "/hotkeys" | "/shortcuts" => {
             let output = String::from("# Meow's Input Shortcuts
 
@@ -150,9 +751,14 @@ pub fn handle_command(
 * **Alt+B** / **Opt+Left**: Move back one word
 * **Alt+F** / **Opt+Right**: Move forward one word
 * **Arrows**: Navigate history and line
+* **Ctrl+T**: Open a new chat tab
+* **Ctrl+Left** / **Ctrl+Right**: Switch between tabs
 * **ESC** / **Ctrl+C**: Cancel current AI request
+* **Ctrl+S**: Stop the reply here and keep what arrived as the answer
+* **Ctrl+D**: Send the message composed with `/more`
+* **Ctrl+N**: Freeze or restart the status animation for this session
 
-*Note: Some terminals intercept Ctrl+W/U/C.*
+*Note: Some terminals intercept Ctrl+W/U/C, and some never pass Shift+Enter on; `/more` composes a multi-line message one Enter at a time instead, and `/keytest ACTION` binds whatever your terminal does send.*
 ");
             (CommandResult::Continue, Some(output))
         }
@@ -165,8 +771,41 @@ pub fn handle_command(
 * `/provider`: Check/switch provider
 * `/provider list`: List configured providers
 * `/personality [NAME]`: Check/switch personality
+* `/persona [off|on]`: Professional mode (plain persona, no kaomoji) or back to the configured personality
 * `/tokens`: Show current token usage
+* `/prompt show|tokens|reload`: Inspect or regenerate the system prompt
+* `/system [set TEXT|reset]`: Show the system prompt; `set` replaces the persona with TEXT (tool docs stay) and saves it in the config, `reset` goes back to the personality
 * `/markdown`: Toggle Markdown rendering nya~
+* `/wrap [on|off|COLUMNS|full|left|center|scroll N]`: Text width, alignment and word wrap of the output pane
+* `/save [NAME]`: Save the conversation, system prompt included, as a named session
+* `/load [NAME]`: Replace the conversation with a saved session (lists the saved ones if NAME isn't found)
+* `/more`: Compose a message line by line; Enter adds a line, `/send` or Ctrl+D sends it all, `/more` again drops it
+* `/keytest [ACTION]`: Show the bytes the next key sends; with ACTION, bind that key to it (see `[keys]` in the config docs)
+* `/checkpoint [NAME]`: Snapshot the files changed this session, and later ones before they first change (lists checkpoints without NAME)
+* `/restore NAME`: Write a checkpoint's files back and remove the files it noted as not present
+* `/goal set TEXT`: Keep a goal in front of the model on every request, across compactions; `/goal done [N]` completes it and asks for a final check; `/goal list`, `/goal clear`
+* `/page`: Reopen the last long command or tool output in the pager (space/b page, j/k scroll, q close)
+* `/mem`: Show heap usage, broken down by subsystem
+* `/diff-answers`: Compare the answer `/retry` replaced with the new one sentence by sentence, rewordings marked word by word
+* `/issues`: List open chainlink issues and refresh them in the model's context
+* `/issue link N`: Link the session to chainlink issue N (tool commits reference it, the summary is commented on it at each /save and on exit); `/issue unlink` stops
+* `/1` `/2` `/3`: Send a suggested follow-up (with `suggestions=true` in the config)
+* `/latency`: Time to first token and turn time per provider/model this session (p50/p90/p99, histogram)
+* `/stats`: Requests, retries, cancelled turns, tokens streamed, average TTFT and TPS, and tool calls by name this session
+* `/retry [hot|TEMPERATURE]`: Drop the last reply (and its tool calls) and send your last message again; `hot` rolls at a higher temperature
+* `/image <PATH>`: Attach an image to your next message for a vision model (`@shot.png` in the message works too)
+* `/think [show|hide]`: Show reasoning models' `<think>` blocks dimmed, or fold them into one line (default); they are never sent back
+* `/tools`: List the model's tools by category, marking any that are unavailable
+* `/models all [refresh]`: Models from every configured provider as `provider/model`; `/model provider/model` switches to one
+* `/quick [on|off]`: Quick-answer mode: short replies, no tools, no tool docs in the prompt
+* `/verbosity [brief|normal|detailed] [default]`: Reply length preset; `default` saves it for the current provider
+* `/timeout [SECONDS] [retries N] [backoff MS] [save]`: Show or change how long a request may go without data and how often it is retried; `save` writes it to the config
+* `/animation [on|off]`: Toggle the status animation (off is kinder to slow serial links)
+* `/diff`: Full-screen view of the last file edit (j/k scroll, q close)
+* `/params`: Edit the request parameters (temperature, top_p, num_predict, seed, stop, keep_alive) for the current model; `s` saves them in the config
+* `/set [KEY] [VALUE]`: Set a request parameter for every model and save it (`/set temperature 0.2`); without a value it is unset, without a key all are listed
+* `/view [PATH|off]`: Split view with a file preview (follows edits, or pinned to PATH)
+* `/motd [TEXT|@issues|off]`: Show or set the message of the day
 * `/hotkeys`: Show input shortcuts
 * `/test_stream`: Run internal renderer tests
 * `/quit`: Jack out of the matrix
@@ -190,3 +829,36 @@ pub fn handle_command(
         }
     }
 }
+
+/// `/mem`: allocator counters per subsystem, or estimates when the
+/// instrumented allocator isn't installed
+fn mem_report(history: &[Message]) -> String {
+    use crate::heap;
+
+    let mut out = format!("～ Heap in use: {} KB ～\n", libakuma::memory_usage() / 1024);
+    if heap::is_active() {
+        let total = heap::total();
+        out.push_str(&format!("  {:<8} {:>9} {:>9} {:>9}\n", "tag", "live KB", "peak KB", "allocs"));
+        for tag in heap::TAGS {
+            let s = heap::stats(tag);
+            out.push_str(&format!("  {:<8} {:>9} {:>9} {:>9}\n", tag.name(), s.live / 1024, s.peak / 1024, s.allocs));
+        }
+        out.push_str(&format!("  {:<8} {:>9} {:>9} {:>9}\n", "total", total.live / 1024, total.peak / 1024, total.allocs));
+    } else {
+        let history_bytes: usize = history.iter().map(|m| m.role.capacity() + m.content.capacity()).sum();
+        out.push_str(&format!("  - history: ~{} KB in {} messages\n", history_bytes / 1024, history.len()));
+        out.push_str(&format!("  - tui scrollback: ~{} KB\n", crate::ui::tui::tabs::scrollback_bytes() / 1024));
+        out.push_str("  (estimates; build with the heap-profile feature for per-subsystem counters)\n");
+    }
+    out
+}
+
+/// Build the system prompt from the current settings and put it in place
+/// of the history's first message
+fn rebuild_system_prompt(config: &Config, history: &mut Vec<Message>, system_prompt: &mut String) {
+    *system_prompt = super::prompt::build_system_prompt(config);
+    match history.first_mut() {
+        Some(first) if first.role == "system" => *first = Message::new("system", system_prompt.as_str()),
+        _ => history.insert(0, Message::new("system", system_prompt.as_str())),
+    }
+}
@@ -0,0 +1,91 @@
+//! Session goals (`/goal`)
+//!
+//! A goal set with `/goal set` rides along with every request as a system
+//! note, like the verbosity hint, so it is never part of the history and
+//! survives any number of compactions. `/goal done` retires it and asks the
+//! model for a last check that the work really is finished.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+struct Goal {
+    text: String,
+    done: bool,
+}
+
+static mut GOALS: Vec<Goal> = Vec::new();
+
+fn goals() -> &'static mut Vec<Goal> {
+    unsafe { &mut *core::ptr::addr_of_mut!(GOALS) }
+}
+
+/// A tab's goals while another tab is active
+#[derive(Default)]
+pub struct Parked(Vec<Goal>);
+
+/// Take the goals out for a tab that is being put aside
+pub fn park() -> Parked {
+    Parked(core::mem::take(goals()))
+}
+
+pub fn unpark(parked: Parked) {
+    *goals() = parked.0;
+}
+
+/// Add a goal; returns its number
+pub fn set(text: &str) -> usize {
+    let text = text.trim().trim_matches('"').trim();
+    goals().push(Goal { text: String::from(text), done: false });
+    goals().len()
+}
+
+/// Mark goal `n` (1-based; the first open goal if None) as done and return
+/// its text
+pub fn done(n: Option<usize>) -> Result<String, &'static str> {
+    let goal = match n {
+        Some(n) => goals().get_mut(n.wrapping_sub(1)).ok_or("No goal with that number")?,
+        None => goals().iter_mut().find(|g| !g.done).ok_or("No open goals")?,
+    };
+    if goal.done {
+        return Err("That goal is already done");
+    }
+    goal.done = true;
+    Ok(goal.text.clone())
+}
+
+/// The first open goal
+pub fn current() -> Option<String> {
+    goals().iter().find(|g| !g.done).map(|g| g.text.clone())
+}
+
+pub fn clear() {
+    goals().clear();
+}
+
+/// The /goal list
+pub fn list() -> String {
+    if goals().is_empty() {
+        return String::from("～ No goals yet nya~ Set one with /goal set TEXT ～");
+    }
+    let mut out = String::from("# Goals\n\n");
+    for (i, g) in goals().iter().enumerate() {
+        let mark = if g.done { "x" } else { " " };
+        out.push_str(&format!("{}. [{}] {}\n", i + 1, mark, g.text));
+    }
+    out
+}
+
+/// System note with the open goals, sent with every request
+pub fn context_note() -> Option<String> {
+    let open: Vec<&Goal> = goals().iter().filter(|g| !g.done).collect();
+    if open.is_empty() {
+        return None;
+    }
+    let mut note = String::from("Session goals the user set; keep working toward them and say when one looks complete:");
+    for g in open {
+        note.push_str("\n- ");
+        note.push_str(&g.text);
+    }
+    Some(note)
+}
@@ -2,26 +2,225 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
 
+use crate::config::ApiType;
+use super::attachments::{self, Attachment, Image};
+
+/// Largest piece of message text held in one allocation
+const CHUNK_SIZE: usize = 4096;
+
+/// Message text stored as a list of chunks of at most `CHUNK_SIZE` bytes.
+/// Appending never moves what is already stored and no allocation grows
+/// past a chunk, which keeps long replies from fragmenting the heap.
+#[derive(Clone, Default)]
+pub struct Content {
+    chunks: Vec<String>,
+    /// (counting generation, tokens) from the last `token_count`
+    tokens: core::cell::Cell<(u32, usize)>,
+}
+
+impl Content {
+    pub fn push_str(&mut self, mut text: &str) {
+        self.tokens.set((0, 0));
+        while !text.is_empty() {
+            let room = match self.chunks.last() {
+                Some(last) if last.len() < CHUNK_SIZE => CHUNK_SIZE - last.len(),
+                _ => 0,
+            };
+            let mut take = core::cmp::min(room, text.len());
+            while !text.is_char_boundary(take) { take -= 1; }
+            if take == 0 {
+                let size = core::cmp::min(CHUNK_SIZE, text.len());
+                self.chunks.push(String::with_capacity(size));
+                continue;
+            }
+            if let Some(last) = self.chunks.last_mut() {
+                if last.capacity() - last.len() < take {
+                    // Grow geometrically, but never past a chunk
+                    let want = core::cmp::min(CHUNK_SIZE, core::cmp::max(last.len() * 2, last.len() + take));
+                    last.reserve_exact(want - last.len());
+                }
+                last.push_str(&text[..take]);
+            }
+            text = &text[take..];
+        }
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.chunks.iter().map(|c| c.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks().map(str::len).sum()
+    }
+
+    /// Estimated tokens, counted once per way of counting
+    pub fn token_count(&self) -> usize {
+        let generation = super::tokens::generation();
+        let (cached_generation, cached) = self.tokens.get();
+        if cached_generation == generation {
+            return cached;
+        }
+        let count = self.chunks().map(super::tokens::count).sum();
+        self.tokens.set((generation, count));
+        count
+    }
+
+    /// Heap bytes held, chunk list included
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|c| c.capacity()).sum::<usize>()
+            + self.chunks.capacity() * core::mem::size_of::<String>()
+    }
+
+    /// Only the last chunk can have slack; the others are full
+    pub fn shrink_to_fit(&mut self) {
+        if let Some(last) = self.chunks.last_mut() {
+            last.shrink_to_fit();
+        }
+        self.chunks.shrink_to_fit();
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        let mut content = Content::default();
+        content.push_str(text);
+        content
+    }
+}
+
+impl From<String> for Content {
+    /// Small strings are kept as they are, without a copy
+    fn from(text: String) -> Self {
+        if text.len() <= CHUNK_SIZE {
+            Content { chunks: alloc::vec![text], ..Content::default() }
+        } else {
+            Content::from(text.as_str())
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    pub content: Content,
+    /// For a "tool" message: the native call it answers, when the provider
+    /// gave the call an id
+    pub tool_call_id: Option<String>,
+    /// For an "assistant" message: its native tool calls, as the provider's
+    /// `tool_calls` JSON array
+    pub tool_calls: Option<String>,
+    /// Large tool output or `@file` contents, sent after the text while the
+    /// message is recent; see `attachments`
+    pub attachments: Vec<Attachment>,
+    /// Images on a user message, sent along while it is recent
+    pub images: Vec<Image>,
 }
 
 impl Message {
     pub fn new(role: &str, content: &str) -> Self {
+        let _t = crate::heap::tag(crate::heap::Tag::History);
         Self {
             role: String::from(role),
-            content: String::from(content),
+            content: Content::from(content),
+            tool_call_id: None,
+            tool_calls: None,
+            attachments: Vec::new(),
+            images: Vec::new(),
         }
     }
 
+    /// A tool result, answering native call `call_id` if there was one
+    pub fn tool(content: &str, call_id: Option<&str>) -> Self {
+        let mut msg = Self::new("tool", content);
+        msg.tool_call_id = call_id.map(String::from);
+        msg
+    }
+
     pub fn write_json(&self, out: &mut String) {
-        out.push_str("{\"role\":\"");
-        out.push_str(&self.role);
-        out.push_str("\",\"content\":\"");
-        json_escape_to(&self.content, out);
-        out.push_str("\"}");
+        self.json_pieces(|p| out.push_str(p));
+    }
+
+    /// Feed the message's JSON to `f` piece by piece, escaping on the fly.
+    /// This is the stored form, with every field kept and the attachments
+    /// written into the content. Images are left out.
+    pub fn json_pieces(&self, f: impl FnMut(&str)) {
+        self.pieces(&self.role, true, true, true, None, f);
+    }
+
+    /// The message as `api_type` wants it in a request. OpenAI gets tool
+    /// results as role "tool" answering the assistant's `tool_calls`; a
+    /// result without a call id (text protocol) and everything on Ollama is
+    /// folded into a user message, the way tool results always went.
+    /// Attachments and images are sent in full only when `inline`.
+    pub fn request_pieces(&self, api_type: &ApiType, inline: bool, f: impl FnMut(&str)) {
+        let native = *api_type == ApiType::OpenAI;
+        let images = inline.then_some(api_type);
+        match self.role.as_str() {
+            "tool" if native && self.tool_call_id.is_some() => self.pieces("tool", true, false, inline, images, f),
+            "tool" => self.pieces("user", false, false, inline, images, f),
+            _ => self.pieces(&self.role, false, native, inline, images, f),
+        }
+    }
+
+    /// `format`: the provider format to send the images in, None to leave
+    /// them out
+    fn pieces(&self, role: &str, with_call_id: bool, with_calls: bool, inline: bool, format: Option<&ApiType>, mut f: impl FnMut(&str)) {
+        let images: Vec<&Image> = match format {
+            Some(_) => self.images.iter().filter(|i| i.data.is_some()).collect(),
+            None => Vec::new(),
+        };
+        // OpenAI wants a message with images as a list of content parts
+        let parts = !images.is_empty() && format == Some(&ApiType::OpenAI);
+        f("{\"role\":\"");
+        f(role);
+        if let (true, Some(id)) = (with_call_id, self.tool_call_id.as_deref()) {
+            f("\",\"tool_call_id\":\"");
+            json_escape_with(id, &mut f);
+        }
+        f(if parts { "\",\"content\":[{\"type\":\"text\",\"text\":\"" } else { "\",\"content\":\"" });
+        for chunk in self.content.chunks() {
+            json_escape_with(chunk, &mut f);
+        }
+        for att in &self.attachments {
+            att.pieces(inline, |p| json_escape_with(p, &mut f));
+        }
+        f("\"");
+        if parts {
+            f("}");
+            for image in &images {
+                f(",{\"type\":\"image_url\",\"image_url\":{\"url\":\"data:");
+                f(image.mime);
+                f(";base64,");
+                image.data.iter().flat_map(|d| d.chunks()).for_each(&mut f);
+                f("\"}}");
+            }
+            f("]");
+        } else if !images.is_empty() {
+            f(",\"images\":[");
+            for (i, image) in images.iter().enumerate() {
+                f(if i > 0 { ",\"" } else { "\"" });
+                image.data.iter().flat_map(|d| d.chunks()).for_each(&mut f);
+                f("\"");
+            }
+            f("]");
+        }
+        if let (true, Some(calls)) = (with_calls, self.tool_calls.as_deref()) {
+            f(",\"tool_calls\":");
+            f(calls);
+        }
+        f("}");
+    }
+
+    /// Parse a message previously produced by `write_json`
+    pub fn from_json(json: &str) -> Option<Self> {
+        let role = extract_json_string(json, "role")?;
+        let content = extract_json_string(json, "content")?;
+        let tool_call_id = extract_json_string(json, "tool_call_id");
+        let tool_calls = json.find("\"tool_calls\"")
+            .and_then(|p| crate::api::tool_calls::object_at(&json[p + "\"tool_calls\"".len()..]))
+            .filter(|calls| calls.starts_with('['))
+            .map(String::from);
+        Some(Self { role, content: Content::from(content), tool_call_id, tool_calls, attachments: Vec::new(), images: Vec::new() })
     }
 }
 
@@ -32,40 +231,121 @@ pub fn trim_history(history: &mut Vec<Message>) {
         let to_remove = history.len() - MAX_HISTORY_SIZE;
         history.drain(1..1 + to_remove);
     }
+    // A tool result whose call was trimmed away would be rejected; drop it too
+    while history.len() > 1 && history[1].role == "tool" {
+        history.remove(1);
+    }
+}
+
+/// Turn the last round of native tool calls back into plain messages, for
+/// when it was cut short and some of the calls will never be answered
+pub fn drop_pending_calls(history: &mut [Message]) {
+    if let Some(pos) = history.iter().rposition(|m| m.tool_calls.is_some()) {
+        history[pos].tool_calls = None;
+        for msg in &mut history[pos + 1..] {
+            msg.tool_call_id = None;
+        }
+    }
 }
 
 pub fn compact_history(history: &mut Vec<Message>) {
     for msg in history.iter_mut() {
         msg.role.shrink_to_fit();
         msg.content.shrink_to_fit();
+        if let Some(calls) = msg.tool_calls.as_mut() {
+            calls.shrink_to_fit();
+        }
+        for att in msg.attachments.iter_mut() {
+            if let Some(body) = att.body.as_mut() {
+                body.shrink_to_fit();
+            }
+        }
+        for image in msg.images.iter_mut() {
+            if let Some(data) = image.data.as_mut() {
+                data.shrink_to_fit();
+            }
+        }
     }
     history.shrink_to_fit();
 }
 
 pub fn estimate_tokens(text: &str) -> usize {
-    (text.len() + 3) / 4
+    super::tokens::count(text)
 }
 
+/// Estimated tokens the history takes in a request, attachments counted
+/// the way they would be sent
 pub fn calculate_history_tokens(history: &[Message]) -> usize {
     history
         .iter()
-        .map(|msg| estimate_tokens(&msg.content) + estimate_tokens(&msg.role) + 4)
+        .enumerate()
+        .map(|(i, msg)| {
+            let inline = attachments::is_inlined(i, history.len());
+            msg.content.token_count() + estimate_tokens(&msg.role) + msg.tool_calls.as_deref().map_or(0, estimate_tokens) + 4
+                + msg.attachments.iter().map(|a| a.token_count(inline)).sum::<usize>()
+                + msg.images.iter().map(|i| i.token_count(inline)).sum::<usize>()
+        })
         .sum()
 }
 
-fn json_escape_to(s: &str, out: &mut String) {
-    for c in s.chars() {
-        match c {
-            '"' => out.push_str("\\\""),
-            '\\' => out.push_str("\\\\"),
-            '\n' => out.push_str("\\n"),
-            '\r' => out.push_str("\\r"),
-            '\t' => out.push_str("\\t"),
+pub fn json_escape_to(s: &str, out: &mut String) {
+    json_escape_with(s, |p| out.push_str(p));
+}
+
+/// JSON-escape `s`, handing `f` runs of plain text and escape sequences
+/// in order. Nothing is allocated.
+pub fn json_escape_with(s: &str, mut f: impl FnMut(&str)) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let mut unicode = *b"\\u0000";
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
             c if c.is_control() => {
-                let code = c as u32;
-                out.push_str(&format!("\\u{:04x}", code));
+                // Control characters are all below U+00A0
+                let code = c as usize;
+                unicode[4] = HEX[(code >> 4) & 0xf];
+                unicode[5] = HEX[code & 0xf];
+                core::str::from_utf8(&unicode).unwrap_or("")
+            }
+            _ => continue,
+        };
+        if start < i { f(&s[start..i]); }
+        f(escaped);
+        start = i + c.len_utf8();
+    }
+    if start < s.len() { f(&s[start..]); }
+}
+
+pub fn extract_json_string(json: &str, key: &str) -> Option<String> {
+    let pattern = format!("\"{}\":\"", key);
+    let start = json.find(&pattern)?;
+    let value_start = start + pattern.len();
+    let mut result = String::new();
+    let mut chars = json[value_start..].chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => {
+                if let Some(&next) = chars.peek() {
+                    chars.next();
+                    match next {
+                        'n' => result.push('\n'), 'r' => result.push('\r'), 't' => result.push('\t'), '"' => result.push('"'), '\\' => result.push('\\'), '/' => result.push('/'),
+                        'u' => {
+                            let mut hex = String::new();
+                            for _ in 0..4 { if let Some(h) = chars.next() { hex.push(h); } }
+                            if let Ok(code) = u32::from_str_radix(&hex, 16) { if let Some(ch) = char::from_u32(code) { result.push(ch); } }
+                        }
+                        _ => { result.push('\\'); result.push(next); }
+                    }
+                }
             }
-            _ => out.push(c),
+            _ => result.push(c),
         }
     }
-}
\ No newline at end of file
+    Some(result)
+}
@@ -0,0 +1,93 @@
+//! Reply-language detection
+//!
+//! A cheap guess at the language of a user message: the dominant non-Latin
+//! script decides outright, and Latin-script text is scored against short
+//! stopword lists. Only used to ask the model to answer in kind, so it errs
+//! towards English (None) when unsure.
+
+/// Minimum letters before a guess is made; short commands stay English
+const MIN_LETTERS: usize = 12;
+/// Stopword hits needed, and lead over English, to call a Latin language
+const MIN_STOPWORDS: usize = 2;
+
+struct Latin {
+    name: &'static str,
+    words: &'static [&'static str],
+}
+
+const ENGLISH: &[&str] = &["the", "and", "is", "are", "to", "of", "in", "it", "this", "that", "what", "how", "with", "for", "you", "can", "please", "file", "my"];
+
+const LATIN: &[Latin] = &[
+    Latin { name: "Spanish", words: &["el", "la", "los", "las", "que", "de", "y", "es", "por", "para", "una", "con", "cómo", "qué", "archivo", "puedes", "está"] },
+    Latin { name: "French", words: &["le", "la", "les", "des", "est", "et", "que", "une", "pour", "dans", "avec", "pas", "fichier", "peux", "comment", "c'est", "je"] },
+    Latin { name: "German", words: &["der", "die", "das", "und", "ist", "nicht", "ein", "eine", "ich", "mit", "für", "wie", "datei", "kannst", "bitte", "was", "zu"] },
+    Latin { name: "Portuguese", words: &["o", "os", "que", "de", "não", "uma", "com", "para", "é", "você", "como", "arquivo", "pode", "isso", "está"] },
+    Latin { name: "Italian", words: &["il", "lo", "gli", "che", "di", "è", "non", "una", "con", "per", "come", "file", "puoi", "questo", "sono"] },
+    Latin { name: "Dutch", words: &["de", "het", "een", "en", "is", "niet", "van", "dat", "ik", "met", "voor", "hoe", "bestand", "kun", "je"] },
+];
+
+/// Language name for a non-English message, None for English or unknown
+pub fn detect(text: &str) -> Option<&'static str> {
+    let mut letters = 0usize;
+    let mut latin = 0usize;
+    let mut counts = [0usize; 9];
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        match script(c) {
+            Some(i) => counts[i] += 1,
+            None => latin += 1,
+        }
+    }
+    if letters < MIN_LETTERS {
+        return None;
+    }
+
+    // Mostly non-Latin letters: the most frequent script decides
+    if (letters - latin) * 2 > letters {
+        let (best, _) = counts.iter().enumerate().max_by_key(|(_, n)| **n)?;
+        return Some(match best {
+            0 => if text.chars().any(|c| matches!(c, 'і' | 'ї' | 'є' | 'ґ')) { "Ukrainian" } else { "Russian" },
+            1 => "Japanese",
+            2 => if counts[1] > 0 { "Japanese" } else { "Chinese" },
+            3 => "Korean",
+            4 => "Arabic",
+            5 => "Hebrew",
+            6 => "Greek",
+            7 => "Thai",
+            _ => "Hindi",
+        });
+    }
+
+    let lower = text.to_lowercase();
+    let words: alloc::vec::Vec<&str> = lower
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let hits = |list: &[&str]| words.iter().filter(|w| list.contains(w)).count();
+
+    let english = hits(ENGLISH);
+    let (name, score) = LATIN.iter()
+        .map(|l| (l.name, hits(l.words)))
+        .max_by_key(|(_, s)| *s)?;
+    if score >= MIN_STOPWORDS && score > english {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Index into the script counters, None for Latin and everything else
+fn script(c: char) -> Option<usize> {
+    match c as u32 {
+        0x0400..=0x04FF => Some(0),                   // Cyrillic
+        0x3040..=0x30FF => Some(1),                   // Hiragana, Katakana
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => Some(2), // CJK ideographs
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Some(3), // Hangul
+        0x0600..=0x06FF => Some(4),                   // Arabic
+        0x0590..=0x05FF => Some(5),                   // Hebrew
+        0x0370..=0x03FF => Some(6),                   // Greek
+        0x0E00..=0x0E7F => Some(7),                   // Thai
+        0x0900..=0x097F => Some(8),                   // Devanagari
+        _ => None,
+    }
+}
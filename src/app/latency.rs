@@ -0,0 +1,113 @@
+//! Session latency samples per provider/model
+//!
+//! Every reply's time to first token and every completed turn's wall time
+//! are kept per `provider/model`, so `/latency` can compare, say, the local
+//! Ollama box against a remote API with percentiles from real use rather
+//! than a single remembered number.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+/// Samples kept per series; older ones are dropped first
+const MAX_SAMPLES: usize = 512;
+/// Histogram bucket upper bounds in ms; the last bucket is open-ended
+const BUCKETS_MS: [u64; 7] = [100, 250, 500, 1000, 2000, 5000, 10000];
+const BAR_WIDTH: usize = 24;
+
+struct Series {
+    /// `provider/model`
+    key: String,
+    ttft_us: Vec<u64>,
+    turn_us: Vec<u64>,
+}
+
+static mut SERIES: Vec<Series> = Vec::new();
+
+fn series(provider: &str, model: &str) -> &'static mut Series {
+    let all = unsafe { &mut *core::ptr::addr_of_mut!(SERIES) };
+    let key = format!("{}/{}", provider, model);
+    match all.iter().position(|s| s.key == key) {
+        Some(i) => &mut all[i],
+        None => {
+            all.push(Series { key, ttft_us: Vec::new(), turn_us: Vec::new() });
+            all.last_mut().unwrap()
+        }
+    }
+}
+
+fn push(samples: &mut Vec<u64>, us: u64) {
+    if samples.len() == MAX_SAMPLES {
+        samples.remove(0);
+    }
+    samples.push(us);
+}
+
+/// Time to first token of one reply (0 = no token arrived, not recorded)
+pub fn record_ttft(provider: &str, model: &str, us: u64) {
+    if us > 0 {
+        push(&mut series(provider, model).ttft_us, us);
+    }
+}
+
+/// Wall time of a whole turn, tool calls included
+pub fn record_turn(provider: &str, model: &str, us: u64) {
+    push(&mut series(provider, model).turn_us, us);
+}
+
+/// Nearest-rank percentile of sorted samples, in ms
+fn percentile_ms(sorted: &[u64], p: usize) -> u64 {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1] / 1000
+}
+
+fn summarize(out: &mut String, label: &str, samples: &[u64]) {
+    if samples.is_empty() {
+        out.push_str(&format!("  {:<5} no samples\n", label));
+        return;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    out.push_str(&format!(
+        "  {:<5} n={:<4} p50 {} ms  p90 {} ms  p99 {} ms\n",
+        label, sorted.len(), percentile_ms(&sorted, 50), percentile_ms(&sorted, 90), percentile_ms(&sorted, 99)
+    ));
+
+    let mut counts = [0usize; BUCKETS_MS.len() + 1];
+    for us in &sorted {
+        let ms = us / 1000;
+        let i = BUCKETS_MS.iter().position(|b| ms < *b).unwrap_or(BUCKETS_MS.len());
+        counts[i] += 1;
+    }
+    let max = counts.iter().copied().max().unwrap_or(1).max(1);
+    for (i, n) in counts.iter().enumerate() {
+        if *n == 0 {
+            continue;
+        }
+        let label = match BUCKETS_MS.get(i) {
+            Some(b) => format!("<{}", fmt_ms(*b)),
+            None => format!(">={}", fmt_ms(BUCKETS_MS[BUCKETS_MS.len() - 1])),
+        };
+        let bar = "#".repeat((n * BAR_WIDTH).div_ceil(max));
+        out.push_str(&format!("        {:>6} {:<width$} {}\n", label, bar, n, width = BAR_WIDTH));
+    }
+}
+
+fn fmt_ms(ms: u64) -> String {
+    if ms >= 1000 { format!("{}s", ms / 1000) } else { format!("{}ms", ms) }
+}
+
+/// The /latency report
+pub fn report() -> String {
+    let all = unsafe { &*core::ptr::addr_of!(SERIES) };
+    if all.is_empty() {
+        return String::from("～ No latency samples yet this session nya~");
+    }
+    let mut out = String::from("～ Latency this session ～\n");
+    for s in all.iter() {
+        out.push_str(&format!("\n{}\n", s.key));
+        summarize(&mut out, "ttft", &s.ttft_us);
+        summarize(&mut out, "turn", &s.turn_us);
+    }
+    out
+}
@@ -0,0 +1,127 @@
+//! Running session metrics (`/stats`)
+//!
+//! Totals kept as the session goes, to look at while it runs: requests and
+//! how they ended, tokens streamed back, time to first token and streaming
+//! time for the averages, retries, cancelled turns and tool calls by name.
+//! The client counts requests and retries, `chat_once` cancellations and
+//! the tool dispatcher the calls. Token counts are the same estimate the
+//! footer uses.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::api::StreamResponse;
+
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+static FAILED: AtomicUsize = AtomicUsize::new(0);
+static STOPPED: AtomicUsize = AtomicUsize::new(0);
+static RETRIES: AtomicUsize = AtomicUsize::new(0);
+static CANCELLED: AtomicUsize = AtomicUsize::new(0);
+static TOKENS: AtomicUsize = AtomicUsize::new(0);
+static TTFT_US: AtomicU64 = AtomicU64::new(0);
+static TTFT_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+/// Streaming time and the tokens streamed in it, for the average rate
+static STREAM_US: AtomicU64 = AtomicU64::new(0);
+static STREAM_TOKENS: AtomicUsize = AtomicUsize::new(0);
+/// Calls and failures per tool
+static mut TOOLS: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+
+fn tools() -> &'static mut BTreeMap<String, (usize, usize)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(TOOLS) }
+}
+
+/// How one `send_with_retry` call ended
+pub fn record_request(result: &Result<StreamResponse, &str>) {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let (text, stats) = match result {
+        // Counted as a cancelled turn by the chat loop
+        Err("Request cancelled") => return,
+        Err(_) => {
+            FAILED.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        Ok(StreamResponse::Stopped(t, s)) => {
+            STOPPED.fetch_add(1, Ordering::Relaxed);
+            (t, s)
+        }
+        Ok(StreamResponse::Complete(t, s) | StreamResponse::Partial(t, s)) => (t, s),
+    };
+    let tokens = super::tokens::count(text);
+    TOKENS.fetch_add(tokens, Ordering::Relaxed);
+    if stats.ttft_us > 0 {
+        TTFT_US.fetch_add(stats.ttft_us, Ordering::Relaxed);
+        TTFT_SAMPLES.fetch_add(1, Ordering::Relaxed);
+    }
+    if stats.stream_us > 0 {
+        STREAM_US.fetch_add(stats.stream_us, Ordering::Relaxed);
+        STREAM_TOKENS.fetch_add(tokens, Ordering::Relaxed);
+    }
+}
+
+/// A request tried again, or resumed after its stream broke off
+pub fn record_retry() {
+    RETRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A turn the user cancelled
+pub fn record_cancel() {
+    CANCELLED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_tool(name: &str, success: bool) {
+    let entry = tools().entry(String::from(name)).or_insert((0, 0));
+    entry.0 += 1;
+    if !success {
+        entry.1 += 1;
+    }
+}
+
+pub fn report() -> String {
+    let requests = REQUESTS.load(Ordering::Relaxed);
+    let mut out = String::from("～ Session stats ～\n");
+
+    let mut ended = Vec::new();
+    for (n, what) in [(FAILED.load(Ordering::Relaxed), "failed"), (STOPPED.load(Ordering::Relaxed), "stopped early")] {
+        if n > 0 {
+            ended.push(format!("{} {}", n, what));
+        }
+    }
+    let ended = if ended.is_empty() { String::new() } else { format!(" ({})", ended.join(", ")) };
+    let _ = writeln!(out, "  requests    {}{}", requests, ended);
+    let _ = writeln!(out, "  retries     {}", RETRIES.load(Ordering::Relaxed));
+    let _ = writeln!(out, "  cancelled   {}", CANCELLED.load(Ordering::Relaxed));
+
+    let tokens = TOKENS.load(Ordering::Relaxed);
+    let _ = writeln!(out, "  streamed    ~{} tokens", tokens);
+    let samples = TTFT_SAMPLES.load(Ordering::Relaxed);
+    if samples > 0 {
+        let _ = writeln!(out, "  avg TTFT    {}ms", TTFT_US.load(Ordering::Relaxed) / samples as u64 / 1000);
+    } else {
+        out.push_str("  avg TTFT    -\n");
+    }
+    let stream_us = STREAM_US.load(Ordering::Relaxed);
+    if stream_us > 0 {
+        let tps = STREAM_TOKENS.load(Ordering::Relaxed) as f64 / (stream_us as f64 / 1_000_000.0);
+        let _ = writeln!(out, "  avg TPS     {:.1}", tps);
+    } else {
+        out.push_str("  avg TPS     -\n");
+    }
+
+    let mut by_count: Vec<(&String, &(usize, usize))> = tools().iter().collect();
+    by_count.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+    let total: usize = by_count.iter().map(|(_, (n, _))| *n).sum();
+    if total == 0 {
+        out.push_str("  tool calls  none\n");
+    } else {
+        let _ = writeln!(out, "  tool calls  {}", total);
+        for (name, (calls, failed)) in by_count {
+            let failed = if *failed > 0 { format!(" ({} failed)", failed) } else { String::new() };
+            let _ = writeln!(out, "    {:<18} {}{}", name, calls, failed);
+        }
+    }
+    out
+}
@@ -1,7 +1,23 @@
 pub mod history;
+pub mod attachments;
 pub mod chat;
 pub mod commands;
 pub mod state;
+pub mod session;
+pub mod prompt;
+pub mod postprocess;
+pub mod lang;
+pub mod changes;
+pub mod checkpoint;
+pub mod goals;
+pub mod latency;
+pub mod metrics;
+pub mod narration;
+pub mod params;
+pub mod suggest;
+pub mod summary;
+pub mod word_diff;
+pub mod tokens;
 
 pub use history::*;
 pub use chat::chat_once;
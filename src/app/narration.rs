@@ -0,0 +1,87 @@
+//! Progress notes for long tool chains
+//!
+//! Once a turn has run a few tools, a one-line note is printed before the
+//! next one, at most every 20 seconds: the step number, what the tool is
+//! about to do, how many calls are still queued in the model's reply and
+//! the open `/goal`, if any. meow has no task list of its own, so the calls
+//! the model has already written out are the only plan there is to count.
+//! Watching a long autonomous run, the scrollback then says where it is
+//! without reading every tool result.
+
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::tools::helpers::extract_string_field;
+
+/// Tools run quietly before the first note
+const QUIET_STEPS: usize = 3;
+/// Minimum time between two notes
+const NOTE_INTERVAL_US: u64 = 20_000_000;
+/// Longest argument shown (commands and patterns get cut)
+const MAX_ARG_CHARS: usize = 60;
+
+static STEP: AtomicUsize = AtomicUsize::new(0);
+static LAST_NOTE_US: AtomicU64 = AtomicU64::new(0);
+
+/// A new user turn: count steps from one again
+pub fn start_turn() {
+    STEP.store(0, Ordering::Relaxed);
+    LAST_NOTE_US.store(0, Ordering::Relaxed);
+}
+
+/// Count the tool call about to run; returns the note to print, if one is due.
+/// `queued` is how many calls of the same reply come after this one.
+pub fn before_tool(tool_json: &str, queued: usize) -> Option<String> {
+    let step = STEP.fetch_add(1, Ordering::Relaxed) + 1;
+    let now = libakuma::uptime();
+    let last = LAST_NOTE_US.load(Ordering::Relaxed);
+    if step <= QUIET_STEPS || (last != 0 && now - last < NOTE_INTERVAL_US) {
+        return None;
+    }
+    LAST_NOTE_US.store(now, Ordering::Relaxed);
+
+    let mut note = format!("step {}: {}", step, action(tool_json));
+    match queued {
+        0 => {}
+        1 => note.push_str(" — 1 more tool queued in this reply"),
+        n => note.push_str(&format!(" — {} more tools queued in this reply", n)),
+    }
+    if let Some(goal) = super::goals::current() {
+        note.push_str(&format!(" (goal: {})", goal));
+    }
+    Some(note)
+}
+
+/// What a tool call does, in a few words
+fn action(tool_json: &str) -> String {
+    let tool = extract_string_field(tool_json, "tool").unwrap_or_default();
+    let arg = |field: &str| extract_string_field(tool_json, field).map(|a| shorten(&a)).unwrap_or_default();
+    match tool.as_str() {
+        "FileRead" | "FileReadLines" => format!("reading {}", arg("filename")),
+        "FileWrite" | "FileAppend" | "FileEdit" => format!("editing {}", arg("filename")),
+        "FileDelete" => format!("deleting {}", arg("filename")),
+        "FileList" | "FileTree" => format!("listing {}", arg("path")),
+        "FileCopy" | "FileMove" => format!("{} {} to {}", if tool == "FileCopy" { "copying" } else { "moving" }, arg("source"), arg("destination")),
+        "FileRename" => format!("renaming {}", arg("source_filename")),
+        "CodeSearch" => format!("searching for '{}'", arg("pattern")),
+        "Shell" => format!("running `{}`", arg("cmd")),
+        "HttpFetch" => format!("fetching {}", arg("url")),
+        "WebSearch" => format!("searching the web for '{}'", arg("query")),
+        "Cd" => format!("changing to {}", arg("path")),
+        t if t.starts_with("Git") => format!("git {}", t[3..].to_lowercase()),
+        t if t.starts_with("Chainlink") => format!("issue tracker: {}", t[9..].to_lowercase()),
+        "" => String::from("running a tool"),
+        t => format!("running {}", t),
+    }
+}
+
+fn shorten(text: &str) -> String {
+    let line = text.lines().next().unwrap_or("");
+    if line.chars().count() <= MAX_ARG_CHARS && !text.contains('\n') {
+        return String::from(line);
+    }
+    let mut out: String = line.chars().take(MAX_ARG_CHARS).collect();
+    out.push('…');
+    out
+}
@@ -0,0 +1,159 @@
+//! Request parameters per model (`/params`)
+//!
+//! Each model has a set of `GenOptions` for the session, starting from the
+//! config's `[params:MODEL]` section if it has one, over the options for
+//! every model (top-level config keys, `/set`). The `Params` middleware
+//! writes them into every chat request; sampling chosen for a single
+//! request (the retries after an unusable reply, `/quick`, `/retry hot`)
+//! still wins.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::api::middleware::{Middleware, Request};
+use crate::config::{ApiType, Config, GenOptions};
+use super::history::json_escape_to;
+
+/// Saved in the config, and changed this session
+static mut SAVED: Vec<(String, GenOptions)> = Vec::new();
+static mut SESSION: Vec<(String, GenOptions)> = Vec::new();
+/// For every model, under the per-model ones
+static mut GLOBAL: GenOptions = GenOptions {
+    temperature: None,
+    top_p: None,
+    num_predict: None,
+    seed: None,
+    stop: Vec::new(),
+    keep_alive: None,
+};
+
+fn saved() -> &'static mut Vec<(String, GenOptions)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(SAVED) }
+}
+
+fn session() -> &'static mut Vec<(String, GenOptions)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(SESSION) }
+}
+
+fn global_mut() -> &'static mut GenOptions {
+    unsafe { &mut *core::ptr::addr_of_mut!(GLOBAL) }
+}
+
+/// Take the saved parameters from the config
+pub fn load(config: &Config) {
+    *saved() = config.model_params.clone();
+    *global_mut() = config.gen.clone();
+}
+
+/// The parameters for every model
+pub fn global() -> GenOptions {
+    global_mut().clone()
+}
+
+/// Set one of the parameters for every model (`/set`) and save it in the
+/// config; an empty `value` unsets it
+pub fn set_global(key: &str, value: &str, config: &mut Config) -> Result<(), &'static str> {
+    let mut opts = global();
+    opts.set(key, value)?;
+    config.gen = opts.clone();
+    *global_mut() = opts;
+    config.save()
+}
+
+/// The parameters requests to `model` go out with
+pub fn current(model: &str) -> GenOptions {
+    session().iter().chain(saved().iter())
+        .find(|(m, _)| m == model)
+        .map(|(_, o)| o.clone())
+        .unwrap_or_default()
+}
+
+/// `model`'s own parameters, with the ones for every model filling in
+/// what they leave unset
+pub fn effective(model: &str) -> GenOptions {
+    let own = current(model);
+    let all = global_mut();
+    GenOptions {
+        temperature: own.temperature.or(all.temperature),
+        top_p: own.top_p.or(all.top_p),
+        num_predict: own.num_predict.or(all.num_predict),
+        seed: own.seed.or(all.seed),
+        stop: if own.stop.is_empty() { all.stop.clone() } else { own.stop },
+        keep_alive: own.keep_alive.or_else(|| all.keep_alive.clone()),
+    }
+}
+
+/// Use `opts` for `model` for the rest of the session
+pub fn set_current(model: &str, opts: GenOptions) {
+    match session().iter_mut().find(|(m, _)| m == model) {
+        Some((_, o)) => *o = opts,
+        None => session().push((String::from(model), opts)),
+    }
+}
+
+/// Store `model`'s current parameters in the config
+pub fn save(model: &str, config: &mut Config) -> Result<(), &'static str> {
+    let opts = current(model);
+    for list in [&mut config.model_params, saved()] {
+        list.retain(|(m, _)| m != model);
+        if !opts.is_empty() {
+            list.push((String::from(model), opts.clone()));
+        }
+    }
+    config.save()
+}
+
+/// Writes the current model's parameters into each chat request
+pub struct Params;
+
+impl Middleware for Params {
+    fn name(&self) -> &'static str { "params" }
+
+    fn on_request(&self, req: &mut Request) {
+        let opts = effective(req.model);
+        if let Some(t) = opts.temperature {
+            add_option(req, "temperature", format!("{:.2}", t));
+        }
+        if let Some(p) = opts.top_p {
+            add_option(req, "top_p", format!("{:.2}", p));
+        }
+        if let Some(seed) = opts.seed {
+            add_option(req, "seed", format!("{}", seed));
+        }
+        if !opts.stop.is_empty() {
+            let mut list = String::from("[");
+            for (i, s) in opts.stop.iter().enumerate() {
+                if i > 0 { list.push(','); }
+                list.push('"');
+                json_escape_to(s, &mut list);
+                list.push('"');
+            }
+            list.push(']');
+            add_option(req, "stop", list);
+        }
+        // The reply cap itself is written by `MaxTokens`, which runs later
+        if req.max_tokens.is_none() {
+            req.max_tokens = opts.num_predict;
+        }
+        if let (Some(keep), ApiType::Ollama) = (&opts.keep_alive, &req.provider.api_type) {
+            // Plain seconds go as a number, durations like "5m" as a string
+            let field = if keep.parse::<i64>().is_ok() {
+                format!("\"keep_alive\":{}", keep)
+            } else {
+                let mut field = String::from("\"keep_alive\":\"");
+                json_escape_to(keep, &mut field);
+                field.push('"');
+                field
+            };
+            req.fields.push(field);
+        }
+    }
+}
+
+/// Add a sampling option unless the request already has one by that name
+fn add_option(req: &mut Request, key: &'static str, value: String) {
+    if !req.options.iter().any(|(k, _)| *k == key) {
+        req.options.push((key, value));
+    }
+}
@@ -0,0 +1,245 @@
+//! Post-processing of completed assistant replies
+//!
+//! Each pass is a small `fn(&str) -> String` run in order over the reply
+//! before it is stored in history. Which passes run comes from config
+//! (`strip_filler`, `collapse_blank_lines`, `redact_secrets`) and the
+//! professional-mode toggle, and is mirrored into `app::state` so the chat
+//! loop doesn't need the config. ANSI stripping always runs: escapes the
+//! model emits are shown as streamed, but never sent back to the provider.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::config::Config;
+
+#[derive(Clone, Copy)]
+pub struct Passes {
+    pub strip_filler: bool,
+    pub collapse_blank_lines: bool,
+    pub strip_kaomoji: bool,
+    pub redact: bool,
+}
+
+impl Passes {
+    pub const fn none() -> Self {
+        Self { strip_filler: false, collapse_blank_lines: false, strip_kaomoji: false, redact: false }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            strip_filler: config.strip_filler,
+            collapse_blank_lines: config.collapse_blank_lines,
+            strip_kaomoji: config.professional,
+            redact: config.redact_secrets,
+        }
+    }
+}
+
+struct Pass {
+    enabled: fn(&Passes) -> bool,
+    run: fn(&str) -> String,
+}
+
+/// Order matters: redaction runs last so no earlier pass can reassemble a
+/// secret it split
+const PIPELINE: &[Pass] = &[
+    Pass { enabled: |_| true, run: strip_ansi },
+    Pass { enabled: |p| p.strip_kaomoji, run: strip_kaomoji },
+    Pass { enabled: |p| p.strip_filler, run: strip_filler },
+    Pass { enabled: |p| p.collapse_blank_lines, run: collapse_blank_lines },
+    Pass { enabled: |p| p.redact, run: redact_secrets },
+];
+
+/// Run every enabled pass over a completed reply
+pub fn process(text: &str, passes: &Passes) -> String {
+    let mut out = String::from(text);
+    for pass in PIPELINE {
+        if (pass.enabled)(passes) {
+            out = (pass.run)(&out);
+        }
+    }
+    out
+}
+
+/// Remove ANSI escape sequences and stray control characters, keeping
+/// newlines and tabs. CRLF becomes LF.
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters and intermediates up to a final byte in @..~
+                Some('[') => {
+                    for n in chars.by_ref() {
+                        if ('@'..='~').contains(&n) { break; }
+                    }
+                }
+                // OSC/DCS/etc.: up to BEL or ESC \
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(n) = chars.next() {
+                        if n == '\x07' { break; }
+                        if n == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // Two-character sequences like ESC 7 / ESC c
+                _ => {}
+            },
+            '\u{9b}' => {
+                for n in chars.by_ref() {
+                    if ('@'..='~').contains(&n) { break; }
+                }
+            }
+            '\n' | '\t' => out.push(c),
+            c if c.is_control() => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Closing lines that add nothing once the answer is given
+const FILLER_PREFIXES: &[&str] = &[
+    "let me know if",
+    "feel free to",
+    "i hope this helps",
+    "hope this helps",
+    "hope that helps",
+    "is there anything else",
+    "if you have any other questions",
+    "if you need anything else",
+    "happy coding",
+];
+
+/// Drop trailing filler lines
+fn strip_filler(text: &str) -> String {
+    let mut lines: Vec<&str> = text.lines().collect();
+    while let Some(last) = lines.last() {
+        let t = last.trim().to_lowercase();
+        if t.is_empty() || FILLER_PREFIXES.iter().any(|f| t.starts_with(f)) {
+            lines.pop();
+        } else {
+            break;
+        }
+    }
+    lines.join("\n")
+}
+
+/// At most one blank line in a row, except inside code blocks
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    let mut blank_run = 0;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if !in_code && line.trim().is_empty() {
+            blank_run += 1;
+            if blank_run > 1 { continue; }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if !text.ends_with('\n') { out.pop(); }
+    out
+}
+
+/// Characters that only show up in kaomoji and cat decorations
+fn is_kaomoji_char(c: char) -> bool {
+    matches!(c,
+        'ω' | '･' | '・' | '‥' | '´' | '｀' | '°' | '≧' | '≦' | '◕' | '▽' | 'ﾉ' | 'ノ' | 'ᴗ' | '♪' | '♡' | '～' | '〜' | '̀' | '́' | 'ง' | '٩' | '۶' | 'っ' | '╯' | '╰' | '｡'
+    )
+}
+
+/// Remove kaomoji like `(=^･ω･^=)` and nya-isms; code blocks are left alone
+fn strip_kaomoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if in_code || line.trim_start().starts_with("```") {
+            out.push_str(line);
+        } else {
+            out.push_str(strip_kaomoji_line(line).trim_end());
+        }
+        out.push('\n');
+    }
+    if !text.ends_with('\n') { out.pop(); }
+    out
+}
+
+fn is_kaomoji(group: &str) -> bool {
+    group.chars().any(is_kaomoji_char) || (group.contains('^') && group.contains('='))
+}
+
+fn strip_kaomoji_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('(') {
+        let (before, after) = rest.split_at(open);
+        out.push_str(before);
+        // A parenthesised group is a kaomoji if it holds a kaomoji char, or
+        // both `^` and `=` as in (=^.^=)
+        match after.find(')') {
+            Some(close) if is_kaomoji(&after[..close]) => {
+                rest = &after[close + 1..];
+            }
+            _ => {
+                out.push('(');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    let mut cleaned: String = out.chars().filter(|c| !is_kaomoji_char(*c)).collect();
+    for nya in [" nya~!", " nya~", " nya!", " nya", " Nya~!", " Nya~", " nyaa~"] {
+        cleaned = cleaned.replace(nya, "");
+    }
+    cleaned
+}
+
+/// Prefixes of well-known credential formats
+const SECRET_PREFIXES: &[&str] = &["sk-", "ghp_", "gho_", "ghs_", "github_pat_", "xoxb-", "xoxp-", "AKIA", "glpat-", "hf_"];
+
+/// Mask API keys and bearer tokens, keeping the first few characters
+fn redact_secrets(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut prev_bearer = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices().chain(core::iter::once((text.len(), ' '))) {
+        if !is_token_char(c) || i == text.len() {
+            let word = &text[start..i];
+            if !word.is_empty() && (prev_bearer || looks_like_secret(word)) && word.len() >= 16 {
+                let keep: String = word.chars().take(4).collect();
+                out.push_str(&keep);
+                out.push_str("…[redacted]");
+            } else {
+                out.push_str(word);
+            }
+            if !word.is_empty() {
+                prev_bearer = word.eq_ignore_ascii_case("bearer");
+            }
+            if i < text.len() { out.push(c); }
+            start = i + c.len_utf8();
+        }
+    }
+    out
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+fn looks_like_secret(word: &str) -> bool {
+    SECRET_PREFIXES.iter().any(|p| word.starts_with(p))
+        && word.chars().any(|c| c.is_ascii_digit())
+}
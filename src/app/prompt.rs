@@ -0,0 +1,191 @@
+//! System prompt assembly
+//!
+//! The prompt is built from a persona (replaced by a local MEOW.md, or by the
+//! text set with `/system set`), the common tool registry plus any
+//! `[tool:NAME]` tools from the config, the chainlink section when chainlink
+//! is installed, and the project map under `meow here`. Kept as parts so
+//! `/prompt tokens` can show what each one costs and `/prompt reload` can
+//! rebuild it mid-session.
+//!
+//! Models with a small context window (a 4k num_ctx leaves next to nothing
+//! after the full prompt) get a compact variant: the persona's opening
+//! paragraph and a one-line-per-tool list generated from the tool registry.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use libakuma::{open, close, read_fd, fstat, open_flags};
+
+use crate::config::{Config, COMMON_TOOLS, DEFAULT_CONTEXT_WINDOW};
+use crate::tools;
+use crate::tools::registry;
+
+/// Context windows below this get the compact prompt
+pub const SMALL_CONTEXT: usize = 16_384;
+/// Longest persona kept in the compact prompt
+const COMPACT_PERSONA_CHARS: usize = 400;
+
+const COMPACT_TOOLS_HEADER: &str = r#"## Tools
+
+Call a tool by replying with a ```json block holding {"command": {"tool": "Name", "args": {...}}}, then stop and wait for the result. Never write tool results yourself. Tools (args, then what it does):
+"#;
+
+/// Context window of the active model
+static CONTEXT_WINDOW: AtomicUsize = AtomicUsize::new(DEFAULT_CONTEXT_WINDOW);
+
+pub fn set_context_window(tokens: usize) {
+    CONTEXT_WINDOW.store(tokens, Ordering::Relaxed);
+}
+
+pub fn context_window() -> usize {
+    CONTEXT_WINDOW.load(Ordering::Relaxed)
+}
+
+/// Whether prompts are currently built in the compact, reduced-capability form
+pub fn is_compact() -> bool {
+    context_window() < SMALL_CONTEXT
+}
+
+/// Repository root when running under `meow here`, for the project map
+static mut PROJECT_ROOT: Option<String> = None;
+
+pub fn set_project_root(root: &str) {
+    unsafe { *core::ptr::addr_of_mut!(PROJECT_ROOT) = Some(String::from(root)); }
+}
+
+fn project_root() -> Option<&'static str> {
+    unsafe { (*core::ptr::addr_of!(PROJECT_ROOT)).as_deref() }
+}
+
+pub struct PromptParts {
+    /// Where the persona text came from: "/system", "MEOW.md" or the personality name
+    pub persona_source: String,
+    pub persona: String,
+    pub tools: Cow<'static, str>,
+    pub chainlink: Option<&'static str>,
+    pub project_map: Option<String>,
+}
+
+impl PromptParts {
+    pub fn assemble(&self) -> String {
+        let mut prompt = String::new();
+        prompt.push_str(&self.persona);
+        prompt.push_str("\n\n");
+        prompt.push_str(&self.tools);
+        if let Some(c) = self.chainlink {
+            prompt.push_str(c);
+        }
+        if let Some(ref m) = self.project_map {
+            prompt.push_str(m);
+        }
+        prompt
+    }
+}
+
+pub fn build_parts(config: &Config) -> PromptParts {
+    // A local MEOW.md in the sandbox root replaces the persona, unless
+    // professional mode asked for the plain one; `/system set` beats both
+    let local = if config.professional { None } else { load_local_prompt() };
+    let (persona_source, persona) = match (&config.system_prompt, local) {
+        (Some(custom), _) => (String::from("/system"), custom.clone()),
+        (None, Some(prompt)) => (String::from("MEOW.md"), prompt),
+        (None, None) => {
+            let p = config.personality();
+            (String::from(p.name), String::from(p.description))
+        }
+    };
+
+    if is_compact() {
+        // The registry list already covers chainlink, and the project map
+        // would eat the window on its own
+        return PromptParts {
+            persona_source,
+            persona: compact_persona(&persona),
+            tools: Cow::Owned(compact_tools()),
+            chainlink: None,
+            project_map: None,
+        };
+    }
+
+    PromptParts {
+        persona_source,
+        persona,
+        tools: match tools::custom::prompt_section() {
+            Some(custom) => Cow::Owned(format!("{}{}", COMMON_TOOLS, custom)),
+            None => Cow::Borrowed(COMMON_TOOLS),
+        },
+        chainlink: if tools::chainlink_available() { Some(tools::chainlink::CHAINLINK_TOOLS_SECTION) } else { None },
+        project_map: project_root().map(crate::cli::here::project_map),
+    }
+}
+
+/// The persona's first paragraph, cut at a sentence end if it is long
+fn compact_persona(persona: &str) -> String {
+    let first = persona.trim_start().split("\n\n").next().unwrap_or("");
+    if first.len() <= COMPACT_PERSONA_CHARS {
+        return String::from(first);
+    }
+    let mut cut = COMPACT_PERSONA_CHARS;
+    while !first.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let head = &first[..cut];
+    match head.rfind(". ") {
+        Some(i) => String::from(&head[..=i]),
+        None => String::from(head),
+    }
+}
+
+/// One line per usable tool, straight from the registry
+fn compact_tools() -> String {
+    let mut out = String::from(COMPACT_TOOLS_HEADER);
+    for t in registry::TOOLS.iter().filter(|t| registry::disabled_reason(t).is_none()) {
+        out.push_str(&format!("- {} {}\n", t.name, t.usage));
+    }
+    for t in tools::custom::all() {
+        out.push_str(&format!("- {} {}\n", t.name, tools::custom::usage(t)));
+    }
+    out
+}
+
+pub fn build_system_prompt(config: &Config) -> String {
+    build_parts(config).assemble()
+}
+
+fn load_local_prompt() -> Option<String> {
+    let root = tools::get_sandbox_root();
+    let path = if root == "/" { String::from("/MEOW.md") } else { format!("{}/MEOW.md", root) };
+    let fd = open(&path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return None;
+    }
+
+    let stat = match fstat(fd) {
+        Ok(s) => s,
+        Err(_) => {
+            close(fd);
+            return None;
+        }
+    };
+
+    let size = stat.st_size as usize;
+    if size == 0 || size > 64 * 1024 {
+        close(fd);
+        return None;
+    }
+
+    let mut buf = alloc::vec![0u8; size];
+    let bytes_read = read_fd(fd, &mut buf);
+    close(fd);
+
+    if bytes_read <= 0 {
+        return None;
+    }
+
+    match String::from_utf8(buf) {
+        Ok(s) => Some(s),
+        Err(_) => None,
+    }
+}
@@ -0,0 +1,172 @@
+//! Session persistence for Meow
+//!
+//! Saves and restores chat history under /etc/meow/sessions as JSON lines
+//! (one message per line), so a conversation can continue across invocations.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::{open, close, read_fd, write_fd, fstat, read_dir, open_flags};
+
+use super::history::Message;
+use crate::tools::fs::{self, ReadFileError};
+
+/// Directory holding persisted sessions
+pub const SESSIONS_DIR: &str = "/etc/meow/sessions";
+/// Maximum size of a session file we are willing to load (1MB)
+const MAX_SESSION_SIZE: usize = 1024 * 1024;
+/// Session the TUI saves after every turn and offers back on the next start
+pub const AUTOSAVE_NAME: &str = "autosave";
+/// Session `/save` and `/load` use when no name is given
+pub const DEFAULT_NAME: &str = "default";
+
+/// Full path of the file backing a named session
+pub fn session_path(name: &str) -> String {
+    format!("{}/{}.jsonl", SESSIONS_DIR, name)
+}
+
+/// Names of the saved sessions, sorted
+pub fn list_sessions() -> Vec<String> {
+    let mut names: Vec<String> = match read_dir(SESSIONS_DIR) {
+        Some(entries) => entries.into_iter().filter_map(|e| e.name.strip_suffix(".jsonl").map(String::from)).collect(),
+        None => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+/// Session names become file names, so keep them to a safe character set
+pub fn is_valid_session_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Write the full history to a named session, returns the number of messages saved
+pub fn save_session(name: &str, history: &[Message]) -> Result<usize, &'static str> {
+    if !is_valid_session_name(name) {
+        return Err("Invalid session name (use letters, digits, '-', '_' or '.')");
+    }
+
+    libakuma::mkdir_p(SESSIONS_DIR);
+
+    let mut content = String::new();
+    for msg in history {
+        msg.write_json(&mut content);
+        content.push('\n');
+    }
+
+    let fd = open(&session_path(name), open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return Err("Failed to open session file for writing");
+    }
+
+    let bytes_written = write_fd(fd, content.as_bytes());
+    close(fd);
+
+    if bytes_written < 0 {
+        return Err("Failed to write session file");
+    }
+
+    Ok(history.len())
+}
+
+/// Load a named session, returns Err if it does not exist or cannot be parsed
+pub fn load_session(name: &str) -> Result<Vec<Message>, &'static str> {
+    if !is_valid_session_name(name) {
+        return Err("Invalid session name (use letters, digits, '-', '_' or '.')");
+    }
+
+    let buf = fs::read_file(&session_path(name), MAX_SESSION_SIZE).map_err(|e| match e {
+        ReadFileError::NotFound => "Session not found",
+        ReadFileError::TooLarge => "Session file too large",
+        ReadFileError::Unreadable => "Failed to read session file",
+    })?;
+    if buf.is_empty() {
+        return Err("Session file is empty");
+    }
+
+    let content = core::str::from_utf8(&buf)
+        .map_err(|_| "Session file is not valid UTF-8")?;
+
+    let mut history = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        if let Some(msg) = Message::from_json(line) {
+            history.push(msg);
+        }
+    }
+
+    if history.is_empty() {
+        return Err("Session contains no messages");
+    }
+
+    Ok(history)
+}
+
+fn autosave_meta_path() -> String {
+    format!("{}/{}.meta", SESSIONS_DIR, AUTOSAVE_NAME)
+}
+
+/// Save the TUI conversation as the autosave, with a stamp next to it whose
+/// mtime says when
+pub fn autosave(history: &[Message]) -> Result<usize, &'static str> {
+    let saved = save_session(AUTOSAVE_NAME, history)?;
+    let meta = format!("messages={}\n", saved);
+    let fd = open(&autosave_meta_path(), open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return Err("Failed to open autosave stamp for writing");
+    }
+    write_fd(fd, meta.as_bytes());
+    close(fd);
+    Ok(saved)
+}
+
+pub struct AutosaveInfo {
+    pub age_us: u64,
+    pub messages: usize,
+}
+
+/// Modification time of `path` in seconds, by the filesystem's clock
+fn mtime(path: &str) -> Option<u64> {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let stat = fstat(fd);
+    close(fd);
+    stat.ok().map(|s| s.st_mtime as u64)
+}
+
+/// Age and size of the autosave. meow has no clock of its own, so the age
+/// comes from the filesystem's, which survives a reboot: the stamp's mtime
+/// against that of a file written now. None if there is no autosave, or the
+/// filesystem's clock went backwards.
+pub fn autosave_info() -> Option<AutosaveInfo> {
+    let fd = open(&autosave_meta_path(), open_flags::O_RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 128];
+    let n = read_fd(fd, &mut buf);
+    close(fd);
+    if n <= 0 {
+        return None;
+    }
+    let text = core::str::from_utf8(&buf[..n as usize]).ok()?;
+    let field = |key: &str| text.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix('='));
+    let messages = field("messages")?.parse().ok()?;
+    let saved = mtime(&autosave_meta_path())?;
+    let now_path = format!("{}/.now", SESSIONS_DIR);
+    let fd = open(&now_path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return None;
+    }
+    write_fd(fd, b"\n");
+    close(fd);
+    let age_us = mtime(&now_path)?.checked_sub(saved)?.saturating_mul(1_000_000);
+    Some(AutosaveInfo { age_us, messages })
+}
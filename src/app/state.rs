@@ -6,7 +6,17 @@ use core::cell::UnsafeCell;
 
 pub static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
 pub static CANCELLED: AtomicBool = AtomicBool::new(false);
+/// Soft stop (Ctrl+S): end the reply being streamed but keep it
+pub static SOFT_STOP: AtomicBool = AtomicBool::new(false);
 pub static STREAMING: AtomicBool = AtomicBool::new(false);
+/// Low-bandwidth mode (`--low-bandwidth` or `low_bandwidth=true`)
+pub static LOW_BANDWIDTH: AtomicBool = AtomicBool::new(false);
+/// Professional mode (`--plain` or `/persona off`): no cat-themed UI strings
+pub static PLAIN: AtomicBool = AtomicBool::new(false);
+/// Quick-answer mode (`--quick` or `/quick`): no tools, short replies
+pub static QUICK: AtomicBool = AtomicBool::new(false);
+/// `auto_compact=true`: compact the history before it outgrows the threshold
+pub static AUTO_COMPACT: AtomicBool = AtomicBool::new(false);
 
 pub struct AppState {
     pub global_input: String,
@@ -18,6 +28,11 @@ pub struct AppState {
     pub provider_name: String,
     pub last_history_kb: usize,
     pub render_markdown: bool,
+    pub animation: bool,
+    pub postprocess: super::postprocess::Passes,
+    pub verbosity: crate::config::Verbosity,
+    /// Lines gathered by /more, sent together with /send or Ctrl+D
+    pub composition: Option<Vec<String>>,
 }
 
 struct AtomicAppState {
@@ -49,6 +64,10 @@ impl AtomicAppState {
                     provider_name: String::from("unknown"),
                     last_history_kb: 0,
                     render_markdown: true,
+                    animation: true,
+                    postprocess: super::postprocess::Passes::none(),
+                    verbosity: crate::config::Verbosity::Normal,
+                    composition: None,
                 });
             }
             self.initialized.store(true, Ordering::Release);
@@ -122,3 +141,18 @@ pub fn set_last_history_kb(kb: usize) { with_state(|s| s.last_history_kb = kb);
 
 pub fn get_render_markdown() -> bool { with_state(|s| s.render_markdown) }
 pub fn set_render_markdown(val: bool) { with_state(|s| s.render_markdown = val); }
+
+pub fn get_animation() -> bool { with_state(|s| s.animation) }
+pub fn set_animation(val: bool) { with_state(|s| s.animation = val); }
+pub fn get_postprocess() -> super::postprocess::Passes { with_state(|s| s.postprocess) }
+pub fn set_postprocess(val: super::postprocess::Passes) { with_state(|s| s.postprocess = val); }
+
+pub fn get_verbosity() -> crate::config::Verbosity { with_state(|s| s.verbosity) }
+pub fn set_verbosity(val: crate::config::Verbosity) { with_state(|s| s.verbosity = val); }
+
+pub fn start_composition() { with_state(|s| s.composition = Some(Vec::new())); }
+pub fn compose_line(line: String) { with_state(|s| if let Some(c) = s.composition.as_mut() { c.push(line); }); }
+/// Lines composed so far, None outside /more
+pub fn composition_len() -> Option<usize> { with_state(|s| s.composition.as_ref().map(|c| c.len())) }
+/// End /more, returning the composed message
+pub fn take_composition() -> Option<String> { with_state(|s| s.composition.take().map(|c| c.join("\n"))) }
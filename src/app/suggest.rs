@@ -0,0 +1,136 @@
+//! End-of-turn suggestion chips
+//!
+//! With `suggestions` on, the offers a reply ends with ("Would you like me
+//! to add tests?", or a list under "Next steps:") become up to three numbered
+//! chips, drawn by the footer in the row above the status line. `/1`, `/2` and `/3` send the matching one as the next
+//! message. Taken from the reply itself, so they cost no extra request.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use super::history::Message;
+
+const MAX_SUGGESTIONS: usize = 3;
+/// Longer offers are left out rather than cut mid-thought
+const MAX_CHARS: usize = 60;
+/// Only the end of the reply is searched
+const TAIL_LINES: usize = 10;
+
+/// Question openings that offer to do something, in lower case
+const OFFERS: &[&str] = &[
+    "would you like me to ",
+    "do you want me to ",
+    "want me to ",
+    "should i ",
+    "shall i ",
+];
+
+static mut CURRENT: Vec<String> = Vec::new();
+/// The chips as the footer shows them, empty when there are none
+static mut LINE: String = String::new();
+
+fn current() -> &'static mut Vec<String> {
+    unsafe { &mut *core::ptr::addr_of_mut!(CURRENT) }
+}
+
+fn line_mut() -> &'static mut String {
+    unsafe { &mut *core::ptr::addr_of_mut!(LINE) }
+}
+
+/// Replace the chips with ones from the last assistant message in `history`
+pub fn update(history: &[Message]) {
+    let reply: String = match history.last() {
+        Some(m) if m.role == "assistant" => m.content.chunks().collect(),
+        _ => String::new(),
+    };
+    *current() = extract(&reply);
+    let chips: Vec<String> = current().iter().enumerate().map(|(i, s)| format!("[/{}] {}", i + 1, s)).collect();
+    *line_mut() = chips.join("  ");
+}
+
+/// Drop the chips, which belong to the previous reply
+pub fn clear() {
+    current().clear();
+    line_mut().clear();
+}
+
+/// The line the footer draws above the status row
+pub fn line() -> &'static str {
+    line_mut().as_str()
+}
+
+/// The suggestion behind `/n` (1-based)
+pub fn get(n: usize) -> Option<String> {
+    current().get(n.checked_sub(1)?).cloned()
+}
+
+fn extract(reply: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut add = |text: &str| {
+        let text = text.trim().trim_matches('*').trim_end_matches(['?', '.', ':']).trim();
+        if text.is_empty() || text.chars().count() > MAX_CHARS || out.len() == MAX_SUGGESTIONS {
+            return;
+        }
+        let mut chars = text.chars();
+        let first = chars.next().unwrap();
+        let text = format!("{}{}", first.to_uppercase(), chars.as_str());
+        if !out.contains(&text) {
+            out.push(text);
+        }
+    };
+
+    let lines = prose_lines(reply);
+    let tail = &lines[lines.len().saturating_sub(TAIL_LINES)..];
+    let mut in_list = false;
+    for line in tail {
+        let trimmed = line.trim();
+        let lower = trimmed.to_lowercase();
+        if let Some(item) = list_item(trimmed) {
+            if in_list {
+                add(item);
+            }
+            continue;
+        }
+        // A list right after "Next steps:" or a question holds the options
+        in_list = trimmed.ends_with('?') || lower.contains("next step") || lower.ends_with("options:");
+        for sentence in trimmed.split_inclusive(['.', '!', '?']) {
+            let s = sentence.trim();
+            if !s.ends_with('?') {
+                continue;
+            }
+            let s = ["Or ", "or ", "And ", "and "].iter().find_map(|p| s.strip_prefix(p)).unwrap_or(s);
+            let lower = s.to_lowercase();
+            if let Some(offer) = OFFERS.iter().find(|o| lower.starts_with(*o)) {
+                add(&s[offer.len()..]);
+            }
+        }
+    }
+    out
+}
+
+/// Lines outside fenced code blocks
+fn prose_lines(text: &str) -> Vec<&str> {
+    let mut in_code = false;
+    text.lines()
+        .filter(|l| {
+            if l.trim_start().starts_with("```") {
+                in_code = !in_code;
+                return false;
+            }
+            !in_code && !l.trim().is_empty()
+        })
+        .collect()
+}
+
+/// Text of a `- item`, `* item` or `1. item` line
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some(rest);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        return line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "));
+    }
+    None
+}
@@ -0,0 +1,121 @@
+//! End-of-session summary
+//!
+//! Counters fed by the chat loop and the change journal: turns, estimated
+//! tokens each way, tool calls by name, files the turns changed and commits
+//! made. When the TUI quits they become the summary printed under the
+//! goodbye and added to the history, so a saved or crash-dumped transcript
+//! ends with it too. Token counts are the same bytes/4 estimate the footer
+//! uses; no prices are known, so there is no cost line.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Files listed by name before the rest are only counted
+const MAX_FILES_LISTED: usize = 6;
+
+static START_US: AtomicU64 = AtomicU64::new(0);
+static TURNS: AtomicUsize = AtomicUsize::new(0);
+static TOKENS_IN: AtomicUsize = AtomicUsize::new(0);
+static TOKENS_OUT: AtomicUsize = AtomicUsize::new(0);
+static COMMITS: AtomicUsize = AtomicUsize::new(0);
+static mut TOOLS: BTreeMap<String, usize> = BTreeMap::new();
+static mut FILES: BTreeSet<String> = BTreeSet::new();
+
+fn tools() -> &'static mut BTreeMap<String, usize> {
+    unsafe { &mut *core::ptr::addr_of_mut!(TOOLS) }
+}
+
+fn files() -> &'static mut BTreeSet<String> {
+    unsafe { &mut *core::ptr::addr_of_mut!(FILES) }
+}
+
+/// Start the clock; called once when the session begins
+pub fn start() {
+    START_US.store(libakuma::uptime(), Ordering::Relaxed);
+}
+
+pub fn record_turn() {
+    TURNS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// One request: `tokens_in` sent (estimated), `bytes_out` streamed back
+pub fn record_request(tokens_in: usize, bytes_out: usize) {
+    TOKENS_IN.fetch_add(tokens_in, Ordering::Relaxed);
+    TOKENS_OUT.fetch_add(bytes_out.div_ceil(4), Ordering::Relaxed);
+}
+
+pub fn record_tool(name: &str, success: bool) {
+    *tools().entry(String::from(name)).or_insert(0) += 1;
+    if success && name == "GitCommit" {
+        COMMITS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A file a turn created, modified, renamed or deleted
+pub fn record_file(path: &str) {
+    files().insert(String::from(path));
+}
+
+fn fmt_tokens(n: usize) -> String {
+    if n >= 1000 { format!("~{}.{}k", n / 1000, n % 1000 / 100) } else { format!("~{}", n) }
+}
+
+fn fmt_duration(us: u64) -> String {
+    let s = us / 1_000_000;
+    match (s / 3600, s % 3600 / 60, s % 60) {
+        (0, 0, sec) => format!("{}s", sec),
+        (0, min, sec) => format!("{}m {:02}s", min, sec),
+        (h, min, _) => format!("{}h {:02}m", h, min),
+    }
+}
+
+/// The summary, None if the session never sent a message
+pub fn report() -> Option<String> {
+    let turns = TURNS.load(Ordering::Relaxed);
+    if turns == 0 {
+        return None;
+    }
+    let mut out = String::from("～ Session summary ～\n");
+    let elapsed = libakuma::uptime().saturating_sub(START_US.load(Ordering::Relaxed));
+    out.push_str(&format!("  duration  {}\n", fmt_duration(elapsed)));
+    out.push_str(&format!("  turns     {}\n", turns));
+    out.push_str(&format!(
+        "  tokens    {} in / {} out\n",
+        fmt_tokens(TOKENS_IN.load(Ordering::Relaxed)),
+        fmt_tokens(TOKENS_OUT.load(Ordering::Relaxed))
+    ));
+
+    let mut by_count: Vec<(&String, &usize)> = tools().iter().collect();
+    by_count.sort_by(|a, b| b.1.cmp(a.1));
+    let total: usize = by_count.iter().map(|(_, n)| **n).sum();
+    if total == 0 {
+        out.push_str("  tools     none\n");
+    } else {
+        let list: Vec<String> = by_count.iter().map(|(name, n)| format!("{} {}", name, n)).collect();
+        out.push_str(&format!("  tools     {} ({})\n", total, list.join(", ")));
+    }
+
+    let changed = files();
+    if changed.is_empty() {
+        out.push_str("  files     none changed\n");
+    } else {
+        let list: Vec<&str> = changed.iter().take(MAX_FILES_LISTED).map(|s| s.as_str()).collect();
+        let more = changed.len().saturating_sub(MAX_FILES_LISTED);
+        let more = if more > 0 { format!(" and {} more", more) } else { String::new() };
+        out.push_str(&format!("  files     {} changed: {}{}\n", changed.len(), list.join(", "), more));
+    }
+    out.push_str(&format!("  commits   {}\n", COMMITS.load(Ordering::Relaxed)));
+    Some(out)
+}
+
+/// Comment the summary on the issue the session is linked to (`/issue link`),
+/// on `/save` and on exit. Returns the issue id when the comment was posted.
+pub fn post_to_linked_issue() -> Option<usize> {
+    let id = crate::tools::chainlink::linked_issue()?;
+    let text = report()?;
+    let comment = format!("meow session summary\n{}", text.trim_start_matches("～ Session summary ～\n"));
+    crate::tools::chainlink::tool_chainlink_comment(id, &comment).success.then_some(id)
+}
@@ -0,0 +1,108 @@
+//! Token counting
+//!
+//! Bytes divided by four is close for English prose and far off elsewhere:
+//! code spends tokens on punctuation and indentation, a CJK character is a
+//! token or more in three bytes, long numbers split every few digits. This
+//! estimates what a BPE tokenizer would produce by walking the text in the
+//! same kinds of pieces it merges: words, digit groups, symbol runs,
+//! whitespace runs and non-Latin characters.
+//!
+//! A provider whose tokenizer doesn't fit that can set `chars_per_token`
+//! in its config section, and counting falls back to characters divided by
+//! that ratio. Counts are cached per message, keyed on the setting in
+//! effect, so the footer can recount history cheaply on every redraw.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::config::Provider;
+
+/// Characters per token × 100 for the current provider, 0 for the tokenizer
+static CHARS_PER_TOKEN: AtomicU32 = AtomicU32::new(0);
+/// Bumped whenever the way of counting changes, invalidating cached counts
+static GENERATION: AtomicU32 = AtomicU32::new(1);
+
+/// Count the way `provider` asks for
+pub fn configure(provider: &Provider) {
+    let ratio = provider.chars_per_token.map(|r| (r * 100.0) as u32).unwrap_or(0);
+    if CHARS_PER_TOKEN.swap(ratio, Ordering::Relaxed) != ratio {
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Identifies the current counting setup, for caches
+pub fn generation() -> u32 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Estimated tokens in `text`
+pub fn count(text: &str) -> usize {
+    match CHARS_PER_TOKEN.load(Ordering::Relaxed) {
+        0 => estimate(text),
+        ratio => (text.chars().count() * 100).div_ceil(ratio as usize),
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Piece {
+    Word,
+    Digits,
+    Symbols,
+    Space,
+    Newlines,
+    /// Scripts tokenizers mostly keep per character (CJK, kana, hangul)
+    Wide,
+    /// Other non-ASCII letters (accents, Cyrillic, Greek): ~2 characters a token
+    Other,
+}
+
+fn piece(c: char) -> Piece {
+    match c {
+        'a'..='z' | 'A'..='Z' | '_' | '\'' => Piece::Word,
+        '0'..='9' => Piece::Digits,
+        '\n' => Piece::Newlines,
+        ' ' | '\t' | '\r' => Piece::Space,
+        c if c.is_ascii() => Piece::Symbols,
+        '\u{1100}'..='\u{11FF}' | '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FFEF}' | '\u{20000}'..='\u{2FFFF}' => Piece::Wide,
+        // Emoji and other symbols usually take several byte-level tokens
+        c if (c as u32) >= 0x1F000 => Piece::Wide,
+        _ => Piece::Other,
+    }
+}
+
+/// Tokens for one run of `len` characters of the same kind
+fn run_tokens(kind: Piece, len: usize) -> usize {
+    match kind {
+        // Common words are one token; long ones split every ~4 characters
+        Piece::Word => if len <= 6 { 1 } else { 1 + (len - 6).div_ceil(4) },
+        // Numbers split into groups of up to three digits
+        Piece::Digits => len.div_ceil(3),
+        // Operators pair up ("->", "::", "==", "*/")
+        Piece::Symbols => len.div_ceil(2),
+        // A single space rides on the next word; indentation is one token per 4
+        Piece::Space => if len == 1 { 0 } else { len.div_ceil(4) },
+        Piece::Newlines => if len <= 2 { 1 } else { 2 },
+        Piece::Wide => len + len / 2,
+        Piece::Other => len.div_ceil(2),
+    }
+}
+
+fn estimate(text: &str) -> usize {
+    let mut total = 0;
+    let mut run: Option<(Piece, usize)> = None;
+    for c in text.chars() {
+        let kind = piece(c);
+        run = match run {
+            Some((k, n)) if k == kind => Some((k, n + 1)),
+            Some((k, n)) => {
+                total += run_tokens(k, n);
+                Some((kind, 1))
+            }
+            None => Some((kind, 1)),
+        };
+    }
+    if let Some((k, n)) = run {
+        total += run_tokens(k, n);
+    }
+    total
+}
@@ -0,0 +1,172 @@
+//! Sentence- and word-level text diff
+//!
+//! `/diff-answers` uses this to show how two answers to the same prompt
+//! differ: a longest-common-subsequence diff over sentences, and for a
+//! sentence that was reworded rather than added or dropped, a second diff
+//! over its words. Marked up like `git diff --word-diff=plain`: `[-removed-]`
+//! and `{+added+}`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+/// LCS table cells we are willing to allocate (u16 each). A differing middle
+/// larger than this is reported as one removal plus one addition.
+const MAX_CELLS: usize = 200_000;
+/// Unchanged sentences shown around a change; longer runs are collapsed
+const CONTEXT: usize = 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diff two token sequences. Common prefix and suffix are matched first, so
+/// the table only covers the part that differs.
+pub fn diff<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Change<'a>> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut out: Vec<Change<'a>> = a[..prefix].iter().map(|t| Change::Same(t)).collect();
+    let (n, m) = (am.len(), bm.len());
+    if (n + 1) * (m + 1) > MAX_CELLS || n >= u16::MAX as usize || m >= u16::MAX as usize {
+        out.extend(am.iter().map(|t| Change::Removed(t)));
+        out.extend(bm.iter().map(|t| Change::Added(t)));
+    } else {
+        // lcs[i][j]: common subsequence length of am[i..] and bm[j..]
+        let mut lcs = alloc::vec![0u16; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * (m + 1) + j] = if am[i] == bm[j] {
+                    lcs[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if am[i] == bm[j] {
+                out.push(Change::Same(am[i]));
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1] {
+                out.push(Change::Removed(am[i]));
+                i += 1;
+            } else {
+                out.push(Change::Added(bm[j]));
+                j += 1;
+            }
+        }
+        out.extend(am[i..].iter().map(|t| Change::Removed(t)));
+        out.extend(bm[j..].iter().map(|t| Change::Added(t)));
+    }
+    out.extend(a[a.len() - suffix..].iter().map(|t| Change::Same(t)));
+    out
+}
+
+/// Sentences of `text`: split after `.`, `!` or `?` followed by whitespace,
+/// and at line breaks. Blank pieces are dropped.
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let mut start = 0;
+        let bytes = line.as_bytes();
+        for i in 0..bytes.len() {
+            let end_mark = matches!(bytes[i], b'.' | b'!' | b'?');
+            if end_mark && bytes.get(i + 1).is_some_and(|c| c.is_ascii_whitespace()) {
+                out.push(line[start..=i].trim());
+                start = i + 1;
+            }
+        }
+        out.push(line[start..].trim());
+    }
+    out.retain(|s| !s.is_empty());
+    out
+}
+
+/// A reworded sentence with its word changes marked inline, or None when
+/// the two share too few words for that to read better than -/+ lines
+fn inline_words(old: &str, new: &str) -> Option<String> {
+    let a: Vec<&str> = old.split_whitespace().collect();
+    let b: Vec<&str> = new.split_whitespace().collect();
+    let changes = diff(&a, &b);
+    let same = changes.iter().filter(|c| matches!(c, Change::Same(_))).count();
+    if same * 2 < a.len().max(b.len()) {
+        return None;
+    }
+    let mut words: Vec<String> = Vec::new();
+    for change in changes {
+        match change {
+            Change::Same(w) => words.push(String::from(w)),
+            Change::Removed(w) => words.push(format!("[-{}-]", w)),
+            Change::Added(w) => words.push(format!("{{+{}+}}", w)),
+        }
+    }
+    // Merge neighbouring markers of the same kind: "[-a-] [-b-]" -> "[-a b-]"
+    Some(words.join(" ").replace("-] [-", " ").replace("+} {+", " "))
+}
+
+/// The /diff-answers report for two answers, `old` first
+pub fn compare(old: &str, new: &str) -> String {
+    let a = sentences(old);
+    let b = sentences(new);
+    let changes = diff(&a, &b);
+
+    let removed = changes.iter().filter(|c| matches!(c, Change::Removed(_))).count();
+    let added = changes.iter().filter(|c| matches!(c, Change::Added(_))).count();
+    let same = changes.len() - removed - added;
+    if removed == 0 && added == 0 {
+        return format!("～ The answers are identical ({} sentences) ～", same);
+    }
+
+    let mut out = format!("～ Answer diff: {} sentences removed, {} added, {} unchanged ～\n\n", removed, added, same);
+    let mut i = 0;
+    while i < changes.len() {
+        if let Change::Same(_) = changes[i] {
+            let run = changes[i..].iter().take_while(|c| matches!(c, Change::Same(_))).count();
+            let first = i == 0;
+            let last = i + run == changes.len();
+            let keep_before = if first { 0 } else { CONTEXT.min(run) };
+            let keep_after = if last { 0 } else { CONTEXT.min(run - keep_before) };
+            for c in &changes[i..i + keep_before] {
+                if let Change::Same(s) = c { out.push_str(&format!("  {}\n", s)); }
+            }
+            let hidden = run - keep_before - keep_after;
+            if hidden > 0 {
+                out.push_str(&format!("  ... {} unchanged\n", hidden));
+            }
+            for c in &changes[i + run - keep_after..i + run] {
+                if let Change::Same(s) = c { out.push_str(&format!("  {}\n", s)); }
+            }
+            i += run;
+            continue;
+        }
+
+        // A run of removals and additions; pair them up as rewordings
+        let run = changes[i..].iter().take_while(|c| !matches!(c, Change::Same(_))).count();
+        let gone: Vec<&str> = changes[i..i + run].iter().filter_map(|c| match c { Change::Removed(s) => Some(*s), _ => None }).collect();
+        let came: Vec<&str> = changes[i..i + run].iter().filter_map(|c| match c { Change::Added(s) => Some(*s), _ => None }).collect();
+        let (mut gone_lines, mut came_lines) = (String::new(), String::new());
+        for k in 0..gone.len().max(came.len()) {
+            let reworded = match (gone.get(k), came.get(k)) {
+                (Some(old), Some(new)) => inline_words(old, new),
+                _ => None,
+            };
+            match reworded {
+                Some(line) => out.push_str(&format!("~ {}\n", line)),
+                None => {
+                    if let Some(s) = gone.get(k) { gone_lines.push_str(&format!("- [-{}-]\n", s)); }
+                    if let Some(s) = came.get(k) { came_lines.push_str(&format!("+ {{+{}+}}\n", s)); }
+                }
+            }
+        }
+        out.push_str(&gone_lines);
+        out.push_str(&came_lines);
+        i += run;
+    }
+    out
+}
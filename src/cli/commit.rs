@@ -0,0 +1,99 @@
+//! `meow commit [--yes] [--cheap]`
+//!
+//! Collects the staged diff, asks the model for a conventional-commit
+//! message, confirms it with the user and commits via the git tool layer.
+
+use alloc::string::String;
+use alloc::format;
+
+use crate::config::{Config, Provider};
+use crate::tools::{git, shell};
+
+/// Larger diffs are cut to keep the request small
+const MAX_DIFF_IN_PROMPT: usize = 24 * 1024;
+
+const COMMIT_SYSTEM_PROMPT: &str = "You write git commit messages in the Conventional Commits format \
+(type(scope): summary). Reply with the commit message only: a summary line of at most 72 characters, \
+optionally followed by a blank line and a short body. No code fences, no commentary, no tool calls.";
+
+pub fn run(args: &[String], model: &str, provider: &Provider, config: &Config) -> i32 {
+    let mut assume_yes = false;
+    let mut cheap = false;
+    for a in args {
+        match a.as_str() {
+            "--yes" | "-y" => assume_yes = true,
+            "--cheap" => cheap = true,
+            other => {
+                libakuma::print(&format!("meow commit: unknown option '{}'\n", other));
+                return 1;
+            }
+        }
+    }
+
+    let diff = match shell::run_command(&git::diff_command(true, None)) {
+        Ok(d) => d,
+        Err(e) => {
+            libakuma::print(&format!("meow commit: failed to collect diff: {}\n", e));
+            return 1;
+        }
+    };
+    if diff.exit_code != 0 {
+        libakuma::print(&format!("meow commit: diff failed (exit code {}):\n{}\n", diff.exit_code, diff.stdout));
+        return 1;
+    }
+    if diff.stdout.trim().is_empty() {
+        libakuma::print("meow commit: nothing staged. Stage changes first (scratch add <path>).\n");
+        return 1;
+    }
+
+    let model = if cheap {
+        config.cheap_model.as_deref().unwrap_or(model)
+    } else {
+        model
+    };
+
+    let mut end = diff.stdout.len().min(MAX_DIFF_IN_PROMPT);
+    while !diff.stdout.is_char_boundary(end) { end -= 1; }
+    let truncated = if end < diff.stdout.len() { "\n[diff truncated]" } else { "" };
+    let prompt = format!(
+        "Write a commit message for this staged diff:\n\n```diff\n{}{}\n```",
+        &diff.stdout[..end], truncated
+    );
+
+    let reply = match super::complete(model, provider, COMMIT_SYSTEM_PROMPT, &prompt) {
+        Ok(r) => r,
+        Err(e) => {
+            libakuma::print(&format!("meow commit: {}\n", e));
+            return 1;
+        }
+    };
+
+    let message = clean_message(&reply);
+    if message.is_empty() {
+        libakuma::print("meow commit: the model returned an empty message\n");
+        return 1;
+    }
+
+    libakuma::print(&format!("\n--- proposed commit message ---\n{}\n-------------------------------\n", message));
+    if !assume_yes && !super::confirm("Commit with this message? [y/N] ") {
+        libakuma::print("Aborted, nothing committed.\n");
+        return 1;
+    }
+
+    let result = git::tool_git_commit(&message, false);
+    libakuma::print(&result.output);
+    libakuma::print("\n");
+    if result.success { 0 } else { 1 }
+}
+
+/// Strip code fences, quotes and surrounding whitespace models like to add
+fn clean_message(reply: &str) -> String {
+    let mut lines: alloc::vec::Vec<&str> = reply
+        .lines()
+        .filter(|l| !l.trim_start().starts_with("```"))
+        .collect();
+    while lines.first().map_or(false, |l| l.trim().is_empty()) { lines.remove(0); }
+    while lines.last().map_or(false, |l| l.trim().is_empty()) { lines.pop(); }
+    let joined = lines.join("\n");
+    String::from(joined.trim().trim_matches('"').trim())
+}
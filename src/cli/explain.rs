@@ -0,0 +1,67 @@
+//! `meow explain -- <command...>`
+//!
+//! Runs a command through the shell tool machinery, captures its output
+//! and exit code, and builds a prompt asking the model to explain it.
+//! Spawned commands have one output pipe, so stderr isn't told apart.
+
+use alloc::string::String;
+use alloc::format;
+
+use crate::tools::shell;
+
+/// Only the tail of long output is sent to the model (errors live at the end)
+const MAX_CAPTURE_IN_PROMPT: usize = 8 * 1024;
+
+/// Run the command and assemble the explanation prompt
+pub fn build_prompt(args: &[String]) -> Result<String, String> {
+    if args.is_empty() {
+        return Err(String::from("explain requires a command, e.g. meow explain -- make test"));
+    }
+
+    let command = join_command(args);
+    let captured = shell::run_command(&command)?;
+
+    let output = tail_on_char_boundary(&captured.stdout, MAX_CAPTURE_IN_PROMPT);
+    let truncated = if output.len() < captured.stdout.len() { " (truncated, showing the end)" } else { "" };
+
+    let mut prompt = format!("I ran the following command:\n\n```\n{}\n```\n\n", command);
+    prompt.push_str(&format!("It exited with code {}.\n\n", captured.exit_code));
+    if output.trim().is_empty() {
+        prompt.push_str("It produced no output.\n\n");
+    } else {
+        prompt.push_str(&format!("Captured output{}:\n\n```\n{}\n```\n\n", truncated, output));
+    }
+    if captured.exit_code == 0 {
+        prompt.push_str("Explain what this output means. Be concise and do not call any tools.");
+    } else {
+        prompt.push_str("Explain why it failed and suggest how to fix it. Be concise and do not call any tools.");
+    }
+
+    Ok(prompt)
+}
+
+/// Re-join argv into a single command line that `tokenize_command` splits back the same way
+fn join_command(args: &[String]) -> String {
+    let mut cmd = String::new();
+    for (i, a) in args.iter().enumerate() {
+        if i > 0 { cmd.push(' '); }
+        if a.is_empty() || a.contains(|c: char| c == ' ' || c == '\t' || c == '"' || c == '\'' || c == '\\') {
+            cmd.push('"');
+            for c in a.chars() {
+                if c == '"' || c == '\\' { cmd.push('\\'); }
+                cmd.push(c);
+            }
+            cmd.push('"');
+        } else {
+            cmd.push_str(a);
+        }
+    }
+    cmd
+}
+
+fn tail_on_char_boundary(s: &str, max: usize) -> &str {
+    if s.len() <= max { return s; }
+    let mut start = s.len() - max;
+    while !s.is_char_boundary(start) { start += 1; }
+    &s[start..]
+}
@@ -0,0 +1,126 @@
+//! `meow here`
+//!
+//! "Work on this repo" entry point: moves to the enclosing repository root so
+//! it becomes the sandbox root, applies the project's `.meow/config`, and
+//! builds a project map for the system prompt before opening the TUI.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::read_dir;
+
+use crate::config::Config;
+
+/// Project-level overrides, relative to the repository root
+const PROJECT_CONFIG: &str = ".meow/config";
+/// How deep the project map descends below the root
+const MAP_MAX_DEPTH: usize = 2;
+/// Cap on listed entries so large trees don't swamp the context window
+const MAP_MAX_ENTRIES: usize = 200;
+
+/// Find the repository root above the current directory and chdir into it.
+/// Must run before anything queries the working directory, because the
+/// sandbox root is taken from the process cwd on first use.
+pub fn enter() -> Result<String, String> {
+    let cwd = String::from(libakuma::getcwd());
+    let root = find_repo_root(&cwd)
+        .ok_or_else(|| format!("no repository found above {} (looked for .git)", cwd))?;
+
+    let result = libakuma::chdir(&root);
+    if result != 0 {
+        return Err(format!("failed to change directory to {}: error {}", root, result));
+    }
+    Ok(root)
+}
+
+fn find_repo_root(start: &str) -> Option<String> {
+    let mut dir = String::from(start.trim_end_matches('/'));
+    loop {
+        let probe = if dir.is_empty() { "/" } else { dir.as_str() };
+        if let Some(entries) = read_dir(probe) {
+            if entries.into_iter().any(|e| e.name == ".git") {
+                return Some(String::from(probe));
+            }
+        }
+        match dir.rfind('/') {
+            Some(pos) if !dir.is_empty() => dir.truncate(pos),
+            _ => return None,
+        }
+    }
+}
+
+/// Apply `model`, `provider` and `personality` from the project's
+/// `.meow/config`. Command-line flags are applied afterwards and still win.
+pub fn apply_project_config(config: &mut Config, root: &str) {
+    let Some(content) = read_small_file(&format!("{}/{}", root, PROJECT_CONFIG)) else { return; };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        let value = value.trim();
+        if value.is_empty() { continue; }
+        match key.trim() {
+            "model" => config.current_model = String::from(value),
+            "provider" => {
+                if config.get_provider(value).is_some() {
+                    config.current_provider = String::from(value);
+                } else {
+                    libakuma::print(&format!("meow here: ignoring unknown provider '{}' in {}\n", value, PROJECT_CONFIG));
+                }
+            }
+            "personality" => config.current_personality = String::from(value),
+            _ => {}
+        }
+    }
+}
+
+/// Indented listing of the top levels of the repository
+pub fn project_map(root: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    walk(root, 0, &mut lines);
+
+    let mut map = String::from("\n\n## Project Map\nTop levels of the repository you are working in:\n```\n");
+    for line in &lines {
+        map.push_str(line);
+        map.push('\n');
+    }
+    if lines.len() >= MAP_MAX_ENTRIES {
+        map.push_str("... (truncated)\n");
+    }
+    map.push_str("```\n");
+    map
+}
+
+fn walk(path: &str, depth: usize, lines: &mut Vec<String>) {
+    let Some(entries) = read_dir(path) else { return; };
+    let mut entries: Vec<_> = entries.into_iter().collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+    for entry in entries {
+        if lines.len() >= MAP_MAX_ENTRIES { return; }
+        if entry.name == "." || entry.name == ".." || entry.name == ".git"
+            || entry.name == "target" || entry.name == "node_modules" {
+            continue;
+        }
+
+        let indent = "  ".repeat(depth);
+        if entry.is_dir {
+            lines.push(format!("{}{}/", indent, entry.name));
+            if depth + 1 < MAP_MAX_DEPTH {
+                walk(&format!("{}/{}", path.trim_end_matches('/'), entry.name), depth + 1, lines);
+            }
+        } else {
+            lines.push(format!("{}{}", indent, entry.name));
+        }
+    }
+}
+
+fn read_small_file(path: &str) -> Option<String> {
+    let data = crate::tools::fs::read_file(path, 16 * 1024).ok()?;
+    if data.is_empty() {
+        return None;
+    }
+    String::from_utf8(data).ok()
+}
@@ -0,0 +1,62 @@
+//! Workflow subcommands (`meow explain`, `meow commit`, `meow review`, `meow pr-desc`, `meow here`, ...)
+//!
+//! Each subcommand gathers its own context and then either hands a prepared
+//! prompt to the regular one-shot chat path or drives a short exchange itself.
+
+pub mod commit;
+pub mod explain;
+pub mod here;
+pub mod pr_desc;
+pub mod review;
+
+use alloc::string::String;
+
+use crate::api::{self, StreamResponse};
+use crate::app::{Message, calculate_history_tokens};
+use crate::config::{Provider, DEFAULT_CONTEXT_WINDOW};
+
+/// Subcommands recognised as the first command-line argument
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subcommand {
+    Explain,
+    Commit,
+    Review,
+    PrDesc,
+    Here,
+}
+
+impl Subcommand {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "explain" => Some(Subcommand::Explain),
+            "commit" => Some(Subcommand::Commit),
+            "review" => Some(Subcommand::Review),
+            "pr-desc" => Some(Subcommand::PrDesc),
+            "here" => Some(Subcommand::Here),
+            _ => None,
+        }
+    }
+}
+
+/// Send a single system + user exchange without the tool loop and return the reply
+pub fn complete(model: &str, provider: &Provider, system: &str, user: &str) -> Result<String, &'static str> {
+    let history = [Message::new("system", system), Message::new("user", user)];
+    let outgoing = [&history[0], &history[1]];
+
+    let current_tokens = calculate_history_tokens(&history);
+    let mem_kb = libakuma::memory_usage() / 1024;
+    let response = api::send_with_retry(model, provider, &outgoing, &api::Sampling::default(), false, current_tokens, DEFAULT_CONTEXT_WINDOW, mem_kb)?;
+    libakuma::print("\n");
+
+    match response {
+        StreamResponse::Complete(text, _) | StreamResponse::Partial(text, _) | StreamResponse::Stopped(text, _) => Ok(text),
+    }
+}
+
+/// Ask a yes/no question on the plain terminal, defaulting to "no"
+pub fn confirm(question: &str) -> bool {
+    libakuma::print(question);
+    let mut buf = [0u8; 16];
+    let n = libakuma::read_fd(0, &mut buf);
+    n > 0 && (buf[0] == b'y' || buf[0] == b'Y')
+}
@@ -0,0 +1,243 @@
+//! `meow pr-desc [BASE] [--post]`
+//!
+//! Reads the commit log and diff of the current branch against BASE and asks
+//! the model for a pull request title and markdown description. With `--post`
+//! and `forge_api`/`forge_token` configured, the pull request is opened too.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma_tls::{HttpHeaders, HttpStreamTls, StreamResult, TLS_RECORD_SIZE};
+
+use crate::api::transport::{self, Endpoint};
+use crate::app::json_escape_to;
+use crate::config::{Config, Provider};
+use crate::tools::{git, shell};
+
+const DEFAULT_BASE: &str = "main";
+/// Larger diffs are cut to keep the request small
+const MAX_DIFF_IN_PROMPT: usize = 24 * 1024;
+/// Commit logs are usually short, but a long-lived branch can have many
+const MAX_LOG_IN_PROMPT: usize = 8 * 1024;
+/// Give up on the forge after this long without a byte of reply
+const FORGE_TIMEOUT_US: u64 = 30_000_000;
+/// A new pull request's JSON is a few KB; stop reading well past that
+const MAX_FORGE_REPLY: usize = 256 * 1024;
+
+const PR_SYSTEM_PROMPT: &str = "You write pull request descriptions. Reply with the title on the first line \
+(plain text, at most 72 characters), then a blank line, then a markdown body: one or two sentences on what \
+changes and why, followed by a short bullet list of notable changes. No code fences around the whole reply, \
+no commentary, no tool calls.";
+
+pub fn run(args: &[String], model: &str, provider: &Provider, config: &Config) -> i32 {
+    let mut base: Option<&str> = None;
+    let mut post = false;
+    for a in args {
+        match a.as_str() {
+            "--post" => post = true,
+            other if other.starts_with('-') => {
+                libakuma::print(&format!("meow pr-desc: unknown option '{}'\n", other));
+                return 1;
+            }
+            other => base = Some(other),
+        }
+    }
+    let base = base.unwrap_or(DEFAULT_BASE);
+
+    let forge = match (post, &config.forge_api, &config.forge_token) {
+        (false, _, _) => None,
+        (true, Some(api), Some(token)) => Some((api.as_str(), token.as_str())),
+        (true, _, _) => {
+            libakuma::print("meow pr-desc: --post needs forge_api and forge_token in /etc/meow/config\n");
+            return 1;
+        }
+    };
+
+    let branch = match current_branch() {
+        Some(b) => b,
+        None => {
+            libakuma::print("meow pr-desc: could not determine the current branch\n");
+            return 1;
+        }
+    };
+    if branch == base {
+        libakuma::print(&format!("meow pr-desc: already on '{}', check out a feature branch first\n", base));
+        return 1;
+    }
+
+    let range = format!("{}..{}", base, branch);
+    let log = match capture(&format!("scratch log --oneline {}", range)) {
+        Ok(l) => l,
+        Err(e) => {
+            libakuma::print(&format!("meow pr-desc: failed to read log: {}\n", e));
+            return 1;
+        }
+    };
+    let diff = match capture(&git::diff_command(false, Some(&range))) {
+        Ok(d) => d,
+        Err(e) => {
+            libakuma::print(&format!("meow pr-desc: failed to collect diff: {}\n", e));
+            return 1;
+        }
+    };
+    if log.trim().is_empty() && diff.trim().is_empty() {
+        libakuma::print(&format!("meow pr-desc: no changes between {} and {}\n", base, branch));
+        return 0;
+    }
+
+    let prompt = format!(
+        "Branch `{}` targets `{}`.\n\nCommits:\n```\n{}\n```\n\nDiff:\n```diff\n{}\n```",
+        branch, base, head_of(&log, MAX_LOG_IN_PROMPT), head_of(&diff, MAX_DIFF_IN_PROMPT)
+    );
+
+    let reply = match super::complete(model, provider, PR_SYSTEM_PROMPT, &prompt) {
+        Ok(r) => r,
+        Err(e) => {
+            libakuma::print(&format!("meow pr-desc: {}\n", e));
+            return 1;
+        }
+    };
+
+    let (title, body) = split_title(&reply);
+    if title.is_empty() {
+        libakuma::print("meow pr-desc: the model returned an empty description\n");
+        return 1;
+    }
+
+    libakuma::print(&format!("\n--- {} ---\n\n{}\n", title, body));
+
+    let Some((api, token)) = forge else { return 0; };
+    if !super::confirm("Open a pull request with this description? [y/N] ") {
+        libakuma::print("Aborted, nothing posted.\n");
+        return 1;
+    }
+    match open_pull_request(api, token, &title, &body, &branch, base) {
+        Ok(url) => {
+            libakuma::print(&format!("～ Pull request opened: {} nya~! ～\n", url));
+            0
+        }
+        Err(e) => {
+            libakuma::print(&format!("meow pr-desc: {}\n", e));
+            1
+        }
+    }
+}
+
+/// Run a git command and return its stdout, treating a non-zero exit as an error
+fn capture(cmd: &str) -> Result<String, String> {
+    let out = shell::run_command(cmd)?;
+    if out.exit_code != 0 {
+        return Err(format!("exit code {}: {}", out.exit_code, out.stdout.trim()));
+    }
+    Ok(out.stdout)
+}
+
+/// Name of the checked-out branch, from the `* name` line of `scratch branch`
+fn current_branch() -> Option<String> {
+    let out = capture("scratch branch").ok()?;
+    out.lines()
+        .find_map(|l| l.trim_start().strip_prefix("* "))
+        .map(|b| String::from(b.trim()))
+}
+
+/// First `max` bytes of `s` (on a char boundary), marked when cut
+fn head_of(s: &str, max: usize) -> String {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) { end -= 1; }
+    let mut out = String::from(&s[..end]);
+    if end < s.len() {
+        out.push_str("\n[truncated]");
+    }
+    out
+}
+
+/// Split the reply into its title line and the remaining markdown body
+fn split_title(reply: &str) -> (String, String) {
+    let reply = reply.trim();
+    let (first, rest) = reply.split_once('\n').unwrap_or((reply, ""));
+    let title = first.trim().trim_start_matches('#').trim().trim_matches('"').trim_matches('`');
+    (String::from(title), String::from(rest.trim()))
+}
+
+/// POST {api}/pulls and return the `html_url` of the new pull request.
+/// `HttpStreamTls` hands back only the body and reports a non-2xx status as
+/// an error, so a 201 is told from other successes by the reply itself.
+fn open_pull_request(api: &str, token: &str, title: &str, body: &str, head: &str, base: &str) -> Result<String, String> {
+    let (host, port, base_path) = split_url(api).ok_or_else(|| String::from("forge_api must be an https:// URL"))?;
+    let path = format!("{}/pulls", base_path.trim_end_matches('/'));
+
+    let mut json = String::from("{\"title\":\"");
+    json_escape_to(title, &mut json);
+    json.push_str("\",\"body\":\"");
+    json_escape_to(body, &mut json);
+    json.push_str("\",\"head\":\"");
+    json_escape_to(head, &mut json);
+    json.push_str("\",\"base\":\"");
+    json_escape_to(base, &mut json);
+    json.push_str("\"}");
+
+    let stream = transport::connect_tcp(&Endpoint::Tcp { host: host.clone(), port, tls: true })?;
+
+    let mut read_buf = alloc::vec![0u8; TLS_RECORD_SIZE];
+    let mut write_buf = alloc::vec![0u8; TLS_RECORD_SIZE];
+    let mut http_stream = HttpStreamTls::connect(stream, &host, &mut read_buf, &mut write_buf)
+        .map_err(|e| format!("TLS error: {:?}", e))?;
+
+    let mut headers = HttpHeaders::new();
+    headers.content_type("application/json");
+    headers.bearer_auth(token);
+    // GitHub refuses API requests without one
+    headers.add("User-Agent", concat!("meow/", env!("CARGO_PKG_VERSION")));
+    http_stream.post(&host, &path, &json, &headers).map_err(|_| String::from("Failed to send request"))?;
+
+    let mut response: Vec<u8> = Vec::new();
+    let mut last_data_us = libakuma::uptime();
+    loop {
+        match http_stream.read_chunk() {
+            StreamResult::Data(data) => {
+                last_data_us = libakuma::uptime();
+                response.extend_from_slice(&data);
+                if response.len() > MAX_FORGE_REPLY {
+                    return Err(String::from("forge reply too large"));
+                }
+            }
+            StreamResult::WouldBlock => {
+                if libakuma::uptime() - last_data_us > FORGE_TIMEOUT_US {
+                    return Err(format!("no reply from the forge in {}s", FORGE_TIMEOUT_US / 1_000_000));
+                }
+                libakuma::sleep_ms(10);
+            }
+            StreamResult::Done => break,
+            StreamResult::Error(e) => return Err(format!("forge refused the pull request: {:?}", e)),
+        }
+    }
+
+    let text = core::str::from_utf8(&response).map_err(|_| String::from("Invalid UTF-8 response"))?;
+    // A created pull request, not some other 2xx body: it has a number and a page
+    match extract_html_url(text).filter(|_| text.contains("\"number\":")) {
+        Some(url) => Ok(url),
+        None => Err(format!("forge did not return a pull request URL:\n{}", head_of(text, 512))),
+    }
+}
+
+/// Host, port and path of an https:// URL
+fn split_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("https://")?;
+    let (host_port, path) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, ""),
+    };
+    let (host, port) = match host_port.rfind(':') {
+        Some(pos) => (&host_port[..pos], host_port[pos + 1..].parse::<u16>().ok()?),
+        None => (host_port, 443),
+    };
+    Some((String::from(host), port, String::from(path)))
+}
+
+fn extract_html_url(json: &str) -> Option<String> {
+    let pattern = "\"html_url\":\"";
+    let start = json.find(pattern)? + pattern.len();
+    let end = json[start..].find('"')?;
+    Some(String::from(&json[start..start + end]))
+}
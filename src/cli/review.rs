@@ -0,0 +1,171 @@
+//! `meow review [BASE..HEAD] [--issues]`
+//!
+//! Splits the diff of a ref range into per-file sections, chunks large
+//! sections, and streams a review for each chunk. With `--issues`, every
+//! finding is filed in chainlink.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::config::Provider;
+use crate::tools::{chainlink, git, shell};
+
+const DEFAULT_RANGE: &str = "main..HEAD";
+/// Maximum diff text sent to the model per request
+const MAX_CHUNK_SIZE: usize = 12 * 1024;
+
+const REVIEW_SYSTEM_PROMPT: &str = "You are a meticulous code reviewer. Review the diff you are given. \
+Report only real problems: bugs, missing error handling, security issues, confusing code. \
+Put each finding on its own line in the form `FINDING: <severity high|medium|low> | <short title> | <explanation>`. \
+If the diff looks fine, reply `No findings.` Do not call any tools.";
+
+/// Diff text belonging to a single file
+struct FileDiff {
+    path: String,
+    body: String,
+}
+
+pub fn run(args: &[String], model: &str, provider: &Provider) -> i32 {
+    let mut range: Option<&str> = None;
+    let mut file_issues = false;
+    for a in args {
+        match a.as_str() {
+            "--issues" => file_issues = true,
+            other if other.starts_with('-') => {
+                libakuma::print(&format!("meow review: unknown option '{}'\n", other));
+                return 1;
+            }
+            other => range = Some(other),
+        }
+    }
+    let range = range.unwrap_or(DEFAULT_RANGE);
+
+    if file_issues && !chainlink::chainlink_available() {
+        libakuma::print("meow review: --issues requires chainlink in /bin\n");
+        return 1;
+    }
+
+    let diff = match shell::run_command(&git::diff_command(false, Some(range))) {
+        Ok(d) => d,
+        Err(e) => {
+            libakuma::print(&format!("meow review: failed to collect diff: {}\n", e));
+            return 1;
+        }
+    };
+    if diff.exit_code != 0 {
+        libakuma::print(&format!("meow review: diff failed (exit code {}):\n{}\n", diff.exit_code, diff.stdout));
+        return 1;
+    }
+    if diff.stdout.trim().is_empty() {
+        libakuma::print(&format!("meow review: no changes in {}\n", range));
+        return 0;
+    }
+
+    let files = split_by_file(&diff.stdout);
+    let mut findings_total = 0;
+    let mut failed = false;
+
+    for file in &files {
+        let chunks = chunk_diff(&file.body, MAX_CHUNK_SIZE);
+        for (i, chunk) in chunks.iter().enumerate() {
+            if chunks.len() > 1 {
+                libakuma::print(&format!("\n=== {} (part {}/{}) ===\n", file.path, i + 1, chunks.len()));
+            } else {
+                libakuma::print(&format!("\n=== {} ===\n", file.path));
+            }
+
+            let prompt = format!("Review the changes to `{}` in {}:\n\n```diff\n{}\n```", file.path, range, chunk);
+            let reply = match super::complete(model, provider, REVIEW_SYSTEM_PROMPT, &prompt) {
+                Ok(r) => r,
+                Err(e) => {
+                    libakuma::print(&format!("meow review: {}\n", e));
+                    failed = true;
+                    continue;
+                }
+            };
+
+            for line in reply.lines() {
+                let Some(finding) = line.trim().strip_prefix("FINDING:") else { continue; };
+                findings_total += 1;
+                if file_issues {
+                    file_finding(&file.path, finding.trim());
+                }
+            }
+        }
+    }
+
+    libakuma::print(&format!("\n～ Reviewed {} file(s) in {}: {} finding(s) ～\n", files.len(), range, findings_total));
+    if failed { 1 } else { 0 }
+}
+
+/// Split unified diff output on `diff --git` headers. The path comes from
+/// the `+++ b/` line (`--- a/` for a deletion), since the header itself is
+/// ambiguous when a path contains spaces; binary and mode-only changes have
+/// neither, so they fall back to the header's last word.
+fn split_by_file(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    // Between a `diff --git` header and the file's first hunk
+    let mut in_header = false;
+    for line in diff.lines() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let last = rest.split_whitespace().last().unwrap_or("(unknown)");
+            let path = last.strip_prefix("b/").unwrap_or(last);
+            files.push(FileDiff { path: String::from(path), body: String::new() });
+            in_header = true;
+        } else if line.starts_with("@@") {
+            in_header = false;
+        } else if in_header {
+            let named = line
+                .strip_prefix("+++ b/")
+                .or_else(|| line.strip_prefix("--- a/"))
+                .map(|p| p.trim_end_matches('\t'));
+            if let (Some(path), Some(file)) = (named, files.last_mut()) {
+                file.path = String::from(path);
+            }
+        }
+        if files.is_empty() {
+            files.push(FileDiff { path: String::from("(all changes)"), body: String::new() });
+        }
+        let current = files.last_mut().unwrap();
+        current.body.push_str(line);
+        current.body.push('\n');
+    }
+    files
+}
+
+/// Cut a file diff into chunks, preferring hunk (`@@`) boundaries
+fn chunk_diff(body: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in body.lines() {
+        let at_boundary = line.starts_with("@@") || current.len() + line.len() + 1 > max;
+        if at_boundary && current.len() + line.len() + 1 > max / 2 && !current.is_empty() {
+            chunks.push(core::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Create a chainlink issue for a `severity | title | explanation` finding
+fn file_finding(path: &str, finding: &str) {
+    let mut parts = finding.splitn(3, '|').map(|p| p.trim());
+    let severity = parts.next().unwrap_or("medium").to_lowercase();
+    let title = parts.next().unwrap_or(finding);
+    let explanation = parts.next().unwrap_or("");
+
+    let priority = match severity.as_str() {
+        "high" | "low" | "medium" => severity.as_str(),
+        _ => "medium",
+    };
+    let description = format!("{}: {}", path, explanation);
+    let result = chainlink::tool_chainlink_create(&format!("[review] {}", title), Some(&description), Some(priority));
+    if !result.success {
+        libakuma::print(&format!("  [!] Failed to file issue: {}\n", result.output));
+    }
+}
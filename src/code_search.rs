@@ -1,7 +1,9 @@
 //! Code search module for meow
 //!
-//! Provides grep-like search functionality for Rust source files.
+//! Provides grep-like search functionality over the text files of a tree.
 //! Uses simple string matching (no regex) to stay no_std compatible.
+//! Files can be narrowed down by a glob or a list of extensions; binary
+//! files (a NUL byte near the start, or not UTF-8) are skipped.
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -9,17 +11,89 @@ use alloc::format;
 
 use libakuma::{open, close, read_fd, fstat, read_dir, open_flags};
 
-/// Maximum number of matches to return (to avoid overwhelming output)
-const MAX_MATCHES: usize = 50;
+/// Matches returned unless the caller asks for another number
+pub const DEFAULT_MAX_MATCHES: usize = 50;
+/// Upper bound on `max_results` (to avoid overwhelming output)
+const MAX_MATCHES_LIMIT: usize = 500;
+/// Bytes checked for a NUL when deciding a file is binary
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
 /// Maximum file size to search (256KB)
 const MAX_FILE_SIZE: usize = 256 * 1024;
 
-/// Search for a pattern in Rust files recursively
+/// Which files to search
+#[derive(Default)]
+pub struct FileFilter {
+    /// Glob over the path below the search root (`src/**/*.rs`), or over
+    /// the file name when it has no `/` (`*.toml`)
+    pub glob: Option<String>,
+    /// Extensions without the dot (`rs`, `md`); empty for any
+    pub extensions: Vec<String>,
+}
+
+impl FileFilter {
+    /// `extensions` as the tool takes it: comma or space separated, dots optional
+    pub fn new(glob: Option<String>, extensions: Option<&str>) -> Self {
+        let extensions = extensions.unwrap_or("")
+            .split([',', ' '])
+            .map(|e| e.trim().trim_start_matches("*.").trim_start_matches('.'))
+            .filter(|e| !e.is_empty())
+            .map(String::from)
+            .collect();
+        FileFilter { glob: glob.filter(|g| !g.is_empty()), extensions }
+    }
+
+    fn accepts(&self, relative: &str) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        if !self.extensions.is_empty() {
+            let ext = name.rsplit_once('.').map(|(_, e)| e).unwrap_or("");
+            if !self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)) {
+                return false;
+            }
+        }
+        match &self.glob {
+            Some(g) if g.contains('/') => glob_match(g.trim_start_matches("./").as_bytes(), relative.as_bytes()),
+            Some(g) => glob_match(g.as_bytes(), name.as_bytes()),
+            None => true,
+        }
+    }
+}
+
+/// `*` matches within a path segment, `**` across segments, `?` one character
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some(b'*') if glob.get(1) == Some(&b'*') => {
+            // `**/` also matches no directories at all
+            let rest = &glob[2..];
+            let after_slash = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]) || glob_match(after_slash, &text[i..]))
+        }
+        Some(b'*') => {
+            let rest = &glob[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => text.first().is_some_and(|c| *c != b'/') && glob_match(&glob[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+/// Search for a pattern in text files recursively
 ///
 /// # Arguments
 /// * `pattern` - Text pattern to search for (simple string matching)
 /// * `directory` - Root directory to search in
 /// * `context_lines` - Number of lines of context to show before/after matches
+/// * `filter` - Which files to look at
+/// * `max_results` - Matches to show at most (capped at 500)
+/// * `offset` - Matches to skip, for the next page of a long result
 ///
 /// # Returns
 /// A formatted string with all matches, or an error
@@ -27,33 +101,45 @@ pub fn search_to_string(
     pattern: &str,
     directory: &str,
     context_lines: usize,
+    filter: &FileFilter,
+    max_results: usize,
+    offset: usize,
 ) -> Result<String, &'static str> {
     if pattern.is_empty() {
         return Err("Empty search pattern");
     }
+    let max_results = max_results.clamp(1, MAX_MATCHES_LIMIT);
 
-    let mut matches: Vec<Match> = Vec::new();
-    search_recursive(directory, pattern, context_lines, &mut matches);
+    let mut search = Search { root: directory, pattern, context_lines, filter, max_results, skip: offset, matches: Vec::new() };
+    search.recursive(directory);
+    let matches = search.matches;
 
+    if matches.is_empty() && offset > 0 {
+        return Ok(format!("No more matches for '{}' after the first {}", pattern, offset));
+    }
     if matches.is_empty() {
         return Ok(format!("No matches found for pattern: {}", pattern));
     }
 
     let total_matches = matches.len();
-    let truncated = total_matches > MAX_MATCHES;
+    let truncated = total_matches > max_results;
     let display_matches = if truncated {
-        &matches[..MAX_MATCHES]
+        &matches[..max_results]
     } else {
         &matches[..]
     };
 
     let mut output = String::new();
-    output.push_str(&format!(
-        "Found {} matches for '{}'",
-        total_matches, pattern
-    ));
-    if truncated {
-        output.push_str(&format!(" (showing first {})", MAX_MATCHES));
+    if truncated || offset > 0 {
+        output.push_str(&format!(
+            "Matches {}-{} for '{}'",
+            offset + 1, offset + display_matches.len(), pattern
+        ));
+    } else {
+        output.push_str(&format!(
+            "Found {} matches for '{}'",
+            total_matches, pattern
+        ));
     }
     output.push_str(":\n\n");
 
@@ -65,6 +151,12 @@ pub fn search_to_string(
         }
         output.push('\n');
     }
+    if truncated {
+        output.push_str(&format!(
+            "[more matches: call CodeSearch again with \"offset\": {} for the next page, or narrow it with glob or extensions]\n",
+            offset + max_results
+        ));
+    }
 
     Ok(output)
 }
@@ -76,108 +168,128 @@ struct Match {
     context: Vec<String>,
 }
 
-/// Recursively search through directories
-fn search_recursive(
-    path: &str,
-    pattern: &str,
+struct Search<'a> {
+    root: &'a str,
+    pattern: &'a str,
     context_lines: usize,
-    matches: &mut Vec<Match>,
-) {
-    if matches.len() >= MAX_MATCHES * 2 {
-        // Stop early if we have way more than we need
-        return;
-    }
+    filter: &'a FileFilter,
+    max_results: usize,
+    /// Matches still to pass over before collecting (`offset`)
+    skip: usize,
+    matches: Vec<Match>,
+}
 
-    // Check if path is a directory
-    if let Some(entries) = read_dir(path) {
-        for entry in entries {
-            // Skip common non-source directories
-            if entry.name == "target" || entry.name == ".git" || entry.name == "node_modules" {
-                continue;
-            }
+impl Search<'_> {
+    /// Recursively search through directories
+    fn recursive(&mut self, path: &str) {
+        if self.matches.len() > self.max_results {
+            // One more than shown is enough to say the list was cut
+            return;
+        }
 
-            let full_path = if path.ends_with('/') {
-                format!("{}{}", path, entry.name)
-            } else {
-                format!("{}/{}", path, entry.name)
-            };
+        // Check if path is a directory
+        if let Some(mut entries) = read_dir(path) {
+            // A fixed order keeps pages apart from one call to the next
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            for entry in entries {
+                // Skip common non-source directories
+                if entry.name == "target" || entry.name == ".git" || entry.name == "node_modules" {
+                    continue;
+                }
 
-            if entry.is_dir {
-                search_recursive(&full_path, pattern, context_lines, matches);
-            } else if entry.name.ends_with(".rs") {
-                search_file(&full_path, pattern, context_lines, matches);
+                let full_path = if path.ends_with('/') {
+                    format!("{}{}", path, entry.name)
+                } else {
+                    format!("{}/{}", path, entry.name)
+                };
+
+                if entry.is_dir {
+                    self.recursive(&full_path);
+                } else if self.filter.accepts(self.relative(&full_path)) {
+                    self.file(&full_path);
+                }
             }
-        }
-    } else {
-        // Not a directory, try as a file
-        if path.ends_with(".rs") {
-            search_file(path, pattern, context_lines, matches);
+        } else {
+            // Not a directory, try as a file
+            self.file(path);
         }
     }
-}
 
-/// Search a single file for matches
-fn search_file(
-    path: &str,
-    pattern: &str,
-    context_lines: usize,
-    matches: &mut Vec<Match>,
-) {
-    let fd = open(path, open_flags::O_RDONLY);
-    if fd < 0 {
-        return;
+    /// `path` below the search root
+    fn relative<'p>(&self, path: &'p str) -> &'p str {
+        path.strip_prefix(self.root).map(|r| r.trim_start_matches('/')).unwrap_or(path)
     }
 
-    // Get file size
-    let stat = match fstat(fd) {
-        Ok(s) => s,
-        Err(_) => {
+    /// Search a single file for matches
+    fn file(&mut self, path: &str) {
+        let fd = open(path, open_flags::O_RDONLY);
+        if fd < 0 {
+            return;
+        }
+
+        // Get file size
+        let stat = match fstat(fd) {
+            Ok(s) => s,
+            Err(_) => {
+                close(fd);
+                return;
+            }
+        };
+
+        let size = stat.st_size as usize;
+        if size == 0 || size > MAX_FILE_SIZE {
             close(fd);
             return;
         }
-    };
 
-    let size = stat.st_size as usize;
-    if size == 0 || size > MAX_FILE_SIZE {
+        let mut buf = alloc::vec![0u8; size];
+        let bytes_read = read_fd(fd, &mut buf);
         close(fd);
-        return;
-    }
 
-    let mut buf = alloc::vec![0u8; size];
-    let bytes_read = read_fd(fd, &mut buf);
-    close(fd);
+        if bytes_read <= 0 {
+            return;
+        }
+        let data = &buf[..bytes_read as usize];
 
-    if bytes_read <= 0 {
-        return;
-    }
+        // Binary files: a NUL early on, or not UTF-8
+        if data[..data.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+            return;
+        }
+        let content = match core::str::from_utf8(data) {
+            Ok(s) => s,
+            Err(_) => return,
+        };
 
-    let content = match core::str::from_utf8(&buf[..bytes_read as usize]) {
-        Ok(s) => s,
-        Err(_) => return,
-    };
+        // Split into lines
+        let lines: Vec<&str> = content.lines().collect();
 
-    // Split into lines
-    let lines: Vec<&str> = content.lines().collect();
+        for (idx, line) in lines.iter().enumerate() {
+            if self.matches.len() > self.max_results {
+                return;
+            }
+            if line.contains(self.pattern) {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                    continue;
+                }
+                let line_num = idx + 1; // 1-indexed
 
-    for (idx, line) in lines.iter().enumerate() {
-        if line.contains(pattern) {
-            let line_num = idx + 1; // 1-indexed
+                // Collect context lines
+                let start = idx.saturating_sub(self.context_lines);
+                let end = (idx + self.context_lines + 1).min(lines.len());
 
-            // Collect context lines
-            let start = idx.saturating_sub(context_lines);
-            let end = (idx + context_lines + 1).min(lines.len());
+                let mut context = Vec::new();
+                for (i, l) in lines.iter().enumerate().take(end).skip(start) {
+                    let prefix = if i == idx { ">" } else { " " };
+                    context.push(format!("{} {:>4}: {}", prefix, i + 1, l));
+                }
 
-            let mut context = Vec::new();
-            for i in start..end {
-                let prefix = if i == idx { ">" } else { " " };
-                context.push(format!("{} {:>4}: {}", prefix, i + 1, lines[i]));
+                self.matches.push(Match {
+                    file: String::from(path),
+                    line_num,
+                    context,
+                });
             }
-
-            matches.push(Match {
-                file: String::from(path),
-                line_num,
-                context,
-            });
         }
     }
 }
@@ -13,10 +13,25 @@ pub const TOKEN_LIMIT_FOR_COMPACTION: usize = 32_000;
 /// Default context window if we can't query the model
 pub const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
 
+/// Token count at which compaction is suggested. Small windows can't wait
+/// for the fixed limit, so there it is a share of the window instead.
+pub fn compaction_threshold(context_window: usize) -> usize {
+    TOKEN_LIMIT_FOR_COMPACTION.min(context_window * 3 / 4)
+}
+
 /// Maximum size for tool output to be kept in memory (32KB).
 /// If output exceeds this, it should be written to a temp file.
 pub const MAX_TOOL_OUTPUT_SIZE: usize = 32 * 1024;
 
+/// Chat request retry defaults (`max_retries`, `initial_backoff_ms`,
+/// `request_timeout_s`)
+pub const DEFAULT_MAX_RETRIES: u32 = 9;
+pub const DEFAULT_INITIAL_BACKOFF_MS: u64 = 500;
+pub const DEFAULT_REQUEST_TIMEOUT_S: u64 = 60;
+
+/// Tools that run without asking unless `auto_approve` says otherwise
+pub const DEFAULT_AUTO_APPROVE: &str = "read-only";
+
 /// Default size for the buffer used by tool_shell to capture command output
 pub const TOOL_BUFFER_SIZE: usize = 8 * 1024; // 8KB
 
@@ -181,6 +196,26 @@ pub const PERSONALITIES: &[Personality] = &[
     },
 ];
 
+pub const PLAIN_PERSONA: &str = r#"You are a software engineering assistant. Be direct and professional.
+
+Style:
+- Answer in plain, neutral language; no role-play, emoticons or catchphrases
+- Lead with the answer, then only the detail needed to act on it
+- Prefer short paragraphs and lists; show code instead of describing it
+- Say so plainly when you are unsure or something failed
+
+Your output may be pasted into tickets, chat or code review as-is."#;
+
+/// Used instead of the configured personality in professional mode
+/// (`--plain`, `/persona off`)
+pub const PLAIN_PERSONALITY: Personality = Personality {
+    name: "Plain",
+    description: PLAIN_PERSONA,
+    ack_tui: "Understood. I'll use relative paths for file operations within the current directory.",
+    ack_one_shot: "Understood.",
+    error_format: "Error: {}\n",
+};
+
 pub const COMMON_TOOLS: &str = r#"## Available Tools
 
 You have access to filesystem tools! When you need to perform file operations, output a JSON command block like this:
@@ -198,6 +233,8 @@ You have access to filesystem tools! When you need to perform file operations, o
 
 1. **FileRead** - Read file contents
    Args: `{"filename": "path/to/file"}`
+   Note: A file over ~31KB comes back as its first and last lines (numbered) plus an
+         outline of the definitions in between; read the part you need with FileReadLines.
 
 2. **FileWrite** - Create or overwrite a file
    Args: `{"filename": "path/to/file", "content": "file contents"}`
@@ -210,6 +247,9 @@ You have access to filesystem tools! When you need to perform file operations, o
 
 5. **FileList** - List directory contents
    Args: `{"path": "/directory/path"}`
+   Args: `{"path": "/directory/path", "offset": 300, "limit": 100}` - a page of a large directory
+   Note: Large directories come back as one comma-separated line (directories end in /)
+         with the total count; a truncated listing says which offset to ask for next.
 
 6. **FolderCreate** - Create a directory
    Args: `{"path": "/new/directory/path"}`
@@ -226,6 +266,9 @@ You have access to filesystem tools! When you need to perform file operations, o
 10. **HttpFetch** - Fetch content from HTTP or HTTPS URLs
     Args: `{"url": "http(s)://host[:port]/path"}`
     Note: Supports both http:// and https://. Max 64KB response. HTTPS uses TLS 1.3.
+          Follows up to 5 redirects. Compressed (gzip) responses are refused.
+          HTML pages come back as readable text with links kept as [text](url);
+          add "raw": "true" only when you need the markup itself.
 
 ### Directory Navigation:
 
@@ -300,30 +343,57 @@ After cloning, use Cd to enter the repository before running other git commands.
     Args: `{}`
     Note: Removes all files from the staging area without deleting them.
 
+26. **GitDiff** - Show changes as a unified diff
+    Args: `{}` - unstaged changes in the working tree
+    Args: `{"staged": "true"}` - changes staged for commit
+    Args: `{"range": "main..HEAD"}` - changes between two refs
+
 ### Code Editing Tools:
 
-26. **FileReadLines** - Read specific line ranges from a file
+27. **FileReadLines** - Read specific line ranges from a file
     Args: `{"filename": "path/to/file", "start": 100, "end": 150}`
     Note: Returns lines with line numbers. Great for navigating large files.
 
-27. **CodeSearch** - Search for patterns in Rust source files
+28. **CodeSearch** - Search for text in the files of a directory tree
     Args: `{"pattern": "search text", "path": "directory", "context": 2}`
-    Note: Searches .rs files recursively. Returns matches with context lines.
-
-28. **FileEdit** - Precise search-and-replace editing
+    Args: `{"pattern": "TODO", "path": ".", "extensions": "md,toml"}` - only these file types
+    Args: `{"pattern": "fn main", "path": ".", "glob": "src/**/*.rs", "max_matches": 100}`
+    Args: `{"pattern": "TODO", "path": ".", "offset": 50}` - the next page of matches
+    Note: Searches every text file recursively (binary files are skipped) and returns
+          matches with context lines, 50 by default. A glob without `/` matches file names.
+          A cut-off result ends with the offset to ask for next.
+
+29. **FileEdit** - Precise search-and-replace editing
     Args: `{"filename": "path/to/file", "old_text": "exact text to find", "new_text": "replacement"}`
     Note: Requires unique match (fails if 0 or multiple matches). Returns diff output.
 
-29. **Shell** - Execute a shell command
+30. **Shell** - Execute a shell command
     Args: `{"cmd": "your command here"}`
     Note: Runs the specified binary. Use for build commands, git operations, etc.
 
-30. **CompactContext** - Compact conversation history by summarizing it
+31. **CompactContext** - Compact conversation history by summarizing it
     Args: `{"summary": "A comprehensive summary of the conversation so far..."}`
     Note: Use this when the token count displayed in the prompt approaches the limit.
           Provide a detailed summary that captures all important context, decisions made,
           files discussed, and any ongoing work. The summary replaces the conversation history.
 
+32. **AskUser** - Ask the user a clarifying question and wait for the answer
+    Args: `{"question": "Which branch should I base the fix on?"}`
+    Note: Use this instead of ending your turn when you need a decision to continue.
+          The answer comes back as the tool result and you keep working on the same task.
+
+33. **FileTree** - List a directory recursively as an indented tree
+    Args: `{"path": "."}`
+    Args: `{"path": "src", "depth": 2}` - descend at most 2 levels (default 3)
+    Note: Use this instead of repeated FileList calls to get the layout of a repository.
+          .git, target and node_modules are shown but not descended into.
+
+34. **WebSearch** - Search the web
+    Args: `{"query": "rust no_std http client"}`
+    Args: `{"query": "...", "count": 5}` - fewer or more results (default 8, at most 20)
+    Note: Returns titles, URLs and short snippets. Use it to find sources when you
+          don't already know the URL, then read the most relevant ones with HttpFetch.
+
 ### Important Notes:
 - Output the JSON command in a ```json code block
 - After outputting a command, STOP and wait for the result
@@ -390,6 +460,166 @@ impl Default for ApiType {
     }
 }
 
+/// Generation cap (num_predict / max_tokens) for normal replies
+pub const DEFAULT_MAX_TOKENS: usize = 16384;
+
+/// Reply length preset (`/verbosity`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    Brief,
+    Normal,
+    Detailed,
+}
+
+impl Verbosity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verbosity::Brief => "brief",
+            Verbosity::Normal => "normal",
+            Verbosity::Detailed => "detailed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "brief" => Some(Verbosity::Brief),
+            "normal" => Some(Verbosity::Normal),
+            "detailed" => Some(Verbosity::Detailed),
+            _ => None,
+        }
+    }
+
+    /// Generation cap sent as num_predict / max_tokens
+    pub fn max_tokens(&self) -> usize {
+        match self {
+            Verbosity::Brief => 1024,
+            Verbosity::Normal | Verbosity::Detailed => DEFAULT_MAX_TOKENS,
+        }
+    }
+
+    /// Instruction added to each request; None leaves the prompt alone
+    pub fn instruction(&self) -> Option<&'static str> {
+        match self {
+            Verbosity::Brief => Some("Keep replies brief: a few sentences or a short list, code only where needed. Skip preamble and recaps."),
+            Verbosity::Normal => None,
+            Verbosity::Detailed => Some("Give thorough replies: explain your reasoning, cover edge cases and alternatives, and include complete code."),
+        }
+    }
+}
+
+impl Default for Verbosity {
+    fn default() -> Self {
+        Verbosity::Normal
+    }
+}
+
+/// Color depth of the terminal (`color_mode`); see `ui::theme`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    /// Ask the terminal at startup; 24-bit until it answers
+    Auto,
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorMode::Auto => "auto",
+            ColorMode::TrueColor => "truecolor",
+            ColorMode::Ansi256 => "256",
+            ColorMode::Ansi16 => "16",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Some(ColorMode::Auto),
+            "truecolor" | "24bit" => Some(ColorMode::TrueColor),
+            "256" => Some(ColorMode::Ansi256),
+            "16" => Some(ColorMode::Ansi16),
+            _ => None,
+        }
+    }
+}
+
+/// Where the WebSearch tool sends queries (`search_engine`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchEngine {
+    /// The DuckDuckGo Instant Answer API: no key, but only summaries and
+    /// related topics rather than a full result list
+    DuckDuckGo,
+    /// A SearXNG instance at `search_url`, with its JSON format enabled
+    SearXng,
+    /// The Brave Search API; `search_key` is the subscription token
+    Brave,
+}
+
+impl SearchEngine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchEngine::DuckDuckGo => "duckduckgo",
+            SearchEngine::SearXng => "searxng",
+            SearchEngine::Brave => "brave",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "duckduckgo" | "ddg" => Some(SearchEngine::DuckDuckGo),
+            "searxng" | "searx" => Some(SearchEngine::SearXng),
+            "brave" => Some(SearchEngine::Brave),
+            _ => None,
+        }
+    }
+
+    /// Endpoint used when `search_url` is not set
+    pub fn default_url(&self) -> Option<&'static str> {
+        match self {
+            SearchEngine::DuckDuckGo => Some("https://api.duckduckgo.com/"),
+            SearchEngine::SearXng => None,
+            SearchEngine::Brave => Some("https://api.search.brave.com/res/v1/web/search"),
+        }
+    }
+}
+
+/// How requests prove who sent them (`auth=`); the secret is `api_key`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthMode {
+    /// `Authorization: Bearer KEY`
+    Bearer,
+    /// The key as the value of the named header (`header:X-Api-Key`)
+    Header(String),
+    /// HMAC-SHA256 over the timestamp and body, keyed with the shared secret;
+    /// see `api::auth`
+    HmacSha256,
+}
+
+impl AuthMode {
+    pub fn as_string(&self) -> String {
+        match self {
+            AuthMode::Bearer => String::from("bearer"),
+            AuthMode::Header(name) => alloc::format!("header:{}", name),
+            AuthMode::HmacSha256 => String::from("hmac-sha256"),
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(name) = s.strip_prefix("header:") {
+            let name = name.trim();
+            let valid = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+            return if valid { Some(AuthMode::Header(String::from(name))) } else { None };
+        }
+        match s.to_lowercase().as_str() {
+            "bearer" => Some(AuthMode::Bearer),
+            "hmac-sha256" => Some(AuthMode::HmacSha256),
+            _ => None,
+        }
+    }
+}
+
 /// A configured AI provider
 #[derive(Debug, Clone)]
 pub struct Provider {
@@ -397,6 +627,21 @@ pub struct Provider {
     pub base_url: String,
     pub api_type: ApiType,
     pub api_key: Option<String>,
+    /// Reply length used when switching to this provider
+    pub verbosity: Verbosity,
+    /// Send the tool list as a `tools` array and take the provider's
+    /// structured tool calls (`native_tools=true`)
+    pub native_tools: bool,
+    /// Count tokens as characters / this instead of with the built-in
+    /// estimate (`chars_per_token=3.5`)
+    pub chars_per_token: Option<f32>,
+    /// Model to switch to when this provider is selected (`default_model=`)
+    pub default_model: Option<String>,
+    /// How `api_key` is sent (`auth=bearer|header:NAME|hmac-sha256`)
+    pub auth: AuthMode,
+    /// Headers sent with every request, e.g. `HTTP-Referer` for OpenRouter
+    /// (`extra_headers=Name: value`, one line per header)
+    pub extra_headers: Vec<(String, String)>,
 }
 
 impl Provider {
@@ -407,6 +652,12 @@ impl Provider {
             base_url: String::from("http://10.0.2.2:11434"),
             api_type: ApiType::Ollama,
             api_key: None,
+            verbosity: Verbosity::Normal,
+            native_tools: false,
+            chars_per_token: None,
+            default_model: None,
+            auth: AuthMode::Bearer,
+            extra_headers: Vec::new(),
         }
     }
 
@@ -438,11 +689,16 @@ impl Provider {
         self.base_url.starts_with("https://")
     }
 
-    /// Get the base path from the URL (e.g., "/openai/v1" from "https://api.groq.com/openai/v1")
+    /// Get the base path from the URL (e.g., "/openai/v1" from "https://api.groq.com/openai/v1").
+    /// unix:// URLs are all socket path, so the API sits at the root.
     pub fn base_path(&self) -> &str {
-        let url = self.base_url
-            .trim_start_matches("http://")
-            .trim_start_matches("https://");
+        if self.base_url.starts_with("unix://") {
+            return "";
+        }
+        let url = match self.base_url.split_once("://") {
+            Some((_, rest)) => rest,
+            None => self.base_url.as_str(),
+        };
         match url.find('/') {
             Some(pos) => &url[pos..],
             None => "",
@@ -450,6 +706,78 @@ impl Provider {
     }
 }
 
+/// Request parameters for a model (`[params:MODEL]` sections, `/params`)
+/// or for all of them (`/set`); what is unset is left to the provider
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    /// Reply length cap (`max_tokens` on OpenAI), instead of the /verbosity one
+    pub num_predict: Option<usize>,
+    pub seed: Option<u64>,
+    /// Stop sequences
+    pub stop: Vec<String>,
+    /// How long Ollama keeps the model loaded, e.g. `5m` or `-1` (Ollama only)
+    pub keep_alive: Option<String>,
+}
+
+impl GenOptions {
+    pub const KEYS: [&'static str; 6] = ["temperature", "top_p", "num_predict", "seed", "stop", "keep_alive"];
+
+    /// Set `key` from its text form (stop sequences comma-separated, `\n`
+    /// for a line break); an empty value unsets it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), &'static str> {
+        let value = value.trim();
+        let empty = value.is_empty();
+        match key {
+            "temperature" => self.temperature = if empty { None } else {
+                Some(value.parse::<f32>().ok().filter(|t| (0.0..=2.0).contains(t)).ok_or("temperature is a number from 0 to 2")?)
+            },
+            "top_p" => self.top_p = if empty { None } else {
+                Some(value.parse::<f32>().ok().filter(|p| (0.0..=1.0).contains(p)).ok_or("top_p is a number from 0 to 1")?)
+            },
+            "num_predict" => self.num_predict = if empty { None } else {
+                Some(value.parse::<usize>().ok().filter(|n| *n > 0).ok_or("num_predict is a token count")?)
+            },
+            "seed" => self.seed = if empty { None } else { Some(value.parse().map_err(|_| "seed is a whole number")?) },
+            "stop" => self.stop = value.split(',').filter(|s| !s.is_empty()).map(unescape_line).collect(),
+            "keep_alive" => self.keep_alive = if empty { None } else { Some(String::from(value)) },
+            _ => return Err("unknown parameter"),
+        }
+        Ok(())
+    }
+
+    /// `key` in the text form `set` reads, None when unset
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "temperature" => self.temperature.map(|t| alloc::format!("{}", t)),
+            "top_p" => self.top_p.map(|p| alloc::format!("{}", p)),
+            "num_predict" => self.num_predict.map(|n| alloc::format!("{}", n)),
+            "seed" => self.seed.map(|s| alloc::format!("{}", s)),
+            "stop" if !self.stop.is_empty() => Some(self.stop.iter().map(|s| escape_line(s)).collect::<Vec<_>>().join(",")),
+            "keep_alive" => self.keep_alive.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == GenOptions::default()
+    }
+}
+
+/// A tool defined in the config (`[tool:NAME]` section), run as a command
+/// through the Shell tool's sandbox and risk checks
+#[derive(Debug, Clone)]
+pub struct CustomTool {
+    pub name: String,
+    /// Command template; `{arg}` is replaced with that argument (`cmd=`)
+    pub cmd: String,
+    /// What the tool does, for the system prompt (`description=`)
+    pub description: String,
+    /// Argument names, all required (`args=a,b`)
+    pub args: Vec<String>,
+}
+
 /// Main configuration structure
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -461,6 +789,91 @@ pub struct Config {
     pub exit_on_escape: bool,
     /// Whether to render markdown or show raw text
     pub render_markdown: bool,
+    /// Smaller/faster model used by helper subcommands (e.g. `meow commit --cheap`)
+    pub cheap_model: Option<String>,
+    /// Forge API base for the repository (e.g. https://api.github.com/repos/OWNER/REPO)
+    pub forge_api: Option<String>,
+    /// Token used when posting to the forge API
+    pub forge_token: Option<String>,
+    /// Animate the status line (off = redraw the footer only when it changes)
+    pub animation: bool,
+    /// Low-bandwidth mode for slow SSH/serial links (no animation, markdown or cat, line-buffered streaming)
+    pub low_bandwidth: bool,
+    /// Message of the day shown under the greeting; `@issues` shows the open chainlink issue count
+    pub motd: Option<String>,
+    /// Persona text replacing the personality's and MEOW.md (`/system set`)
+    pub system_prompt: Option<String>,
+    /// Drop trailing filler ("Let me know if...") from replies
+    pub strip_filler: bool,
+    /// Collapse runs of blank lines in replies
+    pub collapse_blank_lines: bool,
+    /// Mask secrets (API keys, tokens) in replies before they enter history
+    pub redact_secrets: bool,
+    /// Professional mode: plain persona, no kaomoji in replies or cat-themed UI strings
+    pub professional: bool,
+    /// Re-root absolute paths outside the sandbox in Shell arguments instead of rejecting the command
+    pub rewrite_shell_paths: bool,
+    /// Show the sandbox root to the model as / and read absolute tool paths relative to it
+    pub virtual_paths: bool,
+    /// Send a tiny priming request when the TUI starts or the model changes, so the model is loaded before the first message
+    pub warmup: bool,
+    /// Show up to three follow-up suggestions after each reply, sent with /1 /2 /3
+    pub suggestions: bool,
+    /// When another meow instance is working in the same sandbox, turn file-changing tools off instead of only warning
+    pub exclusive_sandbox: bool,
+    /// Directory tools are confined to (unset = the directory meow starts in)
+    pub sandbox_root: Option<String>,
+    /// Offer to resume the autosaved TUI session if it is at most this many minutes old (0 = never ask)
+    pub resume_max_age_min: u64,
+    /// Add the open chainlink issues to the context message at session start (refresh with /issues)
+    pub issue_context: bool,
+    /// Widest text column in the output pane (0 = full pane width)
+    pub wrap_width: u16,
+    /// Center the narrowed text column in the pane instead of keeping it on the left
+    pub wrap_center: bool,
+    /// Word-wrap output; off cuts long lines at the edge (scroll sideways with /wrap scroll)
+    pub word_wrap: bool,
+    /// Indent of your messages in the output pane
+    pub indent_user: u16,
+    /// Indent of replies in the output pane
+    pub indent_assistant: u16,
+    /// Indent of tool notifications and output in the output pane
+    pub indent_tool: u16,
+    /// Append the conversation as plain text to this file (unset = no transcript)
+    pub transcript_file: Option<String>,
+    /// Append request bodies, responses and tool calls as JSON lines to this file (unset = no log)
+    pub log_file: Option<String>,
+    /// Compact the history on its own before a message would take it past the compaction threshold
+    pub auto_compact: bool,
+    /// Experimental: read files the reply names while it streams, ahead of the FileRead call
+    pub prefetch_reads: bool,
+    /// Terminal color depth; `auto` asks the terminal when the TUI starts
+    pub color_mode: ColorMode,
+    /// Times a failed chat request is tried again
+    pub max_retries: u32,
+    /// Wait before the first retry; doubles with each further one
+    pub initial_backoff_ms: u64,
+    /// Give up on a response after this long without any data from the server
+    pub request_timeout_s: u64,
+    /// Backend for the WebSearch tool
+    pub search_engine: SearchEngine,
+    /// Search endpoint, when not the engine's public one (required for SearXNG)
+    pub search_url: Option<String>,
+    /// API key for the search engine (Brave)
+    pub search_key: Option<String>,
+    /// Tools that run without asking; `read-only` covers every tool that changes nothing, `*` all of them
+    pub auto_approve: Vec<String>,
+    /// `[keys]` section: (action, key sequence as written)
+    pub keys: Vec<(String, String)>,
+    /// `[tool:NAME]` sections
+    pub custom_tools: Vec<CustomTool>,
+    /// Request parameters for every model (top-level `temperature=` etc.,
+    /// `/set`); a model's `[params:MODEL]` keys take precedence
+    pub gen: GenOptions,
+    /// `[params:MODEL]` sections: request parameters saved from `/params`
+    pub model_params: Vec<(String, GenOptions)>,
+    /// Settings found while loading that meow refuses to run with (not saved)
+    pub errors: Vec<String>,
 }
 
 impl Default for Config {
@@ -472,12 +885,68 @@ impl Default for Config {
             providers: alloc::vec![Provider::ollama_default()],
             exit_on_escape: false,
             render_markdown: false,
+            cheap_model: None,
+            forge_api: None,
+            forge_token: None,
+            animation: true,
+            low_bandwidth: false,
+            motd: None,
+            system_prompt: None,
+            strip_filler: true,
+            collapse_blank_lines: true,
+            redact_secrets: true,
+            professional: false,
+            rewrite_shell_paths: false,
+            virtual_paths: false,
+            warmup: false,
+            suggestions: false,
+            exclusive_sandbox: false,
+            sandbox_root: None,
+            resume_max_age_min: 120,
+            issue_context: false,
+            wrap_width: 0,
+            wrap_center: false,
+            word_wrap: true,
+            indent_user: 4,
+            indent_assistant: 9,
+            indent_tool: 9,
+            transcript_file: None,
+            log_file: None,
+            auto_compact: false,
+            prefetch_reads: false,
+            color_mode: ColorMode::Auto,
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff_ms: DEFAULT_INITIAL_BACKOFF_MS,
+            request_timeout_s: DEFAULT_REQUEST_TIMEOUT_S,
+            search_engine: SearchEngine::DuckDuckGo,
+            search_url: None,
+            search_key: None,
+            auto_approve: alloc::vec![String::from(DEFAULT_AUTO_APPROVE)],
+            keys: Vec::new(),
+            custom_tools: Vec::new(),
+            gen: GenOptions::default(),
+            model_params: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The personality in effect: the plain one in professional mode,
+    /// otherwise `current_personality` (falling back to Meow)
+    pub fn personality(&self) -> &'static Personality {
+        if self.professional {
+            return &PLAIN_PERSONALITY;
         }
+        PERSONALITIES
+            .iter()
+            .find(|p| p.name == self.current_personality)
+            .unwrap_or(&PERSONALITIES[0])
     }
 }
 
 /// Config file path
-const CONFIG_PATH: &str = "/etc/meow/config";
+pub const CONFIG_PATH: &str = "/etc/meow/config";
 const CONFIG_DIR: &str = "/etc/meow";
 
 impl Config {
@@ -542,9 +1011,54 @@ impl Config {
             providers: Vec::new(),
             exit_on_escape: false,
             render_markdown: true,
+            cheap_model: None,
+            forge_api: None,
+            forge_token: None,
+            animation: true,
+            low_bandwidth: false,
+            motd: None,
+            system_prompt: None,
+            strip_filler: true,
+            collapse_blank_lines: true,
+            redact_secrets: true,
+            professional: false,
+            rewrite_shell_paths: false,
+            virtual_paths: false,
+            warmup: false,
+            suggestions: false,
+            exclusive_sandbox: false,
+            sandbox_root: None,
+            resume_max_age_min: 120,
+            issue_context: false,
+            wrap_width: 0,
+            wrap_center: false,
+            word_wrap: true,
+            indent_user: 4,
+            indent_assistant: 9,
+            indent_tool: 9,
+            transcript_file: None,
+            log_file: None,
+            auto_compact: false,
+            prefetch_reads: false,
+            color_mode: ColorMode::Auto,
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff_ms: DEFAULT_INITIAL_BACKOFF_MS,
+            request_timeout_s: DEFAULT_REQUEST_TIMEOUT_S,
+            search_engine: SearchEngine::DuckDuckGo,
+            search_url: None,
+            search_key: None,
+            auto_approve: alloc::vec![String::from(DEFAULT_AUTO_APPROVE)],
+            keys: Vec::new(),
+            custom_tools: Vec::new(),
+            gen: GenOptions::default(),
+            model_params: Vec::new(),
+            errors: Vec::new(),
         };
 
         let mut current_provider: Option<Provider> = None;
+        let mut current_tool: Option<CustomTool> = None;
+        let mut current_params: Option<(String, GenOptions)> = None;
+        let mut in_keys = false;
 
         for line in content.lines() {
             let line = line.trim();
@@ -553,18 +1067,55 @@ impl Config {
             }
 
             // Check for section header [provider:name]
-            if line.starts_with("[provider:") && line.ends_with(']') {
-                // Save previous provider if any
+            if line.starts_with('[') {
+                // A new section ends the previous one
                 if let Some(p) = current_provider.take() {
                     config.providers.push(p);
                 }
+                if let Some(t) = current_tool.take() {
+                    config.custom_tools.push(t);
+                }
+                if let Some(p) = current_params.take() {
+                    config.model_params.push(p);
+                }
+                in_keys = false;
+            }
 
+            if line == "[keys]" {
+                in_keys = true;
+                continue;
+            }
+
+            if line.starts_with("[params:") && line.ends_with(']') {
+                let model = &line[8..line.len() - 1];
+                current_params = Some((String::from(model.trim()), GenOptions::default()));
+                continue;
+            }
+
+            if line.starts_with("[tool:") && line.ends_with(']') {
+                let name = &line[6..line.len() - 1];
+                current_tool = Some(CustomTool {
+                    name: String::from(name.trim()),
+                    cmd: String::new(),
+                    description: String::new(),
+                    args: Vec::new(),
+                });
+                continue;
+            }
+
+            if line.starts_with("[provider:") && line.ends_with(']') {
                 let name = &line[10..line.len() - 1];
                 current_provider = Some(Provider {
                     name: String::from(name),
                     base_url: String::new(),
                     api_type: ApiType::Ollama,
                     api_key: None,
+                    verbosity: Verbosity::Normal,
+                    native_tools: false,
+                    chars_per_token: None,
+                    default_model: None,
+                    auth: AuthMode::Bearer,
+                    extra_headers: Vec::new(),
                 });
                 continue;
             }
@@ -574,7 +1125,25 @@ impl Config {
                 let key = line[..eq_pos].trim();
                 let value = line[eq_pos + 1..].trim();
 
-                if let Some(ref mut p) = current_provider {
+                if in_keys {
+                    config.keys.push((String::from(key), String::from(value)));
+                } else if let Some((_, ref mut opts)) = current_params {
+                    let _ = opts.set(key, value);
+                } else if let Some(ref mut t) = current_tool {
+                    // One surrounding pair, so a command may itself end in a quote
+                    let value = value
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .unwrap_or(value);
+                    match key {
+                        "cmd" => t.cmd = String::from(value),
+                        "description" => t.description = String::from(value),
+                        "args" => {
+                            t.args = value.split(',').map(str::trim).filter(|a| !a.is_empty()).map(String::from).collect();
+                        }
+                        _ => {}
+                    }
+                } else if let Some(ref mut p) = current_provider {
                     // Inside a provider section
                     match key {
                         "base_url" => p.base_url = String::from(value),
@@ -588,6 +1157,44 @@ impl Config {
                                 p.api_key = Some(String::from(value));
                             }
                         }
+                        "verbosity" => {
+                            if let Some(v) = Verbosity::from_str(value) {
+                                p.verbosity = v;
+                            }
+                        }
+                        "ca_cert" | "insecure_skip_verify" => {
+                            // The TLS library takes no trust settings, so
+                            // these would silently do nothing
+                            config.errors.push(alloc::format!(
+                                "[provider:{}] {}= is not supported: this build cannot change how TLS certificates are checked",
+                                p.name, key
+                            ));
+                        }
+                        "native_tools" => {
+                            p.native_tools = value.to_lowercase() == "true";
+                        }
+                        "chars_per_token" => {
+                            p.chars_per_token = value.parse::<f32>().ok().filter(|r| *r > 0.0);
+                        }
+                        "default_model" => {
+                            if !value.is_empty() {
+                                p.default_model = Some(String::from(value));
+                            }
+                        }
+                        "auth" => {
+                            if let Some(a) = AuthMode::from_str(value) {
+                                p.auth = a;
+                            }
+                        }
+                        "extra_headers" => {
+                            // May repeat; each line adds one header
+                            if let Some((name, val)) = value.split_once(':') {
+                                let name = name.trim();
+                                if !name.is_empty() && !name.contains(char::is_whitespace) {
+                                    p.extra_headers.push((String::from(name), String::from(val.trim())));
+                                }
+                            }
+                        }
                         _ => {}
                     }
                 } else {
@@ -602,6 +1209,160 @@ impl Config {
                         "render_markdown" => {
                             config.render_markdown = value.to_lowercase() != "false";
                         }
+                        "cheap_model" => {
+                            if !value.is_empty() {
+                                config.cheap_model = Some(String::from(value));
+                            }
+                        }
+                        "forge_api" => {
+                            if !value.is_empty() {
+                                config.forge_api = Some(String::from(value));
+                            }
+                        }
+                        "forge_token" => {
+                            if !value.is_empty() {
+                                config.forge_token = Some(String::from(value));
+                            }
+                        }
+                        "animation" => {
+                            config.animation = value.to_lowercase() != "false";
+                        }
+                        "low_bandwidth" => {
+                            config.low_bandwidth = value.to_lowercase() == "true";
+                        }
+                        "auto_compact" => {
+                            config.auto_compact = value.to_lowercase() == "true";
+                        }
+                        "prefetch_reads" => {
+                            config.prefetch_reads = value.to_lowercase() == "true";
+                        }
+                        "color_mode" => {
+                            if let Some(m) = ColorMode::from_str(value) {
+                                config.color_mode = m;
+                            }
+                        }
+                        "max_retries" => {
+                            if let Ok(n) = value.parse() {
+                                config.max_retries = n;
+                            }
+                        }
+                        "initial_backoff_ms" => {
+                            if let Ok(n) = value.parse() {
+                                config.initial_backoff_ms = n;
+                            }
+                        }
+                        "request_timeout_s" => {
+                            if let Ok(n) = value.parse::<u64>() {
+                                config.request_timeout_s = n.max(1);
+                            }
+                        }
+                        "search_engine" => {
+                            if let Some(e) = SearchEngine::from_str(value) {
+                                config.search_engine = e;
+                            }
+                        }
+                        "search_url" => {
+                            if !value.is_empty() {
+                                config.search_url = Some(String::from(value));
+                            }
+                        }
+                        "search_key" => {
+                            if !value.is_empty() {
+                                config.search_key = Some(String::from(value));
+                            }
+                        }
+                        "auto_approve" => {
+                            config.auto_approve = value.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+                        }
+                        "motd" => {
+                            if !value.is_empty() {
+                                config.motd = Some(String::from(value));
+                            }
+                        }
+                        "system_prompt" => {
+                            if !value.is_empty() {
+                                config.system_prompt = Some(unescape_line(value));
+                            }
+                        }
+                        "strip_filler" => {
+                            config.strip_filler = value.to_lowercase() != "false";
+                        }
+                        "collapse_blank_lines" => {
+                            config.collapse_blank_lines = value.to_lowercase() != "false";
+                        }
+                        "redact_secrets" => {
+                            config.redact_secrets = value.to_lowercase() != "false";
+                        }
+                        "professional" => {
+                            config.professional = value.to_lowercase() == "true";
+                        }
+                        "rewrite_shell_paths" => {
+                            config.rewrite_shell_paths = value.to_lowercase() == "true";
+                        }
+                        "virtual_paths" => {
+                            config.virtual_paths = value.to_lowercase() == "true";
+                        }
+                        "warmup" => {
+                            config.warmup = value.to_lowercase() == "true";
+                        }
+                        "suggestions" => {
+                            config.suggestions = value.to_lowercase() == "true";
+                        }
+                        "exclusive_sandbox" => {
+                            config.exclusive_sandbox = value.to_lowercase() == "true";
+                        }
+                        "sandbox_root" => {
+                            if !value.is_empty() {
+                                config.sandbox_root = Some(String::from(value));
+                            }
+                        }
+                        "resume_max_age_min" => {
+                            if let Ok(minutes) = value.parse() {
+                                config.resume_max_age_min = minutes;
+                            }
+                        }
+                        "issue_context" => {
+                            config.issue_context = value.to_lowercase() == "true";
+                        }
+                        "wrap_width" => {
+                            if let Ok(n) = value.parse() {
+                                config.wrap_width = n;
+                            }
+                        }
+                        "wrap_center" => {
+                            config.wrap_center = value.to_lowercase() == "true";
+                        }
+                        "word_wrap" => {
+                            config.word_wrap = value.to_lowercase() != "false";
+                        }
+                        "indent_user" => {
+                            if let Ok(n) = value.parse() {
+                                config.indent_user = n;
+                            }
+                        }
+                        "indent_assistant" => {
+                            if let Ok(n) = value.parse() {
+                                config.indent_assistant = n;
+                            }
+                        }
+                        "indent_tool" => {
+                            if let Ok(n) = value.parse() {
+                                config.indent_tool = n;
+                            }
+                        }
+                        "transcript_file" => {
+                            if !value.is_empty() {
+                                config.transcript_file = Some(String::from(value));
+                            }
+                        }
+                        "log_file" => {
+                            if !value.is_empty() {
+                                config.log_file = Some(String::from(value));
+                            }
+                        }
+                        _ if GenOptions::KEYS.contains(&key) => {
+                            let _ = config.gen.set(key, value);
+                        }
                         _ => {}
                     }
                 }
@@ -612,6 +1373,14 @@ impl Config {
         if let Some(p) = current_provider {
             config.providers.push(p);
         }
+        if let Some(t) = current_tool {
+            config.custom_tools.push(t);
+        }
+        if let Some(p) = current_params {
+            config.model_params.push(p);
+        }
+        // A tool without a command can't run
+        config.custom_tools.retain(|t| !t.name.is_empty() && !t.cmd.is_empty());
 
         // Ensure we have at least the default provider
         if config.providers.is_empty() {
@@ -666,7 +1435,154 @@ impl Config {
 
         content.push_str("render_markdown=");
         content.push_str(if self.render_markdown { "true" } else { "false" });
-        content.push_str("\n\n");
+        content.push('\n');
+
+        content.push_str("animation=");
+        content.push_str(if self.animation { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("low_bandwidth=");
+        content.push_str(if self.low_bandwidth { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("auto_compact=");
+        content.push_str(if self.auto_compact { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("prefetch_reads=");
+        content.push_str(if self.prefetch_reads { "true" } else { "false" });
+        content.push('\n');
+
+        if self.color_mode != ColorMode::Auto {
+            content.push_str("color_mode=");
+            content.push_str(self.color_mode.as_str());
+            content.push('\n');
+        }
+
+        if self.max_retries != DEFAULT_MAX_RETRIES {
+            content.push_str(&alloc::format!("max_retries={}\n", self.max_retries));
+        }
+        if self.initial_backoff_ms != DEFAULT_INITIAL_BACKOFF_MS {
+            content.push_str(&alloc::format!("initial_backoff_ms={}\n", self.initial_backoff_ms));
+        }
+        if self.request_timeout_s != DEFAULT_REQUEST_TIMEOUT_S {
+            content.push_str(&alloc::format!("request_timeout_s={}\n", self.request_timeout_s));
+        }
+        if self.search_engine != SearchEngine::DuckDuckGo {
+            content.push_str("search_engine=");
+            content.push_str(self.search_engine.as_str());
+            content.push('\n');
+        }
+        if let Some(ref url) = self.search_url {
+            content.push_str(&alloc::format!("search_url={}\n", url));
+        }
+        if let Some(ref key) = self.search_key {
+            content.push_str(&alloc::format!("search_key={}\n", key));
+        }
+        if self.auto_approve.len() != 1 || self.auto_approve[0] != DEFAULT_AUTO_APPROVE {
+            content.push_str(&alloc::format!("auto_approve={}\n", self.auto_approve.join(",")));
+        }
+
+        content.push_str("strip_filler=");
+        content.push_str(if self.strip_filler { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("collapse_blank_lines=");
+        content.push_str(if self.collapse_blank_lines { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("redact_secrets=");
+        content.push_str(if self.redact_secrets { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("professional=");
+        content.push_str(if self.professional { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("rewrite_shell_paths=");
+        content.push_str(if self.rewrite_shell_paths { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("virtual_paths=");
+        content.push_str(if self.virtual_paths { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("warmup=");
+        content.push_str(if self.warmup { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("suggestions=");
+        content.push_str(if self.suggestions { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("exclusive_sandbox=");
+        content.push_str(if self.exclusive_sandbox { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("issue_context=");
+        content.push_str(if self.issue_context { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("wrap_center=");
+        content.push_str(if self.wrap_center { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str("word_wrap=");
+        content.push_str(if self.word_wrap { "true" } else { "false" });
+        content.push('\n');
+
+        content.push_str(&alloc::format!("resume_max_age_min={}\n", self.resume_max_age_min));
+        content.push_str(&alloc::format!("wrap_width={}\n", self.wrap_width));
+        content.push_str(&alloc::format!("indent_user={}\n", self.indent_user));
+        content.push_str(&alloc::format!("indent_assistant={}\n", self.indent_assistant));
+        content.push_str(&alloc::format!("indent_tool={}\n", self.indent_tool));
+
+        if let Some(ref m) = self.cheap_model {
+            content.push_str("cheap_model=");
+            content.push_str(m);
+            content.push('\n');
+        }
+        if let Some(ref f) = self.forge_api {
+            content.push_str("forge_api=");
+            content.push_str(f);
+            content.push('\n');
+        }
+        if let Some(ref t) = self.forge_token {
+            content.push_str("forge_token=");
+            content.push_str(t);
+            content.push('\n');
+        }
+        if let Some(ref m) = self.motd {
+            content.push_str("motd=");
+            content.push_str(m);
+            content.push('\n');
+        }
+        if let Some(ref p) = self.system_prompt {
+            content.push_str("system_prompt=");
+            content.push_str(&escape_line(p));
+            content.push('\n');
+        }
+        if let Some(ref t) = self.transcript_file {
+            content.push_str("transcript_file=");
+            content.push_str(t);
+            content.push('\n');
+        }
+        if let Some(ref l) = self.log_file {
+            content.push_str("log_file=");
+            content.push_str(l);
+            content.push('\n');
+        }
+        for key in GenOptions::KEYS {
+            if let Some(value) = self.gen.get(key) {
+                content.push_str(&alloc::format!("{}={}\n", key, value));
+            }
+        }
+        if let Some(ref root) = self.sandbox_root {
+            content.push_str("sandbox_root=");
+            content.push_str(root);
+            content.push('\n');
+        }
+        content.push('\n');
 
         // Providers
         for p in &self.providers {
@@ -688,9 +1604,68 @@ impl Config {
                 content.push('\n');
             }
 
+            if p.verbosity != Verbosity::Normal {
+                content.push_str("verbosity=");
+                content.push_str(p.verbosity.as_str());
+                content.push('\n');
+            }
+
+            if p.native_tools {
+                content.push_str("native_tools=true\n");
+            }
+
+            if let Some(r) = p.chars_per_token {
+                content.push_str(&alloc::format!("chars_per_token={}\n", r));
+            }
+
+            if let Some(ref m) = p.default_model {
+                content.push_str("default_model=");
+                content.push_str(m);
+                content.push('\n');
+            }
+
+            if p.auth != AuthMode::Bearer {
+                content.push_str("auth=");
+                content.push_str(&p.auth.as_string());
+                content.push('\n');
+            }
+
+            for (name, value) in &p.extra_headers {
+                content.push_str(&alloc::format!("extra_headers={}: {}\n", name, value));
+            }
+
             content.push('\n');
         }
 
+        if !self.keys.is_empty() {
+            content.push_str("[keys]\n");
+            for (action, seq) in &self.keys {
+                content.push_str(action);
+                content.push('=');
+                content.push_str(seq);
+                content.push('\n');
+            }
+        }
+
+        for (model, opts) in self.model_params.iter().filter(|(_, o)| !o.is_empty()) {
+            content.push_str(&alloc::format!("\n[params:{}]\n", model));
+            for key in GenOptions::KEYS {
+                if let Some(value) = opts.get(key) {
+                    content.push_str(&alloc::format!("{}={}\n", key, value));
+                }
+            }
+        }
+
+        for t in &self.custom_tools {
+            content.push_str(&alloc::format!("\n[tool:{}]\ncmd=\"{}\"\n", t.name, t.cmd));
+            if !t.description.is_empty() {
+                content.push_str(&alloc::format!("description=\"{}\"\n", t.description));
+            }
+            if !t.args.is_empty() {
+                content.push_str(&alloc::format!("args={}\n", t.args.join(",")));
+            }
+        }
+
         content
     }
 
@@ -722,3 +1697,35 @@ impl Config {
         self.providers.len() < initial_len
     }
 }
+
+/// `text` as one config line: backslashes and line breaks written as `\\` and `\n`
+fn escape_line(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undo `escape_line`
+fn unescape_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
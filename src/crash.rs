@@ -0,0 +1,123 @@
+//! Crash handling
+//!
+//! On panic (including allocation failure, which panics in no_std) the
+//! terminal is put back into a usable state, the in-memory history and the
+//! last raw chunk read from the provider are written to
+//! /var/meow/crash-<uptime>.json, and a short recovery hint is printed.
+//!
+//! libakuma ships its own `#[panic_handler]` and a binary can only have one,
+//! so ours is only compiled with the `panic-handler` feature, for libakuma
+//! builds that leave theirs out.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use libakuma::{open, close, write_fd, open_flags};
+
+use crate::app::Message;
+
+pub const CRASH_DIR: &str = "/var/meow";
+
+/// Size of the last-chunk ring kept for the dump (fixed, so recording never allocates)
+const LAST_CHUNK_SIZE: usize = 2048;
+static mut LAST_CHUNK: [u8; LAST_CHUNK_SIZE] = [0; LAST_CHUNK_SIZE];
+static LAST_CHUNK_LEN: AtomicUsize = AtomicUsize::new(0);
+
+/// History of the running conversation, registered by the chat front-ends
+static HISTORY: AtomicPtr<Vec<Message>> = AtomicPtr::new(core::ptr::null_mut());
+/// Guards against panicking again while dumping
+static IN_CRASH: AtomicBool = AtomicBool::new(false);
+
+/// Point the crash dump at the live history. The caller must keep it alive
+/// (or call `unregister_history`) for as long as it is registered.
+pub fn register_history(history: &mut Vec<Message>) {
+    HISTORY.store(history as *mut Vec<Message>, Ordering::SeqCst);
+}
+
+pub fn unregister_history() {
+    HISTORY.store(core::ptr::null_mut(), Ordering::SeqCst);
+}
+
+/// Remember the most recent raw bytes received from the provider
+pub fn note_stream_chunk(data: &[u8]) {
+    let n = core::cmp::min(data.len(), LAST_CHUNK_SIZE);
+    unsafe {
+        let buf = &mut *core::ptr::addr_of_mut!(LAST_CHUNK);
+        buf[..n].copy_from_slice(&data[data.len() - n..]);
+    }
+    LAST_CHUNK_LEN.store(n, Ordering::SeqCst);
+}
+
+/// The most recent raw bytes received from the provider, as text
+pub fn last_chunk() -> alloc::string::String {
+    let len = LAST_CHUNK_LEN.load(Ordering::SeqCst);
+    let chunk = unsafe { &(*core::ptr::addr_of!(LAST_CHUNK))[..len] };
+    alloc::string::String::from_utf8_lossy(chunk).into_owned()
+}
+
+/// Restore the terminal, dump state and print a recovery hint
+pub fn handle_panic(info: &core::panic::PanicInfo) {
+    if IN_CRASH.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    crate::ui::tui::terminal::restore();
+
+    libakuma::print("\n[meow] crashed: ");
+    libakuma::print(&alloc::format!("{}", info));
+    libakuma::print("\n");
+
+    match write_dump(info) {
+        Some(path) => {
+            libakuma::print("[meow] conversation saved to ");
+            libakuma::print(&path);
+            libakuma::print("\n[meow] the JSON 'history' array holds every message; paste what you need into a new session.\n");
+        }
+        None => libakuma::print("[meow] could not write a crash dump to /var/meow\n"),
+    }
+    libakuma::print("[meow] if the terminal still misbehaves, run 'reset'.\n");
+}
+
+fn write_dump(info: &core::panic::PanicInfo) -> Option<alloc::string::String> {
+    libakuma::mkdir_p(CRASH_DIR);
+    let path = alloc::format!("{}/crash-{}.json", CRASH_DIR, libakuma::uptime() / 1_000_000);
+    let fd = open(&path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return None;
+    }
+
+    // Written piecewise so a low-memory crash still gets as much out as possible
+    let mut head = alloc::string::String::from("{\"uptime_us\":");
+    head.push_str(&alloc::format!("{}", libakuma::uptime()));
+    head.push_str(",\"panic\":\"");
+    crate::app::json_escape_to(&alloc::format!("{}", info), &mut head);
+    head.push_str("\",\"last_chunk\":\"");
+    crate::app::json_escape_to(&last_chunk(), &mut head);
+    head.push_str("\",\"history\":[");
+    write_fd(fd, head.as_bytes());
+
+    let history = HISTORY.load(Ordering::SeqCst);
+    if !history.is_null() {
+        // Safety: registered by the owner, which is still on the stack while we panic
+        let history = unsafe { &*history };
+        for (i, msg) in history.iter().enumerate() {
+            let mut entry = alloc::string::String::new();
+            if i > 0 { entry.push(','); }
+            msg.write_json(&mut entry);
+            write_fd(fd, entry.as_bytes());
+        }
+    }
+
+    write_fd(fd, b"]}\n");
+    close(fd);
+    Some(path)
+}
+
+#[cfg(feature = "panic-handler")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    handle_panic(info);
+    libakuma::exit(101);
+    #[allow(unreachable_code)]
+    loop {}
+}
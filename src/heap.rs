@@ -0,0 +1,162 @@
+//! Heap profiling
+//!
+//! `Instrumented` wraps a global allocator and counts allocations, live
+//! bytes and peak usage per subsystem tag. Code marks the subsystem it is
+//! allocating for with `let _t = heap::tag(Tag::History);`; the tag is
+//! stored in a header byte in front of each block so frees are charged to
+//! the tag that allocated, wherever they happen.
+//!
+//! The wrapper is installed with the `heap-profile` feature. Without it the
+//! counters stay at zero and `/mem` falls back to `memory_usage()` and
+//! size estimates of the big structures.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Tag {
+    Other = 0,
+    History = 1,
+    Tui = 2,
+    Client = 3,
+}
+
+pub const TAGS: [Tag; 4] = [Tag::Other, Tag::History, Tag::Tui, Tag::Client];
+
+impl Tag {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tag::Other => "other",
+            Tag::History => "history",
+            Tag::Tui => "tui",
+            Tag::Client => "client",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Tag::History,
+            2 => Tag::Tui,
+            3 => Tag::Client,
+            _ => Tag::Other,
+        }
+    }
+}
+
+struct Counters {
+    live: AtomicUsize,
+    peak: AtomicUsize,
+    allocs: AtomicUsize,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self { live: AtomicUsize::new(0), peak: AtomicUsize::new(0), allocs: AtomicUsize::new(0) }
+    }
+}
+
+static COUNTERS: [Counters; 4] = [Counters::new(), Counters::new(), Counters::new(), Counters::new()];
+static TOTAL: Counters = Counters::new();
+static CURRENT_TAG: AtomicU8 = AtomicU8::new(0);
+/// Set once the instrumented allocator has served a request
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Restores the previous tag when dropped
+pub struct TagGuard(u8);
+
+impl Drop for TagGuard {
+    fn drop(&mut self) { CURRENT_TAG.store(self.0, Ordering::Relaxed); }
+}
+
+/// Charge allocations to `tag` until the guard is dropped
+pub fn tag(tag: Tag) -> TagGuard {
+    TagGuard(CURRENT_TAG.swap(tag as u8, Ordering::Relaxed))
+}
+
+#[derive(Clone, Copy)]
+pub struct TagStats {
+    pub tag: Tag,
+    pub live: usize,
+    pub peak: usize,
+    pub allocs: usize,
+}
+
+/// True when the instrumented allocator is installed
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn stats(tag: Tag) -> TagStats {
+    let c = &COUNTERS[tag as usize];
+    TagStats {
+        tag,
+        live: c.live.load(Ordering::Relaxed),
+        peak: c.peak.load(Ordering::Relaxed),
+        allocs: c.allocs.load(Ordering::Relaxed),
+    }
+}
+
+/// Totals across all tags; `peak` is the real high-water mark, not the sum
+pub fn total() -> TagStats {
+    TagStats {
+        tag: Tag::Other,
+        live: TOTAL.live.load(Ordering::Relaxed),
+        peak: TOTAL.peak.load(Ordering::Relaxed),
+        allocs: TOTAL.allocs.load(Ordering::Relaxed),
+    }
+}
+
+fn charge(c: &Counters, size: usize) {
+    c.allocs.fetch_add(1, Ordering::Relaxed);
+    let live = c.live.fetch_add(size, Ordering::Relaxed) + size;
+    c.peak.fetch_max(live, Ordering::Relaxed);
+}
+
+fn refund(c: &Counters, size: usize) {
+    c.live.fetch_sub(size, Ordering::Relaxed);
+}
+
+/// Room for the tag byte while keeping the caller's alignment
+fn header(layout: &Layout) -> usize {
+    core::cmp::max(layout.align(), 8)
+}
+
+pub struct Instrumented<A> {
+    inner: A,
+}
+
+impl<A> Instrumented<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for Instrumented<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pad = header(&layout);
+        let Ok(full) = Layout::from_size_align(layout.size() + pad, layout.align()) else {
+            return core::ptr::null_mut();
+        };
+        let base = self.inner.alloc(full);
+        if base.is_null() {
+            return base;
+        }
+        let tag = CURRENT_TAG.load(Ordering::Relaxed);
+        let ptr = base.add(pad);
+        *ptr.sub(1) = tag;
+        ACTIVE.store(true, Ordering::Relaxed);
+        charge(&COUNTERS[Tag::from_u8(tag) as usize], layout.size());
+        charge(&TOTAL, layout.size());
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let pad = header(&layout);
+        let tag = Tag::from_u8(*ptr.sub(1));
+        refund(&COUNTERS[tag as usize], layout.size());
+        refund(&TOTAL, layout.size());
+        let full = Layout::from_size_align_unchecked(layout.size() + pad, layout.align());
+        self.inner.dealloc(ptr.sub(pad), full);
+    }
+}
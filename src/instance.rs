@@ -0,0 +1,163 @@
+//! Concurrent-instance detection
+//!
+//! Each running meow claims its sandbox with a lock file under /var/meow
+//! (`lock-<hash of the sandbox path>`) holding its pid, the sandbox path and
+//! a heartbeat. A second meow in the same sandbox finds the lock of a live
+//! process and warns; with `exclusive_sandbox` it also turns the
+//! file-changing tools off until the first one goes away.
+//!
+//! A holder is live while `/proc/<pid>` exists. A lock whose process is
+//! gone (a meow that crashed or was killed) is stale: it is removed and
+//! taken over. On a kernel without /proc the heartbeat decides instead
+//! (system uptime, refreshed while the owner runs): a lock older than
+//! `STALE_AFTER_US`, or from before a reboot, is stale. Releasing the lock
+//! on exit removes the file.
+
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use libakuma::{open, close, read_fd, write_fd, read_dir, open_flags};
+
+use crate::crash::CRASH_DIR;
+use crate::tools;
+use crate::util::{fnv1a, FNV_OFFSET};
+
+/// Heartbeat refresh interval while running
+const HEARTBEAT_US: u64 = 30_000_000;
+/// Without /proc: a lock not refreshed for this long belongs to an
+/// instance that is gone
+const STALE_AFTER_US: u64 = 90_000_000;
+
+/// Our pid, set by `acquire`
+static PID: AtomicU32 = AtomicU32::new(0);
+static OWNED: AtomicBool = AtomicBool::new(false);
+static LAST_BEAT_US: AtomicU64 = AtomicU64::new(0);
+
+struct Lock {
+    pid: u32,
+    sandbox: String,
+    heartbeat: u64,
+}
+
+impl Lock {
+    fn is_live(&self) -> bool {
+        match process_exists(self.pid) {
+            Some(exists) => exists,
+            None => {
+                let now = libakuma::uptime();
+                self.heartbeat <= now && now - self.heartbeat < STALE_AFTER_US
+            }
+        }
+    }
+}
+
+/// Whether process `pid` is running; None when there is no /proc to ask
+fn process_exists(pid: u32) -> Option<bool> {
+    read_dir("/proc")?;
+    Some(read_dir(&format!("/proc/{}", pid)).is_some())
+}
+
+fn lock_path(sandbox: &str) -> String {
+    // FNV-1a keeps the file name short whatever the path looks like
+    let hash = fnv1a(FNV_OFFSET, sandbox.as_bytes());
+    format!("{}/lock-{:016x}", CRASH_DIR, hash)
+}
+
+fn read_lock(path: &str) -> Option<Lock> {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 1024];
+    let n = read_fd(fd, &mut buf);
+    close(fd);
+    if n <= 0 {
+        return None;
+    }
+    let text = core::str::from_utf8(&buf[..n as usize]).ok()?;
+    let field = |key: &str| text.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix('='));
+    Some(Lock {
+        pid: field("pid")?.parse().ok()?,
+        sandbox: String::from(field("sandbox")?),
+        heartbeat: field("heartbeat")?.parse().ok()?,
+    })
+}
+
+fn write_lock(path: &str, contents: &str) -> bool {
+    let fd = open(path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return false;
+    }
+    write_fd(fd, contents.as_bytes());
+    close(fd);
+    true
+}
+
+/// The live instance other than us holding this sandbox, if any. A stale
+/// lock found on the way is removed.
+fn other_holder(path: &str, sandbox: &str) -> Option<Lock> {
+    let lock = read_lock(path)?;
+    if lock.pid == PID.load(Ordering::Relaxed) || lock.sandbox != sandbox {
+        return None;
+    }
+    if !lock.is_live() {
+        let _ = tools::fs::remove_file(path);
+        return None;
+    }
+    Some(lock)
+}
+
+fn claim(path: &str, sandbox: &str) {
+    let now = libakuma::uptime();
+    let contents = format!("pid={}\nsandbox={}\nheartbeat={}\n", PID.load(Ordering::Relaxed), sandbox, now);
+    if write_lock(path, &contents) {
+        OWNED.store(true, Ordering::Relaxed);
+        LAST_BEAT_US.store(now, Ordering::Relaxed);
+        tools::registry::READ_ONLY.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Claim the sandbox at startup. Returns a warning when another live meow
+/// already holds it; we then leave its lock alone and, with `exclusive`,
+/// go read-only.
+pub fn acquire(exclusive: bool) -> Option<String> {
+    PID.store(libakuma::getpid(), Ordering::Relaxed);
+    libakuma::mkdir_p(CRASH_DIR);
+    let sandbox = tools::get_sandbox_root();
+    let path = lock_path(&sandbox);
+
+    let Some(other) = other_holder(&path, &sandbox) else {
+        claim(&path, &sandbox);
+        return None;
+    };
+    LAST_BEAT_US.store(libakuma::uptime(), Ordering::Relaxed);
+    if exclusive {
+        tools::registry::READ_ONLY.store(true, Ordering::Relaxed);
+        Some(format!("another meow instance (pid {}) is working in {}; file-changing tools are off until it exits", other.pid, sandbox))
+    } else {
+        Some(format!("another meow instance (pid {}) is working in {}; edits and config saves may conflict", other.pid, sandbox))
+    }
+}
+
+/// Refresh our heartbeat, or take the lock over once the other instance is
+/// gone. Cheap to call often; it only touches the file every `HEARTBEAT_US`.
+pub fn heartbeat() {
+    let now = libakuma::uptime();
+    if PID.load(Ordering::Relaxed) == 0 || now - LAST_BEAT_US.load(Ordering::Relaxed) < HEARTBEAT_US {
+        return;
+    }
+    LAST_BEAT_US.store(now, Ordering::Relaxed);
+    let sandbox = tools::get_sandbox_root();
+    let path = lock_path(&sandbox);
+    if OWNED.load(Ordering::Relaxed) || other_holder(&path, &sandbox).is_none() {
+        claim(&path, &sandbox);
+    }
+}
+
+/// Give the sandbox up on exit
+pub fn release() {
+    if OWNED.swap(false, Ordering::Relaxed) {
+        let _ = tools::fs::remove_file(&lock_path(&tools::get_sandbox_root()));
+    }
+}
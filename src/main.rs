@@ -9,8 +9,15 @@ extern crate alloc;
 
 mod api;
 mod app;
+mod cli;
 mod code_search;
 mod config;
+mod crypto;
+mod crash;
+mod heap;
+mod instance;
+mod replay;
+mod session_log;
 mod tools;
 mod tui_app;
 mod ui;
@@ -21,17 +28,41 @@ use alloc::string::String;
 use alloc::vec::Vec;
 
 use app::Message;
-use config::{COMMON_TOOLS, Config, DEFAULT_CONTEXT_WINDOW, PERSONALITIES, Provider};
-use libakuma::{arg, argc, close, exit, fstat, open, open_flags, read_fd};
+use config::{Config, DEFAULT_CONTEXT_WINDOW, Provider};
+use libakuma::{arg, argc, exit};
+
+// Built with --no-default-features, so libakuma leaves the global
+// allocator to us and its chunked allocator is wrapped instead.
+#[cfg(feature = "heap-profile")]
+#[global_allocator]
+static ALLOCATOR: heap::Instrumented<libakuma::ChunkedAllocator> =
+    heap::Instrumented::new(libakuma::ChunkedAllocator::new());
 
 #[no_mangle]
 pub extern "C" fn main() {
+    app::summary::start();
     let mut app_config = Config::load();
+    if !app_config.errors.is_empty() {
+        for e in &app_config.errors {
+            libakuma::print(&format!("meow: config: {}\n", e));
+        }
+        libakuma::print("meow: remove these from /etc/meow/config to continue\n");
+        exit(1);
+    }
     let mut model_override: Option<String> = None;
     let mut provider_override: Option<String> = None;
     let mut personality_override: Option<String> = None;
     let mut one_shot_message: Option<String> = None;
+    let mut session_name: Option<String> = None;
+    let mut subcommand: Option<cli::Subcommand> = None;
+    let mut subcommand_args: Vec<String> = Vec::new();
     let mut use_tui = true;
+    let mut fresh = false;
+    let mut record = false;
+    let mut replay_file: Option<String> = None;
+    let mut sandbox_override: Option<String> = None;
+    let mut log_override: Option<String> = None;
+    let mut image_paths: Vec<String> = Vec::new();
 
     let mut i = 1;
     if argc() > 1 {
@@ -42,6 +73,11 @@ pub extern "C" fn main() {
             if first_arg == "test_stream" {
                 exit(crate::ui::tui::stream::run_tests());
             }
+            if let Some(sub) = cli::Subcommand::from_str(first_arg) {
+                subcommand = Some(sub);
+                use_tui = false;
+                i = 2;
+            }
         }
     }
 
@@ -71,11 +107,77 @@ pub extern "C" fn main() {
                     libakuma::print("meow: -P requires a personality name\n");
                     exit(1);
                 }
+            } else if arg_str == "--session" {
+                i += 1;
+                if let Some(s) = arg(i) {
+                    session_name = Some(String::from(s));
+                } else {
+                    libakuma::print("meow: --session requires a session name\n");
+                    exit(1);
+                }
+            } else if arg_str == "--low-bandwidth" {
+                app_config.low_bandwidth = true;
+            } else if arg_str == "--plain" {
+                app_config.professional = true;
+            } else if arg_str == "--quick" {
+                app::state::QUICK.store(true, core::sync::atomic::Ordering::SeqCst);
+            } else if arg_str == "--reset-terminal" {
+                ui::tui::terminal::force_reset();
+                exit(0);
             } else if arg_str == "--tui" {
                 use_tui = true;
+            } else if arg_str == "--fresh" {
+                fresh = true;
+            } else if arg_str == "--sandbox" {
+                i += 1;
+                if let Some(p) = arg(i) {
+                    sandbox_override = Some(String::from(p));
+                } else {
+                    libakuma::print("meow: --sandbox requires a directory\n");
+                    exit(1);
+                }
+            } else if arg_str == "--log" {
+                i += 1;
+                if let Some(p) = arg(i) {
+                    log_override = Some(String::from(p));
+                } else {
+                    libakuma::print("meow: --log requires a file path\n");
+                    exit(1);
+                }
+            } else if arg_str == "--image" {
+                i += 1;
+                if let Some(p) = arg(i) {
+                    image_paths.push(String::from(p));
+                } else {
+                    libakuma::print("meow: --image requires an image file\n");
+                    exit(1);
+                }
+            } else if arg_str == "--record" {
+                record = true;
+            } else if arg_str == "--replay" {
+                i += 1;
+                if let Some(f) = arg(i) {
+                    replay_file = Some(String::from(f));
+                } else {
+                    libakuma::print("meow: --replay requires a replay file\n");
+                    exit(1);
+                }
             } else if arg_str == "-h" || arg_str == "--help" {
                 print_usage();
                 exit(0);
+            } else if arg_str == "--" && subcommand.is_some() {
+                // Everything after "--" belongs to the subcommand verbatim
+                i += 1;
+                while i < argc() {
+                    if let Some(a) = arg(i) {
+                        subcommand_args.push(String::from(a));
+                    }
+                    i += 1;
+                }
+                break;
+            } else if subcommand.is_some() {
+                // Subcommands parse their own positional args and options
+                subcommand_args.push(String::from(arg_str));
             } else if !arg_str.starts_with('-') {
                 one_shot_message = Some(String::from(arg_str));
                 use_tui = false;
@@ -84,8 +186,105 @@ pub extern "C" fn main() {
         i += 1;
     }
 
+    // `meow here` must chdir before anything reads the working directory,
+    // since the sandbox root is captured on first use
+    let mut project_root: Option<String> = None;
+    if subcommand == Some(cli::Subcommand::Here) {
+        if !subcommand_args.is_empty() {
+            libakuma::print("meow here: takes no arguments\n");
+            exit(1);
+        }
+        match cli::here::enter() {
+            Ok(root) => {
+                cli::here::apply_project_config(&mut app_config, &root);
+                project_root = Some(root);
+                use_tui = true;
+            }
+            Err(e) => {
+                libakuma::print(&format!("meow here: {}\n", e));
+                exit(1);
+            }
+        }
+    }
+
+    // `--sandbox` beats `meow here`, which beats `sandbox_root`
+    let sandbox = sandbox_override.or_else(|| if project_root.is_none() { app_config.sandbox_root.clone() } else { None });
+    if let Some(root) = sandbox {
+        if let Err(e) = tools::context::set_sandbox_root(&root) {
+            libakuma::print(&format!("meow: {}\n", e));
+            exit(1);
+        }
+    }
+    // Read once the sandbox is known; they go with the first message
+    for path in &image_paths {
+        if let Err(e) = app::attachments::queue_image(path) {
+            libakuma::print(&format!("meow: {}\n", e));
+            exit(1);
+        }
+    }
+
+    if app_config.low_bandwidth {
+        app::state::LOW_BANDWIDTH.store(true, core::sync::atomic::Ordering::SeqCst);
+    }
+    app::state::PLAIN.store(app_config.professional, core::sync::atomic::Ordering::SeqCst);
+    app::state::AUTO_COMPACT.store(app_config.auto_compact, core::sync::atomic::Ordering::SeqCst);
+    ui::theme::set_mode(app_config.color_mode);
+    api::client::set_retry_policy(app_config.max_retries, app_config.initial_backoff_ms, app_config.request_timeout_s);
+    tools::net::configure_search(app_config.search_engine, app_config.search_url.clone(), app_config.search_key.clone());
+    tools::permission::set_auto_approve(&app_config.auto_approve);
+    app::state::set_render_markdown(app_config.render_markdown && !app_config.low_bandwidth);
+    tools::shell::REWRITE_PATHS.store(app_config.rewrite_shell_paths, core::sync::atomic::Ordering::SeqCst);
+    tools::context::VIRTUAL_PATHS.store(app_config.virtual_paths, core::sync::atomic::Ordering::SeqCst);
+    app::state::set_postprocess(app::postprocess::Passes::from_config(&app_config));
+    app::params::load(&app_config);
+    api::middleware::install_defaults();
+    if app_config.prefetch_reads {
+        api::middleware::register(&tools::prefetch::Prefetch);
+    }
+    for skipped in ui::tui::keys::load(&app_config.keys) {
+        libakuma::print(&format!("meow: [keys] {}\n", skipped));
+    }
+    for skipped in tools::custom::load(&app_config.custom_tools) {
+        libakuma::print(&format!("meow: {}\n", skipped));
+    }
+    ui::output::set_transcript(app_config.transcript_file.clone());
+    if let Some(path) = log_override.or_else(|| app_config.log_file.clone()) {
+        if let Err(e) = session_log::open_log(&path) {
+            libakuma::print(&format!("meow: {}\n", e));
+        }
+    }
+    ui::output::install(alloc::boxed::Box::new(ui::output::TerminalSink));
+
+    // Recorded user lines, entered again once the TUI is up
+    let mut replay_lines: Vec<String> = Vec::new();
+    match (record, &replay_file) {
+        (true, Some(_)) => {
+            libakuma::print("meow: --record and --replay can't be used together\n");
+            exit(1);
+        }
+        (true, None) => {
+            let path = replay::default_path();
+            if let Err(e) = replay::start_recording(&path) {
+                libakuma::print(&format!("meow: {}\n", e));
+                exit(1);
+            }
+            libakuma::print(&format!("meow: recording to {}\n", path));
+        }
+        (false, Some(path)) => match replay::start_replay(path) {
+            Ok(lines) => replay_lines = lines,
+            Err(e) => {
+                libakuma::print(&format!("meow: {}\n", e));
+                exit(1);
+            }
+        },
+        (false, None) => {}
+    }
+
     if let Some(ref prov_name) = provider_override {
-        if app_config.get_provider(prov_name).is_some() {
+        if let Some(p) = app_config.get_provider(prov_name) {
+            if let Some(m) = p.default_model.clone() {
+                app_config.current_model = m;
+            }
             app_config.current_provider = prov_name.clone();
         } else {
             libakuma::print(&format!(
@@ -108,34 +307,37 @@ pub extern "C" fn main() {
         .get_current_provider()
         .cloned()
         .unwrap_or_else(Provider::ollama_default);
+    app::state::set_verbosity(current_provider.verbosity);
+    app::tokens::configure(&current_provider);
 
     let model = app_config.current_model.clone();
 
-    // Assemble system prompt
-    let mut system_prompt = String::new();
-
-    // Check for local MEOW.md in current working directory
-    let local_prompt = load_local_prompt();
-    if let Some(prompt) = local_prompt {
-        system_prompt.push_str(&prompt);
-    } else {
-        // Find personality in registry
-        let persona = PERSONALITIES
-            .iter()
-            .find(|p| p.name == app_config.current_personality);
-        if let Some(p) = persona {
-            system_prompt.push_str(p.description);
-        } else {
-            // Fallback to Meow if not found
-            system_prompt.push_str(PERSONALITIES[0].description);
-        }
+    if let Some(ref root) = project_root {
+        app::prompt::set_project_root(root);
     }
-
-    system_prompt.push_str("\n\n");
-    system_prompt.push_str(COMMON_TOOLS);
-
-    if tools::chainlink_available() {
-        system_prompt.push_str(tools::chainlink::CHAINLINK_TOOLS_SECTION);
+    let system_prompt = app::prompt::build_system_prompt(&app_config);
+
+    if let Some(sub) = subcommand {
+        match sub {
+            cli::Subcommand::Explain => match cli::explain::build_prompt(&subcommand_args) {
+                Ok(prompt) => one_shot_message = Some(prompt),
+                Err(e) => {
+                    libakuma::print(&format!("meow: {}\n", e));
+                    exit(1);
+                }
+            },
+            cli::Subcommand::Commit => {
+                exit(cli::commit::run(&subcommand_args, &model, &current_provider, &app_config));
+            }
+            cli::Subcommand::Review => {
+                exit(cli::review::run(&subcommand_args, &model, &current_provider));
+            }
+            cli::Subcommand::PrDesc => {
+                exit(cli::pr_desc::run(&subcommand_args, &model, &current_provider, &app_config));
+            }
+            // Handled before the config overrides above
+            cli::Subcommand::Here => {}
+        }
     }
 
     if use_tui || one_shot_message.is_none() {
@@ -144,29 +346,45 @@ pub extern "C" fn main() {
 
         let initial_cwd = tools::get_working_dir();
         let sandbox_root = tools::get_sandbox_root();
-        let cwd_context = if sandbox_root == "/" {
+        let mut cwd_context = if sandbox_root == "/" {
             format!(
                 "[System Context] Your current working directory is: {}\nNo sandbox restrictions - you can access any path.",
                 initial_cwd
             )
+        } else if app_config.virtual_paths {
+            format!(
+                "[System Context] Your current working directory is: {}\nThe project root is /; every path you can access is under it.",
+                tools::context::virtualize(initial_cwd)
+            )
         } else {
             format!(
                 "[System Context] Your current working directory is: {}\nSandbox root: {} (you cannot access paths outside this directory)\nUse relative paths like 'docs/' instead of absolute paths like '/docs/'.",
                 initial_cwd, sandbox_root
             )
         };
+        add_issue_context(&app_config, &mut cwd_context);
         history.push(Message::new("user", &cwd_context));
 
         let persona = get_active_personality(&app_config);
         let ack_msg = persona.ack_tui;
         history.push(Message::new("assistant", ack_msg));
 
-        // Skip blocking model info query on startup to prevent hangs.
-        // It can be queried later if needed or configured via commands.
+        // Recordings start from a fresh history so replays can start from the same one
+        if !fresh && !record && replay_file.is_none() {
+            if let Some(resumed) = offer_resume(&app_config, &system_prompt) {
+                history = resumed;
+            }
+        }
+
+        // Not queried here so a slow provider can't hang startup; the TUI
+        // asks once the screen is up, with the metadata timeout
         let context_window = DEFAULT_CONTEXT_WINDOW;
 
         let mut current_model = model;
         let mut current_provider = current_provider;
+        for line in replay_lines {
+            app::state::push_message(line);
+        }
 
         if let Err(e) = tui_app::run_tui(
             &mut current_model,
@@ -176,42 +394,100 @@ pub extern "C" fn main() {
             context_window,
             &system_prompt,
         ) {
+            instance::release();
             libakuma::print(&format!("TUI Error: {}\n", e));
             exit(1);
         }
+        instance::release();
         exit(0);
     }
 
     if let Some(msg) = one_shot_message {
-        let mut history = Vec::new();
-        history.push(Message::new("system", &system_prompt));
-        let initial_cwd = tools::get_working_dir();
-        let sandbox_root = tools::get_sandbox_root();
-        let cwd_context = if sandbox_root == "/" {
-            format!(
-                "[System Context] Current working directory: {}\nNo sandbox restrictions.",
-                initial_cwd
-            )
+        // Continue a named session if one was requested and exists on disk.
+        // Any other load failure stops here, before the save below could
+        // overwrite the file with a fresh history.
+        let restored = match session_name.as_deref().map(app::session::load_session) {
+            None | Some(Err("Session not found")) => None,
+            Some(Ok(h)) => Some(h),
+            Some(Err(e)) => {
+                libakuma::print(&format!(
+                    "meow: can't load session '{}': {}\n",
+                    session_name.as_deref().unwrap_or_default(),
+                    e
+                ));
+                exit(1);
+            }
+        };
+
+        let mut history = match restored {
+            Some(mut h) => {
+                // Always use the freshly assembled system prompt
+                if !h.is_empty() && h[0].role == "system" {
+                    h[0] = Message::new("system", &system_prompt);
+                } else {
+                    h.insert(0, Message::new("system", &system_prompt));
+                }
+                h
+            }
+            None => {
+                let mut history = Vec::new();
+                history.push(Message::new("system", &system_prompt));
+                let initial_cwd = tools::get_working_dir();
+                let sandbox_root = tools::get_sandbox_root();
+                let mut cwd_context = if sandbox_root == "/" {
+                    format!(
+                        "[System Context] Current working directory: {}\nNo sandbox restrictions.",
+                        initial_cwd
+                    )
+                } else if app_config.virtual_paths {
+                    format!(
+                        "[System Context] Current working directory: {}\nThe project root is /.",
+                        tools::context::virtualize(initial_cwd)
+                    )
+                } else {
+                    format!(
+                        "[System Context] Current working directory: {}\nSandbox root: {} - use relative paths.",
+                        initial_cwd, sandbox_root
+                    )
+                };
+                add_issue_context(&app_config, &mut cwd_context);
+                history.push(Message::new("user", &cwd_context));
+
+                let persona = get_active_personality(&app_config);
+                let ack_msg = persona.ack_tui;
+                history.push(Message::new("assistant", ack_msg));
+                history
+            }
+        };
+
+        if let Some(warning) = instance::acquire(app_config.exclusive_sandbox) {
+            libakuma::print(&format!("meow: {}\n", warning));
+        }
+        crate::crash::register_history(&mut history);
+        ui::output::user_input(&msg);
+        let result = if app::state::QUICK.load(core::sync::atomic::Ordering::SeqCst) {
+            let persona = app::prompt::build_parts(&app_config).persona;
+            app::chat::quick_once(&model, &current_provider, &msg, &mut history, &persona)
         } else {
-            format!(
-                "[System Context] Current working directory: {}\nSandbox root: {} - use relative paths.",
-                initial_cwd, sandbox_root
+            app::chat_once(
+                &model,
+                &current_provider,
+                &msg,
+                &mut history,
+                None,
+                &system_prompt,
             )
         };
-        history.push(Message::new("user", &cwd_context));
+        crate::crash::unregister_history();
+        instance::release();
 
-        let persona = get_active_personality(&app_config);
-        let ack_msg = persona.ack_tui;
-        history.push(Message::new("assistant", ack_msg));
+        if let Some(ref name) = session_name {
+            if let Err(e) = app::session::save_session(name, &history) {
+                libakuma::print(&format!("meow: failed to save session '{}': {}\n", name, e));
+            }
+        }
 
-        match app::chat_once(
-            &model,
-            &current_provider,
-            &msg,
-            &mut history,
-            None,
-            &system_prompt,
-        ) {
+        match result {
             Ok(_) => {
                 libakuma::print("\n");
                 exit(0);
@@ -228,50 +504,57 @@ pub extern "C" fn main() {
     exit(0);
 }
 
-fn get_active_personality<'a>(config: &Config) -> &'a crate::config::Personality {
-    PERSONALITIES
-        .iter()
-        .find(|p| p.name == config.current_personality)
-        .unwrap_or(&PERSONALITIES[0]) // fallback to first (Meow)
+/// With `issue_context` on, list the open chainlink issues in the context message
+fn add_issue_context(config: &Config, cwd_context: &mut String) {
+    if !config.issue_context {
+        return;
+    }
+    if let Some(issues) = tools::chainlink::open_issues_context() {
+        *cwd_context = tools::chainlink::with_issues_context(cwd_context, &issues);
+    }
 }
 
-fn load_local_prompt() -> Option<String> {
-    let fd = open("MEOW.md", open_flags::O_RDONLY);
-    if fd < 0 {
+/// Ask on the plain terminal, before the TUI takes over, whether to pick the
+/// autosaved session back up. None when there is no recent one or the answer is no.
+fn offer_resume(config: &Config, system_prompt: &str) -> Option<Vec<Message>> {
+    let max_age_us = config.resume_max_age_min.saturating_mul(60_000_000);
+    let info = app::session::autosave_info()?;
+    if info.age_us > max_age_us {
         return None;
     }
-
-    let stat = match fstat(fd) {
-        Ok(s) => s,
-        Err(_) => {
-            close(fd);
-            return None;
-        }
+    let minutes = info.age_us / 60_000_000;
+    let age = match minutes {
+        0 => String::from("just now"),
+        m if m < 60 => format!("{} min ago", m),
+        m => format!("{}h {:02}m ago", m / 60, m % 60),
     };
-
-    let size = stat.st_size as usize;
-    if size == 0 || size > 64 * 1024 {
-        close(fd);
+    let question = format!("Resume last session from {} ({} messages)? [y/N] ", age, info.messages);
+    if !cli::confirm(&question) {
         return None;
     }
-
-    let mut buf = alloc::vec![0u8; size];
-    let bytes_read = read_fd(fd, &mut buf);
-    close(fd);
-
-    if bytes_read <= 0 {
-        return None;
+    let mut history = match app::session::load_session(app::session::AUTOSAVE_NAME) {
+        Ok(h) => h,
+        Err(e) => {
+            libakuma::print(&format!("meow: could not resume: {}\n", e));
+            return None;
+        }
+    };
+    // Always use the freshly assembled system prompt
+    if !history.is_empty() && history[0].role == "system" {
+        history[0] = Message::new("system", system_prompt);
+    } else {
+        history.insert(0, Message::new("system", system_prompt));
     }
+    Some(history)
+}
 
-    match String::from_utf8(buf) {
-        Ok(s) => Some(s),
-        Err(_) => None,
-    }
+fn get_active_personality(config: &Config) -> &'static crate::config::Personality {
+    config.personality()
 }
 
 fn print_usage() {
     libakuma::print(
-        "  /\\_/\\\n ( o.o )  ～ MEOW-CHAN PROTOCOL ～\n  > ^ <   Cyberpunk Neko AI Assistant\n\nUsage: meow [OPTIONS] [MESSAGE]\n       meow init              # Configure providers\n\nOptions:\n  -m, --model <NAME>      Neural link override\n  -p, --provider <NAME>   Use specific provider\n  -P, --personality <NAM> Switch persona (Meow, Jaffar, Rosie)\n  --tui                   Interactive TUI (default)\n  -h, --help              Display this transmission\n\nInteractive Commands:\n  /clear              Wipe memory banks nya~\n  /model [NAME]       Check/switch/list neural links\n  /provider [NAME]    Check/switch providers\n  /personality [NAME] Check/switch personality\n  /tokens             Show current token usage\n  /help               Command protocol\n  /quit               Jack out\n",
+        "  /\\_/\\\n ( o.o )  ～ MEOW-CHAN PROTOCOL ～\n  > ^ <   Cyberpunk Neko AI Assistant\n\nUsage: meow [OPTIONS] [MESSAGE]\n       meow init              # Configure providers\n       meow explain -- <CMD>  # Run CMD and explain its output\n       meow commit [--yes]    # Write a commit message for staged changes\n       meow review [A..B]     # Review the diff of a ref range\n       meow pr-desc [BASE]    # Draft a PR description (--post to open it)\n       meow here              # Open the TUI sandboxed to this repository\n\nOptions:\n  -m, --model <NAME>      Neural link override\n  -p, --provider <NAME>   Use specific provider\n  -P, --personality <NAM> Switch persona (Meow, Jaffar, Rosie)\n  --session <NAME>        Continue a named one-shot session\n  --low-bandwidth         Plain, line-buffered output for slow links\n  --plain                 Professional persona, no cat-themed output\n  --quick                 Short answers without tools, for quick questions\n  --reset-terminal        Repair a terminal left raw by a killed meow\n  --tui                   Interactive TUI (default)\n  --fresh                 Start the TUI without offering to resume\n  --sandbox <PATH>        Confine tools to PATH (default: where meow starts)\n  --image <PATH>          Attach an image to the message (repeatable)\n  --log <PATH>            Append requests, responses and tool calls to PATH as JSON lines\n  --record                Record responses and tool results to /var/meow\n  --replay <FILE>         Replay a recording instead of the provider and tools\n  -h, --help              Display this transmission\n\nInteractive Commands:\n  /clear              Wipe memory banks nya~\n  /model [NAME]       Check/switch/list neural links\n  /provider [NAME]    Check/switch providers\n  /personality [NAME] Check/switch personality\n  /tokens             Show current token usage\n  /help               Command protocol\n  /quit               Jack out\n",
     );
 }
 
@@ -0,0 +1,261 @@
+//! Record and replay (`--record`, `--replay FILE`)
+//!
+//! Recording writes everything a session took in from outside to a
+//! JSON-lines file as it happens: each provider response (or the error in
+//! its place), each tool result, each line the user entered and the context
+//! window the TUI looked up. Replaying the file answers `send_with_retry`
+//! and `execute_tool_command` from it instead of the network and the
+//! filesystem, and enters the recorded lines again, so a run that went
+//! wrong in the tool loop, compaction or rendering plays out the same way
+//! every time.
+//!
+//! The requests themselves are not recorded; meow builds them again from
+//! the replayed answers. AskUser answers and shell confirmations are not
+//! recorded either and are asked for again. When the run wants something
+//! other than what the file has next (a different tool, or a response where
+//! a tool result was recorded) the replay has diverged, and that call fails
+//! with an error saying so.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::{open, write_fd, open_flags};
+
+use crate::api::{StreamResponse, StreamStats};
+use crate::app::history::{extract_json_string, json_escape_to};
+use crate::tools::helpers::{extract_number_field, extract_string_field};
+use crate::tools::ToolResult;
+use crate::ui::output;
+
+enum Entry {
+    User(String),
+    Context(usize),
+    Response(Result<StreamResponse, String>),
+    Tool { tool: String, result: Option<ToolResult> },
+}
+
+impl Entry {
+    fn kind(&self) -> &'static str {
+        match self {
+            Entry::User(_) => "user",
+            Entry::Context(_) => "context",
+            Entry::Response(_) => "response",
+            Entry::Tool { .. } => "tool",
+        }
+    }
+}
+
+enum Mode {
+    Off,
+    Record(i32),
+    Replay(VecDeque<Entry>),
+}
+
+static mut MODE: Mode = Mode::Off;
+
+fn mode() -> &'static mut Mode {
+    unsafe { &mut *core::ptr::addr_of_mut!(MODE) }
+}
+
+pub fn is_replaying() -> bool {
+    matches!(mode(), Mode::Replay(_))
+}
+
+/// Where `--record` writes when no file is given
+pub fn default_path() -> String {
+    format!("{}/replay-{}.jsonl", crate::crash::CRASH_DIR, libakuma::uptime() / 1_000_000)
+}
+
+/// Record this session to `path`, replacing what it held
+pub fn start_recording(path: &str) -> Result<(), String> {
+    libakuma::mkdir_p(crate::crash::CRASH_DIR);
+    let fd = open(path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_TRUNC);
+    if fd < 0 {
+        return Err(format!("cannot write {}", path));
+    }
+    *mode() = Mode::Record(fd);
+    Ok(())
+}
+
+/// Replay the recording in `path`. Returns the user lines to enter again,
+/// in order.
+pub fn start_replay(path: &str) -> Result<Vec<String>, String> {
+    let data = crate::tools::fs::read_file(path, usize::MAX).map_err(|_| format!("cannot read {}", path))?;
+    let text = core::str::from_utf8(&data).map_err(|_| format!("{} is not UTF-8", path))?;
+    let mut entries = VecDeque::new();
+    let mut users = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(line) {
+            Some(Entry::User(text)) => users.push(text),
+            Some(entry) => entries.push_back(entry),
+            None => return Err(format!("{} line {}: not a replay entry", path, n + 1)),
+        }
+    }
+    *mode() = Mode::Replay(entries);
+    Ok(users)
+}
+
+fn parse(line: &str) -> Option<Entry> {
+    let number = |key: &str| extract_number_field(line, key).unwrap_or(0);
+    match extract_json_string(line, "kind")?.as_str() {
+        "user" => Some(Entry::User(extract_json_string(line, "text")?)),
+        "context" => Some(Entry::Context(extract_number_field(line, "tokens")?)),
+        "response" => {
+            if let Some(e) = extract_json_string(line, "error") {
+                return Some(Entry::Response(Err(e)));
+            }
+            let text = extract_json_string(line, "text")?;
+            let stats = StreamStats {
+                ttft_us: number("ttft_us") as u64,
+                stream_us: number("stream_us") as u64,
+                total_bytes: number("total_bytes"),
+                fakes: number("fakes"),
+            };
+            let response = match extract_json_string(line, "status")?.as_str() {
+                "complete" => StreamResponse::Complete(text, stats),
+                "partial" => StreamResponse::Partial(text, stats),
+                "stopped" => StreamResponse::Stopped(text, stats),
+                _ => return None,
+            };
+            Some(Entry::Response(Ok(response)))
+        }
+        "tool" => {
+            let tool = extract_json_string(line, "tool")?;
+            let result = extract_json_string(line, "output").map(|output| ToolResult {
+                success: line.contains("\"success\":true"),
+                output,
+            });
+            Some(Entry::Tool { tool, result })
+        }
+        _ => None,
+    }
+}
+
+/// A line the user entered
+pub fn note_user(text: &str) {
+    let mut line = String::from("{\"kind\":\"user\",\"text\":\"");
+    json_escape_to(text, &mut line);
+    line.push_str("\"}");
+    write(&line);
+}
+
+/// The context window looked up for the current model
+pub fn note_context(tokens: usize) {
+    write(&format!("{{\"kind\":\"context\",\"tokens\":{}}}", tokens));
+}
+
+/// The recorded context window, when replaying
+pub fn next_context() -> Option<usize> {
+    match next("context")? {
+        Ok(Entry::Context(tokens)) => Some(tokens),
+        _ => None,
+    }
+}
+
+pub fn note_response(result: &Result<StreamResponse, &str>) {
+    if !matches!(mode(), Mode::Record(_)) {
+        return;
+    }
+    let mut line = String::from("{\"kind\":\"response\",");
+    match result {
+        Ok(response) => {
+            let (status, text, stats) = match response {
+                StreamResponse::Complete(t, s) => ("complete", t, s),
+                StreamResponse::Partial(t, s) => ("partial", t, s),
+                StreamResponse::Stopped(t, s) => ("stopped", t, s),
+            };
+            line.push_str(&format!(
+                "\"status\":\"{}\",\"ttft_us\":{},\"stream_us\":{},\"total_bytes\":{},\"fakes\":{},\"text\":\"",
+                status, stats.ttft_us, stats.stream_us, stats.total_bytes, stats.fakes
+            ));
+            json_escape_to(text, &mut line);
+        }
+        Err(e) => {
+            line.push_str("\"error\":\"");
+            json_escape_to(e, &mut line);
+        }
+    }
+    line.push_str("\"}");
+    write(&line);
+}
+
+/// The recorded response, shown as if it had streamed in, when replaying
+pub fn next_response() -> Option<Result<StreamResponse, &'static str>> {
+    let entry = match next("response")? {
+        Ok(Entry::Response(r)) => r,
+        Ok(_) => unreachable!(),
+        Err(e) => Err(e),
+    };
+    Some(match entry {
+        Ok(response) => {
+            let (StreamResponse::Complete(text, stats)
+                | StreamResponse::Partial(text, stats)
+                | StreamResponse::Stopped(text, stats)) = &response;
+            if !text.is_empty() {
+                output::reply_start(stats.ttft_us / 1000, 0);
+                output::reply_chunk(text);
+                output::reply_end();
+            }
+            Ok(response)
+        }
+        // Callers expect the static errors the client returns
+        Err(e) => Err(alloc::boxed::Box::leak(e.into_boxed_str())),
+    })
+}
+
+pub fn note_tool(json: &str, result: &Option<ToolResult>) {
+    if !matches!(mode(), Mode::Record(_)) {
+        return;
+    }
+    let mut line = String::from("{\"kind\":\"tool\",\"tool\":\"");
+    json_escape_to(&extract_string_field(json, "tool").unwrap_or_default(), &mut line);
+    match result {
+        Some(r) => {
+            line.push_str(&format!("\",\"success\":{},\"output\":\"", r.success));
+            json_escape_to(&r.output, &mut line);
+            line.push_str("\"}");
+        }
+        None => line.push_str("\"}"),
+    }
+    write(&line);
+}
+
+/// The recorded result of the tool call in `json`, when replaying
+pub fn next_tool(json: &str) -> Option<Option<ToolResult>> {
+    let wanted = extract_string_field(json, "tool").unwrap_or_default();
+    Some(match next("tool")? {
+        Ok(Entry::Tool { tool, result }) if tool == wanted => result,
+        Ok(Entry::Tool { tool, .. }) => Some(ToolResult::err(&format!(
+            "Replay diverged: the run called {} where the recording has {}", wanted, tool
+        ))),
+        Ok(_) => unreachable!(),
+        Err(e) => Some(ToolResult::err(&e)),
+    })
+}
+
+/// Take the next entry if it is a `kind`. None when not replaying; Err
+/// when the recording has run out or has something else next.
+fn next(kind: &str) -> Option<Result<Entry, String>> {
+    let Mode::Replay(entries) = mode() else { return None };
+    Some(match entries.front() {
+        None => Err(String::from("Replay finished: the recording has nothing more")),
+        Some(e) if e.kind() != kind => Err(format!(
+            "Replay diverged: the run wants a {} where the recording has a {}", kind, e.kind()
+        )),
+        Some(_) => Ok(entries.pop_front().unwrap()),
+    })
+}
+
+/// Append a line to the recording, if there is one. Written at once so a
+/// crash keeps everything up to it.
+fn write(line: &str) {
+    if let Mode::Record(fd) = mode() {
+        write_fd(*fd, line.as_bytes());
+        write_fd(*fd, b"\n");
+    }
+}
@@ -0,0 +1,124 @@
+//! Session debug log (`log_file`, `--log PATH`)
+//!
+//! Off unless configured. When on, one JSON object per line is appended
+//! for every request body sent (each attempt), every response as it ended,
+//! and every tool call with its result, each stamped with the uptime in ms.
+//! A response cut off mid-stream also carries the last raw bytes read, so
+//! the log shows why a stream ended up Partial or a tool call was
+//! mis-parsed. Request headers are left out; they hold the API keys.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use libakuma::{open, write_fd, open_flags};
+
+use crate::api::StreamResponse;
+use crate::app::json_escape_to;
+use crate::tools::ToolResult;
+
+/// Request bodies are written out in pieces of about this size
+const WRITE_CHUNK: usize = 4096;
+
+static FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Append the log to `path` from now on
+pub fn open_log(path: &str) -> Result<(), String> {
+    let fd = open(path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_APPEND);
+    if fd < 0 {
+        return Err(format!("cannot write log file {}", path));
+    }
+    FD.store(fd, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    FD.load(Ordering::Relaxed) >= 0
+}
+
+/// `{"t":..,"kind":"KIND"` with the rest of the entry still to come
+fn entry(kind: &str) -> String {
+    format!("{{\"t\":{},\"kind\":\"{}\"", libakuma::uptime() / 1000, kind)
+}
+
+fn push_field(line: &mut String, key: &str, value: &str) {
+    line.push_str(&format!(",\"{}\":\"", key));
+    json_escape_to(value, line);
+    line.push('"');
+}
+
+fn write(line: &mut String) {
+    line.push_str("}\n");
+    write_fd(FD.load(Ordering::Relaxed), line.as_bytes());
+}
+
+/// A chat request about to go out; `pieces` produces its JSON body
+pub fn request(provider: &str, model: &str, path: &str, attempt: u32, pieces: impl FnOnce(&mut dyn FnMut(&str))) {
+    if !enabled() {
+        return;
+    }
+    let fd = FD.load(Ordering::Relaxed);
+    let mut head = entry("request");
+    push_field(&mut head, "provider", provider);
+    push_field(&mut head, "model", model);
+    push_field(&mut head, "path", path);
+    head.push_str(&format!(",\"attempt\":{},\"body\":", attempt));
+    // The body is already JSON and can be large, so it goes out as it is
+    // produced instead of as one escaped string
+    let mut buf: Vec<u8> = Vec::with_capacity(WRITE_CHUNK);
+    buf.extend_from_slice(head.as_bytes());
+    pieces(&mut |p: &str| {
+        if buf.len() + p.len() > WRITE_CHUNK && !buf.is_empty() {
+            write_fd(fd, &buf);
+            buf.clear();
+        }
+        if p.len() >= WRITE_CHUNK {
+            write_fd(fd, p.as_bytes());
+        } else {
+            buf.extend_from_slice(p.as_bytes());
+        }
+    });
+    buf.extend_from_slice(b"}\n");
+    write_fd(fd, &buf);
+}
+
+/// How a request ended
+pub fn response(result: &Result<StreamResponse, &str>) {
+    if !enabled() {
+        return;
+    }
+    let mut line = entry("response");
+    match result {
+        Ok(response) => {
+            let (status, text, stats) = match response {
+                StreamResponse::Complete(t, s) => ("complete", t, s),
+                StreamResponse::Partial(t, s) => ("partial", t, s),
+                StreamResponse::Stopped(t, s) => ("stopped", t, s),
+            };
+            push_field(&mut line, "status", status);
+            line.push_str(&format!(
+                ",\"ttft_us\":{},\"stream_us\":{},\"bytes\":{},\"fakes\":{}",
+                stats.ttft_us, stats.stream_us, stats.total_bytes, stats.fakes
+            ));
+            push_field(&mut line, "text", text);
+            if status == "partial" {
+                push_field(&mut line, "raw_tail", &crate::crash::last_chunk());
+            }
+        }
+        Err(e) => push_field(&mut line, "error", e),
+    }
+    write(&mut line);
+}
+
+/// A tool call as parsed from the reply, and what came of it
+pub fn tool(json: &str, result: &ToolResult, duration_us: u64) {
+    if !enabled() {
+        return;
+    }
+    let mut line = entry("tool");
+    push_field(&mut line, "call", json);
+    line.push_str(&format!(",\"success\":{},\"ms\":{}", result.success, duration_us / 1000));
+    push_field(&mut line, "output", &result.output);
+    write(&mut line);
+}
@@ -1,4 +1,7 @@
 use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use libakuma::{open, close, open_flags};
 
 use super::mod_types::ToolResult;
@@ -14,6 +17,22 @@ pub fn chainlink_available() -> bool {
     }
 }
 
+/// Issue the session is linked to with `/issue link N`, 0 for none
+static LINKED_ISSUE: AtomicUsize = AtomicUsize::new(0);
+
+pub fn linked_issue() -> Option<usize> {
+    match LINKED_ISSUE.load(Ordering::Relaxed) {
+        0 => None,
+        id => Some(id),
+    }
+}
+
+/// Link the session to an issue (None unlinks). Tool commits then reference
+/// it, and session saves comment on it.
+pub fn link_issue(id: Option<usize>) {
+    LINKED_ISSUE.store(id.unwrap_or(0), Ordering::Relaxed);
+}
+
 pub fn tool_chainlink_init() -> ToolResult {
     tool_shell("chainlink init")
 }
@@ -36,6 +55,57 @@ pub fn tool_chainlink_list(status: Option<&str>) -> ToolResult {
     }
 }
 
+/// Starts the open-issue section of the context message
+pub const ISSUES_CONTEXT_HEADER: &str = "[Open Issues]";
+/// Issues listed in the context message; the rest are only counted
+const MAX_CONTEXT_ISSUES: usize = 15;
+const MAX_CONTEXT_LINE: usize = 100;
+
+/// The `#<id> ...` lines of `chainlink list -s open`
+fn open_issue_lines() -> Option<Vec<String>> {
+    let out = super::shell::run_command("chainlink list -s open").ok()?;
+    if out.exit_code != 0 { return None; }
+    Some(out.stdout.lines().map(|l| l.trim()).filter(|l| {
+        l.starts_with('#') && l[1..].starts_with(|c: char| c.is_ascii_digit())
+    }).map(String::from).collect())
+}
+
+/// Number of open issues, counted from the `#<id>` lines of `chainlink list`
+pub fn count_open_issues() -> Option<usize> {
+    open_issue_lines().map(|lines| lines.len())
+}
+
+/// Compact open-issue list for the context message, so "what should I work
+/// on?" can be answered without a ChainlinkList call. None without chainlink.
+pub fn open_issues_context() -> Option<String> {
+    if !chainlink_available() { return None; }
+    let lines = open_issue_lines()?;
+    if lines.is_empty() {
+        return Some(format!("{} None (refresh with /issues)", ISSUES_CONTEXT_HEADER));
+    }
+    let mut out = format!("{} {} in chainlink (refresh with /issues):", ISSUES_CONTEXT_HEADER, lines.len());
+    for line in lines.iter().take(MAX_CONTEXT_ISSUES) {
+        out.push('\n');
+        match line.char_indices().nth(MAX_CONTEXT_LINE) {
+            Some((cut, _)) => { out.push_str(&line[..cut]); out.push_str("..."); }
+            None => out.push_str(line),
+        }
+    }
+    if lines.len() > MAX_CONTEXT_ISSUES {
+        out.push_str(&format!("\n... and {} more (ChainlinkList shows all)", lines.len() - MAX_CONTEXT_ISSUES));
+    }
+    Some(out)
+}
+
+/// `context` with its open-issue section replaced by `section` (or added)
+pub fn with_issues_context(context: &str, section: &str) -> String {
+    let base = match context.find(ISSUES_CONTEXT_HEADER) {
+        Some(i) => context[..i].trim_end(),
+        None => context.trim_end(),
+    };
+    format!("{}\n\n{}", base, section)
+}
+
 pub fn tool_chainlink_show(id: usize) -> ToolResult {
     tool_shell(&format!("chainlink show {}", id))
 }
@@ -60,30 +130,30 @@ pub fn tool_chainlink_label(id: usize, label: &str) -> ToolResult {
 pub const CHAINLINK_TOOLS_SECTION: &str = r#"
 ### Issue Tracker Tools (Chainlink):
 
-31. **ChainlinkInit** - Initialize the issue tracker database
+33. **ChainlinkInit** - Initialize the issue tracker database
     Args: `{}`
     Note: Creates .chainlink/issues.db in current directory.
 
-32. **ChainlinkCreate** - Create a new issue
+34. **ChainlinkCreate** - Create a new issue
     Args: `{"title": "Issue title", "description": "optional desc", "priority": "low|medium|high"}`
     Note: Priority defaults to "medium" if not specified.
 
-33. **ChainlinkList** - List issues
+35. **ChainlinkList** - List issues
     Args: `{"status": "open|closed|all"}`
     Note: Defaults to "open" if status not specified.
 
-34. **ChainlinkShow** - Show issue details with comments and labels
+36. **ChainlinkShow** - Show issue details with comments and labels
     Args: `{"id": 1}`
 
-35. **ChainlinkClose** - Close an issue
+37. **ChainlinkClose** - Close an issue
     Args: `{"id": 1}`
 
-36. **ChainlinkReopen** - Reopen a closed issue
+38. **ChainlinkReopen** - Reopen a closed issue
     Args: `{"id": 1}`
 
-37. **ChainlinkComment** - Add a comment to an issue
+39. **ChainlinkComment** - Add a comment to an issue
     Args: `{"id": 1, "text": "Comment text"}`
 
-38. **ChainlinkLabel** - Add a label to an issue
+40. **ChainlinkLabel** - Add a label to an issue
     Args: `{"id": 1, "label": "bug"}`
 "#;
\ No newline at end of file
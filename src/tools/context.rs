@@ -3,6 +3,11 @@ use alloc::format;
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::cell::UnsafeCell;
 
+/// Virtual path mode: the sandbox root is shown to the model as `/`, tool
+/// output is rewritten to match, and absolute paths from the model are read
+/// relative to the sandbox root (`virtual_paths` in the config)
+pub static VIRTUAL_PATHS: AtomicBool = AtomicBool::new(false);
+
 // ============================================================================
 // Working Directory State (atomic, with sandbox support)
 // ============================================================================
@@ -79,6 +84,19 @@ impl AtomicWorkingDir {
             (*self.state.get()).as_mut().unwrap().current_dir = path;
         }
     }
+
+    /// Replace the sandbox root; a current directory outside it moves to it
+    fn set_sandbox_root(&self, root: String) {
+        self.ensure_init();
+        // Safety: initialized above, single-threaded
+        unsafe {
+            let state = (*self.state.get()).as_mut().unwrap();
+            if !is_within_sandbox(&state.current_dir, &root) {
+                state.current_dir = root.clone();
+            }
+            state.sandbox_root = root;
+        }
+    }
 }
 
 static WORKING_DIR: AtomicWorkingDir = AtomicWorkingDir::new();
@@ -93,6 +111,31 @@ pub fn get_sandbox_root() -> String {
     WORKING_DIR.get_sandbox_root()
 }
 
+/// Use `path` as the sandbox root instead of the starting directory
+/// (`--sandbox`, `sandbox_root`); relative paths are taken from the process
+/// cwd. If the working directory is outside the new root, meow moves to it.
+pub fn set_sandbox_root(path: &str) -> Result<(), String> {
+    let root = if path.starts_with('/') {
+        normalize_path(path)
+    } else {
+        normalize_path(&format!("{}/{}", libakuma::getcwd(), path))
+    };
+    if is_protected_normalized(&root) {
+        return Err(format!("sandbox '{}' is inside meow's own files", root));
+    }
+    if libakuma::read_dir(&root).is_none() {
+        return Err(format!("sandbox '{}' is not a directory", root));
+    }
+    if !is_within_sandbox(&get_working_dir(), &root) {
+        let result = libakuma::chdir(&root);
+        if result != 0 {
+            return Err(format!("failed to change directory to {}: error {}", root, result));
+        }
+    }
+    WORKING_DIR.set_sandbox_root(root);
+    Ok(())
+}
+
 /// Set the current working directory (internal, after validation)
 pub fn set_working_dir(path: &str) {
     // Normalize path - ensure it starts with /
@@ -144,16 +187,74 @@ pub fn is_within_sandbox(path: &str, sandbox: &str) -> bool {
     path == sandbox || path.starts_with(&format!("{}/", sandbox))
 }
 
+// ============================================================================
+// Protected Paths (meow's own files)
+// ============================================================================
+
+/// Where meow is installed when `arg(0)` doesn't say
+const DEFAULT_BINARY: &str = "/bin/meow";
+
+/// Files and directories the model may never reach, whatever the sandbox
+/// and virtual path settings allow: the running binary, the config (which
+/// holds the sandbox settings and API keys) and /var/meow, where the
+/// checkpoints and crash dumps that undo its mistakes are kept.
+fn protected_paths() -> [String; 4] {
+    let binary = match libakuma::arg(0) {
+        Some(a) if a.starts_with('/') => normalize_path(&String::from(a)),
+        _ => String::from(DEFAULT_BINARY),
+    };
+    [
+        binary,
+        String::from(DEFAULT_BINARY),
+        String::from(crate::config::CONFIG_PATH),
+        String::from(crate::crash::CRASH_DIR),
+    ]
+}
+
+/// Whether `path` (relative to the working directory, or absolute) is one
+/// of meow's own protected files or inside a protected directory
+pub fn is_protected(path: &str) -> bool {
+    is_protected_normalized(&normalize_path(&absolute_path(path)))
+}
+
+fn is_protected_normalized(normalized: &str) -> bool {
+    protected_paths().iter().any(|p| is_within_sandbox(normalized, p))
+}
+
 /// Resolve a path relative to the current working directory
-/// Returns None if the path escapes the sandbox root
+/// Returns None if the path escapes the sandbox root or is protected
 pub fn resolve_path(path: &str) -> Option<String> {
+    let sandbox = get_sandbox_root();
+    
+    // Normalize the path (resolve . and ..)
+    let normalized = normalize_path(&absolute_path(path));
+    
+    // Check if normalized path is within sandbox
+    if !is_within_sandbox(&normalized, &sandbox) {
+        return None;
+    }
+    // meow's own files are off limits even with the sandbox at /
+    if is_protected_normalized(&normalized) {
+        return None;
+    }
+    
+    Some(normalized)
+}
+
+/// `path` made absolute against the working directory (or, for a virtual
+/// path, the sandbox root); not yet normalized
+fn absolute_path(path: &str) -> String {
     let cwd = get_working_dir();
     let sandbox = get_sandbox_root();
     
-    // Compute the absolute path
-    let absolute = if path.starts_with('/') {
-        // Already absolute
-        String::from(path)
+    if path.starts_with('/') {
+        if VIRTUAL_PATHS.load(Ordering::Relaxed) && sandbox != "/" && !is_within_sandbox(&normalize_path(path), &sandbox) {
+            // Virtual path: "/" is the sandbox root
+            format!("{}{}", sandbox, path)
+        } else {
+            // Already absolute
+            String::from(path)
+        }
     } else {
         // Relative to cwd
         if cwd == "/" {
@@ -161,15 +262,33 @@ pub fn resolve_path(path: &str) -> Option<String> {
         } else {
             format!("{}/{}", cwd, path)
         }
-    };
-    
-    // Normalize the path (resolve . and ..)
-    let normalized = normalize_path(&absolute);
-    
-    // Check if normalized path is within sandbox
-    if !is_within_sandbox(&normalized, &sandbox) {
-        return None;
     }
-    
-    Some(normalized)
+}
+
+/// Rewrite host paths under the sandbox root to virtual ones in text shown to
+/// the model (`/work/repo/src` becomes `/src`). No-op outside virtual mode.
+pub fn virtualize(text: String) -> String {
+    let sandbox = get_sandbox_root();
+    if !VIRTUAL_PATHS.load(Ordering::Relaxed) || sandbox == "/" || !text.contains(sandbox.as_str()) {
+        return text;
+    }
+    let is_path_char = |c: char| c.is_alphanumeric() || matches!(c, '/' | '_' | '-' | '.');
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(i) = rest.find(sandbox.as_str()) {
+        let before = rest[..i].chars().next_back();
+        let after = &rest[i + sandbox.len()..];
+        out.push_str(&rest[..i]);
+        match after.chars().next() {
+            // Part of a longer path, e.g. /work/repo2 or /x/work/repo
+            _ if before.is_some_and(is_path_char) => out.push_str(&sandbox),
+            Some('/') => {}
+            Some(c) if is_path_char(c) => out.push_str(&sandbox),
+            _ => out.push('/'),
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
 }
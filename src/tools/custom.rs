@@ -0,0 +1,97 @@
+//! Tools defined in the config
+//!
+//! A `[tool:NAME]` section names a command template:
+//!
+//! ```text
+//! [tool:DeployStaging]
+//! cmd="/bin/deploy staging {target}"
+//! description="Deploy a branch to the staging server"
+//! args=target
+//! ```
+//!
+//! The tool is listed in the system prompt (and the native `tools` array)
+//! next to the built-in ones. A call fills each `{arg}` with its argument,
+//! quoted so it stays one word, and runs the result exactly like a Shell
+//! command: the same sandbox checks on its arguments, the same risk
+//! confirmation, and refused while the sandbox is read-only.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use crate::config::CustomTool;
+use super::helpers::extract_string_field;
+use super::mod_types::ToolResult;
+use super::{registry, shell};
+
+static mut TOOLS: Vec<CustomTool> = Vec::new();
+
+fn tools() -> &'static mut Vec<CustomTool> {
+    unsafe { &mut *core::ptr::addr_of_mut!(TOOLS) }
+}
+
+/// Install the config's tools; returns a note for each one left out
+pub fn load(defined: &[CustomTool]) -> Vec<String> {
+    let mut skipped = Vec::new();
+    tools().clear();
+    for t in defined {
+        if !t.name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            skipped.push(format!("[tool:{}] skipped: names are letters, digits and _ only", t.name));
+        } else if registry::find(&t.name).is_some() || find(&t.name).is_some() {
+            skipped.push(format!("[tool:{}] skipped: a tool with that name already exists", t.name));
+        } else {
+            tools().push(t.clone());
+        }
+    }
+    skipped
+}
+
+pub fn find(name: &str) -> Option<&'static CustomTool> {
+    tools().iter().find(|t| t.name == name)
+}
+
+pub fn all() -> &'static [CustomTool] {
+    tools()
+}
+
+/// The usage line, in the registry's `{a, b} - what it does` form
+pub fn usage(tool: &CustomTool) -> String {
+    let what = if tool.description.is_empty() { format!("runs `{}`", tool.cmd) } else { tool.description.clone() };
+    format!("{{{}}} - {}", tool.args.join(", "), what)
+}
+
+/// Prompt section listing the custom tools, None when there are none
+pub fn prompt_section() -> Option<String> {
+    if tools().is_empty() {
+        return None;
+    }
+    let mut out = String::from("\n### Custom Tools\n\nDefined by the user for this machine; call them like any other tool:\n");
+    for t in tools().iter() {
+        let args: Vec<String> = t.args.iter().map(|a| format!("\"{}\": \"...\"", a)).collect();
+        out.push_str(&format!("- {}: {}\n  Args: `{{{}}}`\n", t.name, usage(t), args.join(", ")));
+    }
+    Some(out)
+}
+
+/// The command a call to `tool` runs, or what is missing
+pub fn command(tool: &CustomTool, json: &str) -> Result<String, String> {
+    let mut cmd = tool.cmd.clone();
+    for arg in &tool.args {
+        let value = extract_string_field(json, arg)
+            .ok_or_else(|| format!("{} requires '{}'", tool.name, arg))?;
+        cmd = cmd.replace(&format!("{{{}}}", arg), &quote(&value));
+    }
+    Ok(cmd)
+}
+
+pub fn run(tool: &CustomTool, json: &str) -> ToolResult {
+    match command(tool, json) {
+        Ok(cmd) => shell::tool_shell(&cmd),
+        Err(e) => ToolResult::err(&e),
+    }
+}
+
+/// `value` as one double-quoted shell word
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -1,24 +1,33 @@
 use alloc::string::String;
 use alloc::format;
 use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use libakuma::{
     open, close, read_fd, write_fd, fstat, mkdir, read_dir,
     open_flags,
 };
 
-use super::context::{resolve_path, get_working_dir, get_sandbox_root, set_working_dir, normalize_path, is_within_sandbox};
+use super::context::{resolve_path, get_working_dir, get_sandbox_root, set_working_dir, is_protected};
 use super::mod_types::ToolResult;
 // MAX_FILE_SIZE is 512KB
 const MAX_FILE_SIZE: usize = 512 * 1024;
 
+/// Byte offset of the last successful FileEdit in its file, for `/diff`;
+/// usize::MAX when there is none
+static LAST_EDIT_AT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
 /// Resolve path or return error
 fn resolve_path_or_err(path: &str) -> Result<String, ToolResult> {
     match resolve_path(path) {
         Some(p) => Ok(p),
+        None if is_protected(path) => Err(ToolResult::err(&format!(
+            "Access denied: '{}' is one of meow's own files (its binary, config or recovery data)",
+            path
+        ))),
         None => Err(ToolResult::err(&format!(
-            "Access denied: '{}' is outside the working directory '{}'",
-            path, get_working_dir()
+            "Access denied: '{}' is outside the sandbox '{}'",
+            path, get_sandbox_root()
         ))),
     }
 }
@@ -44,9 +53,9 @@ pub fn tool_file_read(filename: &str) -> ToolResult {
     };
     
     let size = stat.st_size as usize;
-    if size > MAX_FILE_SIZE {
+    if size > MAX_FILE_SIZE * 4 {
         close(fd);
-        return ToolResult::err("File too large (max 512KB)");
+        return ToolResult::err("File too large (max 2MB); use FileReadLines or CodeSearch");
     }
     
     let mut buf = alloc::vec![0u8; size];
@@ -58,11 +67,116 @@ pub fn tool_file_read(filename: &str) -> ToolResult {
     }
     
     match core::str::from_utf8(&buf[..bytes_read as usize]) {
+        Ok(content) if content.len() > FULL_READ_LIMIT => ToolResult::ok(outlined_read(filename, content)),
         Ok(content) => ToolResult::ok(format!("Contents of '{}':\n```\n{}\n```", filename, content)),
         Err(_) => ToolResult::err("File contains non-UTF8 data"),
     }
 }
 
+/// Files longer than this are shown as head, outline and tail
+const FULL_READ_LIMIT: usize = crate::config::MAX_TOOL_OUTPUT_SIZE - 1024;
+/// Byte budgets for the parts of an outlined read; together they stay
+/// under the tool output limit
+const HEAD_BYTES: usize = 10 * 1024;
+const TAIL_BYTES: usize = 5 * 1024;
+const OUTLINE_BYTES: usize = 10 * 1024;
+/// Longest outline line kept
+const OUTLINE_LINE_CHARS: usize = 100;
+
+/// A long file as its first and last lines, numbered, with an outline of
+/// the structure in between (definitions and headings, found by their
+/// leading keywords), so the model can follow up with FileReadLines on
+/// exactly the part it needs
+fn outlined_read(filename: &str, content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let total = lines.len();
+
+    let fit = |range: &mut dyn Iterator<Item = usize>, budget: usize| {
+        let mut used = 0;
+        range.take_while(|&i| {
+            used += lines[i].len() + 8;
+            used <= budget
+        }).count()
+    };
+    // At least one line each, even a very long one
+    let head = fit(&mut (0..total), HEAD_BYTES).max(1);
+    let tail = fit(&mut (head..total).rev(), TAIL_BYTES).max(1).min(total - head);
+    let middle = head..total - tail;
+
+    let mut output = format!(
+        "'{}' has {} lines ({} bytes), too long to show whole. Lines 1-{} and {}-{} follow; the {} lines between are outlined.\n```\n",
+        filename, total, content.len(), head, middle.end + 1, total, middle.len()
+    );
+    for (i, line) in lines[..head].iter().enumerate() {
+        output.push_str(&format!("{:>4}: {}\n", i + 1, line));
+    }
+    output.push_str("```\n");
+
+    if !middle.is_empty() {
+        output.push_str(&format!("[lines {}-{} omitted; outline with line numbers:]\n", middle.start + 1, middle.end));
+        let mut used = 0;
+        let mut shown = 0;
+        let mut found = 0;
+        let markdown = filename.ends_with(".md") || filename.ends_with(".markdown");
+        for i in middle.clone() {
+            if !is_outline_line(lines[i], markdown) {
+                continue;
+            }
+            found += 1;
+            let text: String = lines[i].trim_end().chars().take(OUTLINE_LINE_CHARS).collect();
+            let entry = format!("{:>4}: {}\n", i + 1, text);
+            if used + entry.len() > OUTLINE_BYTES {
+                continue;
+            }
+            used += entry.len();
+            shown += 1;
+            output.push_str(&entry);
+        }
+        if found == 0 {
+            output.push_str("  (no definitions or headings found)\n");
+        } else if shown < found {
+            output.push_str(&format!("  ({} more outline lines not shown)\n", found - shown));
+        }
+        output.push_str("[read any range with FileReadLines {filename, start, end}]\n");
+    }
+
+    output.push_str("```\n");
+    for (i, line) in lines[middle.end..].iter().enumerate() {
+        output.push_str(&format!("{:>4}: {}\n", middle.end + i + 1, line));
+    }
+    output.push_str("```");
+    output
+}
+
+/// Keywords that open a definition in the usual languages
+const OUTLINE_KEYWORDS: &[&str] = &[
+    "fn ", "impl ", "impl<", "struct ", "enum ", "trait ", "mod ", "type ", "macro_rules!", "#[cfg(test)]",
+    "class ", "def ", "function ", "interface ", "func ", "package ",
+];
+/// Modifiers skipped before looking for a keyword
+const OUTLINE_MODIFIERS: &[&str] = &["pub(crate) ", "pub(super) ", "pub ", "export ", "default ", "async ", "unsafe ", "static "];
+
+/// Whether a line looks like a heading or a definition worth outlining:
+/// a markdown heading (in a markdown file), an ini/toml section, or a known
+/// keyword at shallow indentation
+fn is_outline_line(line: &str, markdown: bool) -> bool {
+    let mut rest = line.trim_start();
+    let indent = line.len() - rest.len();
+    if markdown && indent == 0 && rest.starts_with('#') && rest.trim_start_matches('#').starts_with(' ') {
+        return true;
+    }
+    if indent == 0 && rest.starts_with('[') && rest.trim_end().ends_with(']') {
+        return true;
+    }
+    if indent > 4 {
+        return false;
+    }
+    while let Some(m) = OUTLINE_MODIFIERS.iter().find(|m| rest.starts_with(*m)) {
+        rest = &rest[m.len()..];
+    }
+    OUTLINE_KEYWORDS.iter().any(|k| rest.starts_with(k))
+}
+
 pub fn tool_file_write(filename: &str, content: &str) -> ToolResult {
     let resolved = match resolve_path_or_err(filename) {
         Ok(p) => p,
@@ -120,23 +234,61 @@ pub fn tool_file_exists(filename: &str) -> ToolResult {
     }
 }
 
-pub fn tool_file_list(path: &str) -> ToolResult {
+/// Directories with more entries than this are listed compactly
+const COMPACT_LIST_AFTER: usize = 40;
+/// Most entries FileList shows per call (and the default `limit`);
+/// `offset` pages through the rest
+pub const MAX_LIST_ENTRIES: usize = 300;
+
+pub fn tool_file_list(path: &str, offset: usize, limit: usize) -> ToolResult {
     let resolved = match resolve_path_or_err(path) {
         Ok(p) => p,
         Err(e) => return e,
     };
     
     match read_dir(&resolved) {
-        Some(entries) => {
-            let mut output = format!("Contents of '{}':\n", path);
-            let mut count = 0;
-            for entry in entries {
-                let type_indicator = if entry.is_dir { "/" } else { "" };
-                output.push_str(&format!("  {}{}\n", entry.name, type_indicator));
-                count += 1;
+        Some(mut entries) => {
+            entries.retain(|e| e.name != "." && e.name != "..");
+            if entries.is_empty() {
+                return ToolResult::ok(format!("Contents of '{}':\n  (empty directory)\n", path));
+            }
+            // Stable order, so offsets mean the same thing from call to call
+            entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+            let total = entries.len();
+            if offset >= total {
+                return ToolResult::err(&format!("offset {} is past the end of '{}' ({} entries)", offset, path, total));
             }
-            if count == 0 {
-                output.push_str("  (empty directory)\n");
+            let end = (offset + limit.clamp(1, MAX_LIST_ENTRIES)).min(total);
+            let page = &entries[offset..end];
+
+            let mut output;
+            if total <= COMPACT_LIST_AFTER && offset == 0 && end == total {
+                output = format!("Contents of '{}':\n", path);
+                for entry in page {
+                    let type_indicator = if entry.is_dir { "/" } else { "" };
+                    output.push_str(&format!("  {}{}\n", entry.name, type_indicator));
+                }
+            } else {
+                // One dense line instead of a line per entry; dirs end in /
+                let dirs = entries.iter().filter(|e| e.is_dir).count();
+                output = format!("'{}': {} entries ({} dirs, {} files)", path, total, dirs, total - dirs);
+                if offset > 0 || end < total {
+                    output.push_str(&format!(", showing {}-{}", offset + 1, end));
+                }
+                output.push_str(":\n");
+                for (i, entry) in page.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+                    output.push_str(&entry.name);
+                    if entry.is_dir {
+                        output.push('/');
+                    }
+                }
+                output.push('\n');
+            }
+            if end < total {
+                output.push_str(&format!("[truncated: {} more; next offset: {} (call FileList with \"offset\": {})]\n", total - end, end, end));
             }
             ToolResult::ok(output)
         }
@@ -144,13 +296,141 @@ pub fn tool_file_list(path: &str) -> ToolResult {
     }
 }
 
+/// Directories FileTree doesn't descend into
+const TREE_SKIP: &[&str] = &[".git", "target", "node_modules"];
+/// FileTree stops listing after this many entries
+const TREE_MAX_ENTRIES: usize = 500;
+
+pub fn tool_file_tree(path: &str, depth: usize) -> ToolResult {
+    let resolved = match resolve_path_or_err(path) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let depth = depth.clamp(1, 10);
+    let mut output = format!("Tree of '{}' (depth {}):\n", path, depth);
+    let mut count = 0;
+    if !tree_level(&resolved, 1, depth, &mut output, &mut count) {
+        return ToolResult::err(&format!("Failed to list directory: {}", path));
+    }
+    if count == 0 {
+        output.push_str("  (empty directory)\n");
+    } else if count >= TREE_MAX_ENTRIES {
+        output.push_str(&format!("  ... (stopped at {} entries; list a subdirectory for more)\n", TREE_MAX_ENTRIES));
+    }
+    ToolResult::ok(output)
+}
+
+/// Append the entries under `dir`, directories first, two spaces of indent
+/// per level; false if `dir` can't be read
+fn tree_level(dir: &str, level: usize, depth: usize, output: &mut String, count: &mut usize) -> bool {
+    let Some(mut entries) = read_dir(dir) else { return false };
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    for entry in entries {
+        if entry.name == "." || entry.name == ".." {
+            continue;
+        }
+        if *count >= TREE_MAX_ENTRIES {
+            return true;
+        }
+        *count += 1;
+        for _ in 0..level {
+            output.push_str("  ");
+        }
+        output.push_str(&entry.name);
+        if !entry.is_dir {
+            output.push('\n');
+            continue;
+        }
+        if TREE_SKIP.contains(&entry.name.as_str()) {
+            output.push_str("/ (skipped)\n");
+            continue;
+        }
+        output.push_str("/\n");
+        if level < depth {
+            let child = format!("{}/{}", dir.trim_end_matches('/'), entry.name);
+            tree_level(&child, level + 1, depth, output, count);
+        }
+    }
+    true
+}
+
 pub fn tool_file_delete(filename: &str) -> ToolResult {
-    let _resolved = match resolve_path_or_err(filename) {
+    let resolved = match resolve_path_or_err(filename) {
         Ok(p) => p,
         Err(e) => return e,
     };
-    // Note: libakuma doesn't have unlink syscall yet
-    ToolResult::err(&format!("Delete not yet implemented for: {}", filename))
+    match remove_file(&resolved) {
+        Ok(()) => ToolResult::ok(format!("Deleted '{}'", filename)),
+        Err(e) => ToolResult::err(&e),
+    }
+}
+
+/// Remove the file at resolved path `path`. libakuma has no unlink syscall
+/// yet, so this runs `rm`; the file being gone afterwards is what counts.
+pub fn remove_file(path: &str) -> Result<(), String> {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return Err(format!("File not found: {}", path));
+    }
+    close(fd);
+    let output = super::shell::run_program("rm", &["-f", path])?;
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd >= 0 {
+        close(fd);
+        let detail = output.stdout.trim();
+        return Err(if detail.is_empty() {
+            format!("Failed to delete {} (rm exited with {})", path, output.exit_code)
+        } else {
+            format!("Failed to delete {}: {}", path, detail)
+        });
+    }
+    Ok(())
+}
+
+/// Why `read_file` came back without the contents
+pub enum ReadFileError {
+    NotFound,
+    TooLarge,
+    Unreadable,
+}
+
+/// The whole file at resolved path `path`, refused when it is larger than
+/// `max` bytes. Reads until EOF, since a single read may return less.
+pub fn read_file(path: &str, max: usize) -> Result<Vec<u8>, ReadFileError> {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return Err(ReadFileError::NotFound);
+    }
+    let size = match fstat(fd) {
+        Ok(s) => s.st_size as usize,
+        Err(_) => {
+            close(fd);
+            return Err(ReadFileError::Unreadable);
+        }
+    };
+    if size > max {
+        close(fd);
+        return Err(ReadFileError::TooLarge);
+    }
+    let mut data = Vec::with_capacity(size);
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = read_fd(fd, &mut buf);
+        if n < 0 {
+            close(fd);
+            return Err(ReadFileError::Unreadable);
+        }
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n as usize]);
+        if data.len() > max {
+            close(fd);
+            return Err(ReadFileError::TooLarge);
+        }
+    }
+    close(fd);
+    Ok(data)
 }
 
 pub fn tool_folder_create(path: &str) -> ToolResult {
@@ -168,29 +448,13 @@ pub fn tool_folder_create(path: &str) -> ToolResult {
 }
 
 pub fn tool_cd(path: &str) -> ToolResult {
-    let cwd = get_working_dir();
-    let sandbox = get_sandbox_root();
-    
-    // Compute the new absolute path
-    let absolute = if path.starts_with('/') {
-        String::from(path)
-    } else if cwd == "/" {
-        format!("/{}", path)
-    } else {
-        format!("{}/{}", cwd, path)
+    // Same rules as every other tool path: inside the sandbox, virtual
+    // paths mapped onto it, meow's own directories refused
+    let new_path = match resolve_path_or_err(path) {
+        Ok(p) => p,
+        Err(e) => return e,
     };
     
-    // Normalize the path (resolve . and ..)
-    let new_path = normalize_path(&absolute);
-    
-    // Check if new path is within sandbox
-    if !is_within_sandbox(&new_path, &sandbox) {
-        return ToolResult::err(&format!(
-            "Access denied: '{}' is outside the sandbox '{}'",
-            new_path, sandbox
-        ));
-    }
-    
     // Use chdir syscall to update the process's cwd
     let result = libakuma::chdir(&new_path);
     if result == 0 {
@@ -228,8 +492,8 @@ pub fn tool_file_rename(source: &str, dest: &str) -> ToolResult {
         Err(e) => return e,
     };
     
-    match tool_file_copy_internal(&src_resolved, &dst_resolved) {
-        Ok(_) => ToolResult::ok(format!("Renamed '{}' to '{}' (note: original not deleted yet)", source, dest)),
+    match move_file(&src_resolved, &dst_resolved) {
+        Ok(()) => ToolResult::ok(format!("Renamed '{}' to '{}'", source, dest)),
         Err(e) => ToolResult::err(&e),
     }
 }
@@ -293,6 +557,16 @@ fn tool_file_copy_internal(source: &str, dest: &str) -> Result<String, String> {
     Ok(format!("Copied '{}' to '{}' ({} bytes)", source, dest, bytes_written))
 }
 
+/// Copy, then remove the source. A source that can't be removed is
+/// reported, so the model knows both copies exist.
+fn move_file(source: &str, dest: &str) -> Result<(), String> {
+    if source == dest {
+        return Ok(());
+    }
+    tool_file_copy_internal(source, dest)?;
+    remove_file(source).map_err(|e| format!("Copied to {}, but the source is still there: {}", dest, e))
+}
+
 pub fn tool_file_move(source: &str, dest: &str) -> ToolResult {
     let src_resolved = match resolve_path_or_err(source) {
         Ok(p) => p,
@@ -303,8 +577,8 @@ pub fn tool_file_move(source: &str, dest: &str) -> ToolResult {
         Err(e) => return e,
     };
     
-    match tool_file_copy_internal(&src_resolved, &dst_resolved) {
-        Ok(_) => ToolResult::ok(format!("Moved '{}' to '{}' (note: source not deleted yet)", source, dest)),
+    match move_file(&src_resolved, &dst_resolved) {
+        Ok(()) => ToolResult::ok(format!("Moved '{}' to '{}'", source, dest)),
         Err(e) => ToolResult::err(&e),
     }
 }
@@ -373,6 +647,14 @@ pub fn tool_file_read_lines(filename: &str, start: usize, end: usize) -> ToolRes
     ToolResult::ok(output)
 }
 
+/// Where the last FileEdit put its new text, once
+pub fn take_last_edit_offset() -> Option<usize> {
+    match LAST_EDIT_AT.swap(usize::MAX, Ordering::Relaxed) {
+        usize::MAX => None,
+        offset => Some(offset),
+    }
+}
+
 pub fn tool_file_edit(filename: &str, old_text: &str, new_text: &str) -> ToolResult {
     let resolved = match resolve_path_or_err(filename) {
         Ok(p) => p,
@@ -451,6 +733,8 @@ pub fn tool_file_edit(filename: &str, old_text: &str, new_text: &str) -> ToolRes
         return ToolResult::err("Failed to write file");
     }
 
+    // The text before the match is unchanged, so new_text starts here too
+    LAST_EDIT_AT.store(match_pos, Ordering::Relaxed);
     let line_num = content[..match_pos].matches('\n').count() + 1;
 
     let old_lines: Vec<&str> = old_text.lines().collect();
@@ -1,25 +1,25 @@
 use alloc::string::String;
 use alloc::format;
 use super::mod_types::ToolResult;
-use super::shell::tool_shell;
+use super::shell::{tool_shell, tool_shell_labeled};
 
 pub fn tool_git_clone(url: &str) -> ToolResult {
-    tool_shell(&format!("scratch clone {}", url))
+    tool_shell_labeled(&format!("scratch clone {}", url), Some("cloning"))
 }
 
 pub fn tool_git_pull() -> ToolResult {
-    tool_shell("scratch pull")
+    tool_shell_labeled("scratch pull", Some("pulling"))
 }
 
 pub fn tool_git_fetch() -> ToolResult {
-    tool_shell("scratch fetch")
+    tool_shell_labeled("scratch fetch", Some("fetching"))
 }
 
 pub fn tool_git_push(force: bool) -> ToolResult {
     if force {
         return ToolResult::err("DENIED: Force push is permanently disabled.");
     }
-    tool_shell("scratch push")
+    tool_shell_labeled("scratch push", Some("pushing"))
 }
 
 pub fn tool_git_status() -> ToolResult {
@@ -49,6 +49,11 @@ pub fn tool_git_add(path: &str) -> ToolResult {
 }
 
 pub fn tool_git_commit(message: &str, amend: bool) -> ToolResult {
+    // Commits made while the session is linked to an issue point back at it
+    let message = match super::chainlink::linked_issue() {
+        Some(id) if !message.contains(&format!("#{}", id)) => format!("{}\n\nRefs: #{}", message, id),
+        _ => String::from(message),
+    };
     let escaped_message = message.replace('"', "\\\"");
     if amend {
         tool_shell(&format!("scratch commit --amend -m \"{}\"", escaped_message))
@@ -92,4 +97,21 @@ pub fn tool_git_tag(name: Option<&str>, delete: bool) -> ToolResult {
 
 pub fn tool_git_reset() -> ToolResult {
     tool_shell("scratch reset")
-}
\ No newline at end of file
+}
+
+/// Command line for a diff of the working tree, the index, or a ref range
+pub fn diff_command(staged: bool, range: Option<&str>) -> String {
+    let mut cmd = String::from("scratch diff");
+    if staged {
+        cmd.push_str(" --cached");
+    }
+    if let Some(r) = range {
+        cmd.push(' ');
+        cmd.push_str(r);
+    }
+    cmd
+}
+
+pub fn tool_git_diff(staged: bool, range: Option<&str>) -> ToolResult {
+    tool_shell(&diff_command(staged, range))
+}
@@ -0,0 +1,239 @@
+//! HTML to text for HttpFetch
+//!
+//! A page's markup, scripts and navigation are most of its bytes and none
+//! of its content. This keeps the readable part: the `<main>` or
+//! `<article>` element when the page has one with real text in it, else
+//! the whole body; script, style, nav, aside and footer dropped; headings
+//! as `#` lines, list items as `-` lines, links as `[text](url)` and `<pre>`
+//! kept as it is. Entities are decoded; everything else collapses to text.
+
+use alloc::string::String;
+use alloc::format;
+
+/// Elements skipped with everything inside them
+const SKIPPED: &[&str] = &["script", "style", "noscript", "svg", "template", "iframe", "nav", "aside", "footer", "form", "button", "select"];
+/// Elements that start a new line
+const BLOCKS: &[&str] = &[
+    "p", "div", "br", "tr", "section", "article", "main", "header", "ul", "ol", "table", "blockquote",
+    "figure", "figcaption", "dl", "dt", "dd", "hr", "title",
+];
+/// A `<main>`/`<article>` with less text than this is probably not the content
+const MIN_MAIN_CHARS: usize = 500;
+
+/// Readable text of `html`; `resolve` makes link targets absolute
+pub fn to_text(html: &str, resolve: impl Fn(&str) -> String) -> String {
+    for tag in ["main", "article"] {
+        if let Some(inner) = element(html, tag) {
+            let text = convert(inner, &resolve);
+            if text.chars().filter(|c| !c.is_whitespace()).count() >= MIN_MAIN_CHARS {
+                let title = element(html, "title").map(|t| convert(t, &resolve)).unwrap_or_default();
+                return if title.is_empty() { text } else { format!("{}\n\n{}", title, text) };
+            }
+        }
+    }
+    convert(html, &resolve)
+}
+
+/// Inside of the first `<tag ...>...</tag>`
+fn element<'a>(html: &'a str, tag: &str) -> Option<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", tag);
+    let mut from = 0;
+    let start = loop {
+        let i = from + lower[from..].find(&open)?;
+        // `<main>` or `<main class=..>`, not `<mainframe>`
+        match lower.as_bytes().get(i + open.len()) {
+            Some(b'>') | Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => break i,
+            _ => from = i + open.len(),
+        }
+    };
+    let body = start + lower[start..].find('>')? + 1;
+    let end = body + lower[body..].find(&format!("</{}", tag))?;
+    Some(&html[body..end])
+}
+
+fn convert(html: &str, resolve: &impl Fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(html.len() / 4);
+    // Element being skipped, and how deeply it is nested in itself
+    let mut skipping: Option<(String, usize)> = None;
+    let mut pre = 0usize;
+    // Link target and where its text starts in `out`
+    let mut link: Option<(String, usize)> = None;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            if skipping.is_none() { push_text(&mut out, rest, pre > 0); }
+            break;
+        };
+        if skipping.is_none() { push_text(&mut out, &rest[..lt], pre > 0); }
+        rest = &rest[lt..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |e| &rest[e + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else { break };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/');
+        let name_end = tag.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(tag.len());
+        let name = tag[..name_end].to_ascii_lowercase();
+
+        if let Some((skipped, depth)) = skipping.as_mut() {
+            if *skipped == name {
+                if closing { *depth -= 1; } else if !tag.ends_with('/') { *depth += 1; }
+                if *depth == 0 { skipping = None; }
+            }
+            continue;
+        }
+        if !closing && SKIPPED.contains(&name.as_str()) {
+            if !tag.ends_with('/') { skipping = Some((name, 1)); }
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("pre", false) => { new_line(&mut out, 2); out.push_str("```\n"); pre += 1; }
+            ("pre", true) => { pre = pre.saturating_sub(1); new_line(&mut out, 1); out.push_str("```\n\n"); }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                new_line(&mut out, 2);
+                for _ in 0..name[1..].parse::<usize>().unwrap_or(1) { out.push('#'); }
+                out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => new_line(&mut out, 2),
+            ("li", false) => { new_line(&mut out, 1); out.push_str("- "); }
+            ("td" | "th", false) => if !out.ends_with('\n') && !out.is_empty() { out.push_str(" | ") },
+            ("a", false) => {
+                let href = attribute(tag, "href").unwrap_or_default();
+                if !href.is_empty() && !href.starts_with('#') && !href.starts_with("javascript:") {
+                    out.push('[');
+                    link = Some((resolve(&decode_entities(&href)), out.len()));
+                }
+            }
+            ("a", true) => {
+                if let Some((href, start)) = link.take() {
+                    if out[start..].trim().is_empty() {
+                        out.truncate(start - 1);
+                    } else {
+                        out.push_str("](");
+                        out.push_str(&href);
+                        out.push(')');
+                    }
+                }
+            }
+            ("img", false) => {
+                if let Some(alt) = attribute(tag, "alt").filter(|a| !a.trim().is_empty()) {
+                    out.push_str(&format!("[image: {}]", decode_entities(alt.trim())));
+                }
+            }
+            (n, _) if BLOCKS.contains(&n) => new_line(&mut out, if n == "p" || n == "br" || n == "tr" { 1 } else { 2 }),
+            _ => {}
+        }
+    }
+
+    // At most one blank line in a row, no trailing spaces
+    let mut text = String::with_capacity(out.len());
+    let mut blank = 0;
+    for line in out.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            blank += 1;
+            if blank > 1 || text.is_empty() { continue; }
+        } else {
+            blank = 0;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    String::from(text.trim_end())
+}
+
+/// Append text, collapsing whitespace unless inside `<pre>`
+fn push_text(out: &mut String, text: &str, pre: bool) {
+    let text = decode_entities(text);
+    if pre {
+        out.push_str(&text);
+        return;
+    }
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !out.is_empty() && !out.ends_with(' ') && !out.ends_with('\n') && !out.ends_with('[') {
+                out.push(' ');
+            }
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// End the current line, leaving `breaks` line breaks (2 = a blank line)
+fn new_line(out: &mut String, breaks: usize) {
+    while out.ends_with(' ') { out.pop(); }
+    if out.is_empty() { return; }
+    let have = out.len() - out.trim_end_matches('\n').len();
+    for _ in have..breaks { out.push('\n'); }
+}
+
+/// Value of attribute `name` in the inside of a tag
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let at = from + i;
+        from = at + name.len();
+        // Must be a whole attribute name followed by '='
+        let before_ok = at > 0 && lower.as_bytes()[at - 1].is_ascii_whitespace();
+        let after = lower[from..].trim_start();
+        if !before_ok || !after.starts_with('=') { continue; }
+        let value = tag[tag.len() - after.len() + 1..].trim_start();
+        return Some(match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or("").into(),
+            _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or("").into(),
+        });
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return String::from(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.char_indices().take(12).find(|&(_, c)| c == ';').map(|(i, _)| i) else {
+            out.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" | "#39" => Some('\''),
+            "nbsp" => Some(' '),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            "copy" => Some('©'),
+            _ => entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X"))
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => { out.push(c); rest = &rest[semi + 1..]; }
+            None => { out.push('&'); rest = &rest[1..]; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
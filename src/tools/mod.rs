@@ -2,8 +2,14 @@ pub mod context;
 pub mod fs;
 pub mod git;
 pub mod chainlink;
+pub mod custom;
 pub mod net;
+pub mod html;
 pub mod shell;
+pub mod permission;
+pub mod progress;
+pub mod prefetch;
+pub mod registry;
 pub mod helpers;
 pub mod mod_types;
 
@@ -14,16 +20,60 @@ use alloc::format;
 pub use mod_types::{ToolResult, ToolCall};
 pub use context::{get_working_dir, get_sandbox_root};
 pub use chainlink::chainlink_available;
+pub use progress::progress;
 use helpers::{extract_string_field, extract_number_field};
 
-/// Parse and execute a tool command from JSON
+/// Parse and execute a tool command from JSON. Under `--replay` the
+/// recorded result comes back instead (see `replay`).
 pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
+    let result = match crate::replay::next_tool(json) {
+        Some(recorded) => recorded,
+        None => {
+            let result = execute_live(json);
+            crate::replay::note_tool(json, &result);
+            result
+        }
+    };
+    if let (Some(name), Some(r)) = (extract_string_field(json, "tool"), &result) {
+        crate::app::metrics::record_tool(&name, r.success);
+    }
+    result
+}
+
+fn execute_live(json: &str) -> Option<ToolResult> {
+    if let Some(tool) = extract_string_field(json, "tool").and_then(|name| registry::find(&name)) {
+        if tool.mutates && registry::READ_ONLY.load(core::sync::atomic::Ordering::Relaxed) {
+            return Some(ToolResult::err("Refused: another meow instance is working in this sandbox, so file-changing tools are off. Read-only tools still work."));
+        }
+        if tool.mutates {
+            prefetch::clear();
+        }
+    }
+    if let Some(tool) = extract_string_field(json, "tool").and_then(|name| custom::find(&name)) {
+        // Custom tools run arbitrary commands, so count as file-changing
+        if registry::READ_ONLY.load(core::sync::atomic::Ordering::Relaxed) {
+            return Some(ToolResult::err("Refused: another meow instance is working in this sandbox, so file-changing tools are off. Read-only tools still work."));
+        }
+        prefetch::clear();
+        let mut result = custom::run(tool, json);
+        progress::done();
+        result.output = context::virtualize(result.output);
+        return Some(result);
+    }
+    let result = run_tool(json);
+    progress::done();
+    let mut result = result?;
+    result.output = context::virtualize(result.output);
+    Some(result)
+}
+
+fn run_tool(json: &str) -> Option<ToolResult> {
     let tool_name = extract_string_field(json, "tool")?;
     
     match tool_name.as_str() {
         "FileRead" => {
             let filename = extract_string_field(json, "filename")?;
-            Some(fs::tool_file_read(&filename))
+            Some(prefetch::take(&filename).unwrap_or_else(|| fs::tool_file_read(&filename)))
         }
         "FileWrite" => {
             let filename = extract_string_field(json, "filename")?;
@@ -41,7 +91,14 @@ pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
         }
         "FileList" => {
             let path = extract_string_field(json, "path").unwrap_or_else(|| String::from("/"));
-            Some(fs::tool_file_list(&path))
+            let offset = extract_number_field(json, "offset").unwrap_or(0);
+            let limit = extract_number_field(json, "limit").unwrap_or(fs::MAX_LIST_ENTRIES);
+            Some(fs::tool_file_list(&path, offset, limit))
+        }
+        "FileTree" => {
+            let path = extract_string_field(json, "path").unwrap_or_else(|| String::from("."));
+            let depth = extract_number_field(json, "depth").unwrap_or(3);
+            Some(fs::tool_file_tree(&path, depth))
         }
         "FileDelete" => {
             let filename = extract_string_field(json, "filename")?;
@@ -68,7 +125,15 @@ pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
         }
         "HttpFetch" => {
             let url = extract_string_field(json, "url")?;
-            Some(net::tool_http_fetch(&url))
+            let raw = extract_string_field(json, "raw")
+                .map(|s| s == "true")
+                .unwrap_or(false);
+            Some(net::tool_http_fetch(&url, raw))
+        }
+        "WebSearch" => {
+            let query = extract_string_field(json, "query")?;
+            let count = extract_number_field(json, "count");
+            Some(net::tool_web_search(&query, count))
         }
         "GitClone" => {
             let url = extract_string_field(json, "url")?;
@@ -133,6 +198,13 @@ pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
         "GitReset" => {
             Some(git::tool_git_reset())
         }
+        "GitDiff" => {
+            let staged = extract_string_field(json, "staged")
+                .map(|s| s == "true")
+                .unwrap_or(false);
+            let range = extract_string_field(json, "range");
+            Some(git::tool_git_diff(staged, range.as_deref()))
+        }
         "FileReadLines" => {
             let filename = extract_string_field(json, "filename")?;
             let start = extract_number_field(json, "start").unwrap_or(1);
@@ -143,7 +215,15 @@ pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
             let pattern = extract_string_field(json, "pattern")?;
             let path = extract_string_field(json, "path").unwrap_or_else(|| String::from("."));
             let context = extract_number_field(json, "context").unwrap_or(2);
-            Some(tool_code_search(&pattern, &path, context))
+            let filter = crate::code_search::FileFilter::new(
+                extract_string_field(json, "glob"),
+                extract_string_field(json, "extensions").as_deref(),
+            );
+            let max_results = extract_number_field(json, "max_matches")
+                .or_else(|| extract_number_field(json, "max_results"))
+                .unwrap_or(crate::code_search::DEFAULT_MAX_MATCHES);
+            let offset = extract_number_field(json, "offset").unwrap_or(0);
+            Some(tool_code_search(&pattern, &path, context, &filter, max_results, offset))
         }
         "FileEdit" => {
             let filename = extract_string_field(json, "filename")?;
@@ -225,16 +305,16 @@ pub fn execute_tool_command(json: &str) -> Option<ToolResult> {
     }
 }
 
-fn tool_code_search(pattern: &str, path: &str, context: usize) -> ToolResult {
+fn tool_code_search(pattern: &str, path: &str, context: usize, filter: &crate::code_search::FileFilter, max_results: usize, offset: usize) -> ToolResult {
     let resolved = match context::resolve_path(path) {
         Some(p) => p,
         None => return ToolResult::err(&format!(
-            "Access denied: '{}' is outside the working directory '{}'",
-            path, context::get_working_dir()
+            "Access denied: '{}' is outside the sandbox '{}'",
+            path, context::get_sandbox_root()
         )),
     };
     
-    match crate::code_search::search_to_string(pattern, &resolved, context) {
+    match crate::code_search::search_to_string(pattern, &resolved, context, filter, max_results, offset) {
         Ok(results) => ToolResult::ok(results),
         Err(e) => ToolResult::err(&format!("Search failed: {}", e)),
     }
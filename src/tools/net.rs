@@ -1,120 +1,501 @@
+use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
 use libakuma::net::{TcpStream, resolve};
+use libakuma_tls::HttpHeaders;
 
+use crate::config::SearchEngine;
+use super::helpers::extract_string_field;
+use super::html;
 use super::mod_types::ToolResult;
 
 // Maximum response size for HTTP fetch (64KB)
 const MAX_FETCH_SIZE: usize = 64 * 1024;
+/// Largest page read before conversion to text; the text is then capped
+/// at `MAX_FETCH_SIZE` like any other response
+const MAX_PAGE_SIZE: usize = 256 * 1024;
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
 
-/// HTTP/HTTPS GET fetch tool
-pub fn tool_http_fetch(url: &str) -> ToolResult {
-    let parsed = match parse_http_url(url) {
-        Some(p) => p,
-        None => return ToolResult::err("Invalid URL format. Use: http(s)://host[:port]/path"),
+/// Largest search API reply read (SearXNG pages run to a few hundred KB)
+const MAX_SEARCH_SIZE: usize = 512 * 1024;
+/// Results returned unless `count` says otherwise, and the most allowed
+const DEFAULT_SEARCH_RESULTS: usize = 8;
+const MAX_SEARCH_RESULTS: usize = 20;
+/// Snippets are cut to this many characters
+const MAX_SNIPPET_CHARS: usize = 300;
+
+/// WebSearch backend from the config: engine, endpoint, API key
+static mut SEARCH: (SearchEngine, Option<String>, Option<String>) = (SearchEngine::DuckDuckGo, None, None);
+
+fn search_config() -> &'static mut (SearchEngine, Option<String>, Option<String>) {
+    unsafe { &mut *core::ptr::addr_of_mut!(SEARCH) }
+}
+
+/// Set the backend WebSearch queries (`search_engine`, `search_url`, `search_key`)
+pub fn configure_search(engine: SearchEngine, url: Option<String>, key: Option<String>) {
+    *search_config() = (engine, url, key);
+}
+
+/// HTTP/HTTPS GET fetch tool. HTML pages come back as their readable
+/// text unless `raw` asks for the markup.
+pub fn tool_http_fetch(url: &str, raw: bool) -> ToolResult {
+    if parse_http_url(url).is_none() {
+        return ToolResult::err("Invalid URL format. Use: http(s)://host[:port]/path");
+    }
+    let fetched = match fetch(url, &[], MAX_PAGE_SIZE) {
+        Ok(f) => f,
+        Err(e) => return ToolResult::err(&e),
     };
+    let body = match core::str::from_utf8(&fetched.body) {
+        Ok(text) => text,
+        // A body cut off by the size cap can end inside a character
+        Err(e) if e.error_len().is_none() => core::str::from_utf8(&fetched.body[..e.valid_up_to()]).unwrap_or(""),
+        Err(_) => return ToolResult::err("Response contains non-UTF8 data (binary content)"),
+    };
+    let redirected = if fetched.url != url { format!(", redirected to {}", fetched.url) } else { String::new() };
 
-    if parsed.is_https {
-        match libakuma_tls::https_fetch(url, true, Some(MAX_FETCH_SIZE)) {
-            Ok(body) => {
-                match core::str::from_utf8(&body) {
-                    Ok(text) => {
-                        let truncated = if body.len() >= MAX_FETCH_SIZE { " (truncated)" } else { "" };
-                        ToolResult::ok(format!(
-                            "Fetched {} ({} bytes{}):
-```
-{}
-```",
-                            url, body.len(), truncated, text
-                        ))
-                    }
-                    Err(_) => ToolResult::err("Response contains non-UTF8 data (binary content)"),
-                }
-            }
-            Err(e) => ToolResult::err(&format!("HTTPS fetch failed: {:?}", e)),
-        }
-    } else {
-        let ip = match resolve(parsed.host) {
-            Ok(ip) => ip,
-            Err(_) => return ToolResult::err(&format!("DNS resolution failed for: {}", parsed.host)),
-        };
+    if !raw && is_html(fetched.content_type.as_deref(), body) {
+        let base = parse_http_url(&fetched.url);
+        let text = html::to_text(body, |href| match &base {
+            Some(base) if !has_scheme(href) => resolve_location(base, href),
+            _ => String::from(href),
+        });
+        let (text, cut) = cap(&text, MAX_FETCH_SIZE);
+        let truncated = if cut || fetched.truncated { " (truncated)" } else { "" };
+        return ToolResult::ok(format!(
+            "Fetched {} (HTML page as text, {} bytes{}{}; \"raw\": \"true\" returns the markup):\n\n{}",
+            url, text.len(), truncated, redirected, text
+        ));
+    }
 
-        let addr_str = format!("{}.{}.{}.{}:{}", ip[0], ip[1], ip[2], ip[3], parsed.port);
-        let stream = match TcpStream::connect(&addr_str) {
-            Ok(s) => s,
-            Err(_) => return ToolResult::err(&format!("Connection failed to: {}", addr_str)),
-        };
+    let (text, cut) = cap(body, MAX_FETCH_SIZE);
+    let truncated = if cut || fetched.truncated { " (truncated)" } else { "" };
+    ToolResult::ok(format!(
+        "Fetched {} ({} bytes{}{}):\n```\n{}\n```",
+        url, text.len(), truncated, redirected, text
+    ))
+}
 
-        let request = format!(
-            "GET {} HTTP/1.0
+/// Whether a response is a web page: by its Content-Type, or by its first
+/// bytes when there is none (HTTPS responses)
+fn is_html(content_type: Option<&str>, body: &str) -> bool {
+    if let Some(ct) = content_type {
+        return ct.to_ascii_lowercase().contains("html");
+    }
+    let head = body.trim_start().get(..256).unwrap_or(body.trim_start()).to_ascii_lowercase();
+    head.starts_with("<!doctype html") || head.starts_with("<html") || head.contains("<head>")
+}
 
-             Host: {}
+/// A link with its own scheme (`https:`, `mailto:`), left as it is
+fn has_scheme(href: &str) -> bool {
+    href.split_once(':').is_some_and(|(scheme, _)| !scheme.is_empty() && scheme.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'-' || b == b'.'))
+}
 
-             User-Agent: meow/1.0 (Akuma)
+/// `text` cut to at most `max` bytes on a character boundary, and whether it was cut
+fn cap(text: &str, max: usize) -> (&str, bool) {
+    if text.len() <= max {
+        return (text, false);
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) { end -= 1; }
+    (&text[..end], true)
+}
 
-             Connection: close
+/// The body of a successful GET and where it finally came from
+struct Fetched {
+    url: String,
+    /// Content-Type, when the response headers were seen (plain HTTP)
+    content_type: Option<String>,
+    body: Vec<u8>,
+    truncated: bool,
+}
 
-             
-",
-            parsed.path,
-            parsed.host
-        );
+/// GET `url`, following redirects; the body of the final 2xx reply, read up
+/// to `max` bytes
+fn fetch(url: &str, headers: &[(&str, &str)], max: usize) -> Result<Fetched, String> {
+    let mut current = String::from(url);
+    for _ in 0..=MAX_REDIRECTS {
+        let parsed = parse_http_url(&current).ok_or_else(|| format!("Invalid URL: {}", current))?;
+        super::progress(&format!("fetching {}", parsed.host), None);
 
-        if stream.write_all(request.as_bytes()).is_err() {
-            return ToolResult::err("Failed to send HTTP request");
+        if parsed.is_https {
+            // The TLS client does its own framing and hands back only the body
+            let (body, truncated) = if headers.is_empty() {
+                let body = libakuma_tls::https_fetch(&current, true, Some(max))
+                    .map_err(|e| format!("HTTPS fetch failed: {:?}", e))?;
+                let truncated = body.len() >= max;
+                (body, truncated)
+            } else {
+                let mut h = HttpHeaders::new();
+                for (name, value) in headers {
+                    h.add(name, value);
+                }
+                // https_get takes no limit, so the cap is applied to what it returns
+                let mut body = libakuma_tls::https_get(&current, &h).map_err(|e| format!("HTTPS fetch failed: {:?}", e))?;
+                let truncated = body.len() > max;
+                body.truncate(max);
+                (body, truncated)
+            };
+            check_encoding(None, &body)?;
+            return Ok(Fetched { url: current, content_type: None, body, truncated });
         }
 
-        let mut response = Vec::new();
-        let mut buf = [0u8; 1024];
+        let response = get_plain(&parsed, headers, max)?;
+        if (300..400).contains(&response.status) {
+            let location = header_value(&response.head, "location")
+                .ok_or_else(|| format!("HTTP {} redirect without a Location header", response.status))?;
+            let next = resolve_location(&parsed, location);
+            current = next;
+            continue;
+        }
+        if !(200..300).contains(&response.status) {
+            return Err(format!("HTTP error: status {}", response.status));
+        }
+        let chunked = header_value(&response.head, "transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"));
+        let body = if chunked { decode_chunked(&response.body) } else { response.body };
+        check_encoding(header_value(&response.head, "content-encoding"), &body)?;
+        let content_type = header_value(&response.head, "content-type").map(String::from);
+        return Ok(Fetched { url: current, content_type, body, truncated: response.truncated });
+    }
+    Err(format!("Too many redirects (more than {}), last to {}", MAX_REDIRECTS, current))
+}
 
-        loop {
-            match stream.read(&mut buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    if response.len() + n > MAX_FETCH_SIZE {
-                        let remaining = MAX_FETCH_SIZE - response.len();
-                        response.extend_from_slice(&buf[..remaining]);
-                        break;
-                    }
-                    response.extend_from_slice(&buf[..n]);
-                }
-                Err(e) => {
-                    if e.kind == libakuma::net::ErrorKind::WouldBlock {
-                        libakuma::sleep_ms(1);
-                        continue;
-                    }
+struct PlainResponse {
+    status: u16,
+    /// Status line and headers
+    head: String,
+    /// The body as sent, still chunked if it was
+    body: Vec<u8>,
+    truncated: bool,
+}
+
+/// Plain HTTP GET, read up to `max` bytes of body
+fn get_plain(parsed: &ParsedUrl, headers: &[(&str, &str)], max: usize) -> Result<PlainResponse, String> {
+    let ip = resolve(parsed.host).map_err(|_| format!("DNS resolution failed for: {}", parsed.host))?;
+    let addr_str = format!("{}.{}.{}.{}:{}", ip[0], ip[1], ip[2], ip[3], parsed.port);
+    let stream = TcpStream::connect(&addr_str).map_err(|_| format!("Connection failed to: {}", addr_str))?;
+
+    let host = if parsed.port == 80 { String::from(parsed.host) } else { format!("{}:{}", parsed.host, parsed.port) };
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: meow/1.0 (Akuma)\r\nAccept-Encoding: identity\r\n",
+        parsed.path, host
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("Connection: close\r\n\r\n");
+    if stream.write_all(request.as_bytes()).is_err() {
+        return Err(String::from("Failed to send HTTP request"));
+    }
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    let mut truncated = false;
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                response.extend_from_slice(&buf[..n]);
+                super::progress(&format!("fetching {} ({} KB)", parsed.host, response.len() / 1024), None);
+                // Headers are not counted towards the cap
+                let body_len = find_headers_end(&response).map_or(0, |end| response.len() - end);
+                if body_len >= max {
+                    response.truncate(response.len() - (body_len - max));
+                    truncated = true;
                     break;
                 }
             }
+            Err(e) => {
+                if e.kind == libakuma::net::ErrorKind::WouldBlock {
+                    libakuma::sleep_ms(1);
+                    continue;
+                }
+                break;
+            }
+        }
+    }
+
+    if response.is_empty() {
+        return Err(String::from("Empty response from server"));
+    }
+    let (status, body) = parse_http_response(&response).ok_or("Failed to parse HTTP response")?;
+    let head = String::from_utf8_lossy(&response[..response.len() - body.len()]).into_owned();
+    Ok(PlainResponse { status, head, body: body.to_vec(), truncated })
+}
+
+/// Value of header `name` (any case) in a response head
+fn header_value<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) { Some(value.trim()) } else { None }
+    })
+}
+
+/// Where a `Location` header points, made absolute against `base`
+fn resolve_location(base: &ParsedUrl, location: &str) -> String {
+    let scheme = if base.is_https { "https" } else { "http" };
+    if location.starts_with("http://") || location.starts_with("https://") {
+        String::from(location)
+    } else if let Some(rest) = location.strip_prefix("//") {
+        format!("{}://{}", scheme, rest)
+    } else {
+        let default_port = if base.is_https { 443 } else { 80 };
+        let origin = if base.port == default_port {
+            format!("{}://{}", scheme, base.host)
+        } else {
+            format!("{}://{}:{}", scheme, base.host, base.port)
+        };
+        if location.starts_with('/') {
+            format!("{}{}", origin, location)
+        } else {
+            // Relative to the directory of the current path
+            let path = base.path.split(['?', '#']).next().unwrap_or("/");
+            let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+            let dir = if dir.is_empty() { "/" } else { dir };
+            format!("{}{}{}", origin, dir, location)
+        }
+    }
+}
+
+/// Join the chunks of a `Transfer-Encoding: chunked` body. A body cut short
+/// (by the size cap) gives what arrived before the cut.
+fn decode_chunked(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        let Some(line_end) = data[pos..].windows(2).position(|w| w == b"\r\n") else { break };
+        let line = core::str::from_utf8(&data[pos..pos + line_end]).unwrap_or("");
+        // Chunk extensions after ';' are ignored
+        let Ok(size) = usize::from_str_radix(line.split(';').next().unwrap_or("").trim(), 16) else { break };
+        if size == 0 {
+            break;
         }
+        let start = pos + line_end + 2;
+        let end = core::cmp::min(start + size, data.len());
+        out.extend_from_slice(&data[start..end]);
+        pos = end + 2;
+    }
+    out
+}
+
+/// Refuse compressed bodies: meow has no decompressor, and the text would
+/// be garbage
+fn check_encoding(content_encoding: Option<&str>, body: &[u8]) -> Result<(), String> {
+    let encoding = content_encoding.map(|e| e.trim().to_ascii_lowercase()).unwrap_or_default();
+    if body.starts_with(&[0x1f, 0x8b]) || encoding.contains("gzip") {
+        return Err(String::from("Response is gzip-compressed, which HttpFetch can't decode; try another URL for the same content (a raw or plain-text view)"));
+    }
+    if !encoding.is_empty() && encoding != "identity" {
+        return Err(format!("Response uses Content-Encoding: {}, which HttpFetch can't decode", encoding));
+    }
+    Ok(())
+}
 
-        if response.is_empty() {
-            return ToolResult::err("Empty response from server");
+/// Web search: titles, URLs and snippets from the configured engine
+pub fn tool_web_search(query: &str, count: Option<usize>) -> ToolResult {
+    let query = query.trim();
+    if query.is_empty() {
+        return ToolResult::err("WebSearch requires a non-empty 'query'");
+    }
+    let count = count.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, MAX_SEARCH_RESULTS);
+    let (engine, url, key) = search_config().clone();
+    let Some(base) = url.as_deref().or(engine.default_url()) else {
+        return ToolResult::err("WebSearch with search_engine=searxng needs search_url=http(s)://your-instance/search in the config");
+    };
+    if engine == SearchEngine::Brave && key.is_none() {
+        return ToolResult::err("WebSearch with search_engine=brave needs search_key=YOUR_TOKEN in the config");
+    }
+
+    let sep = if base.contains('?') { '&' } else { '?' };
+    let q = url_encode(query);
+    let request_url = match engine {
+        SearchEngine::DuckDuckGo => format!("{}{}q={}&format=json&no_html=1&skip_disambig=1", base, sep, q),
+        SearchEngine::SearXng => format!("{}{}q={}&format=json", base, sep, q),
+        SearchEngine::Brave => format!("{}{}q={}&count={}", base, sep, q, count),
+    };
+    let mut headers: Vec<(&str, &str)> = alloc::vec![("Accept", "application/json")];
+    if let (SearchEngine::Brave, Some(k)) = (engine, key.as_deref()) {
+        headers.push(("X-Subscription-Token", k));
+    }
+
+    super::progress(&format!("searching {} for '{}'", engine.as_str(), query), None);
+    let body = match fetch(&request_url, &headers, MAX_SEARCH_SIZE) {
+        Ok(f) => f.body,
+        Err(e) => return ToolResult::err(&format!("WebSearch ({}) failed: {}", engine.as_str(), e)),
+    };
+    let json = String::from_utf8_lossy(&body);
+
+    let results = match engine {
+        SearchEngine::DuckDuckGo => ddg_results(&json),
+        SearchEngine::SearXng => parse_results(&json, "results", "title", "url", "content"),
+        SearchEngine::Brave => {
+            let web = json.find("\"web\"").map_or("", |i| &json[i..]);
+            parse_results(web, "results", "title", "url", "description")
+        }
+    };
+    if results.is_empty() {
+        if engine == SearchEngine::DuckDuckGo {
+            return ToolResult::ok(format!(
+                "No results for '{}' (duckduckgo only answers well-known topics; set search_engine=searxng or brave for full web search)",
+                query
+            ));
+        }
+        return ToolResult::ok(format!("No results for '{}' ({})", query, engine.as_str()));
+    }
+
+    let mut out = format!("Search results for '{}' ({}):\n", query, engine.as_str());
+    for (i, r) in results.iter().take(count).enumerate() {
+        out.push_str(&format!("\n{}. {}\n   {}\n", i + 1, r.title, r.url));
+        if !r.snippet.is_empty() {
+            out.push_str(&format!("   {}\n", r.snippet));
         }
+    }
+    out.push_str("\nUse HttpFetch on a URL to read the page.");
+    ToolResult::ok(out)
+}
+
+struct SearchResult {
+    title: String,
+    url: String,
+    snippet: String,
+}
 
-        let (status, body) = match parse_http_response(&response) {
-            Some(r) => r,
-            None => return ToolResult::err("Failed to parse HTTP response"),
+/// Results from the objects in the `array` list of `json`
+fn parse_results(json: &str, array: &str, title: &str, url: &str, snippet: &str) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+    for obj in array_objects(json, array) {
+        let obj = shallow(obj);
+        let (Some(t), Some(u)) = (extract_string_field(&obj, title), extract_string_field(&obj, url)) else { continue };
+        results.push(SearchResult {
+            title: clean(&t),
+            url: u,
+            snippet: clean(&extract_string_field(&obj, snippet).unwrap_or_default()),
+        });
+    }
+    results
+}
+
+/// DuckDuckGo's answer: the abstract, if any, then the related topics
+fn ddg_results(json: &str) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+    let top = shallow(json);
+    if let Some(url) = extract_string_field(&top, "AbstractURL").filter(|u| !u.is_empty()) {
+        results.push(SearchResult {
+            title: clean(&extract_string_field(&top, "Heading").unwrap_or_default()),
+            url,
+            snippet: clean(&extract_string_field(&top, "AbstractText").unwrap_or_default()),
+        });
+    }
+    for topic in parse_results(json, "RelatedTopics", "Text", "FirstURL", "Text") {
+        // The topic text is "Title - description"
+        let (title, snippet) = match topic.title.split_once(" - ") {
+            Some((t, s)) => (String::from(t), String::from(s)),
+            None => (topic.title.clone(), String::new()),
         };
+        results.push(SearchResult { title, url: topic.url, snippet });
+    }
+    results
+}
 
-        if status < 200 || status >= 300 {
-            return ToolResult::err(&format!("HTTP error: status {}", status));
+/// The objects directly inside the array named `key`
+fn array_objects<'a>(json: &'a str, key: &str) -> Vec<&'a str> {
+    let mut objects = Vec::new();
+    let Some(start) = json.find(&format!("\"{}\"", key)) else { return objects };
+    let Some(open) = json[start..].find('[') else { return objects };
+    let bytes = json.as_bytes();
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    let mut obj_start = 0;
+    for i in start + open + 1..bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                if depth == 0 && b == b'{' { obj_start = i; }
+                depth += 1;
+            }
+            b'}' | b']' => {
+                if depth == 0 { break; }
+                depth -= 1;
+                if depth == 0 && b == b'}' { objects.push(&json[obj_start..=i]); }
+            }
+            _ => {}
         }
+    }
+    objects
+}
 
-        match core::str::from_utf8(body) {
-            Ok(text) => {
-                let truncated = if response.len() >= MAX_FETCH_SIZE { " (truncated)" } else { "" };
-                ToolResult::ok(format!(
-                    "Fetched {} ({} bytes{}):
-```
-{}
-```",
-                    url, body.len(), truncated, text
-                ))
+/// `obj` without its nested objects and arrays, so a field lookup only sees
+/// its own fields
+fn shallow(obj: &str) -> String {
+    let mut out = String::with_capacity(obj.len());
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for c in obj.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
             }
-            Err(_) => ToolResult::err("Response contains non-UTF8 data (binary content)"),
+        } else {
+            match c {
+                '"' => in_string = true,
+                '{' | '[' => { depth += 1; if depth > 1 { continue; } }
+                '}' | ']' => { depth = depth.saturating_sub(1); if depth > 0 { continue; } }
+                _ => {}
+            }
+        }
+        if depth <= 1 {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Snippet text without markup, on one line and cut to length
+fn clean(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            c if c.is_whitespace() => if !out.ends_with(' ') && !out.is_empty() { out.push(' ') },
+            c => out.push(c),
         }
     }
+    let out = out.trim_end().replace("&amp;", "&").replace("&quot;", "\"").replace("&#39;", "'").replace("&lt;", "<").replace("&gt;", ">");
+    if out.chars().count() <= MAX_SNIPPET_CHARS {
+        return out;
+    }
+    let mut cut: String = out.chars().take(MAX_SNIPPET_CHARS).collect();
+    cut.push('…');
+    cut
+}
+
+/// Percent-encode a query string value
+fn url_encode(text: &str) -> String {
+    let mut out = String::new();
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b' ' => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
 }
 
 struct ParsedUrl<'a> {
@@ -166,9 +547,7 @@ fn parse_http_response(data: &[u8]) -> Option<(u16, &[u8])> {
 
 fn find_headers_end(data: &[u8]) -> Option<usize> {
     for i in 0..data.len().saturating_sub(3) {
-        if &data[i..i + 4] == b"
-
-" {
+        if &data[i..i + 4] == b"\r\n\r\n" {
             return Some(i + 4);
         }
     }
@@ -0,0 +1,131 @@
+//! Speculative FileRead prefetch (experimental, `prefetch_reads=true`)
+//!
+//! Models often say what they are about to read ("let me check
+//! src/config.rs") a while before the tool call itself has streamed in.
+//! This hook watches the streamed text for paths of files that exist and
+//! reads them between chunks, so when the FileRead call arrives its result
+//! is already there. It is a guess: a prefetched file that is never asked
+//! for is just dropped with the next request, and anything that changes
+//! files throws the cache away.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use libakuma::{close, fstat, open, open_flags};
+
+use crate::api::middleware::{Middleware, Request};
+use super::context::resolve_path;
+use super::mod_types::ToolResult;
+
+/// At most this many files read ahead per reply
+const MAX_PER_REPLY: usize = 4;
+/// Larger files are left for the real call
+const MAX_PREFETCH_SIZE: usize = 64 * 1024;
+/// Longest word still considered a path
+const MAX_PATH_LEN: usize = 200;
+
+struct Cache {
+    /// The path-like word being streamed in, across chunk boundaries
+    word: String,
+    /// Resolved paths already looked at this reply
+    seen: Vec<String>,
+    /// (resolved path, FileRead result)
+    reads: Vec<(String, ToolResult)>,
+}
+
+static mut CACHE: Cache = Cache { word: String::new(), seen: Vec::new(), reads: Vec::new() };
+
+fn cache() -> &'static mut Cache {
+    unsafe { &mut *core::ptr::addr_of_mut!(CACHE) }
+}
+
+pub struct Prefetch;
+
+impl Middleware for Prefetch {
+    fn name(&self) -> &'static str {
+        "prefetch"
+    }
+
+    fn on_request(&self, _req: &mut Request) {
+        clear();
+    }
+
+    fn on_chunk(&self, chunk: &mut String) {
+        let c = cache();
+        for ch in chunk.chars() {
+            if is_path_char(ch) {
+                if c.word.len() < MAX_PATH_LEN {
+                    c.word.push(ch);
+                }
+            } else if !c.word.is_empty() {
+                let word = core::mem::take(&mut c.word);
+                consider(&word);
+            }
+        }
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '.')
+}
+
+/// Looks like a file path: has a directory part or an extension
+fn looks_like_path(word: &str) -> bool {
+    if word.len() < 3 || word.starts_with("//") || word.contains("..") {
+        return false;
+    }
+    let name = word.rsplit('/').next().unwrap_or(word);
+    let has_ext = name.rsplit_once('.').is_some_and(|(stem, ext)| !stem.is_empty() && (1..=5).contains(&ext.len()) && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    !name.is_empty() && (has_ext || word.contains('/'))
+}
+
+fn consider(word: &str) {
+    // Sentence punctuation is not part of the path
+    let word = word.trim_end_matches(['.', '-']);
+    if !looks_like_path(word) {
+        return;
+    }
+    let c = cache();
+    if c.reads.len() >= MAX_PER_REPLY {
+        return;
+    }
+    let Some(resolved) = resolve_path(word) else { return };
+    if c.seen.contains(&resolved) {
+        return;
+    }
+    c.seen.push(resolved.clone());
+    if !small_file(&resolved) {
+        return;
+    }
+    let result = super::fs::tool_file_read(word);
+    if result.success {
+        c.reads.push((resolved, result));
+    }
+}
+
+/// Exists and is no bigger than `MAX_PREFETCH_SIZE`
+fn small_file(path: &str) -> bool {
+    let fd = open(path, open_flags::O_RDONLY);
+    if fd < 0 {
+        return false;
+    }
+    let ok = fstat(fd).is_ok_and(|s| (s.st_size as usize) <= MAX_PREFETCH_SIZE);
+    close(fd);
+    ok
+}
+
+/// The prefetched FileRead result for `filename`, if there is one
+pub fn take(filename: &str) -> Option<ToolResult> {
+    let resolved = resolve_path(filename)?;
+    let reads = &mut cache().reads;
+    let pos = reads.iter().position(|(p, _)| *p == resolved)?;
+    Some(reads.swap_remove(pos).1)
+}
+
+/// Forget everything read ahead, e.g. because a tool may have changed files
+pub fn clear() {
+    let c = cache();
+    c.word.clear();
+    c.seen.clear();
+    c.reads.clear();
+}
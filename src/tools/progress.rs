@@ -0,0 +1,55 @@
+//! Progress reporting for long-running tools
+//!
+//! A tool that may take a while calls `progress("cloning", Some(40))` as it
+//! goes. The front end decides what that looks like: the TUI registers a
+//! sink that puts it in the status pane, and without one a dot is printed
+//! per second of work so a plain terminal still shows the tool is alive.
+//! `execute_tool_command` calls `done` when the tool returns.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Receives (message, percent); an empty message means the tool finished
+pub type Sink = fn(&str, Option<u8>);
+
+/// Minimum time between two updates reaching the sink
+const UPDATE_INTERVAL_US: u64 = 100_000;
+/// Minimum time between two fallback dots
+const DOT_INTERVAL_US: u64 = 1_000_000;
+
+static mut SINK: Option<Sink> = None;
+static LAST_UPDATE_US: AtomicU64 = AtomicU64::new(0);
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn sink() -> Option<Sink> {
+    unsafe { *core::ptr::addr_of!(SINK) }
+}
+
+pub fn set_sink(sink: Option<Sink>) {
+    unsafe { *core::ptr::addr_of_mut!(SINK) = sink; }
+}
+
+/// Report what a tool is doing; `percent` is None when the total is unknown.
+/// Cheap enough to call on every read: updates are rate limited here.
+pub fn progress(msg: &str, percent: Option<u8>) {
+    let now = libakuma::uptime();
+    let interval = if sink().is_some() { UPDATE_INTERVAL_US } else { DOT_INTERVAL_US };
+    let first = !ACTIVE.swap(true, Ordering::Relaxed);
+    if !first && percent != Some(100) && now - LAST_UPDATE_US.load(Ordering::Relaxed) < interval {
+        return;
+    }
+    LAST_UPDATE_US.store(now, Ordering::Relaxed);
+    match sink() {
+        Some(f) => f(msg, percent.map(|p| p.min(100))),
+        None if !first => libakuma::print("."),
+        None => {}
+    }
+}
+
+/// The tool has returned; clear whatever progress it left behind
+pub fn done() {
+    if ACTIVE.swap(false, Ordering::Relaxed) {
+        if let Some(f) = sink() {
+            f("", None);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Tool registry
+//!
+//! One entry per tool the model can call: its category, a one-line usage,
+//! and what it needs to work. `/tools` is generated from this table, so a
+//! new tool only has to be added here (and to `run_tool`) to show up.
+
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use libakuma::{open, close, open_flags};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Category {
+    Fs,
+    Git,
+    Net,
+    Issues,
+    System,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [Category::Fs, Category::Git, Category::Net, Category::Issues, Category::System];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Category::Fs => "Files",
+            Category::Git => "Git (via scratch)",
+            Category::Net => "Network",
+            Category::Issues => "Issue tracker (chainlink)",
+            Category::System => "System",
+        }
+    }
+}
+
+pub struct ToolInfo {
+    pub name: &'static str,
+    pub category: Category,
+    /// Arguments and what the tool does, in one line
+    pub usage: &'static str,
+    /// Program that must be installed in /bin or /usr/bin
+    pub needs: Option<&'static str>,
+    /// Changes files in the sandbox (or the remote), so off while read-only
+    pub mutates: bool,
+}
+
+impl ToolInfo {
+    const fn mutating(self) -> Self {
+        ToolInfo { mutates: true, ..self }
+    }
+}
+
+const fn tool(name: &'static str, category: Category, usage: &'static str, needs: Option<&'static str>) -> ToolInfo {
+    ToolInfo { name, category, usage, needs, mutates: false }
+}
+
+/// Set while another meow instance holds the sandbox and `exclusive_sandbox` is on
+pub static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+const SCRATCH: Option<&str> = Some("scratch");
+const CHAINLINK: Option<&str> = Some("chainlink");
+
+pub const TOOLS: &[ToolInfo] = &[
+    tool("FileRead", Category::Fs, "{filename} - read a file", None),
+    tool("FileReadLines", Category::Fs, "{filename, start, end} - read a line range with numbers", None),
+    tool("FileWrite", Category::Fs, "{filename, content} - create or overwrite a file", None).mutating(),
+    tool("FileAppend", Category::Fs, "{filename, content} - append to a file", None).mutating(),
+    tool("FileEdit", Category::Fs, "{filename, old_text, new_text} - replace one unique match", None).mutating(),
+    tool("FileExists", Category::Fs, "{filename} - check whether a file exists", None),
+    tool("FileList", Category::Fs, "{path, offset?, limit?} - list a directory (compact when large)", None),
+    tool("FileTree", Category::Fs, "{path?, depth?} - recursive listing, skips .git, target and node_modules", None),
+    tool("FileDelete", Category::Fs, "{filename} - delete a file", None).mutating(),
+    tool("FolderCreate", Category::Fs, "{path} - create a directory", None).mutating(),
+    tool("FileCopy", Category::Fs, "{source, destination} - copy a file", None).mutating(),
+    tool("FileMove", Category::Fs, "{source, destination} - move a file", None).mutating(),
+    tool("FileRename", Category::Fs, "{source_filename, destination_filename} - rename a file", None).mutating(),
+    tool("CodeSearch", Category::Fs, "{pattern, path, context, glob?, extensions?, max_matches?, offset?} - search text files", None),
+    tool("Cd", Category::Fs, "{path} - change the working directory", None),
+    tool("Pwd", Category::Fs, "{} - print the working directory", None),
+    tool("GitClone", Category::Git, "{url} - clone a repository", SCRATCH).mutating(),
+    tool("GitFetch", Category::Git, "{} - fetch from the remote", SCRATCH).mutating(),
+    tool("GitPull", Category::Git, "{} - fetch and update", SCRATCH).mutating(),
+    tool("GitPush", Category::Git, "{} - push (never forced)", SCRATCH).mutating(),
+    tool("GitStatus", Category::Git, "{} - show HEAD and branch", SCRATCH),
+    tool("GitBranch", Category::Git, "{name?, delete?} - list, create or delete branches", SCRATCH).mutating(),
+    tool("GitAdd", Category::Git, "{path} - stage a path", SCRATCH).mutating(),
+    tool("GitCommit", Category::Git, "{message, amend?} - commit staged changes", SCRATCH).mutating(),
+    tool("GitCheckout", Category::Git, "{branch} - switch branches", SCRATCH).mutating(),
+    tool("GitConfig", Category::Git, "{key, value?} - get or set a config value", SCRATCH).mutating(),
+    tool("GitLog", Category::Git, "{count?, oneline?} - commit history", SCRATCH),
+    tool("GitTag", Category::Git, "{name?, delete?} - list, create or delete tags", SCRATCH).mutating(),
+    tool("GitReset", Category::Git, "{} - unstage everything", SCRATCH).mutating(),
+    tool("GitDiff", Category::Git, "{staged?, range?} - unified diff", SCRATCH),
+    tool("HttpFetch", Category::Net, "{url, raw?} - GET an http(s) URL (64KB max); web pages come back as text", None),
+    tool("WebSearch", Category::Net, "{query, count?} - search the web: titles, URLs and snippets; the default duckduckgo engine only has instant answers for well-known topics and finds nothing for most queries", None),
+    tool("ChainlinkInit", Category::Issues, "{} - create the issue database", CHAINLINK).mutating(),
+    tool("ChainlinkCreate", Category::Issues, "{title, description?, priority?} - open an issue", CHAINLINK).mutating(),
+    tool("ChainlinkList", Category::Issues, "{status?} - list issues", CHAINLINK),
+    tool("ChainlinkShow", Category::Issues, "{id} - show an issue", CHAINLINK),
+    tool("ChainlinkClose", Category::Issues, "{id} - close an issue", CHAINLINK).mutating(),
+    tool("ChainlinkReopen", Category::Issues, "{id} - reopen an issue", CHAINLINK).mutating(),
+    tool("ChainlinkComment", Category::Issues, "{id, text} - comment on an issue", CHAINLINK).mutating(),
+    tool("ChainlinkLabel", Category::Issues, "{id, label} - label an issue", CHAINLINK).mutating(),
+    tool("Shell", Category::System, "{cmd} - run a program", None).mutating(),
+    tool("CompactContext", Category::System, "{summary} - replace the history with a summary", None),
+    tool("AskUser", Category::System, "{question} - ask the user and wait for the answer", None),
+];
+
+fn has_binary(name: &str) -> bool {
+    ["/bin/", "/usr/bin/"].iter().any(|dir| {
+        let fd = open(&format!("{}{}", dir, name), open_flags::O_RDONLY);
+        if fd >= 0 {
+            close(fd);
+        }
+        fd >= 0
+    })
+}
+
+pub fn find(name: &str) -> Option<&'static ToolInfo> {
+    TOOLS.iter().find(|t| t.name == name)
+}
+
+/// Why a tool can't be used right now, None if it can
+pub fn disabled_reason(tool: &ToolInfo) -> Option<String> {
+    if tool.mutates && READ_ONLY.load(Ordering::Relaxed) {
+        return Some(String::from("read-only, another meow instance holds this sandbox"));
+    }
+    if let Some(bin) = tool.needs {
+        if !has_binary(bin) {
+            return Some(format!("{} not installed", bin));
+        }
+    }
+    None
+}
+
+/// The /tools listing
+pub fn describe() -> String {
+    let mut out = String::from("# Tools\n");
+    let mut disabled = 0;
+    for cat in Category::ALL {
+        out.push_str(&format!("\n**{}**\n", cat.title()));
+        for t in TOOLS.iter().filter(|t| t.category == cat) {
+            match disabled_reason(t) {
+                Some(why) => {
+                    disabled += 1;
+                    out.push_str(&format!("* `{}` {} [disabled: {}]\n", t.name, t.usage, why));
+                }
+                None => out.push_str(&format!("* `{}` {}\n", t.name, t.usage)),
+            }
+        }
+    }
+    let custom = super::custom::all();
+    if !custom.is_empty() {
+        out.push_str("\n**Custom (from the config)**\n");
+        for t in custom {
+            out.push_str(&format!("* `{}` {}\n", t.name, super::custom::usage(t)));
+        }
+    }
+    out.push_str(&format!("\n{} tools, {} disabled\n", TOOLS.len() + custom.len(), disabled));
+    out
+}
+
+/// Arguments the tools read as numbers; everything else is a string
+const NUMERIC_ARGS: &[&str] = &["start", "end", "context", "count", "id", "depth", "max_results", "offset", "limit", "max_matches"];
+
+/// The usable tools as a JSON `tools` array for providers with native tool
+/// calling (`native_tools=true`). Parameters come from the usage line:
+/// `{path, count?}` makes `path` required and `count` optional.
+pub fn schema() -> String {
+    let mut out = String::from("[");
+    let builtin = TOOLS.iter().filter(|t| disabled_reason(t).is_none()).map(|t| (t.name, String::from(t.usage)));
+    let custom = super::custom::all().iter().map(|t| (t.name.as_str(), super::custom::usage(t)));
+    for (name, usage) in builtin.chain(custom) {
+        let (args, what) = usage.split_once("} - ").unwrap_or(("", usage.as_str()));
+        let mut properties = String::new();
+        let mut required = String::new();
+        for arg in args.trim_start_matches('{').split(',').map(str::trim).filter(|a| !a.is_empty()) {
+            let name = arg.trim_end_matches('?');
+            let ty = if NUMERIC_ARGS.contains(&name) { "integer" } else { "string" };
+            if !properties.is_empty() { properties.push(','); }
+            properties.push_str(&format!("\"{}\":{{\"type\":\"{}\"}}", name, ty));
+            if !arg.ends_with('?') {
+                if !required.is_empty() { required.push(','); }
+                required.push_str(&format!("\"{}\"", name));
+            }
+        }
+        if out.len() > 1 { out.push(','); }
+        out.push_str(&format!(
+            "{{\"type\":\"function\",\"function\":{{\"name\":\"{}\",\"description\":\"{}\",\"parameters\":{{\"type\":\"object\",\"properties\":{{{}}},\"required\":[{}]}}}}}}",
+            name, what, properties, required
+        ));
+    }
+    out.push(']');
+    out
+}
@@ -1,19 +1,136 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::format;
+use core::sync::atomic::{AtomicBool, Ordering};
 use libakuma::{spawn, waitpid, read_fd, close, open, open_flags};
 
 use crate::config::TOOL_BUFFER_SIZE;
+use super::context::{get_sandbox_root, is_protected, normalize_path, resolve_path, VIRTUAL_PATHS};
 use super::mod_types::ToolResult;
 
 const EAGAIN_ERRNO: i64 = -11; // Value of EAGAIN from libc_errno
 
+/// Re-root out-of-sandbox absolute paths in Shell arguments instead of
+/// rejecting the command (`rewrite_shell_paths` in the config)
+pub static REWRITE_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Absolute paths any sandboxed command may still name
+const ALWAYS_ALLOWED: &[&str] = &["/dev/null"];
+
+/// Captured result of a spawned command
+pub struct CommandOutput {
+    /// Everything the command printed. libakuma's `spawn` hands back a single
+    /// output pipe, so there is no separate stderr to capture.
+    pub stdout: String,
+    pub exit_code: i32,
+}
+
 pub fn tool_shell(command: &str) -> ToolResult {
-    // Parse the command to get the binary and arguments
+    tool_shell_labeled(command, None)
+}
+
+/// `tool_shell` with the progress shown while it runs, e.g. "cloning"
+/// (defaults to "running <program>")
+pub fn tool_shell_labeled(command: &str, label: Option<&str>) -> ToolResult {
+    let mut tokens = tokenize_command(command);
+    if let Err(e) = sandbox_args(&mut tokens) {
+        return ToolResult::err(&e);
+    }
+    let captured = match run_tokens(&tokens, label) {
+        Ok(c) => c,
+        Err(e) => return ToolResult::err(&e),
+    };
+
+    let mut result_str = String::new();
+    if !captured.stdout.is_empty() {
+        result_str.push_str("stdout:\n```\n");
+        result_str.push_str(&captured.stdout);
+        result_str.push_str("```\n");
+        result_str.push_str(&format!("Exit code: {}", captured.exit_code));
+    } else {
+        result_str.push_str(&format!("(No output)\nExit code: {}", captured.exit_code));
+    }
+
+    if captured.exit_code == 0 {
+        ToolResult::ok(result_str)
+    } else {
+        ToolResult {
+            success: false,
+            output: result_str,
+        }
+    }
+}
+
+/// Keep a model-issued command's arguments inside the sandbox. Absolute
+/// paths (also as `--opt=/path`) and `..` paths that leave the sandbox root
+/// are rejected, or with REWRITE_PATHS absolute ones are re-rooted so
+/// `/etc/hosts` becomes `<root>/etc/hosts`. In virtual path mode absolute
+/// paths are always read relative to the root. The program itself is not
+/// checked since /bin and /usr/bin live outside every sandbox, and relative
+/// paths that stay inside are left alone. meow's own files are refused
+/// everywhere, even with the sandbox at `/`.
+fn sandbox_args(tokens: &mut [String]) -> Result<(), String> {
+    for token in tokens.iter().skip(1) {
+        let path = match token.split_once('=') {
+            Some((opt, value)) if opt.starts_with('-') => value,
+            _ => token.as_str(),
+        };
+        if !path.starts_with('-') && is_protected(path) {
+            return Err(format!("Access denied: '{}' is one of meow's own files (its binary, config or recovery data)", path));
+        }
+    }
+    let sandbox = get_sandbox_root();
+    if sandbox == "/" {
+        return Ok(());
+    }
+    let rewrite = REWRITE_PATHS.load(Ordering::Relaxed) || VIRTUAL_PATHS.load(Ordering::Relaxed);
+
+    for token in tokens.iter_mut().skip(1) {
+        let (prefix, path) = match token.split_once('=') {
+            Some((opt, value)) if opt.starts_with('-') => (&token[..opt.len() + 1], value),
+            _ => ("", token.as_str()),
+        };
+        let checked = path.starts_with('/') || path == ".." || path.starts_with("../") || path.contains("/../");
+        if !checked || ALWAYS_ALLOWED.contains(&path) {
+            continue;
+        }
+        if let Some(resolved) = resolve_path(path) {
+            // A virtual path comes back mapped onto the host
+            if path.starts_with('/') && resolved != normalize_path(path) {
+                *token = format!("{}{}", prefix, resolved);
+            }
+            continue;
+        }
+        if rewrite && path.starts_with('/') {
+            // Normalizing first keeps `..` from climbing back out
+            let rel = normalize_path(path);
+            *token = format!("{}{}{}", prefix, sandbox, if rel == "/" { "" } else { rel.as_str() });
+            continue;
+        }
+        return Err(format!("Access denied: '{}' is outside the sandbox '{}'", path, sandbox));
+    }
+    Ok(())
+}
+
+/// Spawn a command and capture its output and exit code
+pub fn run_command(command: &str) -> Result<CommandOutput, String> {
     // Simple tokenizer: split on whitespace, respecting quotes
-    let tokens = tokenize_command(command);
+    run_tokens(&tokenize_command(command), None)
+}
+
+/// Spawn `program` with `args` as given, no tokenizing or sandbox checks;
+/// for meow's own commands, not the model's
+pub fn run_program(program: &str, args: &[&str]) -> Result<CommandOutput, String> {
+    let mut tokens = alloc::vec![String::from(program)];
+    tokens.extend(args.iter().map(|a| String::from(*a)));
+    run_tokens(&tokens, None)
+}
+
+/// Spawn an already tokenized command (program first) and capture its
+/// output. Commands still running after a second report progress.
+fn run_tokens(tokens: &[String], label: Option<&str>) -> Result<CommandOutput, String> {
     if tokens.is_empty() {
-        return ToolResult::err("Empty command");
+        return Err(String::from("Empty command"));
     }
 
     let binary = &tokens[0];
@@ -48,13 +165,14 @@ pub fn tool_shell(command: &str) -> ToolResult {
     // Spawn the process
     let result = match spawn(&binary_path, Some(&args[..])) {
         Some(r) => r,
-        None => return ToolResult::err(&format!("Failed to spawn '{}' (not found?)", binary_path)),
+        None => return Err(format!("Failed to spawn '{}' (not found?)", binary_path)),
     };
 
     // Read output from child process
     let mut output = Vec::new();
     let mut buf = [0u8; TOOL_BUFFER_SIZE]; 
     let mut waited_ms = 0u32;
+    let started = libakuma::uptime();
     let max_wait_ms = 30000; // 30 seconds timeout
     let max_shell_output = 1024 * 1024; // 1MB absolute max for shell output to avoid OOM
 
@@ -65,7 +183,7 @@ pub fn tool_shell(command: &str) -> ToolResult {
             if output.len() + n as usize > max_shell_output {
                 let _ = libakuma::kill(result.pid); // Kill runaway process
                 close(result.stdout_fd as i32);
-                return ToolResult::err("Command produced too much output (exceeded 1MB limit)");
+                return Err(String::from("Command produced too much output (exceeded 1MB limit)"));
             }
             output.extend_from_slice(&buf[..n as usize]);
             waited_ms = 0; // Reset timeout if we're making progress
@@ -88,34 +206,36 @@ pub fn tool_shell(command: &str) -> ToolResult {
 
             let output_str = core::str::from_utf8(&output).unwrap_or("<binary output>");
 
-            let mut result_str = String::new();
-            if !output_str.is_empty() {
-                result_str.push_str("stdout:\n```\n");
-                result_str.push_str(output_str);
-                result_str.push_str("```\n");
-                result_str.push_str(&format!("Exit code: {}", exit_code));
-            } else {
-                result_str.push_str(&format!("(No output)\nExit code: {}", exit_code));
-            }
-
-            if exit_code == 0 {
-                return ToolResult::ok(result_str);
-            } else {
-                return ToolResult {
-                    success: false,
-                    output: result_str,
-                };
-            }
+            return Ok(CommandOutput {
+                stdout: String::from(output_str),
+                exit_code: exit_code as i32,
+            });
         }
         
         // If no data and process not exited, sleep briefly before next poll
         libakuma::sleep_ms(50);
         waited_ms += 50;
 
+        let secs = (libakuma::uptime() - started) / 1_000_000;
+        if secs > 0 {
+            let msg = match label {
+                Some(l) => format!("{} ({}s)", l, secs),
+                None => format!("running {} ({}s)", binary, secs),
+            };
+            super::progress(&msg, None);
+        }
+
+        // Esc, seen by the input poll behind the progress line
+        if crate::app::state::CANCELLED.load(Ordering::SeqCst) {
+            let _ = libakuma::kill(result.pid);
+            close(result.stdout_fd as i32);
+            return Err(String::from("Command cancelled"));
+        }
+
         if waited_ms >= max_wait_ms {
             let _ = libakuma::kill(result.pid);
             close(result.stdout_fd as i32);
-            return ToolResult::err("Command timed out after 30 seconds");
+            return Err(String::from("Command timed out after 30 seconds"));
         }
     }
 }
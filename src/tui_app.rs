@@ -1,10 +1,10 @@
 use alloc::string::String;
 use alloc::vec::Vec;
+use alloc::boxed::Box;
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use libakuma::{
-    get_terminal_attributes, set_terminal_attributes, 
-    set_cursor_position, clear_screen, poll_input_event, write as akuma_write, show_cursor, fd
+    set_cursor_position, clear_screen, poll_input_event, write as akuma_write, fd
 };
 
 use crate::config::{Provider, Config, COLOR_GRAY_BRIGHT, COLOR_YELLOW, COLOR_RESET, COLOR_BOLD, COLOR_VIOLET, COLOR_USER};
@@ -12,6 +12,13 @@ use crate::app::{self, Message, commands::CommandResult, calculate_history_token
 use crate::ui::tui::layout::{get_pane_layout, TERM_WIDTH, TERM_HEIGHT};
 use crate::ui::tui::input::{self, InputEvent, CURSOR_IDX};
 use crate::ui::tui::render;
+use crate::ui::tui::watchdog;
+use crate::ui::tui::preview;
+use crate::ui::tui::pager;
+use crate::ui::tui::tabs::{self, TabAction, TabSet};
+use crate::ui::tui::terminal::TerminalGuard;
+use crate::ui::tui::wrap::{self, Role};
+use crate::ui::output;
 
 pub static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
 pub static CANCELLED: AtomicBool = AtomicBool::new(false);
@@ -20,21 +27,26 @@ pub static CUR_ROW: core::sync::atomic::AtomicU16 = core::sync::atomic::AtomicU1
 
 struct TuiGuard;
 impl TuiGuard {
-    fn new() -> Self { state::TUI_ACTIVE.store(true, Ordering::SeqCst); TUI_ACTIVE.store(true, Ordering::SeqCst); Self }
+    fn new() -> Self {
+        state::TUI_ACTIVE.store(true, Ordering::SeqCst); TUI_ACTIVE.store(true, Ordering::SeqCst);
+        crate::tools::progress::set_sink(Some(tool_progress));
+        output::install(Box::new(output::TuiSink));
+        Self
+    }
 }
 impl Drop for TuiGuard {
-    fn drop(&mut self) { state::TUI_ACTIVE.store(false, Ordering::SeqCst); TUI_ACTIVE.store(false, Ordering::SeqCst); }
-}
-
-pub mod mode_flags {
-    pub const RAW_MODE_ENABLE: u64 = 0x01;
+    fn drop(&mut self) {
+        crate::tools::progress::set_sink(None);
+        output::install(Box::new(output::TerminalSink));
+        state::TUI_ACTIVE.store(false, Ordering::SeqCst); TUI_ACTIVE.store(false, Ordering::SeqCst);
+    }
 }
 
 pub fn tui_print(s: &str) { render::tui_print(s); }
 pub fn tui_print_assistant(s: &str) { render::tui_print_assistant(s); }
 pub fn tui_print_with_indent(s: &str, prefix: &str, indent: u16, color: Option<&str>) { render::tui_print_with_indent(s, prefix, indent, color); }
 pub fn tui_render_markdown(markdown: &str) {
-    tui_render_markdown_with_indent(markdown, 9, None); // Default to None for general markdown
+    tui_render_markdown_with_indent(markdown, wrap::indent(Role::Assistant), None); // Default to None for general markdown
 }
 pub fn tui_render_markdown_with_indent(markdown: &str, indent: u16, base_style: Option<&'static str>) {
     let renderer = crate::ui::tui::markdown::MarkdownRenderer::new(indent, "", base_style);
@@ -44,12 +56,26 @@ pub fn update_streaming_status(text: &str, dots: u8, time_ms: Option<u64>) {
     state::STREAMING.store(true, Ordering::SeqCst);
     get_pane_layout().update_status(text, dots, time_ms); 
 }
+/// Count streamed reply text for the status row's bytes, tokens and tok/s
+pub fn note_streamed(text: &str) { get_pane_layout().note_stream(text); }
 pub fn clear_streaming_status() { 
     state::STREAMING.store(false, Ordering::SeqCst);
     get_pane_layout().clear_status(); 
 }
 pub fn set_model_and_provider(model: &str, provider: &str) { state::set_model_and_provider(model, provider); }
 pub fn tui_is_cancelled() -> bool { state::CANCELLED.load(Ordering::SeqCst) }
+pub fn tui_is_soft_stopped() -> bool { state::SOFT_STOP.load(Ordering::SeqCst) }
+
+/// Block until the user submits a line (for AskUser mid-turn). None if cancelled.
+pub fn wait_for_user_line(current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<String> {
+    update_streaming_status("[MEOW] waiting for your answer", 0, None);
+    loop {
+        tui_handle_input(current_tokens, token_limit, mem_kb);
+        if tui_is_cancelled() { clear_streaming_status(); return None; }
+        if let Some(line) = state::pop_message() { clear_streaming_status(); return Some(line); }
+        libakuma::sleep_ms(20);
+    }
+}
 
 static mut STREAMING_RENDERER: Option<crate::ui::tui::stream::StreamingRenderer> = None;
 
@@ -57,7 +83,28 @@ pub fn start_streaming(indent: u16) {
     unsafe { *core::ptr::addr_of_mut!(STREAMING_RENDERER) = Some(crate::ui::tui::stream::StreamingRenderer::new(indent)); }
 }
 
+/// Low-bandwidth mode hands the renderer whole lines instead of single tokens,
+/// so wrapping and cursor moves happen once per line
+const LOW_BW_FLUSH_BYTES: usize = 256;
+static mut LINE_BUFFER: String = String::new();
+
 pub fn process_streaming_chunk(chunk: &str) {
+    if state::LOW_BANDWIDTH.load(Ordering::SeqCst) {
+        let pending = unsafe { &mut *core::ptr::addr_of_mut!(LINE_BUFFER) };
+        pending.push_str(chunk);
+        let cut = match pending.rfind('\n') {
+            Some(pos) => pos + 1,
+            None if pending.len() >= LOW_BW_FLUSH_BYTES => pending.len(),
+            None => return,
+        };
+        let ready: String = pending.drain(..cut).collect();
+        render_streaming_chunk(&ready);
+        return;
+    }
+    render_streaming_chunk(chunk);
+}
+
+fn render_streaming_chunk(chunk: &str) {
     unsafe {
         if let Some(r) = (*core::ptr::addr_of_mut!(STREAMING_RENDERER)).as_mut() {
             r.process_chunk(chunk);
@@ -68,6 +115,8 @@ pub fn process_streaming_chunk(chunk: &str) {
 }
 
 pub fn finish_streaming() {
+    let pending = unsafe { core::mem::take(&mut *core::ptr::addr_of_mut!(LINE_BUFFER)) };
+    if !pending.is_empty() { render_streaming_chunk(&pending); }
     unsafe {
         if let Some(mut r) = (*core::ptr::addr_of_mut!(STREAMING_RENDERER)).take() {
             r.finalize();
@@ -75,6 +124,42 @@ pub fn finish_streaming() {
     }
 }
 
+/// Enter under /more: `/send` submits, `/more` again drops the message, and
+/// any other line (blank ones too) is added to it and shown above the prompt
+fn compose(line: String) {
+    match line.trim() {
+        "/more" if state::composition_len().is_none() => {
+            state::start_composition();
+            compose_note("Composing: Enter adds a line, /send or Ctrl+D sends it all, /more drops it");
+        }
+        "/more" => {
+            state::take_composition();
+            compose_note("Composition dropped");
+        }
+        "/send" => send_composition(),
+        _ => {
+            render::tui_print_with_indent(&line, "  + ", wrap::indent(Role::User), Some(COLOR_USER));
+            render::tui_print_with_indent("\n", "", 0, None);
+            state::compose_line(line);
+        }
+    }
+}
+
+fn send_composition() {
+    let Some(msg) = state::take_composition() else { return };
+    if msg.trim().is_empty() {
+        compose_note("Nothing composed, nothing sent");
+        return;
+    }
+    state::add_to_history(&msg);
+    state::push_message(msg);
+}
+
+fn compose_note(text: &str) {
+    render::tui_print_with_indent(text, "     --- ", wrap::indent(Role::Tool), Some(COLOR_GRAY_BRIGHT));
+    render::tui_print_with_indent("\n", "", 0, None);
+}
+
 fn handle_input_event(event: InputEvent, input: &mut String, redraw: &mut bool, quit: &mut bool, exit_on_escape: bool) {
     let idx = CURSOR_IDX.load(Ordering::SeqCst) as usize;
     match event {
@@ -129,6 +214,25 @@ fn handle_input_event(event: InputEvent, input: &mut String, redraw: &mut bool,
         InputEvent::Home | InputEvent::CtrlA => { CURSOR_IDX.store(0, Ordering::SeqCst); *redraw = true; }
         InputEvent::End | InputEvent::CtrlE => { CURSOR_IDX.store(input.chars().count() as u16, Ordering::SeqCst); *redraw = true; }
         InputEvent::ShiftEnter => { input.insert(idx, '\n'); CURSOR_IDX.store((idx + 1) as u16, Ordering::SeqCst); *redraw = true; }
+        // A pasted snippet stays one multi-line entry; tabs become spaces the
+        // prompt can measure
+        InputEvent::PasteStart => input::PASTING.store(true, Ordering::SeqCst),
+        InputEvent::PasteEnd => { input::PASTING.store(false, Ordering::SeqCst); *redraw = true; }
+        InputEvent::Enter | InputEvent::ShiftEnter if input::PASTING.load(Ordering::SeqCst) => {
+            input.insert(idx, '\n'); CURSOR_IDX.store((idx + 1) as u16, Ordering::SeqCst); *redraw = true;
+        }
+        InputEvent::Tab if input::PASTING.load(Ordering::SeqCst) => {
+            input.insert_str(idx, "    "); CURSOR_IDX.store((idx + 4) as u16, Ordering::SeqCst); *redraw = true;
+        }
+        InputEvent::Enter if state::composition_len().is_some() || input.trim() == "/more" => {
+            compose(core::mem::take(input)); CURSOR_IDX.store(0, Ordering::SeqCst); *redraw = true;
+        }
+        InputEvent::CtrlD => {
+            if state::composition_len().is_some() {
+                if !input.is_empty() { state::compose_line(core::mem::take(input)); }
+                send_composition(); CURSOR_IDX.store(0, Ordering::SeqCst); *redraw = true;
+            }
+        }
         InputEvent::Enter => { if !input.is_empty() { state::add_to_history(input); state::push_message(input.clone()); input.clear(); CURSOR_IDX.store(0, Ordering::SeqCst); *redraw = true; } }
         InputEvent::CtrlU => { input.clear(); CURSOR_IDX.store(0, Ordering::SeqCst); *redraw = true; }
         InputEvent::CtrlW | InputEvent::AltLeft => {
@@ -153,18 +257,40 @@ fn handle_input_event(event: InputEvent, input: &mut String, redraw: &mut bool,
             let (nw, nh) = probe_terminal_size();
             TERM_WIDTH.store(nw, Ordering::SeqCst); TERM_HEIGHT.store(nh, Ordering::SeqCst);
             let layout = get_pane_layout(); layout.term_width = nw; layout.term_height = nh; layout.recalculate(layout.footer_height);
+            if layout.split_col > 0 { layout.split_col = nw * 3 / 5; } layout.footer_sig = 0;
             clear_screen(); render::print_greeting(); layout.set_scroll_region();
+            preview::render();
             let o_r = nh.saturating_sub(layout.footer_height + 1 + layout.gap());
             CUR_ROW.store(o_r, Ordering::SeqCst); CUR_COL.store(0, Ordering::SeqCst);
             layout.output_row = o_r; layout.output_col = 0; *redraw = true;
         }
+        // For this session only; `/animation` also saves it
+        InputEvent::CtrlN => { state::set_animation(!state::get_animation()); *redraw = true; }
+        InputEvent::CtrlS => { if state::STREAMING.load(Ordering::SeqCst) { state::SOFT_STOP.store(true, Ordering::SeqCst); } }
         InputEvent::Esc | InputEvent::Interrupt => { state::CANCELLED.store(true, Ordering::SeqCst); CANCELLED.store(true, Ordering::SeqCst); if exit_on_escape || event == InputEvent::Interrupt { *quit = true; } }
         _ => {}
     }
 }
 
+/// Footer numbers from the last input poll, for redraws from tool progress
+static mut FOOTER: (usize, usize, usize) = (0, 0, 0);
+
+/// Tool progress goes to the status pane; input is polled so Esc can stop a
+/// running shell command
+fn tool_progress(msg: &str, percent: Option<u8>) {
+    if msg.is_empty() { clear_streaming_status(); return; }
+    let text = match percent {
+        Some(p) => alloc::format!("[MEOW] {} {}%", msg, p),
+        None => alloc::format!("[MEOW] {}", msg),
+    };
+    update_streaming_status(&text, 0, None);
+    let (t, l, m) = unsafe { *core::ptr::addr_of!(FOOTER) };
+    tui_handle_input(t, l, m);
+}
+
 pub fn tui_handle_input(current_tokens: usize, token_limit: usize, mem_kb: usize) {
     if !TUI_ACTIVE.load(Ordering::SeqCst) { return; }
+    unsafe { *core::ptr::addr_of_mut!(FOOTER) = (current_tokens, token_limit, mem_kb); }
     let mut e_b = [0u8; 16];
     let b_r = poll_input_event(0, &mut e_b);
     let q = input::get_raw_input_queue();
@@ -189,6 +315,27 @@ pub fn tui_handle_input(current_tokens: usize, token_limit: usize, mem_kb: usize
     if redraw || state::STREAMING.load(Ordering::SeqCst) { render::render_footer(current_tokens, token_limit, mem_kb); }
 }
 
+/// "linked to Ollama 0.5.4 @ 10.0.2.2" under the greeting, once the probe answers
+fn show_link(line: &str) {
+    output::notification(COLOR_GRAY_BRIGHT, &alloc::format!("{}\n", line));
+}
+
+/// Redraw the conversation after something took over the screen (tab switch, overlay)
+pub fn restore_screen() {
+    redraw_output_pane();
+    preview::render();
+    tabs::replay_scrollback();
+}
+
+/// Clear the output pane and park the cursor at its bottom, as on startup
+fn redraw_output_pane() {
+    let layout = get_pane_layout();
+    clear_screen(); layout.set_scroll_region(); layout.footer_sig = 0;
+    let o_r = layout.term_height.saturating_sub(layout.footer_height + 1 + layout.gap());
+    CUR_ROW.store(o_r, Ordering::SeqCst); CUR_COL.store(0, Ordering::SeqCst);
+    layout.output_row = o_r; layout.output_col = 0;
+}
+
 fn probe_terminal_size() -> (u16, u16) {
     akuma_write(fd::STDOUT, b"\x1b[999;999H\x1b[6n");
     let mut buf = [0u8; 32];
@@ -211,36 +358,119 @@ fn probe_terminal_size() -> (u16, u16) {
 
 pub fn run_tui(model: &mut String, provider: &mut Provider, config: &mut Config, history: &mut Vec<Message>, context_window: usize, system_prompt: &str) -> Result<(), &'static str> {
     let _guard = TuiGuard::new();
-    let mut old_mode: u64 = 0;
-    get_terminal_attributes(fd::STDIN, &mut old_mode as *mut u64 as u64);
-    set_terminal_attributes(fd::STDIN, 0, mode_flags::RAW_MODE_ENABLE);
+    let term = TerminalGuard::new();
+    crate::crash::register_history(history);
 
     let (w, h) = probe_terminal_size();
     TERM_WIDTH.store(w, Ordering::SeqCst); TERM_HEIGHT.store(h, Ordering::SeqCst);
+    crate::ui::theme::probe();
     state::set_model_and_provider(model, &provider.name);
-    state::set_render_markdown(config.render_markdown);
+    let low_bw = state::LOW_BANDWIDTH.load(Ordering::SeqCst);
+    crate::ui::tui::banner::refresh_motd(config);
+    state::set_render_markdown(config.render_markdown && !low_bw);
+    state::set_animation(config.animation && !low_bw);
+    wrap::configure(config);
     
     let layout = get_pane_layout();
     layout.term_width = w; layout.term_height = h; layout.recalculate(4);
     
-    akuma_write(fd::STDOUT, b"\x1b[>1u\x1b[?1049h");
+    term.enter_screen();
     clear_screen();
     layout.set_scroll_region();
     render::print_greeting();
     use crate::ui::tui::layout::Stdout;
     use core::fmt::Write;
     let mut stdout = Stdout;
-    let _ = write!(stdout, "  {}TIP:{} Type {}/hotkeys{} to see input shortcuts nya~! ♪(=^･ω･^)ﾉ\n\n", COLOR_GRAY_BRIGHT, COLOR_RESET, COLOR_YELLOW, COLOR_RESET);
+    let tip_end = if state::PLAIN.load(Ordering::SeqCst) { "." } else { " nya~! ♪(=^･ω･^)ﾉ" };
+    let _ = write!(stdout, "  {}TIP:{} Type {}/hotkeys{} to see input shortcuts{}\n\n", COLOR_GRAY_BRIGHT, COLOR_RESET, COLOR_YELLOW, COLOR_RESET, tip_end);
+    // Fresh sessions start with the system prompt, the cwd context and the ack
+    let resumed = history.iter().filter(|m| m.role == "user").count().saturating_sub(1);
+    if resumed > 0 {
+        let _ = write!(stdout, "  {}Resumed the last session ({} messages, {} from you){}\n\n", COLOR_GRAY_BRIGHT, history.len(), resumed, COLOR_RESET);
+    }
+    if let Some(warning) = crate::instance::acquire(config.exclusive_sandbox) {
+        let _ = write!(stdout, "  {}[!] {}{}\n\n", COLOR_YELLOW, warning, COLOR_RESET);
+    }
 
     let o_r = h.saturating_sub(layout.footer_height + 1 + layout.gap());
     CUR_ROW.store(o_r, Ordering::SeqCst); CUR_COL.store(0, Ordering::SeqCst);
     layout.output_row = o_r; layout.output_col = 0;
 
+    let mut tab_set = TabSet::new();
+    // Owned so `/prompt reload` can regenerate it mid-session
+    let mut system_prompt = String::from(system_prompt);
+    let mut context_window = context_window;
+    // Model the context window (and warmup) was last set up for
+    let mut active_model = (String::new(), String::new());
+    let mut warming = false;
+    // The context-window lookup for `active_model` is still out
+    let mut sizing = false;
+    // A context window to set up on the next pass
+    let mut sized = None;
+    // Quick mode's persona, and the system prompt it was built alongside
+    let mut quick_persona = (String::new(), String::new());
+    // Provider the version probe last went to
+    let mut probed = String::new();
+    let mut outcome = Ok(());
+
     loop {
+        if probed != provider.name && !crate::replay::is_replaying() {
+            probed = provider.name.clone();
+            if let Some(line) = crate::api::version::start(provider) { show_link(&line); }
+        }
+        if let Some(line) = crate::api::version::poll() { show_link(&line); }
+        if active_model.0 != *model || active_model.1 != provider.name {
+            active_model = (model.clone(), provider.name.clone());
+            app::tokens::configure(provider);
+            // The default until the provider says otherwise; the lookup is
+            // polled below, after the footer is drawn
+            context_window = crate::config::DEFAULT_CONTEXT_WINDOW;
+            sizing = false;
+            if crate::replay::is_replaying() {
+                sized = Some(crate::replay::next_context().unwrap_or(context_window));
+            } else if crate::api::start_model_info(model, provider) {
+                sizing = true;
+            } else {
+                crate::replay::note_context(context_window);
+                sized = Some(context_window);
+            }
+        }
+        if let Some(window) = sized.take() {
+            context_window = window;
+            let was_compact = app::prompt::is_compact();
+            app::prompt::set_context_window(context_window);
+            if app::prompt::is_compact() != was_compact {
+                system_prompt = app::prompt::build_system_prompt(config);
+                match history.first_mut() {
+                    Some(first) if first.role == "system" => *first = Message::new("system", system_prompt.as_str()),
+                    _ => history.insert(0, Message::new("system", system_prompt.as_str())),
+                }
+                if app::prompt::is_compact() {
+                    let _ = write!(stdout, "  {}[!] {} has a {}-token context: using the compact prompt (short persona, one-line tool list){}\n\n", COLOR_YELLOW, model, context_window, COLOR_RESET);
+                }
+            }
+            if config.warmup && !crate::replay::is_replaying() {
+                warming = crate::api::warmup::start(model, provider, &system_prompt);
+                if warming { layout.update_status("[MEOW] warming neural link", 1, None); }
+            }
+        }
+        crate::instance::heartbeat();
+        if warming && !crate::api::warmup::poll() {
+            warming = false;
+            layout.clear_status();
+        }
+
         let c_t = calculate_history_tokens(history);
         let m_kb = libakuma::memory_usage() / 1024;
         state::set_last_history_kb(c_t / 1024);
         render::render_footer(c_t, context_window, m_kb);
+        if sizing {
+            if let Some(window) = crate::api::poll_model_info() {
+                crate::replay::note_context(window);
+                sizing = false;
+                sized = Some(window);
+            }
+        }
 
         let mut e_b = [0u8; 16];
         let b_r = poll_input_event(50, &mut e_b);
@@ -250,17 +480,38 @@ pub fn run_tui(model: &mut String, provider: &mut Provider, config: &mut Config,
         if !q.is_empty() {
             let mut inp = state::get_global_input();
             let (mut q_l, mut red) = (false, false);
+            let mut tab_action = None;
             while !q.is_empty() {
                 let mut t_b = [0u8; 16]; let n_c = core::cmp::min(q.len(), 16);
                 for i in 0..n_c { t_b[i] = q[i]; }
                 let (ev, n) = input::parse_input(&t_b[..n_c]);
                 if n == 0 { break; }
                 for _ in 0..n { q.pop_front(); }
+                match ev {
+                    InputEvent::CtrlT => { tab_action = Some(TabAction::New); break; }
+                    InputEvent::CtrlLeft => { tab_action = Some(TabAction::Prev); break; }
+                    InputEvent::CtrlRight => { tab_action = Some(TabAction::Next); break; }
+                    _ => {}
+                }
                 handle_input_event(ev, &mut inp, &mut red, &mut q_l, config.exit_on_escape);
-                if ev == InputEvent::Enter { break; }
+                if ev == InputEvent::Enter && !input::PASTING.load(Ordering::SeqCst) { break; }
             }
             if red { state::set_global_input(inp); }
             if q_l { break; }
+            if let Some(action) = tab_action {
+                if tab_set.apply(action, model, provider, history, &system_prompt) {
+                    restore_screen();
+                }
+                continue;
+            }
+        }
+
+        if let Some(stuck) = watchdog::check(input::get_raw_input_queue()) {
+            if let Err(e) = watchdog::recover(stuck) {
+                outcome = Err(e);
+                break;
+            }
+            continue;
         }
 
         if let Some(u_i) = state::pop_message() {
@@ -272,42 +523,90 @@ pub fn run_tui(model: &mut String, provider: &mut Provider, config: &mut Config,
             let _ = write!(color_buf, "{}{}", COLOR_VIOLET, COLOR_BOLD);
             tui_print_with_indent(" >  ", "", 0, Some(color_buf.as_str()));
             
-            if config.render_markdown {
-                tui_render_markdown_with_indent(&u_i, 4, Some(COLOR_USER)); // Pass user color here
+            if state::get_render_markdown() {
+                tui_render_markdown_with_indent(&u_i, wrap::indent(Role::User), Some(COLOR_USER)); // Pass user color here
             } else {
-                tui_print_with_indent(&u_i, "", 4, Some(COLOR_USER));
+                tui_print_with_indent(&u_i, "", wrap::indent(Role::User), Some(COLOR_USER));
             }
             tui_print("\n");
+            output::user_input(&u_i);
 
-            if u_i.starts_with('/') {
-                let (res, out) = app::commands::handle_command(&u_i, model, provider, config, history, system_prompt);
+            let turn = if u_i.starts_with('/') {
+                let (res, out) = app::commands::handle_command(&u_i, model, provider, config, history, &mut system_prompt);
                 if let Some(o) = out {
                     tui_print_with_indent("\n", "", 0, None);
-                    if config.render_markdown {
+                    if !pager::fits(&o) {
+                        // Too tall for the pane: read it in the pager, leave a note behind
+                        let title = u_i.split_whitespace().next().unwrap_or("output");
+                        pager::remember(title, &o);
+                        pager::show_last();
+                        restore_screen();
+                        tui_print_with_indent(&alloc::format!("～ {} output ({} lines) was shown in the pager; /page opens it again ～", title, o.lines().count()), "", wrap::indent(Role::Assistant), Some(COLOR_GRAY_BRIGHT));
+                    } else if state::get_render_markdown() {
                         tui_render_markdown(&o);
                     } else {
                         tui_print_assistant(&o);
                     }
                     tui_print_with_indent("\n\n", "", 0, None);
-                    history.push(Message::new("system", &o));
+                    if !matches!(res, CommandResult::Display) {
+                        history.push(Message::new("system", &o));
+                    }
+                }
+                match res {
+                    CommandResult::Quit => break,
+                    CommandResult::Send(prompt) => Some(prompt),
+                    CommandResult::Continue | CommandResult::Display => None,
                 }
-                if let CommandResult::Quit = res { break; }
             } else {
+                Some(u_i)
+            };
+            if let Some(u_i) = turn {
+                if sizing {
+                    // No answer yet: this turn runs with the default, and it
+                    // is recorded before the response so a replay matches
+                    crate::api::cancel_model_info();
+                    crate::replay::note_context(context_window);
+                    sizing = false;
+                    sized = Some(context_window);
+                }
+                // The chips answer the last reply; a failed turn leaves none
+                app::suggest::clear();
                 state::STREAMING.store(true, Ordering::SeqCst);
                 layout.update_status("[MEOW] jacking in", 1, None);
                 tui_print("\n\n");
-                let _ = app::chat::chat_once(model, provider, &u_i, history, Some(context_window), system_prompt);
-                state::STREAMING.store(false, Ordering::SeqCst); state::CANCELLED.store(false, Ordering::SeqCst);
+                if state::QUICK.load(Ordering::SeqCst) {
+                    if quick_persona.0 != system_prompt {
+                        quick_persona = (system_prompt.clone(), app::prompt::build_parts(config).persona);
+                    }
+                    let _ = app::chat::quick_once(model, provider, &u_i, history, &quick_persona.1);
+                } else {
+                    let ok = app::chat::chat_once(model, provider, &u_i, history, Some(context_window), &system_prompt).is_ok();
+                    if ok && config.suggestions {
+                        app::suggest::update(history);
+                    }
+                }
+                state::STREAMING.store(false, Ordering::SeqCst); state::CANCELLED.store(false, Ordering::SeqCst); state::SOFT_STOP.store(false, Ordering::SeqCst);
                 layout.clear_status();
                 let _ = write!(stdout, "{}\n", COLOR_RESET);
                 compact_history(history);
+                let _ = app::session::autosave(history);
             }
         }
     }
 
-    get_pane_layout().reset_scroll_region();
-    akuma_write(fd::STDOUT, b"\x1b[<u");
-    set_terminal_attributes(fd::STDIN, 0, old_mode);
-    clear_screen(); set_cursor_position(0, 0); show_cursor();
-    Ok(())
+    // The final save, of the conversation only: the summary below is shown
+    // and posted to a linked issue, never resumed as part of the session
+    let _ = app::session::autosave(history);
+    let summary = app::summary::report();
+    crate::crash::unregister_history();
+    preview::close_split();
+    drop(term);
+    // After leaving the alternate screen, so it stays on the terminal
+    if let Some(text) = summary {
+        libakuma::print(&text);
+        if let Some(id) = app::summary::post_to_linked_issue() {
+            libakuma::print(&alloc::format!("  (summary commented on chainlink issue #{})\n", id));
+        }
+    }
+    outcome
 }
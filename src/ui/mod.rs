@@ -1 +1,3 @@
 pub mod tui;
+pub mod output;
+pub mod theme;
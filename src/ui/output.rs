@@ -0,0 +1,343 @@
+//! Where chat output goes
+//!
+//! The chat loop and the API client print through an `OutputSink` instead of
+//! asking whether the TUI is up. `TerminalSink` writes to a plain terminal
+//! (the one-shot mode), `TuiSink` to the output pane and status line,
+//! `TranscriptSink` appends the conversation as plain text to a file, and
+//! `Tee` hands everything to several sinks. The TUI installs its sink for as
+//! long as it runs; with `transcript_file` set, every installed sink is teed
+//! into the transcript. A new front end only needs a sink of its own.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::Ordering;
+
+use libakuma::{open, close, read_fd, write_fd, open_flags};
+
+use crate::app::state;
+use crate::config::{COLOR_MEOW, COLOR_RESET};
+use crate::tui_app;
+use crate::ui::theme;
+use crate::ui::tui::layout::Stdout;
+use crate::ui::tui::markdown::MarkdownStream;
+use crate::ui::tui::pager;
+use crate::ui::tui::render;
+use crate::ui::tui::wrap::{self, Role};
+use crate::util::StackBuffer;
+
+pub trait OutputSink {
+    /// Connection progress while a request is set up ("[jacking in", dots,
+    /// retries, "] waiting"); the TUI shows its status line instead
+    fn progress(&mut self, text: &str);
+    /// The first token of a reply arrived `ttft_ms` after the request.
+    /// `erase` columns of progress are on the line (0 = leave it, add a space).
+    fn reply_start(&mut self, ttft_ms: u64, erase: u32);
+    fn reply_chunk(&mut self, text: &str);
+    /// The reply stream ended, completely or not
+    fn reply_end(&mut self);
+    /// Tool output and prompts, in `color` (COLOR_RESET for none)
+    fn message(&mut self, color: &str, text: &str);
+    /// A `     --- ` line: tool calls, timings, questions
+    fn notification(&mut self, color: &str, text: &str);
+    /// Text as is, without indent or color (blank lines between blocks)
+    fn text(&mut self, text: &str);
+    /// Tool output about to be printed, titled like "Shell output"; sinks
+    /// with a pager keep what doesn't fit the screen
+    fn long_text(&mut self, _title: &str, _text: &str) {}
+    /// Waiting on the network; redraw whatever shows the token counts
+    fn tick(&mut self, _current_tokens: usize, _token_limit: usize, _mem_kb: usize) {}
+    /// Read a line from the user mid-turn (AskUser, shell confirmation), with a
+    /// `> ` prompt if `echo`. None if cancelled or the sink has no input.
+    fn read_line(&mut self, _echo: bool, _current_tokens: usize, _token_limit: usize, _mem_kb: usize) -> Option<String> {
+        None
+    }
+    /// A line the user sent, for sinks that keep the conversation
+    fn user_input(&mut self, _line: &str) {}
+}
+
+/// A plain terminal: text goes to stdout with ANSI colors, input comes from stdin
+pub struct TerminalSink;
+
+impl OutputSink for TerminalSink {
+    fn progress(&mut self, text: &str) {
+        libakuma::print(text);
+    }
+
+    fn reply_start(&mut self, ttft_ms: u64, erase: u32) {
+        if erase == 0 {
+            libakuma::print(" ");
+        }
+        for _ in 0..erase {
+            libakuma::print("\x08 \x08");
+        }
+        print_elapsed(ttft_ms);
+        libakuma::print("\n");
+        if state::get_render_markdown() {
+            *terminal_markdown() = Some(MarkdownStream::new(wrap::indent(Role::Assistant), COLOR_MEOW));
+        }
+    }
+
+    fn reply_chunk(&mut self, text: &str) {
+        match terminal_markdown().as_mut() {
+            Some(md) => md.push(text),
+            None => render::tui_print_assistant(text),
+        }
+    }
+
+    fn reply_end(&mut self) {
+        if let Some(mut md) = terminal_markdown().take() {
+            md.finish();
+        }
+    }
+
+    fn message(&mut self, color: &str, text: &str) {
+        if color != COLOR_RESET { libakuma::print(&theme::adapt(color)); }
+        libakuma::print(text);
+        if color != COLOR_RESET { libakuma::print(COLOR_RESET); }
+    }
+
+    fn notification(&mut self, color: &str, text: &str) {
+        libakuma::print(&theme::adapt(color));
+        libakuma::print("     --- ");
+        libakuma::print(text);
+        libakuma::print(COLOR_RESET);
+    }
+
+    fn text(&mut self, text: &str) {
+        libakuma::print(text);
+    }
+
+    fn read_line(&mut self, echo: bool, _current_tokens: usize, _token_limit: usize, _mem_kb: usize) -> Option<String> {
+        if echo { libakuma::print("> "); }
+        let mut buf = [0u8; 1024];
+        let n = read_fd(0, &mut buf);
+        if n <= 0 { return None; }
+        Some(String::from(core::str::from_utf8(&buf[..n as usize]).unwrap_or("").trim()))
+    }
+}
+
+/// The terminal's markdown state for the reply being printed
+static mut TERMINAL_MARKDOWN: Option<MarkdownStream> = None;
+
+fn terminal_markdown() -> &'static mut Option<MarkdownStream> {
+    unsafe { &mut *core::ptr::addr_of_mut!(TERMINAL_MARKDOWN) }
+}
+
+/// "~(=^‥^)ノ [850ms]" after the progress, once the first token is in
+fn print_elapsed(ms: u64) {
+    let mut buf_data = [0u8; 32];
+    let mut buf = StackBuffer::new(&mut buf_data);
+    let mut stdout = Stdout;
+    let face = if state::PLAIN.load(Ordering::SeqCst) { "" } else { "~(=^‥^)ノ " };
+    if ms < 1000 {
+        let _ = write!(buf, "{}[{}ms]", face, ms);
+    } else {
+        let _ = write!(buf, "{}[{}.{}s]", face, ms / 1000, (ms % 1000) / 100);
+    }
+    let _ = write!(stdout, "{}", buf.as_str());
+}
+
+/// The TUI output pane; replies go through the streaming renderer
+pub struct TuiSink;
+
+impl OutputSink for TuiSink {
+    fn progress(&mut self, _text: &str) {}
+
+    fn reply_start(&mut self, _ttft_ms: u64, _erase: u32) {
+        tui_app::start_streaming(wrap::indent(Role::Assistant));
+    }
+
+    fn reply_chunk(&mut self, text: &str) {
+        tui_app::process_streaming_chunk(text);
+    }
+
+    fn reply_end(&mut self) {
+        tui_app::finish_streaming();
+    }
+
+    fn message(&mut self, color: &str, text: &str) {
+        render::tui_print_with_indent(text, "", wrap::indent(Role::Tool), Some(color));
+    }
+
+    fn notification(&mut self, color: &str, text: &str) {
+        if tui_app::CUR_COL.load(Ordering::SeqCst) != 0 {
+            render::tui_print_with_indent("\n", "", 0, None);
+        }
+        render::tui_print_with_indent(text, "     --- ", wrap::indent(Role::Tool), Some(color));
+    }
+
+    fn text(&mut self, text: &str) {
+        render::tui_print_with_indent(text, "", 0, None);
+    }
+
+    fn long_text(&mut self, title: &str, text: &str) {
+        if !pager::fits(text) {
+            pager::remember(title, text);
+        }
+    }
+
+    fn tick(&mut self, current_tokens: usize, token_limit: usize, mem_kb: usize) {
+        render::render_footer(current_tokens, token_limit, mem_kb);
+    }
+
+    fn read_line(&mut self, echo: bool, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<String> {
+        let answer = tui_app::wait_for_user_line(current_tokens, token_limit, mem_kb)?;
+        if echo {
+            render::tui_print_with_indent(" >  ", "", 0, Some(crate::config::COLOR_USER));
+            render::tui_print_with_indent(&answer, "", wrap::indent(Role::User), Some(crate::config::COLOR_USER));
+            render::tui_print_with_indent("\n\n", "", 0, None);
+        }
+        Some(answer)
+    }
+}
+
+/// Appends the conversation to a file as plain text: replies, tool output and
+/// what the user typed, without colors or connection progress
+pub struct TranscriptSink {
+    fd: i32,
+}
+
+impl TranscriptSink {
+    pub fn open(path: &str) -> Option<Self> {
+        let fd = open(path, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_APPEND);
+        if fd < 0 { return None; }
+        Some(Self { fd })
+    }
+
+    fn write(&mut self, text: &str) {
+        write_fd(self.fd, text.as_bytes());
+    }
+}
+
+impl Drop for TranscriptSink {
+    fn drop(&mut self) {
+        close(self.fd);
+    }
+}
+
+impl OutputSink for TranscriptSink {
+    fn progress(&mut self, _text: &str) {}
+
+    fn reply_start(&mut self, _ttft_ms: u64, _erase: u32) {
+        self.write("\n");
+    }
+
+    fn reply_chunk(&mut self, text: &str) {
+        self.write(text);
+    }
+
+    fn reply_end(&mut self) {}
+
+    fn message(&mut self, _color: &str, text: &str) {
+        self.write(text);
+    }
+
+    fn notification(&mut self, _color: &str, text: &str) {
+        self.write("--- ");
+        self.write(text);
+    }
+
+    fn text(&mut self, text: &str) {
+        self.write(text);
+    }
+
+    fn user_input(&mut self, line: &str) {
+        self.write("\n> ");
+        self.write(line);
+        self.write("\n");
+    }
+}
+
+/// Everything to each sink in turn; input comes from the first that has any
+pub struct Tee(pub Vec<Box<dyn OutputSink>>);
+
+impl OutputSink for Tee {
+    fn progress(&mut self, text: &str) {
+        for s in &mut self.0 { s.progress(text); }
+    }
+
+    fn reply_start(&mut self, ttft_ms: u64, erase: u32) {
+        for s in &mut self.0 { s.reply_start(ttft_ms, erase); }
+    }
+
+    fn reply_chunk(&mut self, text: &str) {
+        for s in &mut self.0 { s.reply_chunk(text); }
+    }
+
+    fn reply_end(&mut self) {
+        for s in &mut self.0 { s.reply_end(); }
+    }
+
+    fn message(&mut self, color: &str, text: &str) {
+        for s in &mut self.0 { s.message(color, text); }
+    }
+
+    fn notification(&mut self, color: &str, text: &str) {
+        for s in &mut self.0 { s.notification(color, text); }
+    }
+
+    fn text(&mut self, text: &str) {
+        for s in &mut self.0 { s.text(text); }
+    }
+
+    fn long_text(&mut self, title: &str, text: &str) {
+        for s in &mut self.0 { s.long_text(title, text); }
+    }
+
+    fn tick(&mut self, current_tokens: usize, token_limit: usize, mem_kb: usize) {
+        for s in &mut self.0 { s.tick(current_tokens, token_limit, mem_kb); }
+    }
+
+    fn read_line(&mut self, echo: bool, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<String> {
+        let (i, line) = self.0.iter_mut().enumerate()
+            .find_map(|(i, s)| s.read_line(echo, current_tokens, token_limit, mem_kb).map(|l| (i, l)))?;
+        for s in &mut self.0[i + 1..] { s.user_input(&line); }
+        Some(line)
+    }
+
+    fn user_input(&mut self, line: &str) {
+        for s in &mut self.0 { s.user_input(line); }
+    }
+}
+
+static mut SINK: Option<Box<dyn OutputSink>> = None;
+static mut TRANSCRIPT: Option<String> = None;
+
+fn with<R>(f: impl FnOnce(&mut dyn OutputSink) -> R) -> R {
+    match unsafe { (*core::ptr::addr_of_mut!(SINK)).as_mut() } {
+        Some(sink) => f(sink.as_mut()),
+        None => f(&mut TerminalSink),
+    }
+}
+
+/// File that every installed sink is also teed into (`transcript_file`)
+pub fn set_transcript(path: Option<String>) {
+    unsafe { *core::ptr::addr_of_mut!(TRANSCRIPT) = path; }
+}
+
+/// Make `sink` the output, teed into the transcript file if one is set
+pub fn install(sink: Box<dyn OutputSink>) {
+    let transcript = unsafe { (*core::ptr::addr_of!(TRANSCRIPT)).clone() };
+    let sink = match transcript.as_deref().and_then(TranscriptSink::open) {
+        Some(t) => Box::new(Tee(alloc::vec![sink, Box::new(t)])),
+        None => sink,
+    };
+    unsafe { *core::ptr::addr_of_mut!(SINK) = Some(sink); }
+}
+
+pub fn progress(text: &str) { with(|s| s.progress(text)); }
+pub fn reply_start(ttft_ms: u64, erase: u32) { with(|s| s.reply_start(ttft_ms, erase)); }
+pub fn reply_chunk(text: &str) { crate::tui_app::note_streamed(text); with(|s| s.reply_chunk(text)); }
+pub fn reply_end() { with(|s| s.reply_end()); }
+pub fn message(color: &str, text: &str) { with(|s| s.message(color, text)); }
+pub fn notification(color: &str, text: &str) { with(|s| s.notification(color, text)); }
+pub fn text(text: &str) { with(|s| s.text(text)); }
+pub fn long_text(title: &str, text: &str) { with(|s| s.long_text(title, text)); }
+pub fn tick(current_tokens: usize, token_limit: usize, mem_kb: usize) { with(|s| s.tick(current_tokens, token_limit, mem_kb)); }
+pub fn user_input(line: &str) { crate::replay::note_user(line); with(|s| s.user_input(line)); }
+
+pub fn read_line(echo: bool, current_tokens: usize, token_limit: usize, mem_kb: usize) -> Option<String> {
+    with(|s| s.read_line(echo, current_tokens, token_limit, mem_kb))
+}
@@ -0,0 +1,188 @@
+//! Terminal color depth
+//!
+//! The palette in `config.rs` is written for 256-color terminals plus the
+//! 24-bit lavender of the prompt, which a 256-color-only terminal prints as
+//! garbage. The TUI asks the terminal at startup what it can show, and
+//! every color escape on its way out goes through `adapt`, which maps
+//! 24-bit colors to the nearest 256-color entry and, on a 16-color
+//! terminal, 256-color entries to the nearest basic one. `color_mode` in
+//! the config skips the question.
+//!
+//! The probe sets a 24-bit color and reads it back with DECRQSS, followed
+//! by a DA1 query every terminal answers: a DECRQSS reply with the color
+//! intact means true color, anything else (including no DECRQSS reply
+//! before the DA1 one) means 256 colors. No answer at all leaves the mode
+//! alone.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use libakuma::poll_input_event;
+
+use crate::config::ColorMode;
+
+static MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+
+/// Time allowed for the terminal to answer the probe
+const PROBE_TIMEOUT_MS: u64 = 300;
+
+/// The 16 basic colors as xterm draws them
+const BASIC: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+/// Channel levels of the 6x6x6 color cube (entries 16-231)
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+pub fn mode() -> ColorMode {
+    match MODE.load(Ordering::Relaxed) {
+        m if m == ColorMode::TrueColor as u8 => ColorMode::TrueColor,
+        m if m == ColorMode::Ansi256 as u8 => ColorMode::Ansi256,
+        m if m == ColorMode::Ansi16 as u8 => ColorMode::Ansi16,
+        _ => ColorMode::Auto,
+    }
+}
+
+pub fn set_mode(mode: ColorMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+/// Ask the terminal whether it shows 24-bit color, if the config left it
+/// to us; call in raw mode
+pub fn probe() {
+    if mode() != ColorMode::Auto {
+        return;
+    }
+    libakuma::print("\x1b[38;2;1;2;3m\x1bP$qm\x1b\\\x1b[0m\x1b[c");
+    let mut reply = String::new();
+    let start = libakuma::uptime();
+    let mut buf = [0u8; 64];
+    while libakuma::uptime() - start < PROBE_TIMEOUT_MS * 1000 {
+        let n = poll_input_event(50, &mut buf);
+        if n > 0 {
+            reply.push_str(&String::from_utf8_lossy(&buf[..n as usize]));
+        }
+        // The DA1 answer comes last
+        if reply.contains("\x1b[?") && reply.ends_with('c') {
+            break;
+        }
+    }
+    if reply.is_empty() {
+        return;
+    }
+    let true_color = reply.contains("2:1:2:3") || reply.contains("2;1;2;3");
+    set_mode(if true_color { ColorMode::TrueColor } else { ColorMode::Ansi256 });
+}
+
+/// `text` with its color escapes fitted to the terminal's color depth
+pub fn adapt(text: &str) -> Cow<'_, str> {
+    let mode = mode();
+    if matches!(mode, ColorMode::Auto | ColorMode::TrueColor) || !text.contains("\x1b[") {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut changed = false;
+    while let Some(pos) = rest.find("\x1b[") {
+        out.push_str(&rest[..pos]);
+        let seq = &rest[pos + 2..];
+        let end = seq.find(|c: char| !(c.is_ascii_digit() || c == ';' || c == ':')).unwrap_or(seq.len());
+        if seq[end..].starts_with('m') {
+            let params = &seq[..end];
+            match rewrite_sgr(params, mode) {
+                Some(new) => {
+                    out.push_str(&format!("\x1b[{}m", new));
+                    changed = true;
+                }
+                None => out.push_str(&rest[pos..pos + 2 + end + 1]),
+            }
+            rest = &seq[end + 1..];
+        } else {
+            out.push_str("\x1b[");
+            rest = seq;
+        }
+    }
+    out.push_str(rest);
+    if changed { Cow::Owned(out) } else { Cow::Borrowed(text) }
+}
+
+/// New SGR parameters when `params` holds colors `mode` can't show
+fn rewrite_sgr(params: &str, mode: ColorMode) -> Option<String> {
+    let parts: Vec<&str> = params.split(';').collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut changed = false;
+    let mut i = 0;
+    while i < parts.len() {
+        let ground = parts[i];
+        let is_color = ground == "38" || ground == "48";
+        let num = |j: usize| parts.get(j).and_then(|p| p.parse::<u8>().ok());
+        match (is_color, parts.get(i + 1).copied()) {
+            (true, Some("2")) => {
+                let (Some(r), Some(g), Some(b)) = (num(i + 2), num(i + 3), num(i + 4)) else { return None };
+                out.push(color(ground, nearest_256(r, g, b), mode));
+                changed = true;
+                i += 5;
+            }
+            (true, Some("5")) if mode == ColorMode::Ansi16 => {
+                let n = num(i + 2)?;
+                out.push(color(ground, n, mode));
+                changed = true;
+                i += 3;
+            }
+            _ => {
+                out.push(String::from(ground));
+                i += 1;
+            }
+        }
+    }
+    if changed { Some(out.join(";")) } else { None }
+}
+
+/// SGR parameters for 256-color entry `n` as foreground (`38`) or
+/// background (`48`), in `mode`
+fn color(ground: &str, n: u8, mode: ColorMode) -> String {
+    if mode != ColorMode::Ansi16 {
+        return format!("{};5;{}", ground, n);
+    }
+    let (r, g, b) = rgb_of(n);
+    let basic = nearest(&BASIC, r, g, b) as u8;
+    let base = if ground == "38" { 30 } else { 40 };
+    if basic < 8 { format!("{}", base + basic) } else { format!("{}", base + 60 + basic - 8) }
+}
+
+fn rgb_of(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => BASIC[n as usize],
+        16..=231 => {
+            let i = n - 16;
+            (CUBE[(i / 36) as usize], CUBE[(i / 6 % 6) as usize], CUBE[(i % 6) as usize])
+        }
+        _ => {
+            let v = 8 + 10 * (n - 232);
+            (v, v, v)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).unsigned_abs();
+    d(a.0, b.0).pow(2) + d(a.1, b.1).pow(2) + d(a.2, b.2).pow(2)
+}
+
+fn nearest(palette: &[(u8, u8, u8)], r: u8, g: u8, b: u8) -> usize {
+    (0..palette.len()).min_by_key(|&i| distance(palette[i], (r, g, b))).unwrap_or(0)
+}
+
+/// The 256-color entry closest to an RGB color: the nearest cube color or
+/// gray ramp step, whichever is closer
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| nearest(&CUBE.map(|c| (c, c, c)), v, v, v) as u8;
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let step = (avg.saturating_sub(8) / 10).min(23);
+    let gray = 232 + step;
+    if distance(rgb_of(gray), (r, g, b)) < distance(rgb_of(cube), (r, g, b)) { gray } else { cube }
+}
@@ -0,0 +1,47 @@
+//! Startup banner and message of the day
+//!
+//! /etc/meow/banner.txt replaces the ASCII cat and greeting. The motd line
+//! comes from the `motd` config key and is resolved once at startup (and on
+//! `/motd`), since `@issues` shells out to chainlink.
+
+use alloc::string::String;
+use alloc::format;
+
+use crate::config::Config;
+use crate::tools::chainlink;
+
+pub const BANNER_PATH: &str = "/etc/meow/banner.txt";
+/// Banners are printed on every redraw, so keep them small
+const MAX_BANNER_SIZE: usize = 8 * 1024;
+
+static mut MOTD: Option<String> = None;
+
+/// Custom banner text, if /etc/meow/banner.txt exists
+pub fn load_banner() -> Option<String> {
+    let data = crate::tools::fs::read_file(BANNER_PATH, MAX_BANNER_SIZE).ok()?;
+    if data.is_empty() {
+        return None;
+    }
+    String::from_utf8(data).ok()
+}
+
+/// Resolve the configured motd and remember it for `print_greeting`
+pub fn refresh_motd(config: &Config) -> Option<String> {
+    let motd = config.motd.as_deref().and_then(|m| match m {
+        "@issues" => {
+            if !chainlink::chainlink_available() { return None; }
+            chainlink::count_open_issues().map(|n| match n {
+                0 => String::from("No open issues, purrfect~"),
+                1 => String::from("1 open chainlink issue"),
+                n => format!("{} open chainlink issues", n),
+            })
+        }
+        text => Some(String::from(text)),
+    });
+    unsafe { *core::ptr::addr_of_mut!(MOTD) = motd.clone(); }
+    motd
+}
+
+pub fn motd() -> Option<&'static str> {
+    unsafe { (*core::ptr::addr_of!(MOTD)).as_deref() }
+}
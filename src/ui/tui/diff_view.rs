@@ -0,0 +1,179 @@
+//! Full-screen diff overlay (`/diff`)
+//!
+//! The last FileEdit/FileAppend is kept as a unified-diff hunk. `/diff` draws
+//! it over the whole screen with j/k scrolling; on close the conversation is
+//! redrawn from the tab scrollback.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::fmt::Write;
+
+use libakuma::{poll_input_event, set_cursor_position, clear_screen, hide_cursor, show_cursor};
+
+use crate::config::{COLOR_GRAY_DIM, COLOR_RESET, COLOR_YELLOW, COLOR_BOLD};
+use crate::tools::context::resolve_path;
+use crate::tools::helpers::extract_string_field;
+use super::input::{self, InputEvent};
+use super::layout::{get_pane_layout, CLEAR_TO_EOL, Stdout};
+
+/// Unchanged lines shown around an edit
+const CONTEXT_LINES: usize = 3;
+/// Largest file read back to locate an edit
+const MAX_FILE_SIZE: usize = 256 * 1024;
+
+const COLOR_ADDED: &str = "\x1b[38;5;114m";
+const COLOR_REMOVED: &str = "\x1b[38;5;203m";
+const COLOR_HUNK: &str = "\x1b[38;5;117m";
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineKind {
+    Context,
+    Added,
+    Removed,
+    Hunk,
+}
+
+struct FileChange {
+    path: String,
+    lines: Vec<(LineKind, String)>,
+}
+
+static mut LAST_CHANGE: Option<FileChange> = None;
+
+fn last_change() -> &'static mut Option<FileChange> {
+    unsafe { &mut *core::ptr::addr_of_mut!(LAST_CHANGE) }
+}
+
+pub fn has_change() -> bool {
+    last_change().is_some()
+}
+
+/// Called after each successful tool call; remembers file edits for `/diff`.
+/// `edit_offset` is where FileEdit put the new text.
+pub fn record_tool_change(tool_json: &str, edit_offset: Option<usize>) {
+    let Some(tool) = extract_string_field(tool_json, "tool") else { return; };
+    if tool != "FileEdit" && tool != "FileAppend" { return; }
+    let Some(filename) = extract_string_field(tool_json, "filename") else { return; };
+    let Some(path) = resolve_path(&filename) else { return; };
+    let Some(content) = read_file(&path) else { return; };
+    let file_lines: Vec<&str> = content.lines().collect();
+
+    let (removed, added, start): (Vec<String>, Vec<String>, usize) = if tool == "FileEdit" {
+        let old_text = extract_string_field(tool_json, "old_text").unwrap_or_default();
+        let new_text = extract_string_field(tool_json, "new_text").unwrap_or_default();
+        let Some(before) = edit_offset.and_then(|offset| content.get(..offset)) else { return; };
+        let start = before.matches('\n').count();
+        let removed = old_text.lines().map(String::from).collect();
+        let added = new_text.lines().map(String::from).collect();
+        (removed, added, start)
+    } else {
+        let appended = extract_string_field(tool_json, "content").unwrap_or_default();
+        let start = file_lines.len().saturating_sub(appended.lines().count());
+        (Vec::new(), appended.lines().map(String::from).collect(), start)
+    };
+
+    let start = core::cmp::min(start, file_lines.len());
+    let ctx_start = start.saturating_sub(CONTEXT_LINES);
+    let after = core::cmp::min(start + added.len(), file_lines.len());
+    let ctx_end = core::cmp::min(file_lines.len(), after + CONTEXT_LINES);
+
+    let mut lines = Vec::new();
+    lines.push((LineKind::Hunk, format!(
+        "@@ -{},{} +{},{} @@",
+        ctx_start + 1, (start - ctx_start) + removed.len() + (ctx_end - after),
+        ctx_start + 1, ctx_end - ctx_start
+    )));
+    for l in &file_lines[ctx_start..start] { lines.push((LineKind::Context, String::from(*l))); }
+    for l in removed { lines.push((LineKind::Removed, l)); }
+    for l in added { lines.push((LineKind::Added, l)); }
+    for l in &file_lines[after..ctx_end] { lines.push((LineKind::Context, String::from(*l))); }
+
+    *last_change() = Some(FileChange { path, lines });
+}
+
+/// Run the overlay until q/Esc, then hand the screen back to the caller
+pub fn show() {
+    let Some(change) = last_change().as_ref() else { return; };
+    let layout = get_pane_layout();
+    layout.reset_scroll_region();
+
+    let body_rows = layout.term_height.saturating_sub(2) as usize;
+    let max_top = change.lines.len().saturating_sub(body_rows);
+    let mut top = 0usize;
+    let mut redraw = true;
+
+    loop {
+        if redraw {
+            draw(change, top, body_rows);
+            redraw = false;
+        }
+
+        let mut buf = [0u8; 16];
+        let n = poll_input_event(100, &mut buf);
+        if n <= 0 { continue; }
+        let (event, _) = input::parse_input(&buf[..n as usize]);
+        let old_top = top;
+        match event {
+            InputEvent::Char('q') | InputEvent::Esc | InputEvent::Interrupt => break,
+            InputEvent::Char('j') | InputEvent::Down | InputEvent::Enter => top = core::cmp::min(top + 1, max_top),
+            InputEvent::Char('k') | InputEvent::Up => top = top.saturating_sub(1),
+            InputEvent::Char(' ') | InputEvent::Char('f') => top = core::cmp::min(top + body_rows, max_top),
+            InputEvent::Char('b') => top = top.saturating_sub(body_rows),
+            InputEvent::Char('g') | InputEvent::Home => top = 0,
+            InputEvent::Char('G') | InputEvent::End => top = max_top,
+            _ => {}
+        }
+        redraw = top != old_top;
+    }
+
+    show_cursor();
+}
+
+fn draw(change: &FileChange, top: usize, body_rows: usize) {
+    let layout = get_pane_layout();
+    let w = layout.term_width as usize;
+    let mut stdout = Stdout;
+
+    hide_cursor();
+    clear_screen();
+    set_cursor_position(0, 0);
+    let _ = write!(stdout, "{}{} diff: {}{}", COLOR_YELLOW, COLOR_BOLD, change.path, COLOR_RESET);
+
+    for r in 0..body_rows {
+        set_cursor_position(0, (r + 1) as u64);
+        let Some((kind, text)) = change.lines.get(top + r) else { break; };
+        let (color, marker) = match kind {
+            LineKind::Added => (COLOR_ADDED, "+"),
+            LineKind::Removed => (COLOR_REMOVED, "-"),
+            LineKind::Hunk => (COLOR_HUNK, ""),
+            LineKind::Context => (syntax_color(text), " "),
+        };
+        let mut line = String::new();
+        for c in marker.chars().chain(text.chars()) {
+            if line.chars().count() >= w.saturating_sub(1) { break; }
+            if c == '\t' { line.push_str("    "); } else if !c.is_control() { line.push(c); }
+        }
+        let _ = write!(stdout, "{}{}{}{}", color, line, COLOR_RESET, CLEAR_TO_EOL);
+    }
+
+    set_cursor_position(0, layout.term_height.saturating_sub(1) as u64);
+    let last = core::cmp::min(top + body_rows, change.lines.len());
+    let _ = write!(stdout, "{} lines {}-{} of {}  j/k scroll  space/b page  q close{}{}",
+        COLOR_GRAY_DIM, top + 1, last, change.lines.len(), COLOR_RESET, CLEAR_TO_EOL);
+}
+
+/// Dim comments so the +/- lines stand out in unchanged context
+fn syntax_color(line: &str) -> &'static str {
+    let t = line.trim_start();
+    if t.starts_with("//") || t.starts_with('#') || t.starts_with("/*") || t.starts_with('*') {
+        COLOR_GRAY_DIM
+    } else {
+        COLOR_RESET
+    }
+}
+
+fn read_file(path: &str) -> Option<String> {
+    let data = crate::tools::fs::read_file(path, MAX_FILE_SIZE).ok()?;
+    String::from_utf8(data).ok()
+}
@@ -1,10 +1,13 @@
 use alloc::collections::VecDeque;
-use core::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU64, Ordering};
 
 pub static INPUT_LEN: AtomicU16 = AtomicU16::new(0);
 pub static CURSOR_IDX: AtomicU16 = AtomicU16::new(0);
 pub static PROMPT_SCROLL_TOP: AtomicU16 = AtomicU16::new(0);
 static LAST_INPUT_TIME: AtomicU64 = AtomicU64::new(0);
+/// Between the terminal's paste markers: newlines go into the input instead
+/// of sending it
+pub static PASTING: AtomicBool = AtomicBool::new(false);
 
 static mut RAW_INPUT_QUEUE: Option<VecDeque<u8>> = None;
 
@@ -38,6 +41,17 @@ pub enum InputEvent {
     CtrlU,
     CtrlW,
     CtrlL,
+    CtrlD,
+    CtrlS,
+    CtrlT,
+    /// Toggle the status animation
+    CtrlN,
+    CtrlLeft,
+    CtrlRight,
+    Tab,
+    /// `ESC[200~` / `ESC[201~` around pasted text (bracketed paste)
+    PasteStart,
+    PasteEnd,
     Esc,
     Interrupt,
     Unknown,
@@ -45,6 +59,7 @@ pub enum InputEvent {
 
 pub fn parse_input(buf: &[u8]) -> (InputEvent, usize) {
     if buf.is_empty() { return (InputEvent::Unknown, 0); }
+    if let Some(bound) = super::keys::lookup(buf) { return bound; }
     match buf[0] {
         0x03 => (InputEvent::Interrupt, 1),
         0x0D => {
@@ -68,11 +83,18 @@ pub fn parse_input(buf: &[u8]) -> (InputEvent, usize) {
                         match c {
                             b'A' => return (InputEvent::Up, len),
                             b'B' => return (InputEvent::Down, len),
-                            b'C' => { if seq == b"1;3" { return (InputEvent::AltRight, len); } return (InputEvent::Right, len); }
-                            b'D' => { if seq == b"1;3" { return (InputEvent::AltLeft, len); } return (InputEvent::Left, len); }
+                            b'C' => { if seq == b"1;3" { return (InputEvent::AltRight, len); } if seq == b"1;5" { return (InputEvent::CtrlRight, len); } return (InputEvent::Right, len); }
+                            b'D' => { if seq == b"1;3" { return (InputEvent::AltLeft, len); } if seq == b"1;5" { return (InputEvent::CtrlLeft, len); } return (InputEvent::Left, len); }
                             b'H' => return (InputEvent::Home, len),
                             b'F' => return (InputEvent::End, len),
-                            b'~' => { if seq == b"3" { return (InputEvent::Delete, len); } return (InputEvent::Unknown, len); }
+                            b'~' => {
+                                match seq {
+                                    b"3" => return (InputEvent::Delete, len),
+                                    b"200" => return (InputEvent::PasteStart, len),
+                                    b"201" => return (InputEvent::PasteEnd, len),
+                                    _ => return (InputEvent::Unknown, len),
+                                }
+                            }
                             b'u' => {
                                 if seq == b"13;2" || seq == b"13;5" { return (InputEvent::ShiftEnter, len); }
                                 if let Some(semi_pos) = seq.iter().position(|&b| b == b';') {
@@ -86,9 +108,13 @@ pub fn parse_input(buf: &[u8]) -> (InputEvent, usize) {
                                                         match keycode {
                                                             97 => return (InputEvent::CtrlA, len),
                                                             99 => return (InputEvent::Interrupt, len),
+                                                            100 => return (InputEvent::CtrlD, len),
                                                             101 => return (InputEvent::CtrlE, len),
                                                             106 => return (InputEvent::ShiftEnter, len),
                                                             108 => return (InputEvent::CtrlL, len),
+                                                            110 => return (InputEvent::CtrlN, len),
+                                                            115 => return (InputEvent::CtrlS, len),
+                                                            116 => return (InputEvent::CtrlT, len),
                                                             117 => return (InputEvent::CtrlU, len),
                                                             119 => return (InputEvent::CtrlW, len),
                                                             _ => {}
@@ -149,9 +175,14 @@ pub fn parse_input(buf: &[u8]) -> (InputEvent, usize) {
             return (InputEvent::Esc, 1);
         }
         0x01 => (InputEvent::CtrlA, 1),
+        0x04 => (InputEvent::CtrlD, 1),
         0x05 => (InputEvent::CtrlE, 1),
         0x08 | 0x7F => (InputEvent::Backspace, 1),
+        0x09 => (InputEvent::Tab, 1),
         0x0C => (InputEvent::CtrlL, 1),
+        0x0E => (InputEvent::CtrlN, 1),
+        0x13 => (InputEvent::CtrlS, 1),
+        0x14 => (InputEvent::CtrlT, 1),
         0x15 => (InputEvent::CtrlU, 1),
         0x17 => (InputEvent::CtrlW, 1),
         c if c >= 0x20 && c <= 0x7E => (InputEvent::Char(c as char), 1),
@@ -163,6 +194,10 @@ pub fn update_last_input_time() {
     LAST_INPUT_TIME.store(libakuma::uptime(), Ordering::Relaxed);
 }
 
+pub fn last_input_time() -> u64 {
+    LAST_INPUT_TIME.load(Ordering::Relaxed)
+}
+
 pub fn calculate_input_cursor(input: &str, idx: usize, prompt_width: usize, width: usize) -> (u64, u64) {
     if width == 0 { return (0, 0); }
     let (mut cx, mut cy) = (prompt_width, 0);
@@ -0,0 +1,142 @@
+//! Custom key bindings
+//!
+//! Terminals disagree about what Ctrl/Alt combinations send, and some send
+//! nothing at all. The `[keys]` config section binds an action to the bytes
+//! a terminal does send, e.g. `newline=\e\r` or `cancel=^G`; `parse_input`
+//! checks these before its built-in table. `/keytest ACTION` captures a key
+//! press and binds it.
+//!
+//! Sequences are written with `\e` (escape), `\r`, `\n`, `\t`, `\\`, `\xNN`
+//! and `^X` (Ctrl+X); any other character stands for itself.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+
+use libakuma::poll_input_event;
+
+use super::input::InputEvent;
+
+/// Action names and the input event each one stands for
+pub const ACTIONS: &[(&str, InputEvent)] = &[
+    ("submit", InputEvent::Enter),
+    ("newline", InputEvent::ShiftEnter),
+    ("cancel", InputEvent::Esc),
+    ("clear-line", InputEvent::CtrlU),
+    ("delete-word", InputEvent::CtrlW),
+    ("word-back", InputEvent::AltLeft),
+    ("word-forward", InputEvent::AltRight),
+    ("line-start", InputEvent::CtrlA),
+    ("line-end", InputEvent::CtrlE),
+    ("redraw", InputEvent::CtrlL),
+    ("soft-stop", InputEvent::CtrlS),
+    ("send-composition", InputEvent::CtrlD),
+    ("toggle-animation", InputEvent::CtrlN),
+    ("new-tab", InputEvent::CtrlT),
+    ("prev-tab", InputEvent::CtrlLeft),
+    ("next-tab", InputEvent::CtrlRight),
+];
+
+static mut BINDINGS: Vec<(Vec<u8>, InputEvent)> = Vec::new();
+
+fn bindings() -> &'static mut Vec<(Vec<u8>, InputEvent)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(BINDINGS) }
+}
+
+pub fn action(name: &str) -> Option<InputEvent> {
+    ACTIONS.iter().find(|(n, _)| *n == name).map(|(_, e)| *e)
+}
+
+/// Name of the action bound to `event`, if it has one
+pub fn action_name(event: InputEvent) -> Option<&'static str> {
+    ACTIONS.iter().find(|(_, e)| *e == event).map(|(n, _)| *n)
+}
+
+/// Bind `sequence` (config syntax) to `action_name`, replacing whatever the
+/// same bytes were bound to
+pub fn bind(action_name: &str, sequence: &str) -> Result<(), String> {
+    let event = action(action_name).ok_or_else(|| format!("unknown action '{}'", action_name))?;
+    let bytes = parse_sequence(sequence).ok_or_else(|| format!("bad key sequence '{}' for {}", sequence, action_name))?;
+    let b = bindings();
+    b.retain(|(seq, _)| *seq != bytes);
+    b.push((bytes, event));
+    // Longest first, so a sequence never shadows a longer one it starts
+    b.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    Ok(())
+}
+
+/// Install the `[keys]` section; returns one message per entry that was skipped
+pub fn load(keys: &[(String, String)]) -> Vec<String> {
+    keys.iter().filter_map(|(action, seq)| bind(action, seq).err()).collect()
+}
+
+/// A bound sequence at the start of `buf`, with its length
+pub fn lookup(buf: &[u8]) -> Option<(InputEvent, usize)> {
+    bindings().iter().find(|(seq, _)| buf.starts_with(seq)).map(|(seq, e)| (*e, seq.len()))
+}
+
+pub fn parse_sequence(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'e' => out.push(0x1B),
+                'r' => out.push(b'\r'),
+                'n' => out.push(b'\n'),
+                't' => out.push(b'\t'),
+                '\\' => out.push(b'\\'),
+                'x' => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    out.push(u8::from_str_radix(&hex, 16).ok()?);
+                }
+                _ => return None,
+            },
+            '^' => {
+                let k = chars.next()?.to_ascii_uppercase();
+                if !('@'..='_').contains(&k) { return None; }
+                out.push(k as u8 - b'@');
+            }
+            c => {
+                let mut b = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut b).as_bytes());
+            }
+        }
+    }
+    if out.is_empty() { None } else { Some(out) }
+}
+
+/// Config syntax for raw bytes, the inverse of `parse_sequence`
+pub fn format_sequence(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in bytes {
+        match b {
+            0x1B => out.push_str("\\e"),
+            b'\r' => out.push_str("\\r"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b'\\' => out.push_str("\\\\"),
+            b'^' => out.push_str("\\x5e"),
+            0x00..=0x1F => { out.push('^'); out.push((b + b'@') as char); }
+            0x20..=0x7E => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out
+}
+
+/// Wait up to `timeout_ms` for a key press and return its bytes, gathering
+/// the rest of an escape sequence that arrives in pieces
+pub fn capture(timeout_ms: u64) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buf = [0u8; 16];
+    let n = poll_input_event(timeout_ms, &mut buf);
+    if n <= 0 { return None; }
+    out.extend_from_slice(&buf[..n as usize]);
+    loop {
+        let n = poll_input_event(50, &mut buf);
+        if n <= 0 { break; }
+        out.extend_from_slice(&buf[..n as usize]);
+    }
+    Some(out)
+}
@@ -31,12 +31,24 @@ pub struct PaneLayout {
     pub status_dots: u8,
     pub status_time_ms: Option<u64>,
     pub status_start_us: u64,
+    /// Reply received so far in this stream, in bytes and estimated tokens
+    pub stream_bytes: usize,
+    pub stream_tokens: usize,
+    /// Start of the current tokens/sec window and the token count at that point
+    pub rate_mark_us: u64,
+    pub rate_mark_tokens: usize,
+    /// Tokens/sec over the last full window, in tenths
+    pub stream_rate_x10: u64,
     pub footer_top: u16,
     pub footer_height: u16,
     pub prompt_scroll: u16,
     pub _cursor_idx: u16,
     pub input_prefix_len: u16,
     pub repaint_counter: u16,
+    /// Fingerprint of the last footer drawn, used to skip redraws when animation is off
+    pub footer_sig: u64,
+    /// Column where the preview pane starts (0 = no split)
+    pub split_col: u16,
 }
 
 impl PaneLayout {
@@ -61,12 +73,19 @@ impl PaneLayout {
             status_dots: 0,
             status_time_ms: None,
             status_start_us: 0,
+            stream_bytes: 0,
+            stream_tokens: 0,
+            rate_mark_us: 0,
+            rate_mark_tokens: 0,
+            stream_rate_x10: 0,
             footer_top: separator_row,
             footer_height,
             prompt_scroll: 0,
             _cursor_idx: 0,
             input_prefix_len: 0,
             repaint_counter: 0,
+            footer_sig: 0,
+            split_col: 0,
         }
     }
 
@@ -96,7 +115,28 @@ impl PaneLayout {
         let _ = write!(stdout, "\x1b[1;{}r", self.term_height);
     }
 
+    /// Width available to the conversation pane
+    pub fn output_width(&self) -> u16 {
+        if self.split_col > 0 { self.split_col } else { self.term_width }
+    }
+
+    /// Confine output (wrapping and scrolling) to the conversation pane.
+    /// DECSLRM homes the cursor, so call this before positioning it.
+    pub fn enter_output_margins(&self) {
+        if self.split_col == 0 { return; }
+        let mut stdout = Stdout;
+        let _ = write!(stdout, "\x1b[?69h\x1b[1;{}s", self.split_col);
+    }
+
+    /// Restore full-width margins for the footer and preview pane
+    pub fn leave_output_margins(&self) {
+        if self.split_col == 0 { return; }
+        akuma_write(fd::STDOUT, b"\x1b[?69l");
+    }
+
     pub fn update_status(&mut self, text: &str, dots: u8, time_ms: Option<u64>) {
+        let plain;
+        let text = if crate::app::state::PLAIN.load(Ordering::SeqCst) { plain = plain_status(text); plain.as_str() } else { text };
         if self.status_text != text || self.status_start_us == 0 {
             self.status_text = String::from(text);
             self.status_dots = if dots > 0 { dots } else { 1 };
@@ -121,13 +161,49 @@ impl PaneLayout {
         self.status_dots = 1;
         self.status_time_ms = None;
         self.status_start_us = 0;
+        self.stream_bytes = 0;
+        self.stream_tokens = 0;
+        self.rate_mark_us = 0;
+        self.rate_mark_tokens = 0;
+        self.stream_rate_x10 = 0;
+    }
+
+    /// Count a piece of the reply towards the streaming counters
+    pub fn note_stream(&mut self, text: &str) {
+        if self.rate_mark_us == 0 {
+            self.rate_mark_us = libakuma::uptime();
+        }
+        self.stream_bytes += text.len();
+        self.stream_tokens += crate::app::tokens::count(text);
+    }
+
+    /// Tokens/sec (in tenths), recomputed once a second. It is rolled on
+    /// repaint rather than on data, so a stalled stream drops to zero.
+    pub fn stream_rate_x10(&mut self, now_us: u64) -> u64 {
+        let window = now_us.saturating_sub(self.rate_mark_us);
+        if self.rate_mark_us > 0 && window >= RATE_WINDOW_US {
+            let tokens = (self.stream_tokens - self.rate_mark_tokens) as u64;
+            self.stream_rate_x10 = tokens * 10_000_000 / window;
+            self.rate_mark_us = now_us;
+            self.rate_mark_tokens = self.stream_tokens;
+        }
+        self.stream_rate_x10
     }
 }
 
+/// How long the tokens/sec figure is averaged over
+const RATE_WINDOW_US: u64 = 1_000_000;
+
+/// Status text without the `[MEOW]` tag and netrunner slang
+fn plain_status(text: &str) -> String {
+    let text = text.strip_prefix("[MEOW] ").unwrap_or(text);
+    text.replacen("jacking in", "connecting", 1)
+}
+
 pub struct Stdout;
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        akuma_write(fd::STDOUT, s.as_bytes());
+        akuma_write(fd::STDOUT, crate::ui::theme::adapt(s).as_bytes());
         Ok(())
     }
 }
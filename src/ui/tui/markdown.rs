@@ -2,7 +2,7 @@ use alloc::string::String;
 use alloc::format;
 use core::sync::atomic::Ordering;
 use crate::config::{COLOR_BOLD, COLOR_RESET, COLOR_GRAY_DIM, COLOR_VIOLET, COLOR_YELLOW, BG_CODE};
-use super::render::tui_print_with_indent;
+use super::render::{tui_print_with_indent, tui_print_verbatim};
 use crate::tui_app::CUR_COL;
 
 pub struct MarkdownRenderer {
@@ -48,7 +48,7 @@ impl MarkdownRenderer {
                 styled_line.push_str(COLOR_GRAY_DIM);
                 styled_line.push_str(line);
                 
-                tui_print_with_indent(&styled_line, "", self.indent + 2, None);
+                tui_print_verbatim(&styled_line, self.indent + 2, None);
                 // Reset color at end of line and prepare indentation for next line
                 tui_print_with_indent(format!("{}\n", COLOR_RESET).as_str(), "", self.indent + 2, None);
                 continue;
@@ -170,3 +170,192 @@ impl MarkdownRenderer {
         tui_print_with_indent("", "", self.indent, Some(COLOR_RESET));
     }
 }
+
+/// Markdown for a reply that arrives in pieces. Inline styles (bold,
+/// italic, `code`) are tracked across chunks so text still streams a token
+/// at a time; only the start of a line is held back until it is clear
+/// whether it opens a heading, list item, rule or code fence. Headings,
+/// rules and fences are drawn by `MarkdownRenderer` once their line is in.
+pub struct MarkdownStream {
+    block: MarkdownRenderer,
+    indent: u16,
+    base_style: &'static str,
+    /// Start of the current line, while its kind is undecided
+    pending: String,
+    /// Text in the current style, not printed yet
+    run: String,
+    at_line_start: bool,
+    in_code: bool,
+    bold: bool,
+    italic: bool,
+    code: bool,
+    /// A `*` that may turn out to be the first half of `**`
+    star: bool,
+}
+
+impl MarkdownStream {
+    pub fn new(indent: u16, base_style: &'static str) -> Self {
+        Self {
+            block: MarkdownRenderer::new(indent, "", Some(base_style)),
+            indent,
+            base_style,
+            pending: String::new(),
+            run: String::new(),
+            at_line_start: true,
+            in_code: false,
+            bold: false,
+            italic: false,
+            code: false,
+            star: false,
+        }
+    }
+
+    pub fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            if self.in_code || self.at_line_start {
+                self.pending.push(c);
+                if c == '\n' {
+                    let line = core::mem::take(&mut self.pending);
+                    self.finish_line(&line);
+                } else if !self.in_code {
+                    self.classify();
+                }
+            } else {
+                self.inline(c);
+            }
+        }
+        self.flush();
+    }
+
+    /// Print whatever is still held back
+    pub fn finish(&mut self) {
+        let rest = core::mem::take(&mut self.pending);
+        if self.in_code {
+            self.code_line(&rest);
+        } else {
+            self.inline_str(&rest);
+        }
+        if self.star {
+            self.star = false;
+            self.run.push('*');
+        }
+        self.flush();
+        *self = Self::new(self.indent, self.base_style);
+    }
+
+    /// Decide what an unfinished line at line start is, once that is possible
+    fn classify(&mut self) {
+        let trimmed = self.pending.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("```") {
+            return;
+        }
+        let could_be = |marker: &str| marker.starts_with(trimmed);
+        if could_be("---") || could_be("***") || could_be("___") || could_be("* ") || could_be("- ") {
+            return;
+        }
+        if trimmed.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return;
+        }
+        let line = core::mem::take(&mut self.pending);
+        let rest = line.trim_start();
+        if let Some(item) = rest.strip_prefix("* ").or_else(|| rest.strip_prefix("- ")) {
+            if CUR_COL.load(Ordering::SeqCst) > self.indent {
+                tui_print_with_indent("\n", "", self.indent, None);
+            }
+            tui_print_with_indent(" • ", "", self.indent, Some(COLOR_VIOLET));
+            self.inline_str(item);
+            return;
+        }
+        if let Some(pos) = rest.find(". ") {
+            if pos > 0 && rest[..pos].chars().all(|c| c.is_ascii_digit()) {
+                tui_print_with_indent(&rest[..pos + 2], "", self.indent, Some(COLOR_VIOLET));
+                self.inline_str(&rest[pos + 2..]);
+                return;
+            }
+        }
+        self.inline_str(&line);
+    }
+
+    /// A whole line that was held back: a fence, heading, rule, code or a short line
+    fn finish_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+        if self.in_code {
+            if trimmed.starts_with("```") {
+                self.in_code = false;
+                tui_print_with_indent("\n", "", self.indent, None);
+            } else {
+                self.code_line(line.trim_end_matches('\n'));
+            }
+            return;
+        }
+        if trimmed.starts_with("```") {
+            self.in_code = true;
+            self.block.render(trimmed);
+            return;
+        }
+        let is_heading = trimmed.starts_with('#') && trimmed.chars().take_while(|&c| c == '#').count() <= 6;
+        if is_heading || trimmed == "---" || trimmed == "***" || trimmed == "___" {
+            self.block.render(trimmed);
+            return;
+        }
+        // A short line whose kind never got decided; list markers and all
+        // are handled the same as a long one
+        self.pending = String::from(line.trim_end_matches('\n'));
+        self.classify();
+        let rest = core::mem::take(&mut self.pending);
+        self.inline_str(&rest);
+        self.inline('\n');
+    }
+
+    fn code_line(&self, line: &str) {
+        let style = format!("{}{}", BG_CODE, COLOR_GRAY_DIM);
+        tui_print_verbatim(line, self.indent + 2, Some(&style));
+        tui_print_with_indent("\n", "", self.indent + 2, None);
+    }
+
+    fn inline_str(&mut self, text: &str) {
+        self.at_line_start = false;
+        for c in text.chars() { self.inline(c); }
+    }
+
+    fn inline(&mut self, c: char) {
+        self.at_line_start = false;
+        if self.star {
+            self.star = false;
+            self.flush();
+            if c == '*' {
+                self.bold = !self.bold;
+                return;
+            }
+            self.italic = !self.italic;
+        }
+        match c {
+            '*' if !self.code => self.star = true,
+            '`' => { self.flush(); self.code = !self.code; }
+            '\n' => {
+                // Inline styles don't carry over to the next line
+                self.flush();
+                self.bold = false;
+                self.italic = false;
+                self.code = false;
+                tui_print_with_indent("\n", "", self.indent, None);
+                self.at_line_start = true;
+            }
+            _ => self.run.push(c),
+        }
+    }
+
+    /// Print the pending run in the current style
+    fn flush(&mut self) {
+        if self.run.is_empty() { return; }
+        let mut style = String::from(self.base_style);
+        if self.bold { style.push_str(COLOR_BOLD); }
+        if self.italic { style.push_str("\x1b[3m"); }
+        if self.code {
+            style.push_str(BG_CODE);
+            style.push_str(COLOR_GRAY_DIM);
+        }
+        let run = core::mem::take(&mut self.run);
+        tui_print_with_indent(&run, "", self.indent, Some(&style));
+    }
+}
@@ -1,5 +1,15 @@
 pub mod layout;
 pub mod input;
+pub mod keys;
 pub mod render;
 pub mod markdown;
 pub mod stream;
+pub mod preview;
+pub mod diff_view;
+pub mod params_view;
+pub mod pager;
+pub mod tabs;
+pub mod banner;
+pub mod terminal;
+pub mod watchdog;
+pub mod wrap;
@@ -0,0 +1,137 @@
+//! Full-screen pager for long command output (`/page`)
+//!
+//! A command reply taller than the output pane opens here instead of
+//! scrolling off under the prompt; space/b page, j/k scroll, q closes and
+//! the conversation is redrawn from the tab scrollback. The last long text,
+//! tool output included, is kept so `/page` can open it again.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use libakuma::{poll_input_event, set_cursor_position, clear_screen, hide_cursor, show_cursor};
+
+use crate::config::{COLOR_GRAY_DIM, COLOR_RESET, COLOR_YELLOW, COLOR_BOLD};
+use super::input::{self, InputEvent};
+use super::layout::{get_pane_layout, CLEAR_TO_EOL, Stdout};
+
+/// (title, text) of the last text too long for the pane
+static mut LAST: Option<(String, String)> = None;
+
+fn last() -> &'static mut Option<(String, String)> {
+    unsafe { &mut *core::ptr::addr_of_mut!(LAST) }
+}
+
+/// Keep `text` for `/page`
+pub fn remember(title: &str, text: &str) {
+    *last() = Some((String::from(title), String::from(text)));
+}
+
+pub fn has_text() -> bool {
+    last().is_some()
+}
+
+/// Whether `text` fits in the output pane without scrolling
+pub fn fits(text: &str) -> bool {
+    let layout = get_pane_layout();
+    let rows = layout.output_bottom.saturating_sub(layout.output_top) as usize;
+    let width = (layout.term_width as usize).max(2) - 1;
+    let mut count = 0;
+    for line in text.lines() {
+        count += line.chars().count().div_ceil(width).max(1);
+        if count > rows {
+            return false;
+        }
+    }
+    true
+}
+
+/// Page through the remembered text, if any
+pub fn show_last() {
+    if let Some((title, text)) = last().as_ref() {
+        show(title, text);
+    }
+}
+
+/// Run the pager until q/Esc, then hand the screen back to the caller
+pub fn show(title: &str, text: &str) {
+    let layout = get_pane_layout();
+    layout.reset_scroll_region();
+
+    let lines = wrap_lines(&crate::app::postprocess::strip_ansi(text), (layout.term_width as usize).max(2) - 1);
+    let body_rows = layout.term_height.saturating_sub(2) as usize;
+    let max_top = lines.len().saturating_sub(body_rows);
+    let mut top = 0usize;
+    let mut redraw = true;
+
+    loop {
+        if redraw {
+            draw(title, &lines, top, body_rows);
+            redraw = false;
+        }
+
+        let mut buf = [0u8; 16];
+        let n = poll_input_event(100, &mut buf);
+        if n <= 0 { continue; }
+        let (event, _) = input::parse_input(&buf[..n as usize]);
+        let old_top = top;
+        match event {
+            InputEvent::Char('q') | InputEvent::Esc | InputEvent::Interrupt => break,
+            InputEvent::Char('j') | InputEvent::Down | InputEvent::Enter => top = core::cmp::min(top + 1, max_top),
+            InputEvent::Char('k') | InputEvent::Up => top = top.saturating_sub(1),
+            InputEvent::Char(' ') | InputEvent::Char('f') => top = core::cmp::min(top + body_rows, max_top),
+            InputEvent::Char('b') => top = top.saturating_sub(body_rows),
+            InputEvent::Char('g') | InputEvent::Home => top = 0,
+            InputEvent::Char('G') | InputEvent::End => top = max_top,
+            _ => {}
+        }
+        redraw = top != old_top;
+    }
+
+    show_cursor();
+}
+
+/// Split `text` into screen lines of at most `width` characters
+fn wrap_lines(text: &str, width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut cols = 0;
+        for c in line.chars() {
+            if cols >= width {
+                out.push(core::mem::take(&mut current));
+                cols = 0;
+            }
+            if c == '\t' {
+                current.push_str("    ");
+                cols += 4;
+            } else if !c.is_control() {
+                current.push(c);
+                cols += 1;
+            }
+        }
+        out.push(current);
+    }
+    out
+}
+
+fn draw(title: &str, lines: &[String], top: usize, body_rows: usize) {
+    let layout = get_pane_layout();
+    let mut stdout = Stdout;
+
+    hide_cursor();
+    clear_screen();
+    set_cursor_position(0, 0);
+    let _ = write!(stdout, "{}{} {}{}", COLOR_YELLOW, COLOR_BOLD, title, COLOR_RESET);
+
+    for r in 0..body_rows {
+        set_cursor_position(0, (r + 1) as u64);
+        let Some(line) = lines.get(top + r) else { break; };
+        let _ = write!(stdout, "{}{}", line, CLEAR_TO_EOL);
+    }
+
+    set_cursor_position(0, layout.term_height.saturating_sub(1) as u64);
+    let last = core::cmp::min(top + body_rows, lines.len());
+    let _ = write!(stdout, "{} lines {}-{} of {}  j/k scroll  space/b page  q close{}{}",
+        COLOR_GRAY_DIM, top + 1, last, lines.len(), COLOR_RESET, CLEAR_TO_EOL);
+}
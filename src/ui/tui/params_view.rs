@@ -0,0 +1,138 @@
+//! Request parameters overlay (`/params`)
+//!
+//! Lists what the next request to the current model goes out with and lets
+//! each value be edited in place: j/k to move, Enter to type a new value,
+//! d to go back to the provider's default, s to save the set for the model
+//! in the config. Changes apply from the next request on; see
+//! `app::params`.
+
+use alloc::string::String;
+use alloc::format;
+use core::fmt::Write;
+
+use libakuma::{poll_input_event, set_cursor_position, clear_screen, hide_cursor, show_cursor};
+
+use crate::app::params;
+use crate::config::{Config, GenOptions, COLOR_BOLD, COLOR_GRAY_DIM, COLOR_RESET, COLOR_YELLOW};
+use super::input::{self, InputEvent};
+use super::layout::{get_pane_layout, CLEAR_TO_EOL, Stdout};
+
+/// What each parameter does, shown next to it
+const HINTS: [&str; 6] = [
+    "randomness, 0-2",
+    "nucleus sampling cutoff, 0-1",
+    "reply length cap in tokens",
+    "fixed seed for repeatable replies",
+    "stop sequences, comma-separated",
+    "how long Ollama keeps the model loaded (5m, 1h, -1)",
+];
+
+/// Run the overlay until q/Esc, then hand the screen back to the caller
+pub fn show(model: &str, config: &mut Config) {
+    let layout = get_pane_layout();
+    layout.reset_scroll_region();
+
+    let mut opts = params::current(model);
+    let mut selected = 0usize;
+    // The value being typed, while editing
+    let mut editing: Option<String> = None;
+    let mut status = String::new();
+    let mut redraw = true;
+
+    loop {
+        if redraw {
+            draw(model, &opts, selected, editing.as_deref(), &status);
+            redraw = false;
+        }
+
+        let mut buf = [0u8; 16];
+        let n = poll_input_event(100, &mut buf);
+        if n <= 0 { continue; }
+        let (event, _) = input::parse_input(&buf[..n as usize]);
+        redraw = true;
+        let key = GenOptions::KEYS[selected];
+
+        if let Some(value) = editing.as_mut() {
+            match event {
+                InputEvent::Enter => {
+                    match opts.set(key, value) {
+                        Ok(()) => {
+                            params::set_current(model, opts.clone());
+                            status = format!("{} set for this session", key);
+                        }
+                        Err(e) => status = String::from(e),
+                    }
+                    editing = None;
+                }
+                InputEvent::Esc | InputEvent::Interrupt => editing = None,
+                InputEvent::Backspace => { value.pop(); }
+                InputEvent::CtrlU => value.clear(),
+                InputEvent::Char(c) => value.push(c),
+                _ => redraw = false,
+            }
+            continue;
+        }
+
+        match event {
+            InputEvent::Char('q') | InputEvent::Esc | InputEvent::Interrupt => break,
+            InputEvent::Char('j') | InputEvent::Down => selected = (selected + 1) % GenOptions::KEYS.len(),
+            InputEvent::Char('k') | InputEvent::Up => selected = (selected + GenOptions::KEYS.len() - 1) % GenOptions::KEYS.len(),
+            InputEvent::Enter | InputEvent::Char('e') => {
+                editing = Some(opts.get(key).unwrap_or_default());
+                status.clear();
+            }
+            InputEvent::Char('d') | InputEvent::Delete | InputEvent::Backspace => {
+                let _ = opts.set(key, "");
+                params::set_current(model, opts.clone());
+                status = format!("{} back to the provider default", key);
+            }
+            InputEvent::Char('s') => {
+                status = match params::save(model, config) {
+                    Ok(()) => format!("saved for {}", model),
+                    Err(e) => String::from(e),
+                };
+            }
+            _ => redraw = false,
+        }
+    }
+
+    show_cursor();
+}
+
+fn draw(model: &str, opts: &GenOptions, selected: usize, editing: Option<&str>, status: &str) {
+    let layout = get_pane_layout();
+    let mut stdout = Stdout;
+
+    hide_cursor();
+    clear_screen();
+    set_cursor_position(0, 0);
+    let _ = write!(stdout, "{}{} request parameters: {}{}", COLOR_YELLOW, COLOR_BOLD, model, COLOR_RESET);
+
+    for (i, key) in GenOptions::KEYS.iter().enumerate() {
+        set_cursor_position(0, (i + 2) as u64);
+        let value = match (opts.get(key), *key) {
+            (Some(v), _) => v,
+            (None, "num_predict") => format!("(verbosity: {})", crate::app::state::get_verbosity().max_tokens()),
+            (None, _) => String::from("(provider default)"),
+        };
+        let marker = if i == selected { ">" } else { " " };
+        let bold = if i == selected { COLOR_BOLD } else { "" };
+        let _ = write!(stdout, "{}{} {:<12} {:<24}{} {}{}{}{}",
+            bold, marker, key, value, COLOR_RESET, COLOR_GRAY_DIM, HINTS[i], COLOR_RESET, CLEAR_TO_EOL);
+    }
+
+    let bottom = layout.term_height.saturating_sub(1) as u64;
+    set_cursor_position(0, bottom.saturating_sub(1));
+    let _ = write!(stdout, "{}{}{}", COLOR_YELLOW, status, CLEAR_TO_EOL);
+    set_cursor_position(0, bottom);
+    match editing {
+        Some(value) => {
+            let _ = write!(stdout, "{} = {}{}", GenOptions::KEYS[selected], value, CLEAR_TO_EOL);
+            show_cursor();
+        }
+        None => {
+            let _ = write!(stdout, "{}j/k move  enter edit  d default  s save for {}  q close{}{}",
+                COLOR_GRAY_DIM, model, COLOR_RESET, CLEAR_TO_EOL);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! File preview pane for the split view
+//!
+//! The right-hand column shows a file with line numbers. It follows the file
+//! the model last wrote or edited, or stays on a path pinned with `/view`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::format;
+use core::fmt::Write;
+
+use libakuma::{set_cursor_position, write as akuma_write, fd};
+
+use crate::config::{COLOR_GRAY_DIM, COLOR_RESET, COLOR_YELLOW, COLOR_BOLD};
+use crate::tools::context::resolve_path;
+use crate::tools::fs::{read_file, ReadFileError};
+use crate::tools::helpers::extract_string_field;
+use super::layout::{get_pane_layout, CLEAR_TO_EOL, Stdout};
+
+/// Narrowest terminal that still leaves room for both panes
+const MIN_SPLIT_WIDTH: u16 = 80;
+/// Largest file the preview will load
+const MAX_PREVIEW_SIZE: usize = 256 * 1024;
+
+struct Preview {
+    /// Absolute path of the file shown, None until something is written
+    path: Option<String>,
+    /// Pinned via `/view <path>`; edits to other files don't replace it
+    pinned: bool,
+    lines: Vec<String>,
+    /// First file line shown in the pane
+    top: usize,
+}
+
+static mut PREVIEW: Option<Preview> = None;
+
+fn preview() -> &'static mut Option<Preview> {
+    unsafe { &mut *core::ptr::addr_of_mut!(PREVIEW) }
+}
+
+pub fn is_open() -> bool {
+    preview().is_some()
+}
+
+/// Open the split. With a path the preview is pinned to it; without one it
+/// follows FileWrite/FileEdit/FileAppend.
+pub fn open_split(path: Option<&str>) -> Result<String, String> {
+    let layout = get_pane_layout();
+    if layout.term_width < MIN_SPLIT_WIDTH {
+        return Err(format!("Terminal too narrow for split view (need {} columns)", MIN_SPLIT_WIDTH));
+    }
+
+    let mut p = Preview { path: None, pinned: false, lines: Vec::new(), top: 0 };
+    if let Some(path) = path {
+        let resolved = resolve_path(path).ok_or_else(|| format!("Access denied: '{}' is outside the sandbox", path))?;
+        p.lines = load_lines(&resolved)?;
+        p.path = Some(resolved);
+        p.pinned = true;
+    }
+
+    let msg = match &p.path {
+        Some(path) if p.pinned => format!("～ Split view pinned to {} nya~!", path),
+        _ => String::from("～ Split view on, following file edits nya~!"),
+    };
+    *preview() = Some(p);
+    layout.split_col = layout.term_width * 3 / 5;
+    render();
+    Ok(msg)
+}
+
+/// Close the split and give the conversation its full width back
+pub fn close_split() {
+    let layout = get_pane_layout();
+    if layout.split_col == 0 { return; }
+    for row in 0..=layout.output_bottom {
+        set_cursor_position(layout.split_col as u64, row as u64);
+        akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes());
+    }
+    layout.split_col = 0;
+    *preview() = None;
+}
+
+/// Called after each successful tool call; refreshes the pane for file writes
+pub fn on_tool_success(tool_json: &str) {
+    let Some(p) = preview().as_mut() else { return; };
+    let Some(tool) = extract_string_field(tool_json, "tool") else { return; };
+    if tool != "FileWrite" && tool != "FileEdit" && tool != "FileAppend" { return; }
+    let Some(filename) = extract_string_field(tool_json, "filename") else { return; };
+    let Some(resolved) = resolve_path(&filename) else { return; };
+
+    if p.pinned && p.path.as_deref() != Some(resolved.as_str()) { return; }
+    let Ok(lines) = load_lines(&resolved) else { return; };
+
+    // Scroll to the change: the edited text for FileEdit, the end for FileAppend
+    p.top = match tool.as_str() {
+        "FileEdit" => extract_string_field(tool_json, "new_text")
+            .and_then(|t| t.lines().find(|l| !l.trim().is_empty()).map(String::from))
+            .and_then(|needle| lines.iter().position(|l| l.contains(needle.as_str())))
+            .unwrap_or(0),
+        "FileAppend" => lines.len(),
+        _ => 0,
+    };
+    p.lines = lines;
+    p.path = Some(resolved);
+    render();
+}
+
+/// Draw the preview pane to the right of the split column
+pub fn render() {
+    let Some(p) = preview().as_ref() else { return; };
+    let layout = get_pane_layout();
+    if layout.split_col == 0 { return; }
+
+    let col = layout.split_col as u64;
+    let text_w = layout.term_width.saturating_sub(layout.split_col + 1) as usize;
+    let rows = layout.output_bottom as usize; // row 0 holds the header
+    let num_w = core::cmp::max(3, digits(p.lines.len()));
+
+    // Keep the focus line about a third of the way down
+    let max_top = p.lines.len().saturating_sub(rows);
+    let top = core::cmp::min(p.top.saturating_sub(rows / 3), max_top);
+
+    let mut stdout = Stdout;
+    set_cursor_position(col, 0);
+    let title = p.path.as_deref().unwrap_or("(waiting for a file edit)");
+    let _ = write!(stdout, "{}│{} {}{}", COLOR_GRAY_DIM, COLOR_RESET, COLOR_YELLOW, COLOR_BOLD);
+    write_clipped(title, text_w.saturating_sub(1));
+    let _ = write!(stdout, "{}{}", COLOR_RESET, CLEAR_TO_EOL);
+
+    for r in 0..rows {
+        set_cursor_position(col, (r + 1) as u64);
+        let _ = write!(stdout, "{}│", COLOR_GRAY_DIM);
+        let idx = top + r;
+        if let Some(line) = p.lines.get(idx) {
+            let _ = write!(stdout, "{:>width$} {}", idx + 1, COLOR_RESET, width = num_w);
+            write_clipped(line, text_w.saturating_sub(num_w + 1));
+        } else {
+            let _ = write!(stdout, "{}", COLOR_RESET);
+        }
+        akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes());
+    }
+}
+
+/// Write at most `max` columns of `s`, expanding tabs and dropping control chars
+fn write_clipped(s: &str, max: usize) {
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        if c == '\t' {
+            for _ in 0..4 { if used < max { out.push(' '); used += 1; } }
+            continue;
+        }
+        if c.is_control() { continue; }
+        if used >= max { break; }
+        out.push(c);
+        used += 1;
+    }
+    akuma_write(fd::STDOUT, out.as_bytes());
+}
+
+fn digits(mut n: usize) -> usize {
+    let mut d = 1;
+    while n >= 10 { n /= 10; d += 1; }
+    d
+}
+
+fn load_lines(path: &str) -> Result<Vec<String>, String> {
+    let data = read_file(path, MAX_PREVIEW_SIZE).map_err(|e| match e {
+        ReadFileError::NotFound => format!("Cannot open {}", path),
+        ReadFileError::TooLarge => format!("{} is too large to preview", path),
+        ReadFileError::Unreadable => format!("Cannot read {}", path),
+    })?;
+    Ok(String::from_utf8_lossy(&data).lines().map(String::from).collect())
+}
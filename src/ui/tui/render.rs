@@ -1,12 +1,14 @@
 use core::sync::atomic::Ordering;
 use libakuma::{set_cursor_position, hide_cursor, show_cursor, write as akuma_write, fd};
-use crate::util::StackBuffer;
+use crate::util::{fnv1a, StackBuffer, FNV_OFFSET};
 use core::fmt::Write;
 
 use crate::config::{COLOR_YELLOW, COLOR_RESET, COLOR_VIOLET, COLOR_BOLD, COLOR_GRAY_DIM};
 use crate::app::state::{self, STREAMING};
 use super::layout::{get_pane_layout, TERM_WIDTH, TERM_HEIGHT, CLEAR_TO_EOL, Stdout};
 use super::input::{self, INPUT_LEN, CURSOR_IDX, PROMPT_SCROLL_TOP};
+use super::wrap::{self, Role};
+use crate::ui::theme;
 
 const CAT_ASCII: &str = r#"
                       =#=      .-
@@ -25,11 +27,22 @@ const CAT_ASCII: &str = r#"
                   ::::---#@%%*"#;
 
 pub fn tui_print(s: &str) {
-    tui_print_with_indent(s, "", 9, None);
+    tui_print_with_indent(s, "", wrap::indent(Role::Assistant), None);
 }
 
 pub fn tui_print_assistant(s: &str) {
-    tui_print_with_indent(s, "", 9, Some(crate::config::COLOR_MEOW));
+    tui_print_with_indent(s, "", wrap::indent(Role::Assistant), Some(crate::config::COLOR_MEOW));
+}
+
+/// Set while `tui_print_verbatim` prints
+static VERBATIM: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+/// Print code as is: no word wrap and leading spaces kept; a line longer
+/// than the pane is cut at the edge with a marker
+pub fn tui_print_verbatim(s: &str, indent: u16, color: Option<&str>) {
+    VERBATIM.store(true, Ordering::SeqCst);
+    tui_print_with_indent(s, "", indent, color);
+    VERBATIM.store(false, Ordering::SeqCst);
 }
 
 pub static mut TEST_CAPTURE: Option<alloc::vec::Vec<alloc::string::String>> = None;
@@ -44,21 +57,28 @@ pub fn tui_print_with_indent(s: &str, prefix: &str, indent: u16, color: Option<&
         }
     }
     if s.is_empty() && prefix.is_empty() { return; }
-    let w = TERM_WIDTH.load(Ordering::SeqCst);
+    let verbatim = VERBATIM.load(Ordering::SeqCst);
+    super::tabs::record(s, prefix, indent, color, verbatim);
+    let term_w = TERM_WIDTH.load(Ordering::SeqCst);
     let h = TERM_HEIGHT.load(Ordering::SeqCst);
     let mut col = crate::tui_app::CUR_COL.load(Ordering::SeqCst);
     let mut row = crate::tui_app::CUR_ROW.load(Ordering::SeqCst);
     
     let layout = get_pane_layout();
+    // A narrowed text column moves everything right by `margin`
+    let (margin, w) = wrap::bounds(layout.output_width());
+    let indent = indent + margin;
     let gap = layout.gap();
     let max_row = h.saturating_sub(layout.footer_height + 1 + gap);
 
+    layout.enter_output_margins();
     set_cursor_position(col as u64, row as u64);
-    if let Some(c) = color { akuma_write(fd::STDOUT, c.as_bytes()); }
+    if let Some(c) = color { akuma_write(fd::STDOUT, theme::adapt(c).as_bytes()); }
     if col == 0 {
         if !prefix.is_empty() {
+            for _ in 0..margin { akuma_write(fd::STDOUT, b" "); }
             akuma_write(fd::STDOUT, prefix.as_bytes());
-            col = input::visual_length(prefix) as u16;
+            col = margin + input::visual_length(prefix) as u16;
         } else if indent > 0 {
             for _ in 0..indent { akuma_write(fd::STDOUT, b" "); }
             col = indent;
@@ -93,57 +113,62 @@ pub fn tui_print_with_indent(s: &str, prefix: &str, indent: u16, color: Option<&
     let is_delimiter = |c: char| c == ' ' || c == '\t' || c == '-' || c == '/' || c == '\\' || c == ':';
     let is_punctuation = |c: char| c == ',' || c == '.' || c == '!' || c == '?' || c == ';' || c == ':';
     
-    let mut chars = s.chars().peekable();
-    while let Some(c) = chars.next() {
-        if in_esc {
-            esc_buf.push(c);
-            if c != '[' && c >= '@' && c <= '~' {
-                for ec in esc_buf.iter() {
-                    let mut buf = [0u8; 4];
-                    akuma_write(fd::STDOUT, ec.encode_utf8(&mut buf).as_bytes());
+    if verbatim || !wrap::word_wrap() {
+        print_unwrapped(s, indent, w, max_row, &mut col, &mut row);
+    } else {
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_esc {
+                esc_buf.push(c);
+                if c != '[' && c >= '@' && c <= '~' {
+                    for ec in esc_buf.iter() {
+                        let mut buf = [0u8; 4];
+                        akuma_write(fd::STDOUT, ec.encode_utf8(&mut buf).as_bytes());
+                    }
+                    esc_buf.clear();
+                    in_esc = false;
                 }
-                esc_buf.clear();
-                in_esc = false;
+                continue;
             }
-            continue;
-        }
-        if c == '\x1b' { in_esc = true; esc_buf.clear(); esc_buf.push(c); continue; }
+            if c == '\x1b' { in_esc = true; esc_buf.clear(); esc_buf.push(c); continue; }
         
-        if c == '\n' {
-            flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
-            wrap_line(&mut row, &mut col, max_row);
-        } else if c == '\x08' {
-            if col > indent { col -= 1; akuma_write(fd::STDOUT, b"\x08"); }
-        } else if is_delimiter(c) {
-            word_buf.push(c);
-            if c != '\t' { word_display_len += 1; } else { word_display_len += 4; } // Basic tab handling
+            if c == '\n' {
+                flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
+                wrap_line(&mut row, &mut col, max_row);
+            } else if c == '\x08' {
+                if col > indent { col -= 1; akuma_write(fd::STDOUT, b"\x08"); }
+            } else if is_delimiter(c) {
+                word_buf.push(c);
+                if c != '\t' { word_display_len += 1; } else { word_display_len += 4; } // Basic tab handling
             
-            // If it's a space, we flush AFTER the space to allow wrapping.
-            // If it's a hyphen/slash, we also flush to allow wrapping there.
-            flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
+                // If it's a space, we flush AFTER the space to allow wrapping.
+                // If it's a hyphen/slash, we also flush to allow wrapping there.
+                flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
             
-            if col >= w.saturating_sub(1) { wrap_line(&mut row, &mut col, max_row); }
-        } else {
-            word_buf.push(c);
-            word_display_len += 1;
+                if col >= w.saturating_sub(1) { wrap_line(&mut row, &mut col, max_row); }
+            } else {
+                word_buf.push(c);
+                word_display_len += 1;
             
-            // Peek next to see if it's punctuation. If so, don't flush yet even if we are at the end of line.
-            let next_is_punct = chars.peek().map(|&next| is_punctuation(next)).unwrap_or(false);
+                // Peek next to see if it's punctuation. If so, don't flush yet even if we are at the end of line.
+                let next_is_punct = chars.peek().map(|&next| is_punctuation(next)).unwrap_or(false);
             
-            if word_display_len >= w.saturating_sub(indent) && !next_is_punct {
-                flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
+                if word_display_len >= w.saturating_sub(indent) && !next_is_punct {
+                    flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
+                }
             }
         }
+        flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
     }
-    flush_word(&mut word_buf, &mut word_display_len, &mut col, &mut row, max_row, w, indent);
     if color.is_some() { akuma_write(fd::STDOUT, COLOR_RESET.as_bytes()); }
+    layout.leave_output_margins();
 
     crate::tui_app::CUR_COL.store(col, Ordering::SeqCst);
     crate::tui_app::CUR_ROW.store(row, Ordering::SeqCst);
     layout.output_col = col; layout.output_row = row;
 
     state::with_global_input(|input_str| {
-        let (cx, cy_off) = input::calculate_input_cursor(input_str, CURSOR_IDX.load(Ordering::SeqCst) as usize, INPUT_LEN.load(Ordering::SeqCst) as usize, w as usize);
+        let (cx, cy_off) = input::calculate_input_cursor(input_str, CURSOR_IDX.load(Ordering::SeqCst) as usize, INPUT_LEN.load(Ordering::SeqCst) as usize, term_w as usize);
         let scroll_top = PROMPT_SCROLL_TOP.load(Ordering::SeqCst) as u64;
         let prompt_start_row = h as u64 - layout.footer_height as u64 + 2;
         let final_cy = prompt_start_row + (cy_off - scroll_top);
@@ -152,14 +177,58 @@ pub fn tui_print_with_indent(s: &str, prefix: &str, indent: u16, color: Option<&
     });
 }
 
+/// Logical column on the current unwrapped line, hidden part included
+static LINE_POS: core::sync::atomic::AtomicU16 = core::sync::atomic::AtomicU16::new(0);
+
+/// `word_wrap` off, and code blocks: lines run past the edge instead of
+/// wrapping. What falls left of the horizontal scroll or right of the edge
+/// is not drawn; a `›` in the last column marks a cut line.
+fn print_unwrapped(s: &str, indent: u16, w: u16, max_row: u16, col: &mut u16, row: &mut u16) {
+    let scroll = wrap::h_scroll();
+    let last_col = w.saturating_sub(1);
+    let mut in_esc = false;
+    for c in s.chars() {
+        let mut buf = [0u8; 4];
+        if in_esc || c == '\x1b' {
+            // Colors still apply to whatever is drawn after them
+            akuma_write(fd::STDOUT, c.encode_utf8(&mut buf).as_bytes());
+            in_esc = if c == '\x1b' { true } else { c == '[' || !('@'..='~').contains(&c) };
+            continue;
+        }
+        if c == '\n' {
+            *row += 1;
+            if *row > max_row { *row = max_row; akuma_write(fd::STDOUT, b"\n"); }
+            else { set_cursor_position(0, *row as u64); }
+            for _ in 0..indent { akuma_write(fd::STDOUT, b" "); }
+            *col = indent;
+            LINE_POS.store(0, Ordering::SeqCst);
+            continue;
+        }
+        let width = if c == '\t' { 4 } else { 1 };
+        let pos = LINE_POS.fetch_add(width, Ordering::SeqCst);
+        if pos < scroll {
+            continue;
+        }
+        if *col + width <= last_col {
+            if c == '\t' { akuma_write(fd::STDOUT, b"    "); }
+            else { akuma_write(fd::STDOUT, c.encode_utf8(&mut buf).as_bytes()); }
+            *col += width;
+        } else if *col == last_col {
+            akuma_write(fd::STDOUT, "›".as_bytes());
+            *col += 1;
+        }
+    }
+}
+
 pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
     let mut stdout = Stdout;
     let layout = get_pane_layout();
     let (w, h) = (layout.term_width as usize, layout.term_height as u64);
     layout.repaint_counter = layout.repaint_counter.wrapping_add(1) % 10000;
     let is_streaming = STREAMING.load(Ordering::SeqCst);
+    let animate = state::get_animation();
     let uptime = libakuma::uptime();
-    layout.status_dots = ((uptime / 250_000) % 5 + 1) as u8;
+    layout.status_dots = if animate { ((uptime / 250_000) % 5 + 1) as u8 } else { 3 };
     if !is_streaming && layout.status_text.is_empty() { layout.update_status("[MEOW] awaiting user input", 0, None); }
 
     let mut t_disp_buf_data = [0u8; 16];
@@ -190,12 +259,35 @@ pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
 
     let mut prompt_prefix_buf_data = [0u8; 128]; // Choose a size that's large enough
     let mut prompt_prefix_buf = StackBuffer::new(&mut prompt_prefix_buf_data);
-    let _ = write!(prompt_prefix_buf, "  {}[{}/{}|{}{}{}] {}(=^･ω･^=) > ", COLOR_YELLOW, t_disp, l_disp, m_disp, hist_disp, COLOR_YELLOW, q_disp);
+    let face = if state::PLAIN.load(Ordering::SeqCst) { "" } else { "(=^･ω･^=) " };
+    let _ = write!(prompt_prefix_buf, "  {}[{}/{}|{}{}{}] {}{}> ", COLOR_YELLOW, t_disp, l_disp, m_disp, hist_disp, COLOR_YELLOW, q_disp, face);
     let prompt_prefix = prompt_prefix_buf.as_str();
     let p_len = input::visual_length(&prompt_prefix);
     INPUT_LEN.store(p_len as u16, Ordering::SeqCst);
     layout.input_prefix_len = p_len as u16;
 
+    // Without animation only redraw when something visible changed; over a
+    // slow serial link this turns a constant repaint into an occasional one
+    if animate {
+        layout.footer_sig = 0;
+    } else {
+        let elapsed_s = if layout.status_start_us > 0 { uptime.saturating_sub(layout.status_start_us) / 1_000_000 } else { 0 };
+        let rate = layout.stream_rate_x10(uptime);
+        let mut sig = fnv1a(FNV_OFFSET, prompt_prefix.as_bytes());
+        sig = fnv1a(sig, layout.status_text.as_bytes());
+        sig = fnv1a(sig, crate::app::suggest::line().as_bytes());
+        state::with_global_input(|input_str| sig = fnv1a(sig, input_str.as_bytes()));
+        state::with_model_and_provider(|m, p| { sig = fnv1a(sig, m.as_bytes()); sig = fnv1a(sig, p.as_bytes()); });
+        for n in [current_tokens as u64, token_limit as u64, mem_kb as u64, elapsed_s, layout.status_time_ms.unwrap_or(0), layout.stream_bytes as u64, rate, CURSOR_IDX.load(Ordering::SeqCst) as u64, w as u64, h,
+                  layout.footer_height as u64, super::tabs::TAB_COUNT.load(Ordering::SeqCst) as u64, super::tabs::ACTIVE_TAB.load(Ordering::SeqCst) as u64,
+                  crate::app::prompt::is_compact() as u64, state::composition_len().map_or(0, |n| n as u64 + 1)] {
+            sig = fnv1a(sig, &n.to_le_bytes());
+        }
+        if sig == layout.footer_sig { return; }
+        layout.footer_sig = sig;
+        layout.repaint_counter = 0;
+    }
+
     state::with_global_input(|input_str| {
         let wrapped = input::count_wrapped_lines(input_str, p_len, w);
         let n_f_h = (core::cmp::min(wrapped, core::cmp::min(10, (h / 3) as usize)) + 2) as u16;
@@ -229,10 +321,22 @@ pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
         hide_cursor();
         let s_r = h - eff_f_h as u64;
         if eff_f_h < o_f_h {
-            let o_st_r = (h - o_f_h as u64).saturating_sub(1);
+            // From the old chips row down
+            let o_st_r = (h - o_f_h as u64).saturating_sub(2);
             for r in o_st_r..s_r { set_cursor_position(0, r); let _ = akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes()); }
         }
         
+        // Follow-up chips sit in the gap row just above the status line
+        if layout.gap() > 0 {
+            set_cursor_position(0, s_r.saturating_sub(2)); let _ = akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes());
+            let chips = crate::app::suggest::line();
+            if !chips.is_empty() {
+                let _ = write!(stdout, "  {}", theme::adapt(COLOR_GRAY_DIM));
+                for c in chips.chars().take(w.saturating_sub(4)) { let mut b = [0u8; 4]; let _ = akuma_write(fd::STDOUT, c.encode_utf8(&mut b).as_bytes()); }
+                let _ = write!(stdout, "{}", COLOR_RESET);
+            }
+        }
+
         set_cursor_position(0, s_r.saturating_sub(1)); let _ = akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes());
         if !layout.status_text.is_empty() {
             let _ = write!(stdout, "  {}{}", layout.status_color, layout.status_text);
@@ -248,14 +352,22 @@ pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
             };
 
             if let Some(ms) = ms { 
-                if ms < 1000 { let _ = write!(stdout, "~(=^‥^)ノ [{}ms]", ms); } 
+                if !animate { let _ = write!(stdout, "~(=^‥^)ノ [{}s]", ms / 1000); }
+                else if ms < 1000 { let _ = write!(stdout, "~(=^‥^)ノ [{}ms]", ms); } 
                 else { let _ = write!(stdout, "~(=^‥^)ノ [{}.{}s]", ms / 1000, (ms % 1000) / 100); }
             }
+            if is_streaming && layout.stream_bytes > 0 {
+                let rate = layout.stream_rate_x10(uptime);
+                let _ = write!(stdout, " {}", COLOR_GRAY_DIM);
+                if layout.stream_bytes >= 1024 { let _ = write!(stdout, "{}.{}KB", layout.stream_bytes / 1024, layout.stream_bytes % 1024 * 10 / 1024); }
+                else { let _ = write!(stdout, "{}B", layout.stream_bytes); }
+                let _ = write!(stdout, " ~{} tok {}.{} tok/s", layout.stream_tokens, rate / 10, rate % 10);
+            }
             let _ = write!(stdout, "{}", COLOR_RESET);
         }
         
         set_cursor_position(0, s_r);
-        akuma_write(fd::STDOUT, COLOR_GRAY_DIM.as_bytes());
+        akuma_write(fd::STDOUT, theme::adapt(COLOR_GRAY_DIM).as_bytes());
         for _ in 0..w { akuma_write(fd::STDOUT, "━".as_bytes()); }
         akuma_write(fd::STDOUT, COLOR_RESET.as_bytes());
 
@@ -265,10 +377,26 @@ pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
             let mut stdout = Stdout;
             let _ = write!(stdout, "  {}{}[Provider: {}] [Model: {}]{}", COLOR_GRAY_DIM, COLOR_RESET, prov_n, mod_n, COLOR_RESET);
         });
+        if crate::app::prompt::is_compact() {
+            let _ = write!(stdout, " {}[small context: reduced tools]{}", COLOR_YELLOW, COLOR_RESET);
+        }
+        if let Some(n) = state::composition_len() {
+            let _ = write!(stdout, " {}[composing: {} lines, /send or Ctrl+D]{}", COLOR_YELLOW, n, COLOR_RESET);
+        }
+
+        let tab_count = super::tabs::TAB_COUNT.load(Ordering::SeqCst);
+        if tab_count > 1 {
+            let active = super::tabs::ACTIVE_TAB.load(Ordering::SeqCst);
+            let _ = write!(stdout, " ");
+            for t in 0..tab_count {
+                if t == active { let _ = write!(stdout, " {}{}[{}]{}", COLOR_YELLOW, COLOR_BOLD, t + 1, COLOR_RESET); }
+                else { let _ = write!(stdout, " {}{}{}", COLOR_GRAY_DIM, t + 1, COLOR_RESET); }
+            }
+        }
 
         for i in 0..eff_p_l { set_cursor_position(0, p_r + 1 + i as u64); let _ = akuma_write(fd::STDOUT, CLEAR_TO_EOL.as_bytes()); }
         if s_t == 0 { set_cursor_position(0, p_r + 1); let _ = write!(stdout, "{}{}{}{}", COLOR_VIOLET, COLOR_BOLD, prompt_prefix, COLOR_RESET); }
-        let _ = akuma_write(fd::STDOUT, COLOR_VIOLET.as_bytes());
+        let _ = akuma_write(fd::STDOUT, theme::adapt(COLOR_VIOLET).as_bytes());
         let (mut c_l, mut c_c) = (0, p_len);
         for c in input_str.chars() {
             if c == '\n' { c_l += 1; c_c = 4; }
@@ -290,7 +418,29 @@ pub fn render_footer(current_tokens: usize, token_limit: usize, mem_kb: usize) {
 pub fn print_greeting() {
     let mut stdout = Stdout;
     use core::fmt::Write;
-    let _ = write!(stdout, "\n{}\x1b[38;5;236m", COLOR_RESET);
-    let _ = write!(stdout, "{}", CAT_ASCII);
-    let _ = write!(stdout, "{}\n  {}MEOW!{} ~(=^‥^)ノ\n\n", COLOR_RESET, COLOR_BOLD, COLOR_RESET);
+    let low_bw = state::LOW_BANDWIDTH.load(Ordering::SeqCst);
+    if state::PLAIN.load(Ordering::SeqCst) {
+        let _ = write!(stdout, "\n  {}meow{} {}\n", COLOR_BOLD, COLOR_RESET, env!("CARGO_PKG_VERSION"));
+        if let Some(motd) = super::banner::motd() {
+            let _ = write!(stdout, "  {}{}{}\n", COLOR_YELLOW, motd, COLOR_RESET);
+        }
+        let _ = write!(stdout, "\n");
+        return;
+    }
+    match super::banner::load_banner() {
+        Some(banner) if !low_bw => {
+            let _ = write!(stdout, "\n{}{}\n", COLOR_RESET, banner.trim_end());
+        }
+        _ => {
+            if !low_bw {
+                let _ = write!(stdout, "\n{}\x1b[38;5;236m", COLOR_RESET);
+                let _ = write!(stdout, "{}", CAT_ASCII);
+            }
+            let _ = write!(stdout, "{}\n  {}MEOW!{} ~(=^‥^)ノ\n", COLOR_RESET, COLOR_BOLD, COLOR_RESET);
+        }
+    }
+    if let Some(motd) = super::banner::motd() {
+        let _ = write!(stdout, "  {}{}{}\n", COLOR_YELLOW, motd, COLOR_RESET);
+    }
+    let _ = write!(stdout, "\n");
 }
@@ -1,7 +1,8 @@
 use alloc::string::String;
 use alloc::format;
 use crate::config::{COLOR_MEOW, COLOR_RESET};
-use super::render::tui_print_with_indent;
+use super::render::{tui_print_with_indent, tui_print_verbatim};
+use super::markdown::MarkdownStream;
 
 pub enum StreamState {
     Text,
@@ -21,6 +22,10 @@ pub struct StreamingRenderer {
     indent: u16,
     line_buf: String,
     at_line_start: bool,
+    /// Inside a fenced code block, whose lines are printed verbatim
+    in_code: bool,
+    /// Styles the text with `render_markdown` on; tool calls never reach it
+    markdown: Option<MarkdownStream>,
 }
 
 impl StreamingRenderer {
@@ -30,6 +35,8 @@ impl StreamingRenderer {
             indent,
             line_buf: String::new(),
             at_line_start: true,
+            in_code: false,
+            markdown: crate::app::state::get_render_markdown().then(|| MarkdownStream::new(indent, COLOR_MEOW)),
         }
     }
 
@@ -37,8 +44,22 @@ impl StreamingRenderer {
         for c in chunk.chars() {
             let mut next_state = None;
             let mut chars_to_flush = String::new();
+            let mut verbatim = false;
 
             match &mut self.state {
+                // Code is buffered a line at a time so the closing fence is
+                // seen, and printed without wrapping so indentation survives
+                StreamState::Text if self.in_code => {
+                    self.line_buf.push(c);
+                    if c == '\n' {
+                        if self.line_buf.trim_start().starts_with("```") {
+                            self.in_code = false;
+                        }
+                        chars_to_flush = core::mem::take(&mut self.line_buf);
+                        verbatim = true;
+                        self.at_line_start = true;
+                    }
+                }
                 StreamState::Text => {
                     if c == '\n' {
                         chars_to_flush = self.line_buf.clone();
@@ -91,6 +112,8 @@ impl StreamingRenderer {
                             if !tag.is_empty() && tag != "json" {
                                 // It's like ```rust\n... flush it
                                 chars_to_flush = buffer.clone();
+                                verbatim = true;
+                                self.in_code = true;
                                 next_state = Some(StreamState::Text);
                                 self.at_line_start = buffer.ends_with('\n');
                             }
@@ -98,6 +121,8 @@ impl StreamingRenderer {
                             // No newline yet, check if we are still typing "json"
                             if ! "json".starts_with(after_bt) {
                                 chars_to_flush = buffer.clone();
+                                verbatim = true;
+                                self.in_code = true;
                                 next_state = Some(StreamState::Text);
                                 self.at_line_start = buffer.ends_with('\n');
                             }
@@ -113,12 +138,15 @@ impl StreamingRenderer {
                         } else {
                             // Not a tool call, flush the whole markdown block
                             chars_to_flush = buffer.clone();
+                            verbatim = true;
                             next_state = Some(StreamState::Text);
                             self.at_line_start = buffer.ends_with('\n');
                         }
                     } else if next_state.is_none() && buffer.len() > 16384 {
-                        // Safety fallback
+                        // Safety fallback; the block is still open
                         chars_to_flush = buffer.clone();
+                        verbatim = true;
+                        self.in_code = true;
                         next_state = Some(StreamState::Text);
                         self.at_line_start = buffer.ends_with('\n');
                     }
@@ -157,13 +185,18 @@ impl StreamingRenderer {
                 self.state = ns;
             }
 
-            if !chars_to_flush.is_empty() {
+            if let Some(md) = self.markdown.as_mut() {
+                if !chars_to_flush.is_empty() { md.push(&chars_to_flush); }
+            } else if verbatim {
+                tui_print_verbatim(&chars_to_flush, self.indent, Some(COLOR_MEOW));
+            } else if !chars_to_flush.is_empty() {
                 tui_print_with_indent(&chars_to_flush, "", self.indent, Some(COLOR_MEOW));
             }
         }
     }
 
     pub fn finalize(&mut self) {
+        let verbatim = self.in_code || matches!(self.state, StreamState::BufferingPotentialTool { .. });
         let to_flush = match &mut self.state {
             StreamState::Text => {
                 let s = self.line_buf.clone();
@@ -181,11 +214,17 @@ impl StreamingRenderer {
                 s
             }
         };
-        if !to_flush.is_empty() {
+        if let Some(md) = self.markdown.as_mut() {
+            md.push(&to_flush);
+            md.finish();
+        } else if verbatim {
+            tui_print_verbatim(&to_flush, self.indent, Some(COLOR_MEOW));
+        } else if !to_flush.is_empty() {
             tui_print_with_indent(&to_flush, "", self.indent, Some(COLOR_MEOW));
         }
         self.state = StreamState::Text;
         self.at_line_start = true;
+        self.in_code = false;
     }
 }
 
@@ -264,7 +303,7 @@ fn print_tool_notification(tool: &str, args: &str, indent: u16) {
     } else {
         format!("ToolCalled: {} | Arguments {}\n", tool, args)
     };
-    tui_print_with_indent(&content, "     --- ", 9, Some(crate::config::COLOR_GRAY_DIM));
+    tui_print_with_indent(&content, "     --- ", super::wrap::indent(super::wrap::Role::Tool), Some(crate::config::COLOR_GRAY_DIM));
     tui_print_with_indent("", "", indent, None);
 }
 
@@ -280,6 +319,9 @@ pub fn run_tests() -> i32 {
 
     libakuma::print("--- Meow StreamingRenderer Tests ---\n");
 
+    // The cases check the raw text that reaches the screen
+    crate::app::state::set_render_markdown(false);
+
     let test_cases: [(&str, &str, &[&str]); 4] = [
 
         ("Normal text", "Hello nya~!\n", &["Hello nya~!\n"]),
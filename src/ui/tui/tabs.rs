@@ -0,0 +1,239 @@
+//! Chat tabs for the TUI
+//!
+//! The active tab's history, model and provider live where they always have
+//! (the `run_tui` locals and `app::state`), as do its goals, `/retry` state
+//! and change journal (in their modules), so the rest of the app is unaware
+//! of tabs. Inactive tabs are parked here as `Tab` structs and swapped in on
+//! Ctrl+T / Ctrl+Left / Ctrl+Right. Only the active tab can stream.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::collections::VecDeque;
+use core::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+use crate::app::{chat, changes, goals, Message, state};
+use crate::config::Provider;
+use super::input::CURSOR_IDX;
+use super::render;
+
+/// Number of open tabs, shown in the footer when greater than one
+pub static TAB_COUNT: AtomicU16 = AtomicU16::new(1);
+/// Index of the active tab
+pub static ACTIVE_TAB: AtomicU16 = AtomicU16::new(0);
+/// Set while scrollback is being replayed so it is not recorded twice
+static REPLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Maximum number of open tabs
+pub const MAX_TABS: usize = 9;
+/// Scrollback kept per tab; older output is dropped first
+const MAX_SCROLLBACK_BYTES: usize = 128 * 1024;
+/// How much of a tab's scrollback is redrawn when switching to it
+const REPLAY_BYTES: usize = 16 * 1024;
+
+/// One call to `tui_print_with_indent`, kept so a tab can be redrawn
+pub struct ScrollEntry {
+    pub text: String,
+    pub prefix: String,
+    pub indent: u16,
+    pub color: Option<String>,
+    /// Printed with `tui_print_verbatim` (code block lines)
+    pub verbatim: bool,
+}
+
+/// Scrollback of the active tab
+struct Scrollback {
+    entries: VecDeque<ScrollEntry>,
+    bytes: usize,
+}
+
+static mut SCROLLBACK: Option<Scrollback> = None;
+
+fn scrollback() -> &'static mut Scrollback {
+    unsafe {
+        let ptr = core::ptr::addr_of_mut!(SCROLLBACK);
+        if (*ptr).is_none() {
+            *ptr = Some(Scrollback { entries: VecDeque::new(), bytes: 0 });
+        }
+        (*ptr).as_mut().unwrap()
+    }
+}
+
+/// Bytes of text held in the active tab's scrollback
+pub fn scrollback_bytes() -> usize {
+    scrollback().bytes
+}
+
+/// Record printed output for the active tab
+pub fn record(s: &str, prefix: &str, indent: u16, color: Option<&str>, verbatim: bool) {
+    if REPLAYING.load(Ordering::SeqCst) || s.is_empty() { return; }
+    let _t = crate::heap::tag(crate::heap::Tag::Tui);
+    let sb = scrollback();
+
+    // Streaming prints one token at a time; merge runs with the same style
+    if let Some(last) = sb.entries.back_mut() {
+        if prefix.is_empty() && last.prefix.is_empty() && last.indent == indent && last.color.as_deref() == color && last.verbatim == verbatim {
+            last.text.push_str(s);
+            sb.bytes += s.len();
+            trim(sb);
+            return;
+        }
+    }
+
+    sb.entries.push_back(ScrollEntry {
+        text: String::from(s),
+        prefix: String::from(prefix),
+        indent,
+        color: color.map(String::from),
+        verbatim,
+    });
+    sb.bytes += s.len() + prefix.len();
+    trim(sb);
+}
+
+fn trim(sb: &mut Scrollback) {
+    while sb.bytes > MAX_SCROLLBACK_BYTES && sb.entries.len() > 1 {
+        if let Some(e) = sb.entries.pop_front() {
+            sb.bytes -= e.text.len() + e.prefix.len();
+        }
+    }
+}
+
+/// A parked (inactive) chat tab
+pub struct Tab {
+    pub model: String,
+    pub provider: Provider,
+    pub history: Vec<Message>,
+    input: String,
+    cursor_idx: u16,
+    queue: VecDeque<String>,
+    scrollback: VecDeque<ScrollEntry>,
+    scrollback_bytes: usize,
+    goals: goals::Parked,
+    turn: chat::Parked,
+    changes: changes::Parked,
+}
+
+impl Tab {
+    /// A fresh tab with only the system prompt, like after `/clear`
+    pub fn new(model: &str, provider: &Provider, system_prompt: &str) -> Self {
+        let mut history = Vec::new();
+        history.push(Message::new("system", system_prompt));
+        Self {
+            model: String::from(model),
+            provider: provider.clone(),
+            history,
+            input: String::new(),
+            cursor_idx: 0,
+            queue: VecDeque::new(),
+            scrollback: VecDeque::new(),
+            scrollback_bytes: 0,
+            goals: goals::Parked::default(),
+            turn: chat::Parked::default(),
+            changes: changes::Parked::default(),
+        }
+    }
+}
+
+pub enum TabAction {
+    New,
+    Prev,
+    Next,
+}
+
+/// All open tabs; `slots[active]` is `None` because its state is live
+pub struct TabSet {
+    slots: Vec<Option<Tab>>,
+    active: usize,
+}
+
+impl TabSet {
+    pub fn new() -> Self {
+        let mut slots = Vec::new();
+        slots.push(None);
+        TAB_COUNT.store(1, Ordering::SeqCst);
+        ACTIVE_TAB.store(0, Ordering::SeqCst);
+        Self { slots, active: 0 }
+    }
+
+    /// Apply a tab action, swapping the live state. Returns false if nothing changed.
+    pub fn apply(&mut self, action: TabAction, model: &mut String, provider: &mut Provider, history: &mut Vec<Message>, system_prompt: &str) -> bool {
+        let target = match action {
+            TabAction::New => {
+                if self.slots.len() >= MAX_TABS { return false; }
+                self.slots.push(Some(Tab::new(model, provider, system_prompt)));
+                self.slots.len() - 1
+            }
+            TabAction::Prev if self.slots.len() > 1 => (self.active + self.slots.len() - 1) % self.slots.len(),
+            TabAction::Next if self.slots.len() > 1 => (self.active + 1) % self.slots.len(),
+            _ => return false,
+        };
+
+        let incoming = match self.slots[target].take() {
+            Some(t) => t,
+            None => return false,
+        };
+        self.slots[self.active] = Some(park(model, provider, history));
+        unpark(incoming, model, provider, history);
+        self.active = target;
+
+        TAB_COUNT.store(self.slots.len() as u16, Ordering::SeqCst);
+        ACTIVE_TAB.store(target as u16, Ordering::SeqCst);
+        true
+    }
+}
+
+fn park(model: &mut String, provider: &mut Provider, history: &mut Vec<Message>) -> Tab {
+    let sb = scrollback();
+    let mut queue = VecDeque::new();
+    while let Some(m) = state::pop_message() { queue.push_back(m); }
+    Tab {
+        model: core::mem::take(model),
+        provider: provider.clone(),
+        history: core::mem::take(history),
+        input: state::get_global_input(),
+        cursor_idx: CURSOR_IDX.load(Ordering::SeqCst),
+        queue,
+        scrollback: core::mem::take(&mut sb.entries),
+        scrollback_bytes: core::mem::replace(&mut sb.bytes, 0),
+        goals: goals::park(),
+        turn: chat::park(),
+        changes: changes::park(),
+    }
+}
+
+fn unpark(tab: Tab, model: &mut String, provider: &mut Provider, history: &mut Vec<Message>) {
+    *model = tab.model;
+    *provider = tab.provider;
+    *history = tab.history;
+    state::set_global_input(tab.input);
+    CURSOR_IDX.store(tab.cursor_idx, Ordering::SeqCst);
+    for m in tab.queue { state::push_message(m); }
+    state::set_model_and_provider(model, &provider.name);
+    let sb = scrollback();
+    sb.entries = tab.scrollback;
+    sb.bytes = tab.scrollback_bytes;
+    goals::unpark(tab.goals);
+    chat::unpark(tab.turn);
+    changes::unpark(tab.changes);
+}
+
+/// Redraw the tail of the active tab's scrollback into the output pane
+pub fn replay_scrollback() {
+    let sb = scrollback();
+    let mut start = sb.entries.len();
+    let mut bytes = 0;
+    while start > 0 && bytes < REPLAY_BYTES {
+        start -= 1;
+        bytes += sb.entries[start].text.len();
+    }
+
+    REPLAYING.store(true, Ordering::SeqCst);
+    for e in sb.entries.iter().skip(start) {
+        if e.verbatim {
+            render::tui_print_verbatim(&e.text, e.indent, e.color.as_deref());
+        } else {
+            render::tui_print_with_indent(&e.text, &e.prefix, e.indent, e.color.as_deref());
+        }
+    }
+    REPLAYING.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,85 @@
+//! Terminal guard
+//!
+//! Everything the TUI changes about the terminal is set up and undone here:
+//! raw mode, the kitty keyboard protocol, bracketed paste, the alternate
+//! screen, the scroll region and left/right margins. `restore()` is the single undo path, used
+//! by the guard's Drop and by the crash handler.
+//!
+//! libakuma has no signal or at-exit hooks, so a kill from another shell
+//! still can't be caught. What we can do is make sure every exit path that
+//! meow controls goes through here, and leave the sequence in one place
+//! for `meow --reset-terminal` to replay afterwards.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use libakuma::{get_terminal_attributes, set_terminal_attributes, write as akuma_write, show_cursor, fd};
+
+pub mod mode_flags {
+    pub const RAW_MODE_ENABLE: u64 = 0x01;
+}
+
+/// Margins off, scroll region reset, keyboard protocol popped, bracketed
+/// paste off, attributes cleared, alt screen left, cursor shown
+pub const RESET_SEQUENCE: &[u8] = b"\x1b[?69l\x1b[r\x1b[<u\x1b[?2004l\x1b[0m\x1b[?1049l\x1b[?25h";
+
+/// Attributes from before raw mode
+static SAVED_MODE: AtomicU64 = AtomicU64::new(0);
+static RAW: AtomicBool = AtomicBool::new(false);
+static SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Holds the terminal in raw mode; restores it when dropped
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Save the current attributes and switch stdin to raw mode
+    pub fn new() -> Self {
+        let mut old_mode: u64 = 0;
+        get_terminal_attributes(fd::STDIN, &mut old_mode as *mut u64 as u64);
+        SAVED_MODE.store(old_mode, Ordering::SeqCst);
+        set_terminal_attributes(fd::STDIN, 0, mode_flags::RAW_MODE_ENABLE);
+        RAW.store(true, Ordering::SeqCst);
+        Self
+    }
+
+    /// Push the kitty keyboard protocol, turn on bracketed paste and switch
+    /// to the alternate screen
+    pub fn enter_screen(&self) {
+        akuma_write(fd::STDOUT, ENTER_SEQUENCE);
+        SCREEN.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Keyboard protocol pushed, bracketed paste on, alt screen entered
+const ENTER_SEQUENCE: &[u8] = b"\x1b[>1u\x1b[?2004h\x1b[?1049h";
+
+/// Raw mode and the screen again after `restore`, for the watchdog's soft
+/// restart. The attributes saved by the guard are kept for the final undo.
+pub fn reinit() {
+    set_terminal_attributes(fd::STDIN, 0, mode_flags::RAW_MODE_ENABLE);
+    RAW.store(true, Ordering::SeqCst);
+    akuma_write(fd::STDOUT, ENTER_SEQUENCE);
+    SCREEN.store(true, Ordering::SeqCst);
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) { restore(); }
+}
+
+/// Undo whatever the guard set up. Allocation-free and idempotent, so the
+/// crash handler can call it at any point.
+pub fn restore() {
+    if SCREEN.swap(false, Ordering::SeqCst) {
+        akuma_write(fd::STDOUT, RESET_SEQUENCE);
+    }
+    if RAW.swap(false, Ordering::SeqCst) {
+        set_terminal_attributes(fd::STDIN, 0, SAVED_MODE.load(Ordering::SeqCst));
+        show_cursor();
+    }
+}
+
+/// Recovery for a terminal left broken by a killed meow: emit the reset
+/// sequence and drop raw mode
+pub fn force_reset() {
+    akuma_write(fd::STDOUT, RESET_SEQUENCE);
+    set_terminal_attributes(fd::STDIN, 0, 0);
+}
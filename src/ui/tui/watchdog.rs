@@ -0,0 +1,147 @@
+//! Watchdog for the outer TUI loop
+//!
+//! meow is single-threaded, so nothing can interrupt a loop that is stuck
+//! inside one call. What the outer loop can notice, once per pass, is the
+//! two ways a render or input bug usually shows up:
+//!
+//! - spinning: hundreds of passes a second with no input arriving, which
+//!   means `poll_input_event` stopped waiting (stdin gone bad, raw mode
+//!   lost) and meow is burning a core;
+//! - stuck input: bytes sitting at the front of the raw input queue that
+//!   never parse, so every key pressed afterwards queues up behind them and
+//!   the prompt looks frozen.
+//!
+//! Either way the terminal is restored, the state is appended to
+//! /var/meow/watchdog.log, and the TUI is set up again from scratch: raw
+//! mode, the screen, the layout and an empty input queue. Three recoveries
+//! within a minute end the session instead, with the terminal left usable.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::format;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use libakuma::{open, close, write_fd, open_flags};
+
+use crate::app::state;
+use super::input;
+use super::layout::get_pane_layout;
+
+pub const LOG_FILE: &str = "/var/meow/watchdog.log";
+
+/// Passes counted over this window to tell spinning from polling
+const WINDOW_US: u64 = 1_000_000;
+/// A pass normally waits up to 50ms for input; this many in a window
+/// without any input is a spin
+const SPIN_PASSES: usize = 500;
+/// How long the front of the input queue may stay unparsed
+const STUCK_INPUT_US: u64 = 2_000_000;
+/// Recoveries allowed within `RECOVERY_WINDOW_US` before giving up
+const MAX_RECOVERIES: usize = 3;
+const RECOVERY_WINDOW_US: u64 = 60_000_000;
+
+static WINDOW_START: AtomicU64 = AtomicU64::new(0);
+static PASSES: AtomicUsize = AtomicUsize::new(0);
+/// Input queue length and front byte when it last changed, and when
+static QUEUE_SIG: AtomicU64 = AtomicU64::new(0);
+static QUEUE_SINCE: AtomicU64 = AtomicU64::new(0);
+static RECOVERIES: AtomicUsize = AtomicUsize::new(0);
+static FIRST_RECOVERY: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stuck {
+    Spinning,
+    StuckInput,
+}
+
+impl Stuck {
+    fn describe(&self) -> &'static str {
+        match self {
+            Stuck::Spinning => "outer loop spinning without waiting for input",
+            Stuck::StuckInput => "input queue not draining",
+        }
+    }
+}
+
+/// Count one pass of the outer loop; Some when the TUI looks stuck
+pub fn check(queue: &VecDeque<u8>) -> Option<Stuck> {
+    let now = libakuma::uptime();
+    let passes = PASSES.fetch_add(1, Ordering::Relaxed) + 1;
+    let start = WINDOW_START.load(Ordering::Relaxed);
+    if start == 0 || now - start >= WINDOW_US {
+        WINDOW_START.store(now, Ordering::Relaxed);
+        PASSES.store(0, Ordering::Relaxed);
+    } else if passes >= SPIN_PASSES && now.saturating_sub(input::last_input_time()) > WINDOW_US {
+        return Some(Stuck::Spinning);
+    }
+
+    if queue.is_empty() {
+        QUEUE_SIG.store(0, Ordering::Relaxed);
+        return None;
+    }
+    let sig = ((queue.len() as u64) << 8) | queue[0] as u64;
+    if QUEUE_SIG.swap(sig, Ordering::Relaxed) != sig {
+        QUEUE_SINCE.store(now, Ordering::Relaxed);
+    } else if now - QUEUE_SINCE.load(Ordering::Relaxed) > STUCK_INPUT_US {
+        return Some(Stuck::StuckInput);
+    }
+    None
+}
+
+/// Log what went wrong and set the TUI up again. Err when it has needed
+/// this too often to keep trying.
+pub fn recover(stuck: Stuck) -> Result<(), &'static str> {
+    super::terminal::restore();
+    log(stuck);
+
+    let now = libakuma::uptime();
+    if now - FIRST_RECOVERY.load(Ordering::Relaxed) > RECOVERY_WINDOW_US {
+        FIRST_RECOVERY.store(now, Ordering::Relaxed);
+        RECOVERIES.store(0, Ordering::Relaxed);
+    }
+    if RECOVERIES.fetch_add(1, Ordering::Relaxed) + 1 >= MAX_RECOVERIES {
+        libakuma::print(&format!("\n[meow] the TUI kept getting stuck ({}); details in {}\n", stuck.describe(), LOG_FILE));
+        return Err("TUI watchdog gave up");
+    }
+
+    input::get_raw_input_queue().clear();
+    QUEUE_SIG.store(0, Ordering::Relaxed);
+    PASSES.store(0, Ordering::Relaxed);
+    WINDOW_START.store(0, Ordering::Relaxed);
+    state::STREAMING.store(false, Ordering::SeqCst);
+    super::terminal::reinit();
+    let layout = get_pane_layout();
+    layout.clear_status();
+    layout.recalculate(4);
+    crate::tui_app::restore_screen();
+    crate::tui_app::tui_print(&format!("\n  [!] meow's screen got stuck ({}) and was reset; details in {}\n\n", stuck.describe(), LOG_FILE));
+    Ok(())
+}
+
+/// Append the state worth looking at to the watchdog log
+fn log(stuck: Stuck) {
+    let layout = get_pane_layout();
+    let queue = input::get_raw_input_queue();
+    let mut entry = String::new();
+    let _ = write!(entry, "[{}s] {}\n", libakuma::uptime() / 1_000_000, stuck.describe());
+    let _ = write!(entry, "  passes this window: {}, last input {}ms ago\n",
+        PASSES.load(Ordering::Relaxed), libakuma::uptime().saturating_sub(input::last_input_time()) / 1000);
+    let _ = write!(entry, "  terminal {}x{}, footer {} rows, output {}..{}, cursor row {}\n",
+        layout.term_width, layout.term_height, layout.footer_height, layout.output_top, layout.output_bottom,
+        crate::tui_app::CUR_ROW.load(Ordering::SeqCst));
+    let _ = write!(entry, "  status '{}', streaming {}, input {} bytes\n",
+        layout.status_text, state::STREAMING.load(Ordering::SeqCst), state::with_global_input(|s| s.len()));
+    let _ = write!(entry, "  input queue ({} bytes):", queue.len());
+    for b in queue.iter().take(64) {
+        let _ = write!(entry, " {:02x}", b);
+    }
+    entry.push_str("\n\n");
+
+    libakuma::mkdir_p(crate::crash::CRASH_DIR);
+    let fd = open(LOG_FILE, open_flags::O_WRONLY | open_flags::O_CREAT | open_flags::O_APPEND);
+    if fd >= 0 {
+        write_fd(fd, entry.as_bytes());
+        close(fd);
+    }
+}
@@ -0,0 +1,107 @@
+//! Output pane text width, alignment and indents
+//!
+//! By default text wraps at the pane edge with the assistant's replies
+//! indented 9 columns and the user's 4. `wrap_width` narrows the text to a
+//! column (left-aligned or centered in the pane), the `indent_*` settings
+//! change the per-role indents, and with `word_wrap` off long lines are cut
+//! at the edge instead, with `/wrap scroll N` moving the view sideways.
+//! `tui_print_with_indent` asks here for every line it lays out.
+
+use alloc::string::String;
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+
+use crate::config::Config;
+
+/// Narrowest content column accepted for `wrap_width`
+pub const MIN_WIDTH: u16 = 20;
+
+static MAX_WIDTH: AtomicU16 = AtomicU16::new(0);
+static CENTER: AtomicBool = AtomicBool::new(false);
+static WORD_WRAP: AtomicBool = AtomicBool::new(true);
+static H_SCROLL: AtomicU16 = AtomicU16::new(0);
+static INDENT_USER: AtomicU16 = AtomicU16::new(4);
+static INDENT_ASSISTANT: AtomicU16 = AtomicU16::new(9);
+static INDENT_TOOL: AtomicU16 = AtomicU16::new(9);
+
+#[derive(Clone, Copy)]
+pub enum Role {
+    User,
+    Assistant,
+    /// Tool notifications and output
+    Tool,
+}
+
+pub fn configure(config: &Config) {
+    set_max_width(config.wrap_width);
+    CENTER.store(config.wrap_center, Ordering::SeqCst);
+    WORD_WRAP.store(config.word_wrap, Ordering::SeqCst);
+    INDENT_USER.store(config.indent_user, Ordering::SeqCst);
+    INDENT_ASSISTANT.store(config.indent_assistant, Ordering::SeqCst);
+    INDENT_TOOL.store(config.indent_tool, Ordering::SeqCst);
+}
+
+pub fn indent(role: Role) -> u16 {
+    match role {
+        Role::User => INDENT_USER.load(Ordering::SeqCst),
+        Role::Assistant => INDENT_ASSISTANT.load(Ordering::SeqCst),
+        Role::Tool => INDENT_TOOL.load(Ordering::SeqCst),
+    }
+}
+
+/// 0 = the full pane width
+pub fn set_max_width(cols: u16) {
+    MAX_WIDTH.store(if cols == 0 { 0 } else { cols.max(MIN_WIDTH) }, Ordering::SeqCst);
+}
+
+pub fn set_center(on: bool) {
+    CENTER.store(on, Ordering::SeqCst);
+}
+
+pub fn word_wrap() -> bool {
+    WORD_WRAP.load(Ordering::SeqCst)
+}
+
+pub fn set_word_wrap(on: bool) {
+    WORD_WRAP.store(on, Ordering::SeqCst);
+    if on {
+        H_SCROLL.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Columns of each unwrapped line hidden on the left
+pub fn h_scroll() -> u16 {
+    H_SCROLL.load(Ordering::SeqCst)
+}
+
+pub fn set_h_scroll(cols: u16) {
+    H_SCROLL.store(cols, Ordering::SeqCst);
+}
+
+/// (left margin, right limit) of the text in a pane `pane_width` wide. The
+/// margin is added to every indent; the limit replaces the pane width.
+pub fn bounds(pane_width: u16) -> (u16, u16) {
+    let max = MAX_WIDTH.load(Ordering::SeqCst);
+    if max == 0 || max >= pane_width {
+        return (0, pane_width);
+    }
+    let margin = if CENTER.load(Ordering::SeqCst) { (pane_width - max) / 2 } else { 0 };
+    (margin, margin + max)
+}
+
+/// The /wrap status line
+pub fn describe() -> String {
+    let width = match MAX_WIDTH.load(Ordering::SeqCst) {
+        0 => String::from("full width"),
+        n => format!("{} columns, {}", n, if CENTER.load(Ordering::SeqCst) { "centered" } else { "left" }),
+    };
+    let mode = if word_wrap() {
+        String::from("word wrap on")
+    } else {
+        format!("word wrap off (cut at the edge, scrolled {} columns)", h_scroll())
+    };
+    format!(
+        "～ Text: {}, {}; indents: you {}, assistant {}, tools {} ～",
+        width, mode, indent(Role::User), indent(Role::Assistant), indent(Role::Tool)
+    )
+}
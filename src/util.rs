@@ -1,5 +1,17 @@
 use core::fmt::{self, Write};
 
+/// FNV-1a starting value
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a over `bytes`, continuing from `hash` (`FNV_OFFSET` to start)
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 pub struct StackBuffer<'a> {
     buffer: &'a mut [u8],
     offset: usize,